@@ -0,0 +1,56 @@
+// how long decoding takes, on the fixtures the gradebook tests use and on a gradebook the size of a
+// full schedule late in a term
+#[macro_use]
+extern crate criterion;
+extern crate rvue;
+
+use criterion::{Criterion, Throughput};
+
+use rvue::DecodeLimits;
+use rvue::gradebook::Gradebook;
+
+const STANDARDS: &str = include_str!("../fixtures/gradebook_standards.xml");
+const NO_STANDARDS: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+// the standards fixture with its one course repeated `courses` times and each of that course's
+// assignments repeated `assignments` times, with gradebook IDs kept unique
+fn large(courses: usize, assignments: usize) -> String {
+    let between = |s: &'static str, start: &str, end: &str| -> (&'static str, &'static str, &'static str) {
+        let i = s.find(start).unwrap();
+        let j = s.rfind(end).unwrap() + end.len();
+        (&s[..i], &s[i..j], &s[j..])
+    };
+
+    let (head, course, tail) = between(STANDARDS, "<Course ", "</Course>");
+    let (course_head, assignment_list, course_tail) = between(course, "<Assignment ", "</Assignment>");
+
+    let mut xml = head.to_string();
+    for c in 0..courses {
+        xml.push_str(&course_head.replace("(MA3021)", &format!("(MA30{:02})", c)));
+        for a in 0..assignments {
+            xml.push_str(&assignment_list.replace("GradebookID=\"100", &format!("GradebookID=\"{}{:03}", c, a)));
+        }
+        xml.push_str(course_tail);
+    }
+    xml.push_str(tail);
+    xml
+}
+
+fn decode(c: &mut Criterion) {
+    let large = large(8, 40);
+    let fixtures = [("standards", STANDARDS), ("no_standards", NO_STANDARDS), ("large", large.as_str())];
+
+    let mut group = c.benchmark_group("gradebook");
+    for &(name, xml) in fixtures.iter() {
+        group.throughput(Throughput::Bytes(xml.len() as u64));
+        group.bench_function(name, |b| b.iter(|| Gradebook::from_xml(xml).unwrap()));
+    }
+
+    let skip = DecodeLimits::new().skip_standards();
+    group.throughput(Throughput::Bytes(large.len() as u64));
+    group.bench_function("large_skipping_standards", |b| b.iter(|| Gradebook::from_xml_with(&large, &skip).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
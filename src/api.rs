@@ -1,45 +1,529 @@
+#[cfg(feature="client")]
+use std::env::{self, VarError};
+use std::fmt;
 use std::io::{self, Read};
+#[cfg(feature="client")]
+use std::mem;
 use std::ops::Deref;
+#[cfg(feature="client")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature="client")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature="client")]
+use std::time::Instant;
 
+#[cfg(all(feature="client", feature="serde-serialize"))]
+use audit::{self, AuditEntry, AuditLog};
+
+use credentials::Credentials;
 use decoder::*;
+#[cfg(feature="client")]
+use attendance::Attendance;
+#[cfg(feature="client")]
+use gradebook::{FetchedGradebook, Gradebook};
+#[cfg(feature="client")]
+use messages::Messages;
+#[cfg(feature="client")]
+use schedule::Schedule;
+#[cfg(feature="client")]
+use school::SchoolInfo;
+#[cfg(feature="client")]
+use students::Student;
+
+#[cfg(all(feature="client", feature="serde-serialize"))]
+use chrono::Utc;
 
+#[cfg(feature="client")]
 use reqwest;
-use reqwest::header::{ContentType, Headers};
-use xml::escape::escape_str_attribute;
+#[cfg(feature="client")]
+use transport::{Transport, TransportResponse};
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
 use xml::reader::{Error as ReaderError, EventReader, XmlEvent as ReaderEvent};
-use xml::writer::{EmitterConfig, Result as XmlResult, XmlEvent};
+use xml::writer::{EmitterConfig, Error as WriterError, Result as XmlResult, XmlEvent};
+use zeroize::Zeroizing;
+
+// the default for requests that aren't given an endpoint; every district runs its own server, so
+// anyone outside Portland needs `SVUEClient::district` (or `endpoint`)
+pub const SVUE_ENDPOINT: &str = "https://student-portland.cascadetech.org/portland/Service/PXPCommunication.asmx";
+// where the web service lives relative to a district's StudentVUE/ParentVUE address
+pub const SERVICE_PATH: &str = "Service/PXPCommunication.asmx";
+// the fixed key and account the StudentVUE apps send when searching Edupoint's district directory;
+// they aren't anyone's credentials. Edupoint doesn't publish them, so these are what the apps were
+// seen sending and may change with an app update; `districts::DistrictDirectory` takes others
+pub const DISTRICT_LOOKUP_ENDPOINT: &str = "https://support.edupoint.com/Service/HDInfoCommunication.asmx";
+pub const DISTRICT_LOOKUP_KEY: &str = "5E4B7859-B805-474B-A833-FDB15D205D40";
+pub const DISTRICT_LOOKUP_USER: &str = "EdupointDistrictInfo";
+pub const DISTRICT_LOOKUP_PASSWORD: &str = "Edup01nt";
+pub const SOAP_ACTION: &[u8; 56] = b"http://edupoint.com/webservices/ProcessWebServiceRequest";
+// districts typically lock an account after a handful of bad logins, so this stops well before
+// that; see `SVUEClient::max_auth_failures`
+pub const SUGGESTED_MAX_AUTH_FAILURES: usize = 3;
+// what a request body is allocated with up front; a few times the size of any real one
+const BODY_CAPACITY: usize = 4096;
+
+// the web service URL for a district, given the address its portal is at (e.g.
+// "https://student.district.org/" or "https://sis.district.org/vue"); the login page's URL works
+// too, and a full service URL is kept as is
+pub fn district_endpoint(base_url: &str) -> String {
+    let base = base_url.trim();
+    let base = if base.contains("://") { base.to_string() } else { format!("https://{}", base) };
 
-const SVUE_ENDPOINT: &'static str = "https://student-portland.cascadetech.org/portland/Service/PXPCommunication.asmx";
-const SOAP_ACTION: &'static [u8; 56] = b"http://edupoint.com/webservices/ProcessWebServiceRequest";
+    if base.to_lowercase().ends_with(".asmx") {
+        return base;
+    }
+
+    // drop a page like PXP2_Login.aspx, keeping the directory it's in
+    let base = match base.rfind('/') {
+        Some(i) if base[i..].to_lowercase().ends_with(".aspx") => base[..i].to_string(),
+        _ => base,
+    };
+
+    format!("{}/{}", base.trim_end_matches('/'), SERVICE_PATH)
+}
 
 #[derive(Clone)]
 pub enum SVUEAPIAction {
     RetrieveGrades(Option<i8>),
+    // the students the account can see; see `students::Student`
     RetrieveStudentInfo,
+    // absences and tardies so far this year; see `attendance::Attendance`
+    RetrieveAttendance,
+    // classes and bell times for a term (the current one for None); see `schedule::Schedule`
+    RetrieveClassSchedule(Option<i8>),
+    // the school's address, principal and staff directory; see `school::SchoolInfo`
+    RetrieveSchoolInfo,
+    // the portal's inbox; see `messages::Messages`
+    RetrieveMessages,
+    // the districts near a zip code, from Edupoint's directory rather than a district's server,
+    // along with the directory's key; see `districts::DistrictList::lookup`
+    LookupDistricts(String, String),
 }
 
 impl SVUEAPIAction {
-    fn as_str(&self) -> &'static str {
+    pub(crate) fn as_str(&self) -> &str {
         match *self {
             SVUEAPIAction::RetrieveGrades(_) => "Gradebook",
             SVUEAPIAction::RetrieveStudentInfo => "ChildList",
+            SVUEAPIAction::RetrieveAttendance => "Attendance",
+            SVUEAPIAction::RetrieveClassSchedule(_) => "StudentClassList",
+            SVUEAPIAction::RetrieveSchoolInfo => "StudentSchoolInfo",
+            SVUEAPIAction::RetrieveMessages => "GetPXPMessages",
+            SVUEAPIAction::LookupDistricts(..) => "GetMatchingDistrictList",
+        }
+    }
+
+    fn service_handle(&self) -> &str {
+        match *self {
+            SVUEAPIAction::LookupDistricts(..) => "HDInfoServices",
+            _ => "PXPWebServices",
+        }
+    }
+
+    // the root element of a successful response
+    fn expected_root(&self) -> &str {
+        match *self {
+            SVUEAPIAction::RetrieveClassSchedule(_) => "StudentClassSchedule",
+            SVUEAPIAction::RetrieveSchoolInfo => "StudentSchoolInfoListing",
+            SVUEAPIAction::RetrieveMessages => "PXPMessagesData",
+            SVUEAPIAction::LookupDistricts(..) => "DistrictLists",
+            _ => self.as_str(),
+        }
+    }
+
+    pub fn report_period(&self) -> Option<i8> {
+        match *self {
+            SVUEAPIAction::RetrieveGrades(p) => p,
+            _ => None,
+        }
+    }
+}
+
+// whose data a request is for. A parent logs in with their own account and picks one of their
+// children by the `child_int_id` from `students::Student`; `Parent(None)` leaves the choice to the
+// district, which is enough for listing the children in the first place.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Account {
+    #[default]
+    Student,
+    Parent(Option<String>),
+}
+
+impl Account {
+    pub fn child(child_int_id: &str) -> Account {
+        Account::Parent(Some(child_int_id.to_string()))
+    }
+
+    pub fn is_parent(&self) -> bool {
+        match *self {
+            Account::Parent(_) => true,
+            Account::Student => false,
+        }
+    }
+
+    // the `ChildIntID` parameter; a student's account always uses 0
+    pub fn child_int_id(&self) -> &str {
+        match *self {
+            Account::Parent(Some(ref id)) => id,
+            _ => "0",
         }
     }
 }
 
 pub struct SVUERequest<'a> {
+    account: Account,
     action: SVUEAPIAction,
-    credentials: (&'a str, &'a str),
+    credentials: &'a Credentials,
+    endpoint: &'a str,
+}
+
+#[derive(Clone, Debug)]
+pub enum EnvError {
+    Missing(&'static str),
+    NotUnicode(&'static str),
+}
+
+// owns the credentials and endpoint so they can be loaded once (e.g. from the environment) and
+// reused for every request
+#[cfg(feature="client")]
+#[derive(Clone)]
+pub struct SVUEClient {
+    account: Account,
+    #[cfg(feature="serde-serialize")]
+    audit: Option<Arc<AuditLog>>,
+    // consecutive rejected logins, shared by clones since they all use the same account
+    auth_failures: Arc<AtomicUsize>,
+    // shared by clones rather than copied, so there's one copy of the password to zero
+    credentials: Arc<Credentials>,
+    endpoint: String,
+    // built on first use and shared by clones, so connections (and TLS setup) are reused
+    http: Arc<Mutex<Option<reqwest::blocking::Client>>>,
+    max_auth_failures: Option<usize>,
+    // sends requests instead of `http` when set
+    transport: Option<Arc<dyn Transport + Send + Sync>>,
+}
+
+#[cfg(feature="client")]
+impl SVUEClient {
+    pub fn new(username: &str, password: &str) -> SVUEClient {
+        Self::with_credentials(Credentials::new(username, password))
+    }
+
+    pub fn with_credentials(credentials: Credentials) -> SVUEClient {
+        SVUEClient {
+            account: Account::Student,
+            #[cfg(feature="serde-serialize")]
+            audit: None,
+            auth_failures: Arc::new(AtomicUsize::new(0)),
+            credentials: Arc::new(credentials),
+            endpoint: SVUE_ENDPOINT.to_string(),
+            http: Arc::new(Mutex::new(None)),
+            max_auth_failures: None,
+            transport: None,
+        }
+    }
+
+    // after this many logins in a row are rejected, requests fail with `LikelyBadCredentials`
+    // without being sent; without it the client keeps retrying however many are rejected
+    pub fn max_auth_failures(mut self, max: usize) -> SVUEClient {
+        self.max_auth_failures = Some(max);
+        self
+    }
+
+    // records every request in `log`, including ones held back by `max_auth_failures`; shared by
+    // clones
+    #[cfg(feature="serde-serialize")]
+    pub fn audit_log(mut self, log: AuditLog) -> SVUEClient {
+        self.audit = Some(Arc::new(log));
+        self
+    }
+
+    pub fn auth_failures(&self) -> usize {
+        self.auth_failures.load(Ordering::SeqCst)
+    }
+
+    // lets requests through again, e.g. once the password has been fixed on the district's end
+    pub fn reset_auth_failures(&self) {
+        self.auth_failures.store(0, Ordering::SeqCst);
+    }
+
+    // for callers that need to configure the HTTP client themselves (proxies, timeouts, ...)
+    pub fn http_client(self, http: reqwest::blocking::Client) -> SVUEClient {
+        SVUEClient {
+            http: Arc::new(Mutex::new(Some(http))),
+            ..self
+        }
+    }
+
+    // for sending requests with another HTTP stack entirely; see `transport`
+    pub fn transport<T: Transport + Send + Sync + 'static>(mut self, transport: T) -> SVUEClient {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    fn http(&self) -> Result<reqwest::blocking::Client, SVUERequestError> {
+        // a panic while holding the lock can't leave the Option half-written
+        let mut http = match self.http.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if http.is_none() {
+            *http = Some(reqwest::blocking::Client::builder().build().map_err(SVUERequestError::ClientBuildError)?);
+        }
+
+        Ok(http.as_ref().unwrap().clone())
+    }
+
+    // reads `RVUE_USER` and `RVUE_PASSWORD`, and `RVUE_DISTRICT_URL` or `RVUE_ENDPOINT` if set (the
+    // latter winning if both are)
+    pub fn from_env() -> Result<SVUEClient, EnvError> {
+        let username = Self::env_var("RVUE_USER")?
+            .ok_or(EnvError::Missing("RVUE_USER"))?;
+        let password = Self::env_var("RVUE_PASSWORD")?
+            .ok_or(EnvError::Missing("RVUE_PASSWORD"))?;
+        let district = Self::env_var("RVUE_DISTRICT_URL")?;
+        let endpoint = Self::env_var("RVUE_ENDPOINT")?;
+
+        let mut client = Self::with_credentials(Credentials::from_owned(username, password));
+        if let Some(d) = district {
+            client = client.district(&d);
+        }
+
+        Ok(match endpoint {
+            Some(e) => client.endpoint(&e),
+            None => client,
+        })
+    }
+
+    fn env_var(name: &'static str) -> Result<Option<String>, EnvError> {
+        match env::var(name) {
+            Ok(ref v) if v.is_empty() => Ok(None),
+            Ok(v) => Ok(Some(v)),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(VarError::NotUnicode(_)) => Err(EnvError::NotUnicode(name)),
+        }
+    }
+
+    // the full URL of the web service
+    pub fn endpoint(mut self, endpoint: &str) -> SVUEClient {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    // the district's portal address, with the service path worked out by `district_endpoint`
+    pub fn district(self, base_url: &str) -> SVUEClient {
+        self.endpoint(&district_endpoint(base_url))
+    }
+
+    pub fn endpoint_url(&self) -> &str {
+        &self.endpoint
+    }
+
+    // a parent's account is used with `Account::Parent`; clones share everything else, so
+    // `client.for_child(..)` per child reuses one login's connections and failure count
+    pub fn account(mut self, account: Account) -> SVUEClient {
+        self.account = account;
+        self
+    }
+
+    pub fn for_child(&self, student: &Student) -> SVUEClient {
+        self.clone().account(Account::child(&student.child_int_id))
+    }
+
+    pub fn account_kind(&self) -> &Account {
+        &self.account
+    }
+
+    pub fn username(&self) -> &str {
+        self.credentials.username()
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    // the gradebook for the current reporting period
+    pub fn gradebook(&self) -> Result<Gradebook, SVUERequestError> {
+        Gradebook::retrieve_with(self, None)
+    }
+
+    // `period` is the index of a reporting period, as in `ReportPeriod::index`
+    pub fn gradebook_for_period(&self, period: i8) -> Result<Gradebook, SVUERequestError> {
+        Gradebook::retrieve_with(self, Some(period))
+    }
+
+    // see `Gradebook::retrieve_since`
+    pub fn gradebook_since(&self, previous: &Gradebook) -> Result<FetchedGradebook, SVUERequestError> {
+        Gradebook::retrieve_since(self, None, previous)
+    }
+
+    pub fn attendance(&self) -> Result<Attendance, SVUERequestError> {
+        Attendance::retrieve_with(self)
+    }
+
+    // the current term's classes
+    pub fn schedule(&self) -> Result<Schedule, SVUERequestError> {
+        Schedule::retrieve_with(self, None)
+    }
+
+    pub fn messages(&self) -> Result<Messages, SVUERequestError> {
+        Messages::retrieve_with(self)
+    }
+
+    pub fn school_info(&self) -> Result<SchoolInfo, SVUERequestError> {
+        SchoolInfo::retrieve_with(self)
+    }
+
+    pub fn students(&self) -> Result<Vec<Student>, SVUERequestError> {
+        Student::retrieve_with(self)
+    }
+
+    pub fn perform(&self, action: SVUEAPIAction) -> Result<SVUEResponse, SVUERequestError> {
+        let started = Instant::now();
+        let name = action.as_str().to_string();
+        let period = action.report_period();
+
+        self.check_auth_failures(&name, period, started)?;
+
+        let request = self.request(action);
+        let result = match self.transport {
+            Some(ref transport) => request.send_with(&**transport),
+            None => self.http().and_then(|http| request.send_with(&http)),
+        };
+
+        self.finish(&name, period, started, result)
+    }
+
+    // the request `perform` sends for `action`
+    pub(crate) fn request<'a>(&'a self, action: SVUEAPIAction) -> SVUERequest<'a> {
+        SVUERequest::new(action, &self.credentials)
+            .account(self.account.clone())
+            .endpoint(&self.endpoint)
+    }
+
+    // holds the request back once `max_auth_failures` logins in a row have been rejected
+    pub(crate) fn check_auth_failures(&self, action: &str, period: Option<i8>, started: Instant)
+        -> Result<(), SVUERequestError> {
+
+        let failures = self.auth_failures();
+        match self.max_auth_failures {
+            Some(max) if failures >= max => {
+                let result = Err(SVUERequestError::LikelyBadCredentials(failures));
+                self.audit(action, period, started, &result, false);
+                result.map(|_: SVUEResponse| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // counts a sent request towards (or resets) the auth failures, and audits it
+    pub(crate) fn finish(&self, action: &str, period: Option<i8>, started: Instant,
+                         result: Result<SVUEResponse, SVUERequestError>) -> Result<SVUEResponse, SVUERequestError> {
+        match result {
+            Err(SVUERequestError::SVUEError(ref e)) if e.kind() == SVUEErrorKind::InvalidCredentials => {
+                self.auth_failures.fetch_add(1, Ordering::SeqCst);
+            }
+            // anything else (including network errors) says nothing about the password
+            Err(_) => {}
+            Ok(_) => self.reset_auth_failures(),
+        }
+
+        self.audit(action, period, started, &result, true);
+        result
+    }
+
+    #[cfg(feature="serde-serialize")]
+    fn audit(&self, action: &str, period: Option<i8>, started: Instant, result: &Result<SVUEResponse, SVUERequestError>,
+             sent: bool) {
+        let log = match self.audit {
+            Some(ref log) => log,
+            None => { return; }
+        };
+
+        let elapsed = started.elapsed();
+        let entry = AuditEntry {
+            at: Utc::now(),
+            action: action.to_string(),
+            report_period: period,
+            endpoint: self.endpoint.clone(),
+            outcome: match *result {
+                Ok(_) => "ok",
+                Err(_) if !sent => "blocked",
+                Err(_) => "error",
+            }.to_string(),
+            error: result.as_ref().err().map(audit::describe_error),
+            response_hash: result.as_ref().ok().map(|r| audit::response_hash(&r.xml)),
+            response_bytes: result.as_ref().ok().map(|r| r.xml.len()),
+            duration_ms: elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64,
+        };
+
+        // the request has already been made either way; failing it now over the log would only
+        // make the caller retry it
+        let _ = log.record(&entry);
+    }
+
+    #[cfg(not(feature="serde-serialize"))]
+    fn audit(&self, _: &str, _: Option<i8>, _: Instant, _: &Result<SVUEResponse, SVUERequestError>, _: bool) {}
+}
+
+#[cfg(feature="client")]
+impl fmt::Debug for SVUEClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SVUEClient")
+            .field("auth_failures", &self.auth_failures())
+            .field("credentials", &self.credentials)
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
+
+// what an RT_ERROR is about, going by its message; the wording varies a little between districts
+// and versions, so anything unrecognized is kept as is
+#[derive(Clone, Debug, PartialEq)]
+pub enum SVUEErrorKind {
+    AccountDisabled,
+    InvalidCredentials,
+    ModuleNotEnabled,
+    NoDataForPeriod,
+    Other(String),
+    // the district wants a new password (it expired, or was reset by an admin) before it'll
+    // return anything else
+    PasswordChangeRequired,
+}
+
+impl SVUEErrorKind {
+    pub fn classify(message: &str) -> SVUEErrorKind {
+        let m = message.to_lowercase();
+
+        if m.contains("password") && (m.contains("expired") || m.contains("must be changed") || m.contains("must change")
+                                      || m.contains("change your password")) {
+            SVUEErrorKind::PasswordChangeRequired
+        } else if m.contains("invalid user id or password") || m.contains("password is incorrect") {
+            SVUEErrorKind::InvalidCredentials
+        } else if m.contains("module") && (m.contains("not enabled") || m.contains("not available")
+                                           || m.contains("disabled")) {
+            // ahead of the account check, since a module can be "disabled" too
+            SVUEErrorKind::ModuleNotEnabled
+        } else if m.contains("disabled") || m.contains("locked") {
+            SVUEErrorKind::AccountDisabled
+        } else if m.contains("no data") || m.contains("no grades") {
+            SVUEErrorKind::NoDataForPeriod
+        } else {
+            SVUEErrorKind::Other(message.to_string())
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct DecodedSVUEError {
+    // the method that was called, e.g. "Gradebook"
+    action: String,
     error_message: String,
     stack_trace: String,
 }
 
 impl DecodedSVUEError {
-    fn decode(xml: String) -> DecoderResult<DecodedSVUEError> {
+    fn decode(xml: String, action: &str) -> DecoderResult<DecodedSVUEError> {
         let mut error = None;
         let mut stack_trace = None;
 
@@ -51,19 +535,13 @@ impl DecodedSVUEError {
                     Ok(e) => {
                         match e {
                             ReaderEvent::StartElement { name, attributes, .. } => {
-                                match name.local_name.as_str() {
-                                    "RT_ERROR" => {
-                                        let attrs = attributes_vec_to_map(&attributes);
-                                        error = Some(get_attr_owned!(attrs, "ERROR_MESSAGE"));
-                                    }
-                                    _ => {}
+                                if name.local_name.as_str() == "RT_ERROR" {
+                                    let attrs = attributes_vec_to_map(&attributes);
+                                    error = Some(get_attr_owned!(attrs, "ERROR_MESSAGE"));
                                 }
                             }
                             ReaderEvent::EndElement { name, .. } => {
-                                match name.local_name.as_str() {
-                                    "RT_ERROR" => { break; }
-                                    _ => {}
-                                }
+                                if name.local_name.as_str() == "RT_ERROR" { break; }
                             }
                             ReaderEvent::Characters(cs) => { stack_trace = Some(cs); }
                             ReaderEvent::StartDocument { .. } => {}
@@ -79,6 +557,7 @@ impl DecodedSVUEError {
         match (error, stack_trace) {
             (Some(e), Some(st)) => {
                 Ok(DecodedSVUEError {
+                    action: action.to_string(),
                     error_message: e,
                     stack_trace: st,
                 })
@@ -86,18 +565,56 @@ impl DecodedSVUEError {
             (None, _) | (_, None) => Err(DecodingError::SVUEErrorParsingFailed(xml))
         }
     }
+
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    // the server's message, fit to show to users as is
+    pub fn message(&self) -> &str {
+        &self.error_message
+    }
+
+    // the server-side trace that comes with every error; mostly only useful when reporting a bug
+    pub fn stack_trace(&self) -> &str {
+        &self.stack_trace
+    }
+
+    pub fn kind(&self) -> SVUEErrorKind {
+        SVUEErrorKind::classify(&self.error_message)
+    }
+}
+
+impl fmt::Display for DecodedSVUEError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.action, self.error_message)
+    }
 }
 
 #[derive(Debug)]
 pub enum SVUERequestError {
-    DecodingError(DecodingError),
+    BodyBuildError(WriterError),
+    #[cfg(feature="client")]
+    ClientBuildError(reqwest::Error),
+    DecodingError(Box<DecodingError>),
     ExpectedTagNotFound(String),
+    // the named parameter isn't in the form the API takes, so the request wasn't built
+    InvalidParameter(&'static str),
+    // this many logins in a row were rejected, so the request wasn't sent
+    LikelyBadCredentials(usize),
+    // the district's message, or the URL of the change-password page the request was redirected
+    // to; retrying won't help until the password is changed in the web portal
+    PasswordChangeRequired(String),
     RawDecodingError(ReaderError),
+    #[cfg(feature="client")]
     ReqwestError(reqwest::Error),
+    // from a `transport::Transport` other than rvue's own
+    #[cfg(feature="client")]
+    TransportError(Box<dyn std::error::Error + Send + Sync>),
     ResponseBodyNotFound,
     ResponseReadError(io::Error),
     SVUEError(DecodedSVUEError),
-    SVUEErrorParsingFailed(DecodingError),
+    SVUEErrorParsingFailed(Box<DecodingError>),
 }
 
 pub struct SVUEResponse {
@@ -106,21 +623,40 @@ pub struct SVUEResponse {
 }
 
 impl SVUEResponse {
-    fn new_from_raw<'a>(raw: &'a str, expect: &'a str, action: SVUEAPIAction) -> Result<SVUEResponse, SVUERequestError> {
-        let xml = Self::decode_raw(raw, expect)?;
+    // for when the request was sent by something other than rvue (e.g. `fetch` in a browser):
+    // `raw` is the full SOAP response body
+    pub fn from_soap(raw: &str, action: SVUEAPIAction) -> Result<SVUEResponse, SVUERequestError> {
+        Self::from_reader(raw.as_bytes(), action)
+    }
+
+    // the envelope is parsed straight off `raw` (e.g. the HTTP response) without buffering it; only
+    // the embedded document is kept
+    pub fn from_reader<R: Read>(raw: R, action: SVUEAPIAction) -> Result<SVUEResponse, SVUERequestError> {
+        let xml = Self::decode_raw(raw, &action)?;
 
         Ok(SVUEResponse {
             req_action: action,
-            xml: xml,
+            xml,
         })
     }
 
-    fn decode_raw<'a>(raw: &'a str, expect: &'a str) -> Result<String, SVUERequestError> {
-        let reader = EventReader::new(raw.as_bytes());
+    #[cfg(feature="client")]
+    pub(crate) fn from_transport(resp: TransportResponse, action: SVUEAPIAction) -> Result<SVUEResponse, SVUERequestError> {
+        // some districts answer a forced reset by redirecting to the portal's change-password page
+        // instead of with an RT_ERROR, which would otherwise fail to decode as a SOAP envelope
+        if resp.url.to_lowercase().contains("changepassword") {
+            return Err(SVUERequestError::PasswordChangeRequired(resp.url));
+        }
+
+        Self::from_reader(resp.body, action)
+    }
+
+    fn decode_raw<R: Read>(raw: R, action: &SVUEAPIAction) -> Result<String, SVUERequestError> {
+        let reader = EventReader::new(raw);
 
         for e in reader {
             match e {
-                Ok(ReaderEvent::Characters(cs)) => { return Self::get_expected_xml(cs, expect); }
+                Ok(ReaderEvent::Characters(cs)) => { return Self::get_expected_xml(cs, action); }
                 Ok(_) => {}
                 Err(e) => { return Err(SVUERequestError::RawDecodingError(e)); }
             }
@@ -129,7 +665,10 @@ impl SVUEResponse {
         Err(SVUERequestError::ResponseBodyNotFound)
     }
 
-    fn get_expected_xml<'a>(xml: String, expect: &'a str) -> Result<String, SVUERequestError> {
+    // only looks as far as the root element; the document itself is decoded once, later, by
+    // whoever asked for it (e.g. `Gradebook::from_response`)
+    fn get_expected_xml(xml: String, action: &SVUEAPIAction) -> Result<String, SVUERequestError> {
+        let expect = action.expected_root();
         let mut found = false;
         let mut error = false;
 
@@ -140,10 +679,11 @@ impl SVUEResponse {
                 match e {
                     Ok(ReaderEvent::StartElement { ref name, .. }) => {
                         match name.local_name.as_str() {
-                            x if expect == x => { found = true; break; }
-                            "RT_ERROR" => { error = true; break; }
+                            x if expect == x => { found = true; }
+                            "RT_ERROR" => { error = true; }
                             _ => {}
                         }
+                        break;
                     }
                     Ok(_) => {}
                     Err(e) => { return Err(SVUERequestError::RawDecodingError(e)); }
@@ -155,10 +695,13 @@ impl SVUEResponse {
             Ok(xml)
         } else {
             if error {
-                let err = DecodedSVUEError::decode(xml)
-                    .map_err(|e| SVUERequestError::SVUEErrorParsingFailed(e))?;
+                let err = DecodedSVUEError::decode(xml, action.as_str())
+                    .map_err(|e| SVUERequestError::SVUEErrorParsingFailed(Box::new(e)))?;
 
-                Err(SVUERequestError::SVUEError(err))
+                match err.kind() {
+                    SVUEErrorKind::PasswordChangeRequired => Err(SVUERequestError::PasswordChangeRequired(err.error_message)),
+                    _ => Err(SVUERequestError::SVUEError(err)),
+                }
             } else {
                 Err(SVUERequestError::ExpectedTagNotFound(expect.to_string()))
             }
@@ -175,38 +718,97 @@ macro_rules! write_element {
 }
 
 impl<'a> SVUERequest<'a> {
-    pub fn perform(action: SVUEAPIAction, creds: (&'a str, &'a str)) -> Result<SVUEResponse, SVUERequestError> {
-        let req = SVUERequest {
-            action: action,
+    pub fn new(action: SVUEAPIAction, creds: &'a Credentials) -> SVUERequest<'a> {
+        SVUERequest {
+            account: Account::Student,
+            action,
             credentials: creds,
-        };
+            endpoint: SVUE_ENDPOINT,
+        }
+    }
+
+    pub fn account(mut self, account: Account) -> SVUERequest<'a> {
+        self.account = account;
+        self
+    }
 
-        req.run()
+    pub fn endpoint(mut self, endpoint: &'a str) -> SVUERequest<'a> {
+        self.endpoint = endpoint;
+        self
     }
 
-    fn run(&self) -> Result<SVUEResponse, SVUERequestError> {
-        let body = self.build_body().unwrap();
-        let client = reqwest::Client::new().unwrap();
+    pub fn action(&self) -> &SVUEAPIAction {
+        &self.action
+    }
 
-        let mut headers = Headers::new();
-        headers.set(ContentType("text/xml; charset=utf-8".parse().unwrap()));
-        headers.set_raw("SOAPAction", vec![SOAP_ACTION.to_vec()]);
+    pub fn endpoint_url(&self) -> &str {
+        self.endpoint
+    }
 
-        let mut buffer = String::new();
-        client.post(SVUE_ENDPOINT)
-            .headers(headers)
-            .body(body)
-            .send()
-            .map_err(|e| SVUERequestError::ReqwestError(e))
-            .map(|mut r| {
-                r.read_to_string(&mut buffer)
-                    .map(|_| SVUEResponse::new_from_raw(&buffer, self.action.as_str(), self.action.clone()))
-                    .map_err(|e| SVUERequestError::ResponseReadError(e))?
-            })?
+    #[cfg(feature="client")]
+    pub fn perform(action: SVUEAPIAction, creds: &'a Credentials) -> Result<SVUEResponse, SVUERequestError> {
+        Self::new(action, creds).run()
     }
 
-    fn build_body(&self) -> XmlResult<Vec<u8>> {
-        let mut buffer = Vec::new();
+    #[cfg(feature="client")]
+    pub fn run(&self) -> Result<SVUEResponse, SVUERequestError> {
+        let client = reqwest::blocking::Client::builder().build().map_err(SVUERequestError::ClientBuildError)?;
+        self.run_with(&client)
+    }
+
+    #[cfg(feature="client")]
+    pub fn run_with(&self, client: &reqwest::blocking::Client) -> Result<SVUEResponse, SVUERequestError> {
+        self.send_with(client)
+    }
+
+    #[cfg(feature="client")]
+    pub fn send_with<T: Transport + ?Sized>(&self, transport: &T) -> Result<SVUEResponse, SVUERequestError> {
+        let mut body = self.build_body()?;
+        // moved rather than copied, but the transport gets a plain `Vec` that it frees without
+        // zeroing (reqwest does), so the password in it is left in freed memory; wiping it isn't
+        // something rvue can do once it's handed over
+        let resp = transport.post_soap(self.endpoint, mem::take(&mut *body))?;
+
+        SVUEResponse::from_transport(resp, self.action.clone())
+    }
+
+    // the SOAP envelope to POST to the endpoint, with `SOAPAction` set to `SOAP_ACTION` and a
+    // `text/xml` content type; the response can then be handed to `SVUEResponse::from_soap`
+    // the body holds the password, so it's zeroed when dropped; `send_with` can't keep that up
+    // past the transport
+    pub fn build_body(&self) -> Result<Zeroizing<Vec<u8>>, SVUERequestError> {
+        self.validate()?;
+        self.write_body().map_err(SVUERequestError::BodyBuildError)
+    }
+
+    // the parameters go into the body unescaped (see `write_body`), so anything that isn't the
+    // plain number the API expects is turned away rather than sent
+    fn validate(&self) -> Result<(), SVUERequestError> {
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+        match self.action {
+            SVUEAPIAction::LookupDistricts(ref zip, ref key) => {
+                if zip.len() != 5 || !digits(zip) {
+                    return Err(SVUERequestError::InvalidParameter("MatchToDistrictZipCode"));
+                }
+                if key.is_empty() || !key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                    return Err(SVUERequestError::InvalidParameter("Key"));
+                }
+            }
+            _ => {
+                if !digits(self.account.child_int_id()) {
+                    return Err(SVUERequestError::InvalidParameter("ChildIntID"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_body(&self) -> XmlResult<Zeroizing<Vec<u8>>> {
+        // enough that the buffer isn't reallocated, which would leave a copy of the password
+        // behind in the old allocation
+        let mut buffer = Zeroizing::new(Vec::with_capacity(BODY_CAPACITY));
 
         {
             let mut c = EmitterConfig::new()
@@ -217,8 +819,10 @@ impl<'a> SVUERequest<'a> {
             // includes just `"` and `<`. This is a problem since the StudentVUE API requires
             // `paramStr` to be attribute-escaped.
             // https://github.com/netvl/xml-rs/blob/master/src/escape.rs#L110
+            // Everything else is escaped by hand below; the login is the caller's and can hold
+            // anything.
             c.perform_escaping = false;
-            let mut w = c.create_writer(&mut buffer);
+            let mut w = c.create_writer(&mut *buffer);
 
             let root = XmlEvent::start_element("soap:Envelope")
                 .ns("xsi", "http://www.w3.org/2001/XMLSchema-instance")
@@ -230,14 +834,16 @@ impl<'a> SVUERequest<'a> {
             let req = XmlEvent::start_element("ProcessWebServiceRequest")
                 .ns("", "http://edupoint.com/webservices/");
             w.write(req)?;
-            write_element! { w; "userID" => &self.credentials.0 };
-            write_element! { w; "password" => &self.credentials.1 };
+            write_element! { w; "userID" => escape_str_pcdata(self.credentials.username()).deref() };
+            // escaping copies the password, so the copy is zeroed too
+            let password = Zeroizing::new(escape_str_pcdata(self.credentials.password()).into_owned());
+            write_element! { w; "password" => password.as_str() };
             write_element! { w; "skipLoginLog" => "1" };
-            write_element! { w; "parent" => "0" };
-            write_element! { w; "webServiceHandleName" => "PXPWebServices" };
+            write_element! { w; "parent" => if self.account.is_parent() { "1" } else { "0" } };
+            write_element! { w; "webServiceHandleName" => self.action.service_handle() };
             write_element! { w; "methodName" => self.action.as_str() };
 
-            let params = self.build_params().unwrap();
+            let params = self.build_params()?;
             write_element! { w; "paramStr" => escape_str_attribute(&params).deref() };
             w.write(XmlEvent::end_element())?;
             w.write(XmlEvent::end_element())?;
@@ -259,20 +865,205 @@ impl<'a> SVUERequest<'a> {
 
             let params = XmlEvent::start_element("Parms");
             w.write(params)?;
-            write_element! { w; "ChildIntID" => "0" };
 
             match self.action {
-                SVUEAPIAction::RetrieveGrades(idx) => {
-                    if idx.is_some() {
-                        let idx = idx.unwrap().to_string();
-                        write_element! { w; "ReportPeriod" => &idx };
-                    }
+                SVUEAPIAction::LookupDistricts(..) => {}
+                _ => { write_element! { w; "ChildIntID" => self.account.child_int_id() }; }
+            }
+
+            match self.action {
+                SVUEAPIAction::RetrieveGrades(Some(idx)) => {
+                    write_element! { w; "ReportPeriod" => &idx.to_string() };
+                }
+                SVUEAPIAction::RetrieveClassSchedule(Some(term)) => {
+                    write_element! { w; "TermIndex" => &term.to_string() };
+                }
+                SVUEAPIAction::LookupDistricts(ref zip, ref key) => {
+                    write_element! { w; "Key" => key };
+                    write_element! { w; "MatchToDistrictZipCode" => zip };
                 }
                 _ => {}
             }
             w.write(XmlEvent::end_element())?;
         }
 
-        Ok(String::from_utf8(buffer).unwrap())
+        // the emitter only ever writes the &strs it's given, so this is always valid utf-8
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element_text(xml: &[u8], name: &str) -> Option<String> {
+        let mut inside = false;
+        for e in EventReader::new(xml) {
+            match e.unwrap() {
+                ReaderEvent::StartElement { name: ref n, .. } if n.local_name == name => { inside = true; }
+                ReaderEvent::Characters(text) if inside => { return Some(text); }
+                ReaderEvent::EndElement { .. } if inside => { return Some(String::new()); }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn classifies_error_messages() {
+        let cases = [
+            ("Your password has expired.", SVUEErrorKind::PasswordChangeRequired),
+            ("Password must be changed before continuing", SVUEErrorKind::PasswordChangeRequired),
+            ("You must change your password.", SVUEErrorKind::PasswordChangeRequired),
+            ("Please change your password", SVUEErrorKind::PasswordChangeRequired),
+            ("Invalid user id or password", SVUEErrorKind::InvalidCredentials),
+            ("INVALID USER ID OR PASSWORD", SVUEErrorKind::InvalidCredentials),
+            ("The user name or password is incorrect.", SVUEErrorKind::InvalidCredentials),
+            ("This account has been disabled.", SVUEErrorKind::AccountDisabled),
+            ("Account locked after too many attempts", SVUEErrorKind::AccountDisabled),
+            ("The Gradebook module is not enabled for this school", SVUEErrorKind::ModuleNotEnabled),
+            ("Module not available", SVUEErrorKind::ModuleNotEnabled),
+            ("The Attendance module is disabled", SVUEErrorKind::ModuleNotEnabled),
+            ("No data found for the selected period", SVUEErrorKind::NoDataForPeriod),
+            ("There are no grades to display", SVUEErrorKind::NoDataForPeriod),
+            // "password" alone isn't enough to mean it has to be changed
+            ("Password is incorrect", SVUEErrorKind::InvalidCredentials),
+            // nor "module" alone
+            ("Module error", SVUEErrorKind::Other("Module error".to_string())),
+            ("Object reference not set to an instance of an object.",
+             SVUEErrorKind::Other("Object reference not set to an instance of an object.".to_string())),
+            ("", SVUEErrorKind::Other(String::new())),
+        ];
+
+        for (message, expected) in cases.iter() {
+            assert_eq!(&SVUEErrorKind::classify(message), expected, "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn classifies_a_decoded_error_by_its_message() {
+        let xml = "<RT_ERROR ERROR_MESSAGE=\"Invalid user id or password\"><STACK_TRACE>at Login()</STACK_TRACE></RT_ERROR>";
+        let error = DecodedSVUEError::decode(xml.to_string(), "Gradebook").unwrap();
+
+        assert_eq!(error.action(), "Gradebook");
+        assert_eq!(error.message(), "Invalid user id or password");
+        assert_eq!(error.stack_trace(), "at Login()");
+        assert_eq!(error.kind(), SVUEErrorKind::InvalidCredentials);
+    }
+
+    #[test]
+    fn builds_a_districts_service_url() {
+        let cases = [
+            ("https://student.district.org/", "https://student.district.org/Service/PXPCommunication.asmx"),
+            ("https://student.district.org", "https://student.district.org/Service/PXPCommunication.asmx"),
+            ("https://sis.district.org/vue", "https://sis.district.org/vue/Service/PXPCommunication.asmx"),
+            ("https://sis.district.org/vue//", "https://sis.district.org/vue/Service/PXPCommunication.asmx"),
+            ("  student.district.org  ", "https://student.district.org/Service/PXPCommunication.asmx"),
+            ("http://localhost:8080", "http://localhost:8080/Service/PXPCommunication.asmx"),
+            // the login page
+            ("https://student.district.org/PXP2_Login.aspx", "https://student.district.org/Service/PXPCommunication.asmx"),
+            ("https://sis.district.org/vue/PXP2_Login_Student.ASPX", "https://sis.district.org/vue/Service/PXPCommunication.asmx"),
+            // already the service
+            ("https://student.district.org/Service/PXPCommunication.asmx",
+             "https://student.district.org/Service/PXPCommunication.asmx"),
+            ("https://student.district.org/service/pxpcommunication.ASMX",
+             "https://student.district.org/service/pxpcommunication.ASMX"),
+            // any district, whether or not it's one Edupoint's directory knows about
+            ("vue.example.k12.ca.us", "https://vue.example.k12.ca.us/Service/PXPCommunication.asmx"),
+            ("10.0.0.5/synergy", "https://10.0.0.5/synergy/Service/PXPCommunication.asmx"),
+        ];
+
+        for &(base, expected) in cases.iter() {
+            assert_eq!(district_endpoint(base), expected, "{:?}", base);
+        }
+    }
+
+    #[cfg(feature="client")]
+    #[test]
+    fn uses_the_default_endpoint_until_given_a_district() {
+        assert_eq!(SVUEClient::new("user", "pass").endpoint_url(), SVUE_ENDPOINT);
+        assert_eq!(SVUEClient::new("user", "pass").district("vue.example.k12.ca.us").endpoint_url(),
+                   "https://vue.example.k12.ca.us/Service/PXPCommunication.asmx");
+    }
+
+    #[cfg(feature="client")]
+    struct Rejecting(Arc<AtomicUsize>);
+
+    #[cfg(feature="client")]
+    impl Transport for Rejecting {
+        fn post_soap(&self, _: &str, _: Vec<u8>) -> Result<TransportResponse, SVUERequestError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let envelope = "<soap:Envelope xmlns:soap=\"http://schemas.xmlsoap.org/soap/envelope/\"><soap:Body>\
+                <ProcessWebServiceRequestResponse xmlns=\"http://edupoint.com/webservices/\"><ProcessWebServiceRequestResult>\
+                &lt;RT_ERROR ERROR_MESSAGE=\"Invalid user id or password\"&gt;&lt;STACK_TRACE&gt;test&lt;/STACK_TRACE&gt;&lt;/RT_ERROR&gt;\
+                </ProcessWebServiceRequestResult></ProcessWebServiceRequestResponse></soap:Body></soap:Envelope>";
+
+            Ok(TransportResponse {
+                body: Box::new(envelope.as_bytes()),
+                url: "http://district.example/Service/PXPCommunication.asmx".to_string(),
+            })
+        }
+    }
+
+    #[cfg(feature="client")]
+    #[test]
+    fn keeps_sending_rejected_logins_by_default() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let client = SVUEClient::new("user", "wrong").transport(Rejecting(sent.clone()));
+
+        for _ in 0..(SUGGESTED_MAX_AUTH_FAILURES + 2) {
+            match client.perform(SVUEAPIAction::RetrieveGrades(None)) {
+                Err(SVUERequestError::SVUEError(_)) => {}
+                other => panic!("expected the invalid-password error, got {:?}", other.map(|_| ())),
+            }
+        }
+        assert_eq!(sent.load(Ordering::SeqCst), SUGGESTED_MAX_AUTH_FAILURES + 2);
+    }
+
+    #[cfg(feature="client")]
+    #[test]
+    fn holds_requests_back_once_the_breaker_trips() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let client = SVUEClient::new("user", "wrong")
+            .transport(Rejecting(sent.clone()))
+            .max_auth_failures(2);
+
+        for _ in 0..2 {
+            assert!(client.perform(SVUEAPIAction::RetrieveGrades(None)).is_err());
+        }
+        match client.perform(SVUEAPIAction::RetrieveGrades(None)) {
+            Err(SVUERequestError::LikelyBadCredentials(2)) => {}
+            other => panic!("expected the request to be held back, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn escapes_the_login_in_the_body() {
+        let creds = Credentials::new("ann&bob", "a&b<c>");
+        let body = SVUERequest::new(SVUEAPIAction::RetrieveGrades(None), &creds).build_body().unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(text.contains("<password>a&amp;b&lt;c&gt;</password>"), "{}", text);
+        assert_eq!(element_text(&body, "userID"), Some("ann&bob".to_string()));
+        assert_eq!(element_text(&body, "password"), Some("a&b<c>".to_string()));
+    }
+
+    #[test]
+    fn writes_the_body_without_reallocating() {
+        let creds = Credentials::new(&"u".repeat(64), &"&".repeat(128));
+        let body = SVUERequest::new(SVUEAPIAction::RetrieveGrades(Some(3)), &creds).build_body().unwrap();
+
+        assert_eq!(body.capacity(), BODY_CAPACITY);
+    }
+
+    #[cfg(feature="client")]
+    #[test]
+    fn debug_redacts_the_password() {
+        let client = SVUEClient::new("student", "hunter2-secret");
+        let debug = format!("{:?}", client);
+
+        assert!(debug.contains("student"));
+        assert!(!debug.contains("hunter2-secret"));
     }
 }
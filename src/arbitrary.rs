@@ -0,0 +1,402 @@
+// quickcheck generators for the gradebook model, for property tests of the diffing (e.g. that a
+// gradebook never differs from itself) here and in downstream crates. Values are random but
+// shaped like real data: dates fall within a school year, names come from small pools so
+// pairing by title and id actually finds matches, and nesting stays shallow enough to keep
+// generated gradebooks a manageable size.
+use std::cmp;
+
+use gradebook::*;
+
+use chrono::{Duration, NaiveDate};
+use quickcheck::{Arbitrary, Gen};
+
+const SUBJECTS: &[&str] = &["Algebra 2", "Biology", "Chemistry", "English 10", "Spanish 3", "US History"];
+const TYPES: &[&str] = &["Homework", "Lab", "Participation", "Quiz", "Test"];
+const MARKS: &[&str] = &["A", "A-", "B+", "B", "C", "D", "F", "N/A"];
+
+fn pick(g: &mut Gen, pool: &[&str]) -> String {
+    g.choose(pool).map(|s| s.to_string()).unwrap_or_default()
+}
+
+// quickcheck's own numbers are spread over the whole range of the type (and for floats include NaN
+// and infinity), so these draw from `low` up to `high` instead
+fn int_in(g: &mut Gen, low: i64, high: i64) -> i64 {
+    low + (u32::arbitrary(g) as i64) % (high - low)
+}
+
+fn float_in(g: &mut Gen, low: f64, high: f64) -> f64 {
+    low + (high - low) * (u32::arbitrary(g) as f64 / u32::MAX as f64)
+}
+
+fn date(g: &mut Gen) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2016, 8, 29).unwrap() + Duration::days(int_in(g, 0, 300))
+}
+
+fn id(g: &mut Gen) -> String {
+    int_in(g, 1000, 10000).to_string()
+}
+
+fn proficiency(g: &mut Gen) -> Option<f64> {
+    if bool::arbitrary(g) { Some(float_in(g, 0.0, 4.0)) } else { None }
+}
+
+// at most `max` items, and fewer when quickcheck asks for small values
+fn vec_of<T: Arbitrary>(g: &mut Gen, max: usize) -> Vec<T> {
+    let max = cmp::min(g.size(), max);
+    let len = int_in(g, 0, max as i64 + 1) as usize;
+    (0..len).map(|_| T::arbitrary(g)).collect()
+}
+
+// the first of each item with the same key. the diff pairs things up by these keys, and no real
+// gradebook has two courses (or assignments, ...) that would pair with the same thing
+fn unique_by<T, K: PartialEq, F: Fn(&T) -> K>(items: Vec<T>, key: F) -> Vec<T> {
+    let mut keys = Vec::new();
+
+    items.into_iter()
+        .filter(|i| {
+            let k = key(i);
+            if keys.contains(&k) { false } else { keys.push(k); true }
+        })
+        .collect()
+}
+
+impl Arbitrary for Gradebook {
+    fn arbitrary(g: &mut Gen) -> Gradebook {
+        Gradebook {
+            // a course whose title changed is paired by its base code, so that has to be unique too
+            courses: unique_by(vec_of(g, 8), |c: &Course| match c.title.base_code() {
+                Some(code) => (Some(code), None),
+                None => (None, Some(c.title.clone())),
+            }),
+            last_updated: None,
+            reporting_period: ReportingPeriod::arbitrary(g),
+            reporting_periods: vec_of(g, 4),
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item=Gradebook>> {
+        let gradebook = self.clone();
+
+        Box::new(self.courses.shrink().map(move |courses| Gradebook {
+            courses,
+            ..gradebook.clone()
+        }))
+    }
+}
+
+impl Arbitrary for ReportPeriod {
+    fn arbitrary(g: &mut Gen) -> ReportPeriod {
+        let start_date = date(g);
+
+        ReportPeriod {
+            end_date: start_date + Duration::weeks(9),
+            grade_period: format!("Q{}", int_in(g, 1, 5)),
+            index: int_in(g, 0, 4) as i8,
+            start_date,
+        }
+    }
+}
+
+impl Arbitrary for ReportingPeriod {
+    fn arbitrary(g: &mut Gen) -> ReportingPeriod {
+        let start_date = date(g);
+
+        ReportingPeriod {
+            end_date: start_date + Duration::weeks(9),
+            grade_period: format!("Q{}", int_in(g, 1, 5)),
+            start_date,
+        }
+    }
+}
+
+impl Arbitrary for CourseTitle {
+    fn arbitrary(g: &mut Gen) -> CourseTitle {
+        if int_in(g, 0, 10) == 0 {
+            CourseTitle::Unparseable(String::arbitrary(g))
+        } else {
+            CourseTitle::Parsed(pick(g, SUBJECTS), id(g))
+        }
+    }
+}
+
+impl Arbitrary for Course {
+    fn arbitrary(g: &mut Gen) -> Course {
+        Course {
+            highlight_percentage_cut_off_for_progress_bar: int_in(g, 0, 101) as i8,
+            marks: vec_of(g, 2),
+            period: int_in(g, 1, 8) as i8,
+            room: int_in(g, 100, 300).to_string(),
+            staff: String::arbitrary(g),
+            staff_email: String::arbitrary(g),
+            title: CourseTitle::arbitrary(g),
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item=Course>> {
+        let course = self.clone();
+
+        Box::new(self.marks.shrink().map(move |marks| Course {
+            marks,
+            ..course.clone()
+        }))
+    }
+}
+
+impl Arbitrary for Mark {
+    fn arbitrary(g: &mut Gen) -> Mark {
+        let assignments = unique_by(vec_of(g, 30), |a: &Assignment| a.gradebook_id.clone());
+
+        Mark {
+            assignments: unique_by(assignments, |a| (a.measure.clone(), a.due_date, a.points.clone())),
+            calculated_score_raw: float_in(g, 0.0, 105.0),
+            calculated_score_string: pick(g, MARKS),
+            grade_calculation_summary: vec_of(g, 5),
+            mark_name: format!("Q{}", int_in(g, 1, 5)),
+            standard_views: vec_of(g, 3),
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item=Mark>> {
+        let mark = self.clone();
+
+        Box::new(self.assignments.shrink().map(move |assignments| Mark {
+            assignments,
+            ..mark.clone()
+        }))
+    }
+}
+
+impl Arbitrary for StandardView {
+    fn arbitrary(g: &mut Gen) -> StandardView {
+        StandardView {
+            cal_value: float_in(g, 0.0, 4.0),
+            description: String::arbitrary(g),
+            mark: pick(g, MARKS),
+            proficiency: proficiency(g),
+            proficiency_max_value: 4.0,
+            standard_assignment_views: vec_of(g, 4),
+            subject: pick(g, SUBJECTS),
+            subject_id: int_in(g, 0, 100) as i8,
+        }
+    }
+}
+
+impl Arbitrary for StandardAssignmentView {
+    fn arbitrary(g: &mut Gen) -> StandardAssignmentView {
+        StandardAssignmentView {
+            _type: pick(g, TYPES),
+            assignment: String::arbitrary(g),
+            cal_value: float_in(g, 0.0, 4.0),
+            due_date: date(g),
+            gradebook_id: id(g),
+            mark: pick(g, MARKS),
+            proficiency: proficiency(g),
+            proficiency_max_value: 4.0,
+        }
+    }
+}
+
+impl Arbitrary for AssignmentGradeCalc {
+    fn arbitrary(g: &mut Gen) -> AssignmentGradeCalc {
+        let points_possible = float_in(g, 1.0, 500.0);
+
+        AssignmentGradeCalc {
+            _type: pick(g, TYPES),
+            calculated_mark: pick(g, MARKS),
+            points: float_in(g, 0.0, points_possible),
+            points_possible,
+            weight: AssignmentGradeCalcWeight::arbitrary(g),
+            weighted_pct: AssignmentGradeCalcWeight::arbitrary(g),
+        }
+    }
+}
+
+impl Arbitrary for AssignmentGradeCalcWeight {
+    fn arbitrary(g: &mut Gen) -> AssignmentGradeCalcWeight {
+        if int_in(g, 0, 10) == 0 {
+            AssignmentGradeCalcWeight::Unparseable(String::arbitrary(g))
+        } else {
+            AssignmentGradeCalcWeight::Percentage(float_in(g, 0.0, 100.0))
+        }
+    }
+}
+
+impl Arbitrary for Assignment {
+    fn arbitrary(g: &mut Gen) -> Assignment {
+        let date = date(g);
+        let due_date = date + Duration::days(int_in(g, 0, 14));
+
+        Assignment {
+            _type: pick(g, TYPES),
+            gradebook_id: id(g),
+            measure: String::arbitrary(g),
+            date,
+            due_date,
+            score: AssignmentScore::arbitrary(g),
+            score_type: "Raw Score".to_string(),
+            points: AssignmentPoints::arbitrary(g),
+            notes: String::arbitrary(g),
+            teacher_id: id(g),
+            student_id: id(g),
+            has_drop_box: bool::arbitrary(g),
+            drop_start_date: date,
+            drop_end_date: due_date,
+            standards: unique_by(vec_of(g, 2), |s: &Standard| s.description.clone()),
+            class_stats: if bool::arbitrary(g) { Some(ClassStats::arbitrary(g)) } else { None },
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item=Assignment>> {
+        let assignment = self.clone();
+
+        Box::new(self.notes.shrink().map(move |notes| Assignment {
+            notes,
+            ..assignment.clone()
+        }))
+    }
+}
+
+impl Arbitrary for ClassStats {
+    fn arbitrary(g: &mut Gen) -> ClassStats {
+        let low = float_in(g, 0.0, 60.0);
+        let high = float_in(g, 80.0, 105.0);
+
+        ClassStats {
+            average: Some(float_in(g, low, high)),
+            high: Some(high),
+            low: Some(low),
+            median: if bool::arbitrary(g) { Some(float_in(g, low, high)) } else { None },
+        }
+    }
+}
+
+impl Arbitrary for AssignmentScore {
+    fn arbitrary(g: &mut Gen) -> AssignmentScore {
+        match int_in(g, 0, 7) {
+            0 => AssignmentScore::NotDue,
+            1 => AssignmentScore::NotForGrading,
+            2 => AssignmentScore::NotGraded,
+            3 => AssignmentScore::Percentage(float_in(g, 0.0, 110.0)),
+            4 => {
+                let possible = float_in(g, 1.0, 100.0);
+                AssignmentScore::Score(Points(float_in(g, 0.0, possible)), Points(possible))
+            }
+            5 => AssignmentScore::SeeStandards,
+            _ => AssignmentScore::Unparseable(String::arbitrary(g)),
+        }
+    }
+}
+
+impl Arbitrary for AssignmentPoints {
+    fn arbitrary(g: &mut Gen) -> AssignmentPoints {
+        let possible = float_in(g, 1.0, 100.0);
+
+        match int_in(g, 0, 3) {
+            0 => AssignmentPoints::Ungraded(Points(possible)),
+            1 => AssignmentPoints::Graded(Points(float_in(g, 0.0, possible)), Points(possible)),
+            _ => AssignmentPoints::Unparseable(String::arbitrary(g)),
+        }
+    }
+}
+
+impl Arbitrary for Standard {
+    fn arbitrary(g: &mut Gen) -> Standard {
+        Standard {
+            subject: pick(g, SUBJECTS),
+            mark: pick(g, MARKS),
+            description: String::arbitrary(g),
+            proficiency: proficiency(g),
+            proficiency_max_value: 4.0,
+            standard_screen_assignments: unique_by(vec_of(g, 3), |a: &StandardScreenAssignment| a.assignment.clone()),
+        }
+    }
+}
+
+impl Arbitrary for StandardScreenAssignment {
+    fn arbitrary(g: &mut Gen) -> StandardScreenAssignment {
+        StandardScreenAssignment {
+            _type: pick(g, TYPES),
+            assignment: String::arbitrary(g),
+            due_date: date(g),
+            mark: pick(g, MARKS),
+            proficiency: proficiency(g),
+            proficiency_max_value: 4.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use diff::Changeset;
+
+    use quickcheck::QuickCheck;
+
+    // the changes don't come out in any particular order (added courses and assignments are in
+    // hash map order), so a changeset is compared as the sorted descriptions of its changes
+    fn normalized(changeset: Option<Changeset>) -> Option<Vec<String>> {
+        changeset.map(|cs| {
+            let mut courses: Vec<String> = cs.changes.iter()
+                .map(|ccs| {
+                    let mut assignments: Vec<String> = ccs.assignment_changes.iter()
+                        .flat_map(|acs| acs.iter())
+                        .map(|acs| {
+                            let mut changes: Vec<String> = acs.changes.iter().map(|c| format!("{:?}", c)).collect();
+                            changes.sort();
+                            format!("{:?} {:?} {:?}", acs.old, acs.new, changes)
+                        })
+                        .collect();
+                    assignments.sort();
+                    format!("{:?} {:?} {:?} {:?}", ccs.old, ccs.new, ccs.changes, assignments)
+                })
+                .collect();
+            courses.sort();
+            courses
+        })
+    }
+
+    #[test]
+    fn gradebook_never_differs_from_itself() {
+        fn prop(gradebook: Gradebook) -> bool {
+            Changeset::diff(&gradebook, &gradebook).is_none()
+        }
+
+        QuickCheck::new().quickcheck(prop as fn(Gradebook) -> bool);
+    }
+
+    #[test]
+    fn inverted_diff_is_the_reverse_diff() {
+        fn prop(a: Gradebook, b: Gradebook) -> bool {
+            normalized(Changeset::diff(&a, &b).map(|cs| cs.invert())) == normalized(Changeset::diff(&b, &a))
+        }
+
+        QuickCheck::new().quickcheck(prop as fn(Gradebook, Gradebook) -> bool);
+    }
+
+    // two gradebooks drawn independently hardly share a course, so also diff ones that differ in a
+    // single assignment
+    #[test]
+    fn inverted_diff_is_the_reverse_diff_for_an_edited_gradebook() {
+        fn prop(a: Gradebook, edit: Assignment) -> bool {
+            let mut b = a.clone();
+            let mark = b.courses.iter_mut()
+                .filter_map(|c| c.marks.iter_mut().max_by_key(|m| m.kind()))
+                .next();
+            if let Some(mark) = mark {
+                match mark.assignments.first_mut() {
+                    Some(first) => {
+                        *first = Assignment { gradebook_id: first.gradebook_id.clone(), ..edit };
+                    }
+                    None => mark.assignments.push(edit),
+                }
+            }
+
+            let diff = Changeset::diff(&a, &b);
+            normalized(diff.as_ref().map(|cs| cs.invert())) == normalized(Changeset::diff(&b, &a))
+                && normalized(diff.map(|cs| cs.invert().invert())) == normalized(Changeset::diff(&a, &b))
+        }
+
+        QuickCheck::new().quickcheck(prop as fn(Gradebook, Assignment) -> bool);
+    }
+}
@@ -0,0 +1,477 @@
+// A whole account in one file, to keep after the district deactivates it (e.g. once a student
+// graduates). An archive is a zip with a `manifest.json` describing it, every reporting period's
+// gradebook, the year's attendance and the portal's messages as JSON, and the XML the district sent
+// for each, so a later version of rvue can decode it again. The manifest lists the sections an
+// archive has, and readers skip the ones they don't know; attendance and messages are left out for
+// districts that don't turn those modules on.
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUEClient, SVUEErrorKind, SVUERequestError, SVUEResponse};
+use attendance::Attendance;
+#[cfg(feature="client")]
+use decoder::DecoderResult;
+use gradebook::{Gradebook, ReportPeriod};
+use messages::Messages;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use zip::write::FileOptions;
+use zip::result::ZipError;
+
+// bumped whenever the layout changes in a way older readers can't handle
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const GRADEBOOKS: &str = "gradebooks";
+const ATTENDANCE: &str = "attendance";
+const MESSAGES: &str = "messages";
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(io::Error),
+    // a file the manifest lists isn't in the archive
+    MissingEntry(String),
+    #[cfg(feature="client")]
+    Request(SVUERequestError),
+    Serialization(serde_json::Error),
+    // written by a newer version of rvue
+    UnsupportedVersion(u32),
+    ZipError(ZipError),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    // None when the district didn't list its reporting periods
+    pub period: Option<ReportPeriod>,
+    pub gradebook: String,
+    pub xml: Option<String>,
+}
+
+// a section with a single document, e.g. attendance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SectionEntry {
+    pub json: String,
+    pub xml: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub username: String,
+    pub sections: Vec<String>,
+    pub gradebooks: Vec<ManifestEntry>,
+    // missing from archives written before these sections were added
+    pub attendance: Option<SectionEntry>,
+    pub messages: Option<SectionEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ArchivedGradebook {
+    pub period: Option<ReportPeriod>,
+    pub gradebook: Gradebook,
+    // as the district sent it
+    pub xml: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ArchivedSection<T> {
+    pub data: T,
+    // as the district sent it
+    pub xml: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Archive {
+    pub created_at: DateTime<Utc>,
+    pub username: String,
+    // oldest period first
+    pub gradebooks: Vec<ArchivedGradebook>,
+    // None if the district doesn't offer it
+    pub attendance: Option<ArchivedSection<Attendance>>,
+    pub messages: Option<ArchivedSection<Messages>>,
+}
+
+#[cfg(feature="client")]
+fn fetch_gradebook(client: &SVUEClient, period: Option<i8>) -> Result<Option<(Gradebook, String)>, ArchiveError> {
+    match client.perform(SVUEAPIAction::RetrieveGrades(period)) {
+        Ok(resp) => {
+            let gradebook = Gradebook::from_response(&resp)
+                .map_err(|e| ArchiveError::Request(SVUERequestError::DecodingError(Box::new(e))))?;
+            Ok(Some((gradebook, resp.xml)))
+        }
+        // periods that haven't started (or were never graded) have nothing to keep
+        Err(SVUERequestError::SVUEError(ref e)) if e.kind() == SVUEErrorKind::NoDataForPeriod => Ok(None),
+        Err(e) => Err(ArchiveError::Request(e)),
+    }
+}
+
+// the same request `Attendance::retrieve_with` and `Messages::retrieve_with` make, keeping the XML
+#[cfg(feature="client")]
+fn fetch_section<T, F>(client: &SVUEClient, action: SVUEAPIAction, decode: F)
+    -> Result<Option<ArchivedSection<T>>, ArchiveError>
+    where F: Fn(&SVUEResponse) -> DecoderResult<T> {
+
+    match client.perform(action) {
+        Ok(resp) => {
+            let data = decode(&resp).map_err(|e| ArchiveError::Request(SVUERequestError::DecodingError(Box::new(e))))?;
+            Ok(Some(ArchivedSection {
+                data,
+                xml: Some(resp.xml),
+            }))
+        }
+        // not every district turns on every module
+        Err(SVUERequestError::SVUEError(ref e))
+            if e.kind() == SVUEErrorKind::ModuleNotEnabled || e.kind() == SVUEErrorKind::NoDataForPeriod => Ok(None),
+        Err(e) => Err(ArchiveError::Request(e)),
+    }
+}
+
+fn section_entry<T>(section: &ArchivedSection<T>, name: &str) -> SectionEntry {
+    SectionEntry {
+        json: format!("{}.json", name),
+        xml: section.xml.as_ref().map(|_| format!("{}.xml", name)),
+    }
+}
+
+fn entry_name(period: Option<&ReportPeriod>, extension: &str) -> String {
+    match period {
+        Some(p) => format!("{}/period-{}.{}", GRADEBOOKS, p.index, extension),
+        None => format!("{}/current.{}", GRADEBOOKS, extension),
+    }
+}
+
+fn read_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<String, ArchiveError> {
+    let mut file = match zip.by_name(name) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => { return Err(ArchiveError::MissingEntry(name.to_string())); }
+        Err(e) => { return Err(ArchiveError::ZipError(e)); }
+    };
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).map_err(ArchiveError::Io)?;
+    Ok(contents)
+}
+
+fn write_entry<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &str, contents: &str) -> Result<(), ArchiveError> {
+    zip.start_file(name, FileOptions::default().compression_method(CompressionMethod::Deflated)).map_err(ArchiveError::ZipError)?;
+    zip.write_all(contents.as_bytes()).map_err(ArchiveError::Io)
+}
+
+fn write_section<W: Write + Seek, T: Serialize>(zip: &mut ZipWriter<W>, entry: &SectionEntry, section: &ArchivedSection<T>)
+    -> Result<(), ArchiveError> {
+
+    let json = serde_json::to_string(&section.data).map_err(ArchiveError::Serialization)?;
+    write_entry(zip, &entry.json, &json)?;
+
+    if let (Some(name), Some(xml)) = (entry.xml.as_ref(), section.xml.as_ref()) {
+        write_entry(zip, name, xml)?;
+    }
+    Ok(())
+}
+
+fn read_section<R: Read + Seek, T: DeserializeOwned>(zip: &mut ZipArchive<R>, entry: &SectionEntry)
+    -> Result<ArchivedSection<T>, ArchiveError> {
+
+    let data = serde_json::from_str(&read_entry(zip, &entry.json)?).map_err(ArchiveError::Serialization)?;
+    let xml = match entry.xml {
+        Some(ref name) => Some(read_entry(zip, name)?),
+        None => None,
+    };
+
+    Ok(ArchivedSection {
+        data,
+        xml,
+    })
+}
+
+impl Archive {
+    // every reporting period the district lists, one request each; takes a while for a full year
+    #[cfg(feature="client")]
+    pub fn fetch(client: &SVUEClient) -> Result<Archive, ArchiveError> {
+        let created_at = Utc::now();
+        let (current, current_xml) = fetch_gradebook(client, None)?.unwrap_or_default();
+
+        let mut periods = current.reporting_periods.clone();
+        periods.sort_by_key(|p| p.index);
+        let current_index = current.reporting_period_index();
+
+        let mut gradebooks = Vec::new();
+        if periods.is_empty() {
+            gradebooks.push(ArchivedGradebook {
+                period: None,
+                gradebook: current,
+                xml: if current_xml.is_empty() { None } else { Some(current_xml) },
+            });
+        } else {
+            // the current period was fetched already
+            let mut current = Some((current, current_xml));
+
+            for period in periods {
+                let fetched = if Some(period.index) == current_index && current.is_some() {
+                    current.take()
+                } else {
+                    fetch_gradebook(client, Some(period.index))?
+                };
+
+                if let Some((gradebook, xml)) = fetched {
+                    gradebooks.push(ArchivedGradebook {
+                        period: Some(period),
+                        gradebook,
+                        xml: Some(xml),
+                    });
+                }
+            }
+        }
+
+        Ok(Archive {
+            created_at,
+            username: client.username().to_string(),
+            gradebooks,
+            attendance: fetch_section(client, SVUEAPIAction::RetrieveAttendance, Attendance::from_response)?,
+            messages: fetch_section(client, SVUEAPIAction::RetrieveMessages, Messages::from_response)?,
+        })
+    }
+
+    pub fn gradebook_for_period(&self, index: i8) -> Option<&Gradebook> {
+        self.gradebooks.iter()
+            .find(|g| g.period.as_ref().map(|p| p.index == index).unwrap_or(false))
+            .map(|g| &g.gradebook)
+    }
+
+    pub fn manifest(&self) -> Manifest {
+        let mut sections = vec![GRADEBOOKS.to_string()];
+        if self.attendance.is_some() {
+            sections.push(ATTENDANCE.to_string());
+        }
+        if self.messages.is_some() {
+            sections.push(MESSAGES.to_string());
+        }
+
+        Manifest {
+            version: ARCHIVE_VERSION,
+            created_at: self.created_at,
+            username: self.username.clone(),
+            sections,
+            gradebooks: self.gradebooks.iter().map(|g| ManifestEntry {
+                period: g.period.clone(),
+                gradebook: entry_name(g.period.as_ref(), "json"),
+                xml: g.xml.as_ref().map(|_| entry_name(g.period.as_ref(), "xml")),
+            }).collect(),
+            attendance: self.attendance.as_ref().map(|a| section_entry(a, ATTENDANCE)),
+            messages: self.messages.as_ref().map(|m| section_entry(m, MESSAGES)),
+        }
+    }
+
+    pub fn write_to<W: Write + Seek>(&self, w: W) -> Result<W, ArchiveError> {
+        let manifest = self.manifest();
+        let mut zip = ZipWriter::new(w);
+
+        let manifest_json = serde_json::to_string_pretty(&manifest).map_err(ArchiveError::Serialization)?;
+        write_entry(&mut zip, MANIFEST, &manifest_json)?;
+
+        for (entry, archived) in manifest.gradebooks.iter().zip(self.gradebooks.iter()) {
+            let json = serde_json::to_string(&archived.gradebook).map_err(ArchiveError::Serialization)?;
+            write_entry(&mut zip, &entry.gradebook, &json)?;
+
+            if let (Some(name), Some(xml)) = (entry.xml.as_ref(), archived.xml.as_ref()) {
+                write_entry(&mut zip, name, xml)?;
+            }
+        }
+        if let (Some(entry), Some(attendance)) = (manifest.attendance.as_ref(), self.attendance.as_ref()) {
+            write_section(&mut zip, entry, attendance)?;
+        }
+        if let (Some(entry), Some(messages)) = (manifest.messages.as_ref(), self.messages.as_ref()) {
+            write_section(&mut zip, entry, messages)?;
+        }
+
+        zip.finish().map_err(ArchiveError::ZipError)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        let f = File::create(path).map_err(ArchiveError::Io)?;
+        let mut w = self.write_to(BufWriter::new(f))?;
+        w.flush().map_err(ArchiveError::Io)
+    }
+
+    pub fn read_from<R: Read + Seek>(r: R) -> Result<Archive, ArchiveError> {
+        let mut zip = ZipArchive::new(r).map_err(ArchiveError::ZipError)?;
+
+        let manifest: Manifest = serde_json::from_str(&read_entry(&mut zip, MANIFEST)?)
+            .map_err(ArchiveError::Serialization)?;
+        if manifest.version > ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(manifest.version));
+        }
+
+        let mut gradebooks = Vec::new();
+        for entry in manifest.gradebooks {
+            let gradebook = serde_json::from_str(&read_entry(&mut zip, &entry.gradebook)?)
+                .map_err(ArchiveError::Serialization)?;
+            let xml = match entry.xml {
+                Some(ref name) => Some(read_entry(&mut zip, name)?),
+                None => None,
+            };
+
+            gradebooks.push(ArchivedGradebook {
+                period: entry.period,
+                gradebook,
+                xml,
+            });
+        }
+
+        let attendance = match manifest.attendance {
+            Some(ref entry) => Some(read_section(&mut zip, entry)?),
+            None => None,
+        };
+        let messages = match manifest.messages {
+            Some(ref entry) => Some(read_section(&mut zip, entry)?),
+            None => None,
+        };
+
+        Ok(Archive {
+            created_at: manifest.created_at,
+            username: manifest.username,
+            gradebooks,
+            attendance,
+            messages,
+        })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Archive, ArchiveError> {
+        let f = File::open(path).map_err(ArchiveError::Io)?;
+        Self::read_from(BufReader::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use attendance::PeriodTotal;
+    use diff::Changeset;
+
+    use chrono::TimeZone;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+    const MESSAGES_XML: &str = include_str!("../fixtures/messages.xml");
+
+    fn archive() -> Archive {
+        let gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        let attendance = Attendance {
+            kind: "Period".to_string(),
+            school_name: "Lincoln High".to_string(),
+            total_tardies: vec![PeriodTotal { number: 2, total: 3 }],
+            ..Default::default()
+        };
+
+        Archive {
+            created_at: Utc.with_ymd_and_hms(2019, 6, 14, 18, 0, 0).unwrap(),
+            username: "student".to_string(),
+            gradebooks: vec![ArchivedGradebook {
+                period: gradebook.reporting_periods.first().cloned(),
+                gradebook,
+                xml: Some(GRADEBOOK.to_string()),
+            }],
+            attendance: Some(ArchivedSection {
+                data: attendance,
+                xml: None,
+            }),
+            messages: Some(ArchivedSection {
+                data: Messages::from_xml(MESSAGES_XML).unwrap(),
+                xml: Some(MESSAGES_XML.to_string()),
+            }),
+        }
+    }
+
+    fn written(archive: &Archive) -> Vec<u8> {
+        archive.write_to(Cursor::new(Vec::new())).unwrap().into_inner()
+    }
+
+    // a zip with only `manifest`, as JSON
+    fn with_manifest(manifest: &serde_json::Value) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        write_entry(&mut zip, MANIFEST, &manifest.to_string()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn round_trips_every_section() {
+        let original = archive();
+        let read = Archive::read_from(Cursor::new(written(&original))).unwrap();
+
+        assert_eq!(read.created_at, original.created_at);
+        assert_eq!(read.username, "student");
+        assert_eq!(original.manifest().sections, vec!["gradebooks", "attendance", "messages"]);
+
+        assert_eq!(read.gradebooks.len(), 1);
+        let (gradebook, expected) = (&read.gradebooks[0], &original.gradebooks[0]);
+        assert_eq!(gradebook.period.as_ref().map(|p| p.index), Some(0));
+        assert_eq!(gradebook.xml, expected.xml);
+        assert!(Changeset::diff(&expected.gradebook, &gradebook.gradebook).is_none());
+        assert!(read.gradebook_for_period(0).is_some());
+
+        let attendance = read.attendance.unwrap();
+        assert!(attendance.xml.is_none());
+        assert_eq!(attendance.data.school_name, "Lincoln High");
+        assert_eq!(attendance.data.total_tardies, vec![PeriodTotal { number: 2, total: 3 }]);
+
+        let messages = read.messages.unwrap();
+        assert_eq!(messages.xml.as_deref(), Some(MESSAGES_XML));
+        let ids = |m: &Messages| m.messages.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&messages.data), ids(&original.messages.unwrap().data));
+    }
+
+    #[test]
+    fn leaves_out_sections_the_district_does_not_offer() {
+        let mut original = archive();
+        original.attendance = None;
+        original.messages = None;
+
+        let read = Archive::read_from(Cursor::new(written(&original))).unwrap();
+        assert_eq!(original.manifest().sections, vec!["gradebooks"]);
+        assert!(read.attendance.is_none());
+        assert!(read.messages.is_none());
+        assert_eq!(read.gradebooks.len(), 1);
+    }
+
+    #[test]
+    fn reads_a_manifest_from_before_attendance_and_messages() {
+        let mut manifest = serde_json::to_value(archive().manifest()).unwrap();
+        manifest["gradebooks"] = serde_json::json!([]);
+        manifest["sections"] = serde_json::json!(["gradebooks"]);
+        manifest.as_object_mut().unwrap().remove("attendance");
+        manifest.as_object_mut().unwrap().remove("messages");
+
+        let read = Archive::read_from(Cursor::new(with_manifest(&manifest))).unwrap();
+        assert!(read.gradebooks.is_empty());
+        assert!(read.attendance.is_none() && read.messages.is_none());
+    }
+
+    #[test]
+    fn rejects_an_archive_from_a_newer_version() {
+        let mut manifest = serde_json::to_value(archive().manifest()).unwrap();
+        manifest["version"] = serde_json::json!(ARCHIVE_VERSION + 1);
+
+        match Archive::read_from(Cursor::new(with_manifest(&manifest))) {
+            Err(ArchiveError::UnsupportedVersion(v)) => assert_eq!(v, ARCHIVE_VERSION + 1),
+            other => panic!("expected UnsupportedVersion, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reports_an_entry_the_manifest_lists_but_the_zip_lacks() {
+        let manifest = serde_json::to_value(archive().manifest()).unwrap();
+
+        match Archive::read_from(Cursor::new(with_manifest(&manifest))) {
+            Err(ArchiveError::MissingEntry(name)) => assert_eq!(name, "gradebooks/period-0.json"),
+            other => panic!("expected MissingEntry, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+// The `Attendance` document: every absence and tardy so far this year, each with the periods it
+// covered, and per-period totals. Districts name their reason codes however they like ("Excused",
+// "Illness", "Unexcused Tardy", ...), so the reason is kept as given and `AbsenceReason` sorts it
+// into the few kinds that matter for totals and notifications.
+use std::str::FromStr;
+
+use api::SVUEResponse;
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUEClient, SVUERequestError};
+use decoder::*;
+use diff::Pairable;
+
+use chrono::NaiveDate;
+use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum AbsenceReason {
+    Activity,
+    Excused,
+    Tardy,
+    Unexcused,
+    // kept as the district wrote it
+    Other(String),
+}
+
+impl AbsenceReason {
+    pub fn classify(reason: &str) -> AbsenceReason {
+        let r = reason.trim().to_lowercase();
+
+        // "Unexcused Tardy" is a tardy first; "Unexcused" has to be checked before "Excused"
+        if r.contains("tardy") || r.contains("late") {
+            AbsenceReason::Tardy
+        } else if r.contains("unexcused") || r.contains("truan") || r.contains("unverified") {
+            AbsenceReason::Unexcused
+        } else if r.contains("activity") || r.contains("field trip") || r.contains("school business") {
+            AbsenceReason::Activity
+        } else if r.contains("excused") || r.contains("ill") || r.contains("medical") || r.contains("doctor") {
+            AbsenceReason::Excused
+        } else {
+            AbsenceReason::Other(reason.trim().to_string())
+        }
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct AbsencePeriod {
+    pub course: String,
+    // what was recorded for the period, e.g. "Absent" or "Tardy"
+    pub name: String,
+    pub number: i8,
+    pub reason: String,
+    pub staff: String,
+    pub staff_email: String,
+}
+
+impl AbsencePeriod {
+    pub fn reason_kind(&self) -> AbsenceReason {
+        AbsenceReason::classify(&self.reason)
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct Absence {
+    pub date: NaiveDate,
+    pub note: String,
+    pub periods: Vec<AbsencePeriod>,
+    // for the whole day; empty when each period has its own
+    pub reason: String,
+}
+
+field_slice_helpers!(Absence, {
+    periods => AbsencePeriod
+});
+
+// one record per day, so that's what attendance is diffed by
+impl<'a> Pairable<'a, NaiveDate> for Absence {
+    fn unique_key(&'a self) -> &'a NaiveDate {
+        &self.date
+    }
+}
+
+impl<'a> Pairable<'a, i8> for AbsencePeriod {
+    fn unique_key(&'a self) -> &'a i8 {
+        &self.number
+    }
+}
+
+impl Absence {
+    pub fn reason_kind(&self) -> AbsenceReason {
+        AbsenceReason::classify(&self.reason)
+    }
+
+    // every period the absence covers was missed for the same kind of reason, which is the case
+    // for most all-day absences
+    pub fn is_all(&self, kind: &AbsenceReason) -> bool {
+        if self.periods.is_empty() {
+            self.reason_kind() == *kind
+        } else {
+            self.periods.iter().all(|p| p.reason_kind() == *kind)
+        }
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodTotal {
+    pub number: i8,
+    pub total: u32,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default)]
+pub struct Attendance {
+    pub absences: Vec<Absence>,
+    // "Period" for districts that take attendance every period, "Daily" for once a day
+    pub kind: String,
+    pub period_count: Option<i8>,
+    pub school_name: String,
+    pub total_activities: Vec<PeriodTotal>,
+    pub total_excused: Vec<PeriodTotal>,
+    pub total_tardies: Vec<PeriodTotal>,
+    pub total_unexcused: Vec<PeriodTotal>,
+    pub total_unexcused_tardies: Vec<PeriodTotal>,
+}
+
+field_slice_helpers!(Attendance, {
+    absences => Absence
+});
+
+fn total_for(totals: &[PeriodTotal], period: i8) -> u32 {
+    totals.iter().filter(|t| t.number == period).map(|t| t.total).sum()
+}
+
+impl Attendance {
+    #[cfg(feature="client")]
+    pub fn retrieve_with(client: &SVUEClient) -> Result<Attendance, SVUERequestError> {
+        let resp = client.perform(SVUEAPIAction::RetrieveAttendance)?;
+
+        Self::from_response(&resp).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<Attendance> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<Attendance> {
+        Self::from_xml_with(xml, &DecodeLimits::new())
+    }
+
+    pub fn from_xml_with(xml: &str, limits: &DecodeLimits) -> DecoderResult<Attendance> {
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+        limits.reset();
+
+        match events_iter.next() {
+            Some(Ok(event)) => Self::from_event(event, &mut events_iter, limits),
+            Some(Err(e)) => Err(DecodingError::EventError(e)),
+            None => Err(DecodingError::UnexpectedEnd),
+        }
+    }
+
+    // inclusive of both ends
+    pub fn absences_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<&Absence> {
+        self.absences.iter().filter(|a| a.date >= from && a.date <= to).collect()
+    }
+
+    pub fn excused_in(&self, period: i8) -> u32 {
+        total_for(&self.total_excused, period)
+    }
+
+    pub fn unexcused_in(&self, period: i8) -> u32 {
+        total_for(&self.total_unexcused, period)
+    }
+
+    pub fn tardies_in(&self, period: i8) -> u32 {
+        total_for(&self.total_tardies, period)
+    }
+}
+
+// the totals are lists of `<PeriodTotal Number=".." Total=".."/>` inside an element named for what
+// they count
+fn period_totals(end: &str, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Vec<PeriodTotal>> {
+    let mut totals = Vec::new();
+
+    loop {
+        match events_iter.next() {
+            Some(Ok(event)) => {
+                match event {
+                    _ if is_start_of(&event, "PeriodTotal") => {
+                        totals.push(PeriodTotal::from_event(event, events_iter, limits)?);
+                    }
+                    _ if is_end_of(&event, end) => { return Ok(totals); }
+                    ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
+                    _ => { return Err(DecodingError::UnexpectedEvent(event)); }
+                }
+            }
+            Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+            None => { return Err(DecodingError::UnexpectedEnd); }
+        }
+    }
+}
+
+impl SVUEDecodeable for Attendance {
+    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Attendance> {
+        let mut attendance: Attendance = Default::default();
+
+        loop {
+            match events_iter.next() {
+                Some(Ok(event)) => {
+                    match event {
+                        ReaderEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "Attendance" => {
+                            let attrs = attributes_vec_to_map(attributes);
+
+                            attendance.kind = attrs.get("Type").map(|t| t.to_string()).unwrap_or_default();
+                            attendance.period_count = attrs.get("PeriodCount").and_then(|c| i8::from_str(c).ok());
+                            attendance.school_name = attrs.get("SchoolName").map(|s| s.to_string()).unwrap_or_default();
+                        }
+                        _ if is_start_of(&event, "Absence") => {
+                            attendance.absences.push(Absence::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_start_of(&event, "TotalActivities") => {
+                            attendance.total_activities = period_totals("TotalActivities", events_iter, limits)?;
+                        }
+                        _ if is_start_of(&event, "TotalExcused") => {
+                            attendance.total_excused = period_totals("TotalExcused", events_iter, limits)?;
+                        }
+                        _ if is_start_of(&event, "TotalTardies") => {
+                            attendance.total_tardies = period_totals("TotalTardies", events_iter, limits)?;
+                        }
+                        _ if is_start_of(&event, "TotalUnexcused") => {
+                            attendance.total_unexcused = period_totals("TotalUnexcused", events_iter, limits)?;
+                        }
+                        _ if is_start_of(&event, "TotalUnexcusedTardies") => {
+                            attendance.total_unexcused_tardies =
+                                period_totals("TotalUnexcusedTardies", events_iter, limits)?;
+                        }
+                        _ if is_end_of(&event, "Attendance") => {
+                            return Ok(attendance);
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                None => { return Err(DecodingError::UnexpectedEnd); }
+            }
+        }
+    }
+}
+
+impl SVUEDecodeable for Absence {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Absence> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "Absence" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        let date = parse_date!(attrs, "AbsenceDate");
+                        let note = limits.text(attrs.get("Note").copied().unwrap_or(""));
+                        let reason = attrs.get("Reason").map(|r| r.to_string()).unwrap_or_default();
+                        let mut periods = Vec::new();
+
+                        loop {
+                            match events_iter.next() {
+                                Some(Ok(event)) => {
+                                    match event {
+                                        _ if is_start_of(&event, "Period") => {
+                                            periods.push(AbsencePeriod::from_event(event, events_iter, limits)?);
+                                        }
+                                        _ if is_start_of(&event, "Periods") => {}
+                                        _ if is_end_of(&event, "Absence") => {
+                                            break;
+                                        }
+                                        ReaderEvent::StartElement { .. } => { skip_element(events_iter)?; }
+                                        ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
+                                        _ => { return Err(DecodingError::UnexpectedEvent(event)); }
+                                    }
+                                }
+                                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                                None => { return Err(DecodingError::UnexpectedEnd); }
+                            }
+                        }
+
+                        Ok(Absence {
+                            date,
+                            note,
+                            periods,
+                            reason,
+                        })
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+impl SVUEDecodeable for AbsencePeriod {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<AbsencePeriod> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "Period" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+                        let optional = |a: &str| attrs.get(a).map(|v| v.to_string()).unwrap_or_default();
+
+                        Ok(AbsencePeriod {
+                            course: optional("Course"),
+                            name: optional("Name"),
+                            number: parse_int!(i8, attrs, "Number"),
+                            reason: optional("Reason"),
+                            staff: optional("Staff"),
+                            staff_email: optional("StaffEMail"),
+                        })
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+impl SVUEDecodeable for PeriodTotal {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<PeriodTotal> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "PeriodTotal" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        Ok(PeriodTotal {
+                            number: parse_int!(i8, attrs, "Number"),
+                            total: parse_int!(u32, attrs, "Total"),
+                        })
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTENDANCE: &str = include_str!("../fixtures/attendance.xml");
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2018, month, day).unwrap()
+    }
+
+    #[test]
+    fn sorts_reasons_into_kinds() {
+        let cases = [
+            ("Unexcused Tardy", AbsenceReason::Tardy),
+            ("Late Bus", AbsenceReason::Tardy),
+            ("Unexcused", AbsenceReason::Unexcused),
+            ("Truancy", AbsenceReason::Unexcused),
+            ("Field Trip", AbsenceReason::Activity),
+            (" Excused ", AbsenceReason::Excused),
+            ("Illness", AbsenceReason::Excused),
+            ("Family Vacation ", AbsenceReason::Other("Family Vacation".to_string())),
+        ];
+
+        for &(reason, ref expected) in cases.iter() {
+            assert_eq!(AbsenceReason::classify(reason), *expected, "{:?}", reason);
+        }
+    }
+
+    #[test]
+    fn decodes_absences_with_their_periods() {
+        let attendance = Attendance::from_xml(ATTENDANCE).unwrap();
+
+        assert_eq!(attendance.kind, "Period");
+        assert_eq!(attendance.period_count, Some(7));
+        assert_eq!(attendance.school_name, "Lincoln High School");
+        assert_eq!(attendance.absences.len(), 3);
+
+        let dentist = &attendance.absences[0];
+        assert_eq!(dentist.date, date(9, 14));
+        assert_eq!(dentist.note, "Dentist appointment, back after lunch");
+        assert_eq!(dentist.periods.len(), 2);
+        assert_eq!(dentist.periods[1].course, "English 10 (EN1002)");
+        assert_eq!(dentist.periods[1].staff_email, "cokafor@example.org");
+        assert!(dentist.is_all(&AbsenceReason::Excused));
+
+        // the comments after the periods are skipped
+        let tardy = &attendance.absences[1];
+        assert_eq!(tardy.periods.len(), 1);
+        assert_eq!(tardy.periods[0].number, 3);
+        assert!(tardy.is_all(&AbsenceReason::Tardy));
+
+        // a day with no periods goes by its own reason
+        let trip = &attendance.absences[2];
+        assert!(trip.periods.is_empty());
+        assert!(trip.is_all(&AbsenceReason::Activity));
+    }
+
+    #[test]
+    fn reads_totals_per_period() {
+        let attendance = Attendance::from_xml(ATTENDANCE).unwrap();
+
+        assert_eq!(attendance.excused_in(2), 1);
+        assert_eq!(attendance.tardies_in(3), 1);
+        assert_eq!(attendance.unexcused_in(3), 0);
+        assert_eq!(attendance.excused_in(5), 0);
+        assert_eq!(attendance.total_activities.len(), 2);
+        assert_eq!(attendance.total_unexcused_tardies, vec![PeriodTotal { number: 3, total: 1 }]);
+    }
+
+    #[test]
+    fn finds_absences_between_dates_inclusively() {
+        let attendance = Attendance::from_xml(ATTENDANCE).unwrap();
+
+        let dates: Vec<NaiveDate> = attendance.absences_between(date(9, 14), date(10, 2)).iter()
+            .map(|a| a.date)
+            .collect();
+        assert_eq!(dates, vec![date(9, 14), date(10, 2)]);
+        assert!(attendance.absences_between(date(10, 3), date(10, 8)).is_empty());
+    }
+
+    #[test]
+    fn stops_at_the_element_limit() {
+        match Attendance::from_xml_with(ATTENDANCE, &DecodeLimits::new().max_elements(4)) {
+            Err(DecodingError::ElementLimitExceeded(4)) => {}
+            other => panic!("{:?}", other),
+        }
+        assert!(Attendance::from_xml("<Attendance Type=\"Period\"><Absences>").is_err());
+    }
+}
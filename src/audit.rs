@@ -0,0 +1,228 @@
+// An append-only record of every request an `SVUEClient` makes, one JSON object per line, so
+// everyone sharing a watcher can see what was fetched on their behalf and when. Entries say which
+// action was requested and how it went, plus a hash of the response to tell fetches apart; they
+// never include the username, password or the response itself.
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use api::SVUERequestError;
+use dedup::fnv1a;
+
+use chrono::{DateTime, Utc};
+use serde_json;
+
+#[derive(Debug)]
+pub enum AuditError {
+    Io(io::Error),
+    Serialization(serde_json::Error),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    // the web service method, e.g. "Gradebook"
+    pub action: String,
+    pub report_period: Option<i8>,
+    pub endpoint: String,
+    // "ok", "error", or "blocked" for requests held back without being sent (see
+    // `SVUEClient::max_auth_failures`)
+    pub outcome: String,
+    // what went wrong, without the details of the response
+    pub error: Option<String>,
+    // a hash of the returned document, hex-encoded
+    pub response_hash: Option<String>,
+    pub response_bytes: Option<usize>,
+    pub duration_ms: u64,
+}
+
+impl AuditEntry {
+    pub fn is_ok(&self) -> bool {
+        self.outcome == "ok"
+    }
+}
+
+pub fn response_hash(xml: &str) -> String {
+    format!("{:016x}", fnv1a(xml))
+}
+
+// the kind of error, and the district's own message where there is one; never anything from the
+// request itself
+pub fn describe_error(error: &SVUERequestError) -> String {
+    match *error {
+        SVUERequestError::BodyBuildError(_) => "body_build_error".to_string(),
+        SVUERequestError::ClientBuildError(_) => "client_build_error".to_string(),
+        SVUERequestError::DecodingError(_) => "decoding_error".to_string(),
+        SVUERequestError::ExpectedTagNotFound(ref tag) => format!("expected_tag_not_found: {}", tag),
+        SVUERequestError::InvalidParameter(name) => format!("invalid_parameter: {}", name),
+        SVUERequestError::LikelyBadCredentials(n) => format!("likely_bad_credentials: {} failed logins", n),
+        SVUERequestError::PasswordChangeRequired(_) => "password_change_required".to_string(),
+        SVUERequestError::RawDecodingError(_) => "raw_decoding_error".to_string(),
+        SVUERequestError::ReqwestError(_) => "http_error".to_string(),
+        SVUERequestError::ResponseBodyNotFound => "response_body_not_found".to_string(),
+        SVUERequestError::ResponseReadError(_) => "response_read_error".to_string(),
+        SVUERequestError::SVUEError(ref e) => format!("svue_error: {}", e.message()),
+        SVUERequestError::SVUEErrorParsingFailed(_) => "svue_error_parsing_failed".to_string(),
+        SVUERequestError::TransportError(_) => "transport_error".to_string(),
+    }
+}
+
+pub struct AuditLog {
+    file: Mutex<File>,
+    path: PathBuf,
+}
+
+impl AuditLog {
+    // appends to the file at `path`, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<AuditLog, AuditError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())
+            .map_err(AuditError::Io)?;
+
+        Ok(AuditLog {
+            file: Mutex::new(file),
+            path: path.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> Result<(), AuditError> {
+        let mut line = serde_json::to_string(entry).map_err(AuditError::Serialization)?;
+        line.push('\n');
+
+        // one write per entry, under the lock, so entries from clones of a client never interleave
+        let mut file = match self.file.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(AuditError::Io)
+    }
+
+    // every entry in the log at `path`, oldest first
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<AuditEntry>, AuditError> {
+        let f = File::open(path).map_err(AuditError::Io)?;
+        let mut entries = Vec::new();
+
+        for line in BufReader::new(f).lines() {
+            let line = line.map_err(AuditError::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            entries.push(serde_json::from_str(&line).map_err(AuditError::Serialization)?);
+        }
+
+        Ok(entries)
+    }
+
+    // entries recorded in [from, to)
+    pub fn entries_between<P: AsRef<Path>>(path: P, from: DateTime<Utc>, to: DateTime<Utc>)
+                                           -> Result<Vec<AuditEntry>, AuditError> {
+        Ok(Self::read(path)?.into_iter().filter(|e| e.at >= from && e.at < to).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    fn scratch_file(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("rvue-audit-test-{}-{}", ::std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("audit.jsonl")
+    }
+
+    fn entry(hour: u32, outcome: &str) -> AuditEntry {
+        AuditEntry {
+            at: Utc.with_ymd_and_hms(2018, 10, 1, hour, 0, 0).unwrap(),
+            action: "Gradebook".to_string(),
+            report_period: None,
+            endpoint: "https://sis.example.org/Service/PXPCommunication.asmx".to_string(),
+            outcome: outcome.to_string(),
+            error: None,
+            response_hash: Some(response_hash("<Gradebook />")),
+            response_bytes: Some(13),
+            duration_ms: 120,
+        }
+    }
+
+    #[test]
+    fn appends_entries_across_opens() {
+        let path = scratch_file("append");
+        AuditLog::open(&path).unwrap().record(&entry(1, "ok")).unwrap();
+        {
+            let log = AuditLog::open(&path).unwrap();
+            log.record(&entry(2, "error")).unwrap();
+            log.record(&entry(3, "blocked")).unwrap();
+        }
+        // a stray blank line isn't an entry
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"\n").unwrap();
+
+        let entries = AuditLog::read(&path).unwrap();
+        assert_eq!(entries, vec![entry(1, "ok"), entry(2, "error"), entry(3, "blocked")]);
+        assert!(entries[0].is_ok() && !entries[1].is_ok());
+
+        let at = |hour| Utc.with_ymd_and_hms(2018, 10, 1, hour, 0, 0).unwrap();
+        let between = AuditLog::entries_between(&path, at(2), at(3)).unwrap();
+        assert_eq!(between, vec![entry(2, "error")]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn hashes_responses_to_a_fixed_width() {
+        assert_eq!(response_hash("<Gradebook />").len(), 16);
+        assert_eq!(response_hash("<Gradebook />"), response_hash("<Gradebook />"));
+        assert_ne!(response_hash("<Gradebook />"), response_hash("<Gradebook/>"));
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn records_each_request_without_the_credentials() {
+        use api::SVUEClient;
+        use mock::{self, MockServer};
+
+        let path = scratch_file("client");
+        let gradebook = include_str!("../fixtures/gradebook_no_standards.xml");
+        let server = MockServer::new()
+            .document("Gradebook", gradebook)
+            .rt_error("Attendance", "The Attendance module is disabled")
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let endpoint = mock::endpoint_url(&server);
+        let client = SVUEClient::new("student7", "hunter2")
+            .endpoint(&endpoint)
+            .audit_log(AuditLog::open(&path).unwrap());
+
+        client.gradebook().unwrap();
+        assert!(client.attendance().is_err());
+        server.close();
+
+        let entries = AuditLog::read(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].action.as_str(), entries[0].outcome.as_str()), ("Gradebook", "ok"));
+        assert_eq!(entries[0].response_bytes, Some(gradebook.len()));
+        assert_eq!(entries[0].endpoint, endpoint);
+        assert_eq!((entries[1].action.as_str(), entries[1].outcome.as_str()), ("Attendance", "error"));
+        assert_eq!(entries[1].error.as_deref(), Some("svue_error: The Attendance module is disabled"));
+        assert_eq!(entries[1].response_hash, None);
+
+        let raw = fs::read_to_string(&path).unwrap();
+        assert!(!raw.contains("student7") && !raw.contains("hunter2"));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+// Where "now" comes from for anything date-sensitive. The watcher, the API server, and helpers
+// like `Gradebook::current_report_period` take a clock instead of reading the system time, so
+// they can be pinned to a date (e.g. when replaying saved snapshots).
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+#[cfg(feature="tz")]
+use chrono::{Duration, LocalResult, Offset};
+#[cfg(feature="tz")]
+use chrono_tz::Tz;
+
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+
+    fn today(&self) -> NaiveDate {
+        self.now().naive_utc().date()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// always reports the same instant
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+// reports today's date as it is at the school rather than in UTC, so e.g. a watcher hosted in UTC
+// doesn't consider an assignment overdue at 5pm Pacific. A fixed offset has to be changed by hand
+// across daylight saving transitions; a time zone (`time_zone`, with the `tz` feature) follows them.
+#[derive(Clone, Copy, Debug)]
+pub struct SchoolClock<C> {
+    clock: C,
+    zone: Zone,
+}
+
+// a fixed offset from UTC, or (with the `tz` feature) a time zone, which follows daylight saving
+// time; shared by everything that takes a school's local time
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Zone {
+    Fixed(FixedOffset),
+    #[cfg(feature="tz")]
+    Named(Tz),
+}
+
+impl Zone {
+    pub(crate) fn utc() -> Zone {
+        Zone::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+
+    // `at` only matters to a time zone
+    #[cfg_attr(not(feature="tz"), allow(unused_variables))]
+    pub(crate) fn offset_at(&self, at: DateTime<Utc>) -> FixedOffset {
+        match *self {
+            Zone::Fixed(offset) => offset,
+            #[cfg(feature="tz")]
+            Zone::Named(tz) => at.with_timezone(&tz).offset().fix(),
+        }
+    }
+
+    pub(crate) fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        at.with_timezone(&self.offset_at(at)).naive_local()
+    }
+
+    // the instant it's `local` in the zone. A time skipped when the clocks go forward is read with
+    // the offset from before, so 2:30 on that night is 3:30; one that happens twice when they go
+    // back is the first of the two
+    #[cfg_attr(not(all(feature="client", feature="serde-serialize")), allow(dead_code))]
+    pub(crate) fn instant(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match *self {
+            // a fixed offset has no gaps or overlaps, so this is always a single instant
+            Zone::Fixed(offset) => offset.from_local_datetime(&local).unwrap().with_timezone(&Utc),
+            #[cfg(feature="tz")]
+            Zone::Named(tz) => {
+                match tz.from_local_datetime(&local) {
+                    LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at.with_timezone(&Utc),
+                    LocalResult::None => {
+                        let before = tz.offset_from_utc_datetime(&(local - Duration::days(1))).fix();
+                        let utc = local - Duration::seconds(before.local_minus_utc() as i64);
+                        DateTime::from_naive_utc_and_offset(utc, Utc)
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<C: Clock> SchoolClock<C> {
+    pub fn new(clock: C, offset: FixedOffset) -> SchoolClock<C> {
+        SchoolClock {
+            clock,
+            zone: Zone::Fixed(offset),
+        }
+    }
+
+    // `None` if the offset is a day or more
+    pub fn utc_offset_minutes(clock: C, minutes: i32) -> Option<SchoolClock<C>> {
+        FixedOffset::east_opt(minutes * 60).map(|offset| Self::new(clock, offset))
+    }
+
+    // an IANA name, e.g. "America/Los_Angeles"; `None` if it isn't one
+    #[cfg(feature="tz")]
+    pub fn time_zone(clock: C, name: &str) -> Option<SchoolClock<C>> {
+        name.trim().parse::<Tz>().ok().map(|tz| SchoolClock {
+            clock,
+            zone: Zone::Named(tz),
+        })
+    }
+
+    // the school's offset from UTC right now
+    pub fn offset(&self) -> FixedOffset {
+        self.zone.offset_at(self.clock.now())
+    }
+}
+
+impl<C: Clock> Clock for SchoolClock<C> {
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    fn today(&self) -> NaiveDate {
+        self.zone.local(self.clock.now()).date()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn today(&self) -> NaiveDate {
+        (**self).today()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn today(&self) -> NaiveDate {
+        (**self).today()
+    }
+}
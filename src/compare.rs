@@ -0,0 +1,254 @@
+// Side-by-side comparison of two students' gradebooks, e.g. twins or classmates in the same
+// courses. Courses and assignments are paired the way the diff pairs them between snapshots, but on
+// what's shared between students rather than per-student IDs: a course by its base code (so two
+// sections of the same course line up) and an assignment by its name and due date. The result only
+// holds course titles, assignment names, scores and dates, with the students called "first" and
+// "second", so it can be shared without saying whose gradebooks they were.
+use diff::{repair_pairs, FallbackPairable, Pairable, PairableCollection};
+use gradebook::{Assignment, Course, CourseTitle, Gradebook};
+use report::current_mark;
+
+use chrono::NaiveDate;
+
+// a value with the key it's paired on when comparing students
+struct Keyed<'a, T: 'a> {
+    key: String,
+    fallback: String,
+    value: &'a T,
+}
+
+impl<'a, 'b, T> Pairable<'b, String> for Keyed<'a, T> {
+    fn unique_key(&'b self) -> &'b String {
+        &self.key
+    }
+}
+
+impl<'a, T> FallbackPairable for Keyed<'a, T> {
+    fn fallback_eq(&self, other: &Keyed<'a, T>) -> bool {
+        !self.fallback.is_empty() && self.fallback == other.fallback
+    }
+}
+
+fn normalized(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// courses without a parseable ID fall back to their whole title
+fn course_key<'a>(course: &'a Course) -> Keyed<'a, Course> {
+    Keyed {
+        key: course.title.base_code()
+            .and_then(|c| if c.is_empty() { None } else { Some(c) })
+            .unwrap_or_else(|| normalized(&course.title.to_string())),
+        fallback: normalized(&course.title.to_string()),
+        value: course,
+    }
+}
+
+// teachers sometimes give the same assignment different due dates per section, so two assignments
+// with the same name still pair up if their dates differ
+fn assignment_key<'a>(assignment: &'a Assignment) -> Keyed<'a, Assignment> {
+    Keyed {
+        key: format!("{}|{}", normalized(&assignment.measure), assignment.due_date),
+        fallback: normalized(&assignment.measure),
+        value: assignment,
+    }
+}
+
+fn score(assignment: &Assignment) -> Option<f64> {
+    assignment.points.percentage().or_else(|| assignment.score.percentage())
+}
+
+#[derive(Clone, Debug)]
+pub struct AssignmentComparison {
+    pub measure: String,
+    pub first_due_date: NaiveDate,
+    pub second_due_date: NaiveDate,
+    // percentages, for graded assignments
+    pub first_score: Option<f64>,
+    pub second_score: Option<f64>,
+}
+
+impl AssignmentComparison {
+    // percentage points the first student scored above the second; negative if below
+    pub fn score_difference(&self) -> Option<f64> {
+        match (self.first_score, self.second_score) {
+            (Some(f), Some(s)) => Some(f - s),
+            _ => None,
+        }
+    }
+
+    // days the first student's due date is after the second's; negative if before
+    pub fn due_date_difference(&self) -> i64 {
+        self.first_due_date.signed_duration_since(self.second_due_date).num_days()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CourseComparison {
+    // as the first student's gradebook has it
+    pub course: CourseTitle,
+    pub first_grade: Option<String>,
+    pub second_grade: Option<String>,
+    pub assignments: Vec<AssignmentComparison>,
+    // assignments only one of them has, by name
+    pub only_first: Vec<String>,
+    pub only_second: Vec<String>,
+}
+
+impl CourseComparison {
+    // shared assignments where both were graded and the scores differ
+    pub fn score_differences(&self) -> Vec<&AssignmentComparison> {
+        self.assignments.iter()
+            .filter(|a| a.score_difference().map(|d| d != 0.0).unwrap_or(false))
+            .collect()
+    }
+
+    // shared assignments due on different days
+    pub fn due_date_differences(&self) -> Vec<&AssignmentComparison> {
+        self.assignments.iter().filter(|a| a.due_date_difference() != 0).collect()
+    }
+
+    // the average score difference over assignments both were graded on
+    pub fn average_score_difference(&self) -> Option<f64> {
+        let differences: Vec<f64> = self.assignments.iter().filter_map(|a| a.score_difference()).collect();
+
+        if differences.is_empty() {
+            None
+        } else {
+            Some(differences.iter().sum::<f64>() / differences.len() as f64)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    // in the first student's gradebook order
+    pub courses: Vec<CourseComparison>,
+    pub only_first: Vec<CourseTitle>,
+    pub only_second: Vec<CourseTitle>,
+}
+
+fn compare_courses(first: &Course, second: &Course) -> CourseComparison {
+    let first_assignments: Vec<Keyed<Assignment>> = current_mark(first).into_iter()
+        .flat_map(|m| m.assignments().iter())
+        .map(assignment_key)
+        .collect();
+    let second_assignments: Vec<Keyed<Assignment>> = current_mark(second).into_iter()
+        .flat_map(|m| m.assignments().iter())
+        .map(assignment_key)
+        .collect();
+
+    let mut assignments = Vec::new();
+    let mut only_first = Vec::new();
+    let mut only_second = Vec::new();
+
+    for pair in repair_pairs(first_assignments.pair_with(&second_assignments)) {
+        match pair {
+            (Some(f), Some(s)) => assignments.push(AssignmentComparison {
+                measure: f.value.measure.clone(),
+                first_due_date: f.value.due_date,
+                second_due_date: s.value.due_date,
+                first_score: score(f.value),
+                second_score: score(s.value),
+            }),
+            (Some(f), None) => only_first.push(f.value.measure.clone()),
+            (None, Some(s)) => only_second.push(s.value.measure.clone()),
+            (None, None) => {}
+        }
+    }
+
+    CourseComparison {
+        course: first.title.clone(),
+        first_grade: current_mark(first).map(|m| m.calculated_grade()),
+        second_grade: current_mark(second).map(|m| m.calculated_grade()),
+        assignments,
+        only_first,
+        only_second,
+    }
+}
+
+pub fn compare(first: &Gradebook, second: &Gradebook) -> Comparison {
+    let first_courses: Vec<Keyed<Course>> = first.courses().iter().map(course_key).collect();
+    let second_courses: Vec<Keyed<Course>> = second.courses().iter().map(course_key).collect();
+
+    let mut comparison = Comparison {
+        courses: Vec::new(),
+        only_first: Vec::new(),
+        only_second: Vec::new(),
+    };
+
+    for pair in repair_pairs(first_courses.pair_with(&second_courses)) {
+        match pair {
+            (Some(f), Some(s)) => comparison.courses.push(compare_courses(f.value, s.value)),
+            (Some(f), None) => comparison.only_first.push(f.value.title.clone()),
+            (None, Some(s)) => comparison.only_second.push(s.value.title.clone()),
+            (None, None) => {}
+        }
+    }
+
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::{AssignmentPoints, Points};
+
+    fn first() -> Gradebook {
+        Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap()
+    }
+
+    // a classmate in another English section, who isn't taking Chemistry but is taking Spanish
+    fn second() -> Gradebook {
+        let mut gradebook = first();
+        {
+            let english = &mut gradebook.courses[0];
+            english.title = CourseTitle::Parsed("English 10".to_string(), "EN1002-03".to_string());
+            english.marks[0].calculated_score_string = "B".to_string();
+
+            let assignments = &mut english.marks[0].assignments;
+            assignments[0].measure = "personal  narrative".to_string();
+            assignments[0].due_date = NaiveDate::from_ymd_opt(2018, 9, 28).unwrap();
+            assignments[0].points = AssignmentPoints::Graded(Points(40.0), Points(50.0));
+            assignments[2].measure = "Poetry Quiz".to_string();
+        }
+
+        gradebook.courses[1].title = CourseTitle::Parsed("Spanish 2".to_string(), "WL2200".to_string());
+        gradebook
+    }
+
+    fn titles(titles: &[CourseTitle]) -> Vec<String> {
+        titles.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn pairs_sections_of_the_same_course() {
+        let comparison = compare(&first(), &second());
+
+        assert_eq!(comparison.courses.len(), 1);
+        assert_eq!(comparison.courses[0].course.to_string(), "English 10 (EN1002)");
+        assert_eq!(comparison.courses[0].first_grade.as_deref(), Some("A- (91.2)"));
+        assert_eq!(comparison.courses[0].second_grade.as_deref(), Some("B (91.2)"));
+        assert_eq!(titles(&comparison.only_first), vec!["Chemistry (SC2010)"]);
+        assert_eq!(titles(&comparison.only_second), vec!["Spanish 2 (WL2200)"]);
+    }
+
+    #[test]
+    fn pairs_assignments_by_name_even_when_due_dates_differ() {
+        let comparison = compare(&first(), &second());
+        let english = &comparison.courses[0];
+
+        let measures: Vec<&str> = english.assignments.iter().map(|a| a.measure.as_str()).collect();
+        assert_eq!(measures, vec!["Personal Narrative", "Reading Log"]);
+        assert_eq!(english.only_first, vec!["Vocabulary Check"]);
+        assert_eq!(english.only_second, vec!["Poetry Quiz"]);
+
+        let narrative = &english.assignments[0];
+        assert_eq!(narrative.due_date_difference(), -1);
+        assert_eq!(english.due_date_differences().len(), 1);
+        assert_eq!(english.score_differences().len(), 1);
+        // 91.2% against 80%; the ungraded Reading Log doesn't count
+        assert!((english.average_score_difference().unwrap() - 11.2).abs() < 1e-9);
+        assert_eq!(english.assignments[1].score_difference(), None);
+    }
+}
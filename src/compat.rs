@@ -0,0 +1,82 @@
+// What changed for callers when rvue moved to chrono 0.4 and reqwest 0.12, with the old names kept
+// (deprecated) where a rename is all it takes:
+//
+// - `chrono::UTC` is `chrono::Utc`, so every `DateTime<UTC>` in rvue's signatures is now a
+//   `DateTime<Utc>`.
+// - `SVUEClient::http_client` and `SVUERequest::run_with` take reqwest's blocking client,
+//   `reqwest::blocking::Client`, which is what `reqwest::Client` was in 0.4.
+// - a 4xx or 5xx from the district is a `SVUERequestError::ReqwestError` with the status in
+//   `reqwest::Error::status`, rather than a body that fails to decode.
+//
+// An application on other versions of either crate doesn't have to match rvue's: both are
+// re-exported here for building the values rvue takes, and a `transport::Transport` sends rvue's
+// requests with whatever HTTP client the application already has.
+pub extern crate chrono;
+#[cfg(feature="client")]
+pub extern crate reqwest;
+
+#[deprecated(note="chrono 0.4 calls it `Utc`")]
+pub type UTC = chrono::Utc;
+
+#[cfg(all(test, feature="mock-server"))]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    use api::{SOAP_ACTION, SVUEClient, SVUERequestError};
+    use gradebook::Gradebook;
+    use mock::{self, MockServer};
+    use transport::{self, Transport, TransportResponse};
+
+    // HTTP/1.0 over a bare socket: nothing to do with reqwest, as a transport on some other
+    // version of it (or another HTTP stack) wouldn't be
+    struct BareSocket;
+
+    impl Transport for BareSocket {
+        fn post_soap(&self, endpoint: &str, body: Vec<u8>) -> Result<TransportResponse, SVUERequestError> {
+            let rest = endpoint.trim_start_matches("http://");
+            let (host, path) = rest.split_at(rest.find('/').unwrap());
+
+            let mut stream = TcpStream::connect(host).map_err(transport::error)?;
+            write!(stream, "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: text/xml; charset=utf-8\r\n\
+                            SOAPAction: {}\r\nContent-Length: {}\r\n\r\n",
+                   path, host, String::from_utf8_lossy(SOAP_ACTION), body.len()).map_err(transport::error)?;
+            stream.write_all(&body).map_err(transport::error)?;
+
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).map_err(transport::error)?;
+            assert!(response.starts_with(b"HTTP/1.0 200") || response.starts_with(b"HTTP/1.1 200"));
+            let start = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+
+            Ok(TransportResponse {
+                body: Box::new(Cursor::new(response.split_off(start))),
+                url: endpoint.to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn sends_requests_through_another_http_stack() {
+        const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+        let server = MockServer::new().document("Gradebook", GRADEBOOK).listen("127.0.0.1:0").unwrap();
+        let client = SVUEClient::new("user", "pass").endpoint(&mock::endpoint_url(&server)).transport(BareSocket);
+
+        let gradebook = client.gradebook().unwrap();
+        assert_eq!(gradebook.courses.len(), Gradebook::from_xml(GRADEBOOK).unwrap().courses.len());
+        server.close();
+    }
+
+    #[test]
+    fn reports_errors_from_the_transport() {
+        // a port nothing's listening on any more
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let client = SVUEClient::new("user", "pass")
+            .endpoint(&format!("http://{}/Service/PXPCommunication.asmx", addr))
+            .transport(BareSocket);
+
+        match client.gradebook() {
+            Err(SVUERequestError::TransportError(_)) => {}
+            other => panic!("expected the socket's error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
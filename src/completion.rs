@@ -0,0 +1,224 @@
+// Assignments the student has marked as done, kept locally on top of the gradebook (StudentVUE
+// has no notion of it). Entries are keyed by `gradebook_id`, so they carry over between fetches and
+// stay put when the teacher edits an assignment.
+use std::collections::HashMap;
+#[cfg(feature="serde-serialize")]
+use std::fs::{self, File};
+#[cfg(feature="serde-serialize")]
+use std::io::{BufReader, BufWriter, Write};
+#[cfg(feature="serde-serialize")]
+use std::path::Path;
+
+use clock::Clock;
+use gradebook::{Assignment, Course, Gradebook};
+use report::{current_mark, missing_assignments};
+#[cfg(feature="serde-serialize")]
+use store::StoreError;
+
+use chrono::{DateTime, Duration, Utc};
+#[cfg(feature="serde-serialize")]
+use serde_json;
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default)]
+pub struct CompletionOverlay {
+    done: HashMap<String, DateTime<Utc>>,
+}
+
+impl CompletionOverlay {
+    pub fn new() -> CompletionOverlay {
+        Default::default()
+    }
+
+    // a missing file is an empty overlay
+    #[cfg(feature="serde-serialize")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<CompletionOverlay, StoreError> {
+        if !path.as_ref().exists() {
+            return Ok(Default::default());
+        }
+
+        let f = File::open(path).map_err(StoreError::Io)?;
+        serde_json::from_reader(BufReader::new(f)).map_err(StoreError::Serialization)
+    }
+
+    #[cfg(feature="serde-serialize")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StoreError> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("json.tmp");
+
+        {
+            let f = File::create(&tmp).map_err(StoreError::Io)?;
+            let mut w = BufWriter::new(f);
+            serde_json::to_writer(&mut w, self).map_err(StoreError::Serialization)?;
+            w.flush().map_err(StoreError::Io)?;
+            w.get_ref().sync_all().map_err(StoreError::Io)?;
+        }
+
+        fs::rename(&tmp, path).map_err(StoreError::Io)
+    }
+
+    pub fn mark_done(&mut self, gradebook_id: &str, at: DateTime<Utc>) {
+        self.done.insert(gradebook_id.to_string(), at);
+    }
+
+    // returns whether it was marked
+    pub fn unmark(&mut self, gradebook_id: &str) -> bool {
+        self.done.remove(gradebook_id).is_some()
+    }
+
+    pub fn done_at(&self, gradebook_id: &str) -> Option<DateTime<Utc>> {
+        self.done.get(gradebook_id).cloned()
+    }
+
+    pub fn is_done(&self, assignment: &Assignment) -> bool {
+        self.done.contains_key(&assignment.gradebook_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    // drops entries for assignments that are no longer in `gradebook`, returning how many
+    pub fn prune(&mut self, gradebook: &Gradebook) -> usize {
+        let before = self.done.len();
+        let ids: Vec<&str> = gradebook.courses().iter()
+            .flat_map(|c| c.marks().iter())
+            .flat_map(|m| m.assignments().iter())
+            .map(|a| a.gradebook_id.as_str())
+            .collect();
+
+        self.done.retain(|id, _| ids.contains(&id.as_str()));
+        before - self.done.len()
+    }
+
+    // ungraded, not marked done, and due between today and `within` from now
+    pub fn due_within<'a, C: Clock>(&self, gradebook: &'a Gradebook, clock: &C, within: Duration)
+        -> Vec<(&'a Course, &'a Assignment)> {
+
+        self.pending(gradebook, |a| a.is_due_within(clock, within))
+    }
+
+    pub fn overdue<'a, C: Clock>(&self, gradebook: &'a Gradebook, clock: &C) -> Vec<(&'a Course, &'a Assignment)> {
+        self.pending(gradebook, |a| a.is_overdue(clock))
+    }
+
+    // `report::missing_assignments`, minus what's been marked done (e.g. turned in late but not
+    // graded yet)
+    pub fn missing<'a>(&self, gradebook: &'a Gradebook) -> Vec<(&'a Course, &'a Assignment)> {
+        missing_assignments(gradebook).into_iter()
+            .filter(|&(_, a)| !self.is_done(a))
+            .collect()
+    }
+
+    fn pending<'a, F>(&self, gradebook: &'a Gradebook, f: F) -> Vec<(&'a Course, &'a Assignment)>
+        where F: Fn(&Assignment) -> bool {
+
+        gradebook.courses().iter()
+            .flat_map(|c| {
+                current_mark(c).into_iter()
+                    .flat_map(|m| m.assignments().iter())
+                    .filter(|a| !self.is_done(a) && f(a))
+                    .map(move |a| (c, a))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::FixedClock;
+
+    use chrono::TimeZone;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2018, 10, day, 18, 0, 0).unwrap()
+    }
+
+    fn measures<'a>(found: Vec<(&Course, &'a Assignment)>) -> Vec<&'a str> {
+        found.into_iter().map(|(_, a)| a.measure.as_str()).collect()
+    }
+
+    #[test]
+    fn marks_and_unmarks_by_gradebook_id() {
+        let mut overlay = CompletionOverlay::new();
+        overlay.mark_done("2002", at(1));
+        overlay.mark_done("2002", at(3));
+
+        assert_eq!(overlay.len(), 1);
+        assert_eq!(overlay.done_at("2002"), Some(at(3)));
+        assert!(overlay.unmark("2002"));
+        assert!(!overlay.unmark("2002"));
+        assert!(overlay.is_empty());
+    }
+
+    #[test]
+    fn leaves_done_work_out_of_overdue_and_upcoming() {
+        let gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        let clock = FixedClock(at(3));
+        let mut overlay = CompletionOverlay::new();
+
+        // Vocabulary Check was due 10/02 and Reading Log is due 10/05; neither is graded
+        assert_eq!(measures(overlay.overdue(&gradebook, &clock)), vec!["Vocabulary Check"]);
+        assert_eq!(measures(overlay.due_within(&gradebook, &clock, Duration::days(2))), vec!["Reading Log"]);
+        assert!(overlay.due_within(&gradebook, &clock, Duration::days(1)).is_empty());
+
+        overlay.mark_done("2003", at(2));
+        overlay.mark_done("2002", at(2));
+        assert!(overlay.overdue(&gradebook, &clock).is_empty());
+        assert!(overlay.due_within(&gradebook, &clock, Duration::days(2)).is_empty());
+    }
+
+    #[test]
+    fn subtracts_done_work_from_missing() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses[0].marks[0].assignments[1].notes = "Missing".to_string();
+        gradebook.courses[0].marks[0].assignments[2].notes = "missing".to_string();
+        let mut overlay = CompletionOverlay::new();
+        overlay.mark_done("2002", at(4));
+
+        assert_eq!(measures(overlay.missing(&gradebook)), vec!["Vocabulary Check"]);
+    }
+
+    #[test]
+    fn prunes_assignments_that_left_the_gradebook() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        let mut overlay = CompletionOverlay::new();
+        overlay.mark_done("2001", at(1));
+        overlay.mark_done("2003", at(1));
+        overlay.mark_done("1999", at(1));
+
+        gradebook.courses[0].marks[0].assignments.truncate(2);
+
+        assert_eq!(overlay.prune(&gradebook), 2);
+        assert!(overlay.done_at("2001").is_some());
+        assert_eq!(overlay.prune(&gradebook), 0);
+    }
+
+    #[cfg(feature="serde-serialize")]
+    #[test]
+    fn saves_and_loads_the_overlay() {
+        let dir = ::std::env::temp_dir().join(format!("rvue-completion-test-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("done.json");
+
+        assert!(CompletionOverlay::load(&path).unwrap().is_empty());
+
+        let mut overlay = CompletionOverlay::new();
+        overlay.mark_done("2002", at(4));
+        overlay.save(&path).unwrap();
+
+        let loaded = CompletionOverlay::load(&path).unwrap();
+        assert_eq!(loaded.done_at("2002"), Some(at(4)));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,878 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use api::{Account, SVUEClient};
+use audit::{AuditError, AuditLog};
+use clock::{SchoolClock, SystemClock};
+use credentials::Credentials;
+use diff::{AssignmentChanges, Changeset, CourseChanges};
+use goals::Goals;
+use grading::{RoundingMode, RoundingPolicy};
+use household::Household;
+use notify::FanOut;
+use notify::discord::DiscordNotifier;
+use render::i18n::{self, Strings};
+use render::template::{self, ChangesetTemplate, TemplateError};
+use rules::{CourseMatcher, Delivery, NotificationRules};
+use turnin::{TurnInMarkers, TurnInStatus};
+
+use toml;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    AuditLog(AuditError),
+    Io(io::Error),
+    InvalidCourseRule(String),
+    InvalidRounding(String),
+    InvalidTemplate(TemplateError),
+    InvalidTemplateBase(String),
+    InvalidTurnInMarker(String),
+    InvalidUtcOffset(i32),
+    UnknownTimeZone(String),
+    MissingCredential(String),
+    ParseError(toml::de::Error),
+}
+
+fn default_poll_interval() -> u64 {
+    60 * 60
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Config {
+    // the district's portal address, e.g. "https://student.district.org/"; `endpoint` is the full
+    // web service URL instead, and wins if both are set
+    pub district_url: Option<String>,
+    pub endpoint: Option<String>,
+    // may be left out when every entry in `students` has credentials of its own
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    #[serde(default)]
+    pub notifiers: NotifiersConfig,
+    #[serde(default)]
+    pub filters: FilterConfig,
+    #[serde(default)]
+    pub course_rules: Vec<CourseRuleConfig>,
+    #[serde(default)]
+    pub goals: Vec<GoalConfig>,
+    // how the district rounds displayed grades: "exact" (the default), "half_up", "half_down",
+    // "half_even" or "truncate", to `rounding_places` decimals
+    pub rounding: Option<String>,
+    #[serde(default)]
+    pub rounding_places: u32,
+    // the school's offset from UTC, for due-date checks (e.g. -420 for Pacific daylight time);
+    // UTC if unset. It doesn't change with daylight saving time; `time_zone` does
+    pub utc_offset_minutes: Option<i32>,
+    // the school's IANA time zone, e.g. "America/Los_Angeles"; wins over `utc_offset_minutes`
+    pub time_zone: Option<String>,
+    // a file to record every request in (see `audit`); no log is kept if unset
+    pub audit_log: Option<PathBuf>,
+    // a language tag for notifications and reports, e.g. "es" or "vi"; English if unset or unknown
+    pub language: Option<String>,
+    // custom wording for notifications; see render::template for the placeholders
+    pub template: Option<TemplateConfig>,
+    // the district's own codes and phrases for late, missing, etc. work, checked before the usual
+    // ones; see `turnin`
+    #[serde(default)]
+    pub turn_in_markers: Vec<TurnInMarkerConfig>,
+    // a household's children, for watching more than one; see `household`
+    #[serde(default)]
+    pub students: Vec<StudentConfig>,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(ConfigError::Io)?;
+
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, ConfigError> {
+        toml::from_str(contents).map_err(ConfigError::ParseError)
+    }
+
+    pub fn client(&self) -> Result<SVUEClient, ConfigError> {
+        self.client_for(&self.credentials, &self.district_url, &self.endpoint)
+    }
+
+    // every entry in `students`, each using the top-level district and credentials for whatever
+    // it leaves out; without any, the one student from `client`, labeled with the username
+    pub fn household(&self) -> Result<Household, ConfigError> {
+        if self.students.is_empty() {
+            let client = self.client()?;
+            let label = client.username().to_string();
+            return Ok(Household::new().member(&label, client));
+        }
+
+        self.students.iter().try_fold(Household::new(), |household, s| {
+            let credentials = s.credentials.as_ref().unwrap_or(&self.credentials);
+            let (district_url, endpoint) = if s.district_url.is_some() || s.endpoint.is_some() {
+                (&s.district_url, &s.endpoint)
+            } else {
+                (&self.district_url, &self.endpoint)
+            };
+
+            let mut client = self.client_for(credentials, district_url, endpoint)?;
+            if let Some(ref id) = s.child_int_id {
+                client = client.account(Account::child(id));
+            }
+
+            Ok(household.member(&s.label, client))
+        })
+    }
+
+    fn client_for(&self, credentials: &CredentialsConfig, district_url: &Option<String>, endpoint: &Option<String>)
+        -> Result<SVUEClient, ConfigError> {
+
+        let mut client = SVUEClient::with_credentials(credentials.resolve()?);
+
+        if let Some(ref d) = *district_url {
+            client = client.district(d);
+        }
+        if let Some(ref e) = *endpoint {
+            client = client.endpoint(e);
+        }
+        if let Some(ref path) = self.audit_log {
+            client = client.audit_log(AuditLog::open(path).map_err(ConfigError::AuditLog)?);
+        }
+
+        Ok(client)
+    }
+
+    pub fn clock(&self) -> Result<SchoolClock<SystemClock>, ConfigError> {
+        if let Some(ref name) = self.time_zone {
+            return SchoolClock::time_zone(SystemClock, name)
+                .ok_or_else(|| ConfigError::UnknownTimeZone(name.clone()));
+        }
+
+        let minutes = self.utc_offset_minutes.unwrap_or(0);
+
+        SchoolClock::utc_offset_minutes(SystemClock, minutes).ok_or(ConfigError::InvalidUtcOffset(minutes))
+    }
+
+    pub fn strings(&self) -> Strings {
+        match self.language {
+            Some(ref l) => i18n::for_language(l),
+            None => i18n::english(),
+        }
+    }
+
+    pub fn template(&self) -> Result<ChangesetTemplate, ConfigError> {
+        match self.template {
+            Some(ref t) => t.build(),
+            None => Ok(template::text()),
+        }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+
+    pub fn rules(&self) -> Result<NotificationRules, ConfigError> {
+        self.course_rules.iter().try_fold(NotificationRules::new(), |rules, r| {
+            Ok(rules.rule(r.matcher()?, r.delivery()?))
+        })
+    }
+
+    pub fn rounding(&self) -> Result<RoundingPolicy, ConfigError> {
+        let mode = match self.rounding.as_deref() {
+            None | Some("exact") => RoundingMode::Exact,
+            Some("half_up") => RoundingMode::HalfUp,
+            Some("half_down") => RoundingMode::HalfDown,
+            Some("half_even") => RoundingMode::HalfEven,
+            Some("truncate") => RoundingMode::Truncate,
+            Some(other) => { return Err(ConfigError::InvalidRounding(other.to_string())); }
+        };
+
+        Ok(RoundingPolicy::new(mode, self.rounding_places))
+    }
+
+    pub fn goals(&self) -> Result<Goals, ConfigError> {
+        let rounding = self.rounding()?;
+
+        self.goals.iter().try_fold(Goals::new().rounding(rounding), |goals, g| {
+            Ok(goals.target(course_matcher(&g.course, g.period)?, g.target))
+        })
+    }
+
+    pub fn turn_in_markers(&self) -> Result<TurnInMarkers, ConfigError> {
+        let markers = self.turn_in_markers.iter().try_fold(TurnInMarkers::new(), |markers, m| {
+            let status = TurnInStatus::parse(&m.status)
+                .ok_or(ConfigError::InvalidTurnInMarker(format!("unknown status `{}`", m.status)))?;
+
+            match (&m.code, &m.phrase) {
+                (Some(code), None) => Ok(markers.code(code, status)),
+                (&None, Some(ref phrase)) => Ok(markers.phrase(phrase, status)),
+                _ => Err(ConfigError::InvalidTurnInMarker("exactly one of `code` and `phrase` must be set".to_string())),
+            }
+        })?;
+
+        Ok(markers.defaults())
+    }
+}
+
+fn course_matcher(course: &Option<String>, period: Option<i8>) -> Result<CourseMatcher, ConfigError> {
+    match (course, period) {
+        (Some(title), None) => Ok(CourseMatcher::Title(title.clone())),
+        (&None, Some(period)) => Ok(CourseMatcher::Period(period)),
+        _ => Err(ConfigError::InvalidCourseRule("exactly one of `course` and `period` must be set".to_string())),
+    }
+}
+
+// a target percentage for one of `course` (part of the title) or `period`
+#[derive(Clone, Debug, Deserialize)]
+pub struct GoalConfig {
+    pub course: Option<String>,
+    pub period: Option<i8>,
+    pub target: f64,
+}
+
+// one of `course` (part of the title) or `period`, and a `delivery` of "immediate", "digest" or
+// "mute"
+#[derive(Clone, Debug, Deserialize)]
+pub struct CourseRuleConfig {
+    pub course: Option<String>,
+    pub period: Option<i8>,
+    pub delivery: String,
+}
+
+impl CourseRuleConfig {
+    fn matcher(&self) -> Result<CourseMatcher, ConfigError> {
+        course_matcher(&self.course, self.period)
+    }
+
+    fn delivery(&self) -> Result<Delivery, ConfigError> {
+        match self.delivery.as_str() {
+            "immediate" => Ok(Delivery::Immediate),
+            "digest" => Ok(Delivery::DigestOnly),
+            "mute" => Ok(Delivery::Mute),
+            other => Err(ConfigError::InvalidCourseRule(format!("unknown delivery `{}`", other))),
+        }
+    }
+}
+
+// one of `code` (the whole score, e.g. "LT") or `phrase` (words in the score or notes), and the
+// `status` it means: "absent", "excused", "incomplete", "late" or "missing"
+#[derive(Clone, Debug, Deserialize)]
+pub struct TurnInMarkerConfig {
+    pub code: Option<String>,
+    pub phrase: Option<String>,
+    pub status: String,
+}
+
+// one child in a household: `label` is what they're called in notifications, and the rest, when
+// set, replaces the top-level setting of the same name; `child_int_id` picks the child when
+// `credentials` are a parent's (see `students::Student`)
+#[derive(Clone, Debug, Deserialize)]
+pub struct StudentConfig {
+    pub label: String,
+    pub district_url: Option<String>,
+    pub endpoint: Option<String>,
+    pub credentials: Option<CredentialsConfig>,
+    pub child_int_id: Option<String>,
+}
+
+// the config file only says where to find the credentials, so it can be committed or shared
+// without leaking the password
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CredentialsConfig {
+    pub username: Option<String>,
+    pub username_env: Option<String>,
+    pub password_env: Option<String>,
+    pub password_file: Option<PathBuf>,
+}
+
+impl CredentialsConfig {
+    pub fn resolve(&self) -> Result<Credentials, ConfigError> {
+        let username = match (self.username.as_ref(), self.username_env.as_ref()) {
+            (Some(u), _) => u.clone(),
+            (None, Some(var)) => env::var(var).map_err(|_| ConfigError::MissingCredential(var.clone()))?,
+            (None, None) => { return Err(ConfigError::MissingCredential("username".to_string())); }
+        };
+
+        let password = read_secret(self.password_env.as_ref(), self.password_file.as_ref(), "password")?;
+
+        Ok(Credentials::from_owned(username, password))
+    }
+}
+
+// a secret the config names the environment variable or file of (the variable winning if both
+// are set) rather than holding itself; `name` is what's reported missing if it's neither
+fn read_secret(var: Option<&String>, path: Option<&PathBuf>, name: &str) -> Result<String, ConfigError> {
+    match (var, path) {
+        (Some(var), _) => env::var(var).map_err(|_| ConfigError::MissingCredential(var.clone())),
+        (None, Some(path)) => {
+            let mut secret = String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut secret))
+                .map_err(ConfigError::Io)?;
+            // trimmed in place so no second copy of the secret is made
+            let len = secret.trim_end_matches(['\n', '\r']).len();
+            secret.truncate(len);
+            Ok(secret)
+        }
+        (None, None) => Err(ConfigError::MissingCredential(name.to_string())),
+    }
+}
+
+// each part left unset keeps the wording of `base`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TemplateConfig {
+    // "text" (the default) or "markdown"
+    pub base: Option<String>,
+    pub course: Option<String>,
+    pub change: Option<String>,
+    pub assignment: Option<String>,
+    pub subject: Option<String>,
+    pub separator: Option<String>,
+}
+
+impl TemplateConfig {
+    pub fn build(&self) -> Result<ChangesetTemplate, ConfigError> {
+        let mut t = match self.base.as_deref() {
+            None | Some("text") => template::text(),
+            Some("markdown") => template::markdown(),
+            Some(other) => { return Err(ConfigError::InvalidTemplateBase(other.to_string())); }
+        };
+
+        if let Some(ref c) = self.course {
+            t = t.course(c).map_err(ConfigError::InvalidTemplate)?;
+        }
+        if let Some(ref c) = self.change {
+            t = t.change(c).map_err(ConfigError::InvalidTemplate)?;
+        }
+        if let Some(ref a) = self.assignment {
+            t = t.assignment(a).map_err(ConfigError::InvalidTemplate)?;
+        }
+        if let Some(ref s) = self.subject {
+            t = t.subject(s).map_err(ConfigError::InvalidTemplate)?;
+        }
+        if let Some(ref s) = self.separator {
+            t = t.separator(s);
+        }
+
+        Ok(t)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NotifiersConfig {
+    pub discord: Option<DiscordConfig>,
+    pub email: Option<EmailConfig>,
+    pub pushover: Option<PushoverConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub webhook: Option<WebhookConfig>,
+}
+
+// the webhook URL, bot token and app token below are enough to post as rvue, so like the
+// password they're read from the environment variable or file named here, never the config itself
+#[derive(Clone, Debug, Deserialize)]
+pub struct DiscordConfig {
+    pub webhook_url_env: Option<String>,
+    pub webhook_url_file: Option<PathBuf>,
+    pub username: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EmailConfig {
+    pub server: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password_env: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PushoverConfig {
+    pub app_token_env: Option<String>,
+    pub app_token_file: Option<PathBuf>,
+    pub user_key: String,
+    pub device: Option<String>,
+    pub priority: Option<i8>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token_env: Option<String>,
+    pub bot_token_file: Option<PathBuf>,
+    pub chat_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret_env: Option<String>,
+    pub attempts: Option<u32>,
+}
+
+impl NotifiersConfig {
+    // notifiers whose feature isn't compiled in are skipped
+    #[allow(unused_mut)]
+    pub fn build(&self) -> Result<FanOut, ConfigError> {
+        let mut fan_out = FanOut::new();
+
+        if let Some(ref d) = self.discord {
+            let webhook_url = read_secret(d.webhook_url_env.as_ref(), d.webhook_url_file.as_ref(),
+                                          "notifiers.discord.webhook_url")?;
+            let mut n = DiscordNotifier::new(&webhook_url);
+            if let Some(ref username) = d.username {
+                n = n.username(username);
+            }
+            fan_out = fan_out.sink(n);
+        }
+
+        #[cfg(feature="email")]
+        {
+            use notify::email::{EmailNotifier, SmtpConfig};
+
+            if let Some(ref e) = self.email {
+                let credentials = match (e.username.as_ref(), e.password_env.as_ref()) {
+                    (Some(u), Some(var)) => {
+                        let password = env::var(var).map_err(|_| ConfigError::MissingCredential(var.clone()))?;
+                        Some((u.clone(), password))
+                    }
+                    _ => None,
+                };
+                let smtp = SmtpConfig {
+                    server: e.server.clone(),
+                    port: e.port,
+                    credentials,
+                };
+
+                fan_out = fan_out.sink(e.to.iter().fold(EmailNotifier::new(smtp, &e.from), |n, to| n.recipient(to)));
+            }
+        }
+
+        #[cfg(feature="pushover")]
+        {
+            use notify::pushover::PushoverNotifier;
+
+            if let Some(ref p) = self.pushover {
+                let app_token = read_secret(p.app_token_env.as_ref(), p.app_token_file.as_ref(),
+                                            "notifiers.pushover.app_token")?;
+                let mut n = PushoverNotifier::new(&app_token, &p.user_key);
+                if let Some(ref device) = p.device {
+                    n = n.device(device);
+                }
+                if let Some(priority) = p.priority {
+                    n = n.priority(priority);
+                }
+                fan_out = fan_out.sink(n);
+            }
+        }
+
+        #[cfg(feature="telegram")]
+        {
+            use notify::telegram::TelegramNotifier;
+
+            if let Some(ref t) = self.telegram {
+                let bot_token = read_secret(t.bot_token_env.as_ref(), t.bot_token_file.as_ref(),
+                                            "notifiers.telegram.bot_token")?;
+                fan_out = fan_out.sink(TelegramNotifier::new(&bot_token, &t.chat_id));
+            }
+        }
+
+        #[cfg(feature="webhook")]
+        {
+            use notify::webhook::WebhookNotifier;
+
+            if let Some(ref w) = self.webhook {
+                let mut n = WebhookNotifier::new(&w.url);
+                if let Some(ref var) = w.secret_env {
+                    let secret = env::var(var).map_err(|_| ConfigError::MissingCredential(var.clone()))?;
+                    n = n.secret(&secret);
+                }
+                if let Some(attempts) = w.attempts {
+                    n = n.retries(attempts, Duration::from_secs(2));
+                }
+                fan_out = fan_out.sink(n);
+            }
+        }
+
+        Ok(fan_out)
+    }
+}
+
+// course patterns are matched case-insensitively against the course title; change kinds use the
+// names from `CourseChange::kind`/`AssignmentChange::kind`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct FilterConfig {
+    #[serde(default)]
+    pub include_courses: Vec<String>,
+    #[serde(default)]
+    pub exclude_courses: Vec<String>,
+    #[serde(default)]
+    pub ignore_changes: Vec<String>,
+}
+
+impl FilterConfig {
+    pub fn apply(&self, changeset: &Changeset) -> Option<Changeset> {
+        let changes: Vec<CourseChanges> = changeset.changes.iter()
+            .filter(|ccs| self.course_included(ccs))
+            .filter_map(|ccs| self.filter_kinds(ccs))
+            .collect();
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(Changeset {
+                old: changeset.old.clone(),
+                new: changeset.new.clone(),
+                changes,
+            })
+        }
+    }
+
+    fn course_included(&self, ccs: &CourseChanges) -> bool {
+        let title = match ccs.new.as_ref().or(ccs.old.as_ref()) {
+            Some(c) => c.title.to_string().to_lowercase(),
+            None => { return false; }
+        };
+        let matches = |patterns: &[String]| patterns.iter().any(|p| title.contains(&p.to_lowercase()));
+
+        (self.include_courses.is_empty() || matches(&self.include_courses)) && !matches(&self.exclude_courses)
+    }
+
+    fn filter_kinds(&self, ccs: &CourseChanges) -> Option<CourseChanges> {
+        let ignored = |kind: &str| self.ignore_changes.iter().any(|k| k == kind);
+
+        let changes = ccs.changes.as_ref()
+            .map(|cs| cs.iter().filter(|c| !ignored(c.kind())).cloned().collect::<Vec<_>>())
+            .and_then(|cs| if cs.is_empty() { None } else { Some(cs) });
+        let assignment_changes = ccs.assignment_changes.as_ref()
+            .map(|acs| {
+                acs.iter()
+                    .filter_map(|ac| {
+                        let changes: Vec<_> = ac.changes.iter().filter(|c| !ignored(c.kind())).cloned().collect();
+                        if changes.is_empty() {
+                            None
+                        } else {
+                            Some(AssignmentChanges {
+                                old: ac.old.clone(),
+                                new: ac.new.clone(),
+                                changes,
+                            })
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .and_then(|acs| if acs.is_empty() { None } else { Some(acs) });
+
+        if changes.is_none() && assignment_changes.is_none() {
+            None
+        } else {
+            Some(CourseChanges {
+                old: ccs.old.clone(),
+                new: ccs.new.clone(),
+                assignment_changes,
+                changes,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use api::Account;
+    use gradebook::{AssignmentScore, Gradebook};
+    use notify::Notifier;
+
+    const MINIMAL: &str = r#"
+        [credentials]
+        username = "student"
+        password_env = "RVUE_CONFIG_TEST_MINIMAL_PASSWORD"
+    "#;
+
+    const FULL: &str = r#"
+        district_url = "https://student.district.org/"
+        poll_interval_secs = 900
+        rounding = "half_up"
+        rounding_places = 1
+        time_zone = "America/Los_Angeles"
+        utc_offset_minutes = -420
+        language = "es"
+
+        [credentials]
+        username_env = "RVUE_CONFIG_TEST_FULL_USERNAME"
+        password_env = "RVUE_CONFIG_TEST_FULL_PASSWORD"
+
+        [notifiers.discord]
+        webhook_url_env = "RVUE_CONFIG_TEST_FULL_DISCORD_URL"
+        username = "rvue"
+
+        [filters]
+        include_courses = ["english"]
+        exclude_courses = ["band"]
+        ignore_changes = ["notes_change"]
+
+        [[course_rules]]
+        course = "Chemistry"
+        delivery = "digest"
+
+        [[course_rules]]
+        period = 2
+        delivery = "mute"
+
+        [[goals]]
+        course = "English"
+        target = 93.0
+
+        [template]
+        base = "markdown"
+        subject = "{label}: {courses}"
+        separator = "\n---\n"
+
+        [[turn_in_markers]]
+        code = "LT"
+        status = "late"
+
+        [[turn_in_markers]]
+        phrase = "no credit"
+        status = "missing"
+
+        [[students]]
+        label = "Ada"
+
+        [[students]]
+        label = "Ben"
+        endpoint = "https://other.example.org/Service/PXPCommunication.asmx"
+        child_int_id = "2"
+    "#;
+
+    fn set_env(name: &str, value: &str) {
+        env::set_var(name, value);
+    }
+
+    fn full() -> Config {
+        set_env("RVUE_CONFIG_TEST_FULL_USERNAME", "parent");
+        set_env("RVUE_CONFIG_TEST_FULL_PASSWORD", "hunter2");
+        set_env("RVUE_CONFIG_TEST_FULL_DISCORD_URL", "https://discord.example.org/api/webhooks/1/abc");
+        Config::parse(FULL).unwrap()
+    }
+
+    // the fixture with the first assignment's score replaced
+    fn scored(score: &str) -> Gradebook {
+        let mut gradebook = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+        gradebook.courses[0].marks[0].assignments[0].score = AssignmentScore::Unparseable(score.to_string());
+        gradebook
+    }
+
+    #[test]
+    fn fills_in_defaults_for_a_minimal_config() {
+        set_env("RVUE_CONFIG_TEST_MINIMAL_PASSWORD", "hunter2");
+        let config = Config::parse(MINIMAL).unwrap();
+
+        assert_eq!(config.poll_interval(), Duration::from_secs(60 * 60));
+        assert_eq!(config.rounding().unwrap(), RoundingPolicy::default());
+        assert_eq!(config.clock().unwrap().offset().local_minus_utc(), 0);
+        assert!(config.rules().is_ok());
+        assert!(config.template().is_ok());
+        assert_eq!(config.notifiers.build().unwrap().sink_count(), 0);
+
+        let client = config.client().unwrap();
+        assert_eq!(client.username(), "student");
+        assert_eq!(client.credentials().password(), "hunter2");
+
+        let household = config.household().unwrap();
+        let labels: Vec<&str> = household.members().iter().map(|m| &m.label[..]).collect();
+        assert_eq!(labels, vec!["student"]);
+    }
+
+    #[test]
+    fn reads_every_section_of_a_full_config() {
+        let config = full();
+
+        assert_eq!(config.poll_interval(), Duration::from_secs(900));
+        assert_eq!(config.rounding().unwrap(), RoundingPolicy::new(RoundingMode::HalfUp, 1));
+        assert_eq!(config.filters.ignore_changes, vec!["notes_change".to_string()]);
+        assert_eq!(config.notifiers.build().unwrap().sink_count(), 1);
+        assert!(config.template().is_ok());
+
+        let client = config.client().unwrap();
+        assert_eq!(client.username(), "parent");
+        assert_eq!(client.endpoint_url(), "https://student.district.org/Service/PXPCommunication.asmx");
+
+        let gradebook = scored("LT");
+        let (english, chemistry) = (&gradebook.courses[0], &gradebook.courses[1]);
+        let rules = config.rules().unwrap();
+        assert_eq!(rules.delivery_for(english), Delivery::Mute);
+        assert_eq!(rules.delivery_for(chemistry), Delivery::DigestOnly);
+        assert_eq!(config.goals().unwrap().target_for(english), Some(93.0));
+        assert_eq!(config.goals().unwrap().target_for(chemistry), None);
+
+        let markers = config.turn_in_markers().unwrap();
+        assert_eq!(markers.classify(&english.marks[0].assignments[0]), TurnInStatus::Late);
+        let gradebook = scored("No Credit");
+        assert_eq!(markers.classify(&gradebook.courses[0].marks[0].assignments[0]), TurnInStatus::Missing);
+    }
+
+    #[test]
+    fn prefers_the_time_zone_to_the_utc_offset() {
+        let config = full();
+        let offset = config.clock().unwrap().offset().local_minus_utc();
+
+        // Pacific time, whichever side of daylight saving time the test runs on
+        assert!(offset == -7 * 3600 || offset == -8 * 3600, "{}", offset);
+    }
+
+    #[test]
+    fn builds_a_client_per_student() {
+        let household = full().household().unwrap();
+
+        let ada = &household.find("Ada").unwrap().client;
+        assert_eq!(ada.username(), "parent");
+        assert_eq!(ada.endpoint_url(), "https://student.district.org/Service/PXPCommunication.asmx");
+        assert_eq!(*ada.account_kind(), Account::Student);
+
+        let ben = &household.find("Ben").unwrap().client;
+        assert_eq!(ben.endpoint_url(), "https://other.example.org/Service/PXPCommunication.asmx");
+        assert_eq!(*ben.account_kind(), Account::child("2"));
+    }
+
+    #[test]
+    fn rejects_malformed_toml() {
+        match Config::parse("poll_interval_secs = \"hourly\"") {
+            Err(ConfigError::ParseError(_)) => {}
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_missing_credentials() {
+        let cases = [
+            ("[credentials]\npassword_env = \"RVUE_CONFIG_TEST_MINIMAL_PASSWORD\"", "username"),
+            ("[credentials]\nusername = \"student\"", "password"),
+            ("[credentials]\nusername_env = \"RVUE_CONFIG_TEST_UNSET\"\npassword_env = \"RVUE_CONFIG_TEST_MINIMAL_PASSWORD\"",
+             "RVUE_CONFIG_TEST_UNSET"),
+            ("[credentials]\nusername = \"student\"\npassword_env = \"RVUE_CONFIG_TEST_UNSET\"", "RVUE_CONFIG_TEST_UNSET"),
+        ];
+        set_env("RVUE_CONFIG_TEST_MINIMAL_PASSWORD", "hunter2");
+
+        for &(toml, missing) in cases.iter() {
+            match Config::parse(toml).unwrap().client() {
+                Err(ConfigError::MissingCredential(ref m)) => assert_eq!(m, missing, "{}", toml),
+                Err(e) => panic!("expected {} to be missing, got {:?}", missing, e),
+                Ok(_) => panic!("expected {} to be missing", missing),
+            }
+        }
+    }
+
+    #[test]
+    fn reads_notifier_secrets_from_the_environment_or_a_file() {
+        let path = env::temp_dir().join(format!("rvue-config-test-{}-bot-token", std::process::id()));
+        std::fs::write(&path, "123456:hunter2-secret\n").unwrap();
+        set_env("RVUE_CONFIG_TEST_DISCORD_URL", "https://discord.example.org/api/webhooks/1/hunter2-secret");
+
+        let config = Config::parse(&format!(
+            "[notifiers.discord]\nwebhook_url_env = \"RVUE_CONFIG_TEST_DISCORD_URL\"\n\
+             [notifiers.telegram]\nbot_token_file = \"{}\"\nchat_id = \"42\"", path.display())).unwrap();
+        let fan_out = config.notifiers.build();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = if cfg!(feature="telegram") { 2 } else { 1 };
+        assert_eq!(fan_out.unwrap().sink_count(), expected);
+        assert!(!format!("{:?}", config).contains("hunter2-secret"));
+    }
+
+    #[test]
+    fn reports_missing_notifier_secrets() {
+        let cases = [
+            ("[notifiers.discord]\nusername = \"rvue\"", "notifiers.discord.webhook_url"),
+            ("[notifiers.discord]\nwebhook_url_env = \"RVUE_CONFIG_TEST_UNSET\"", "RVUE_CONFIG_TEST_UNSET"),
+        ];
+
+        for &(toml, missing) in cases.iter() {
+            match Config::parse(toml).unwrap().notifiers.build() {
+                Err(ConfigError::MissingCredential(ref m)) => assert_eq!(m, missing, "{}", toml),
+                Err(e) => panic!("expected {} to be missing, got {:?}", missing, e),
+                Ok(_) => panic!("expected {} to be missing", missing),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_an_unreadable_password_file() {
+        let config = Config::parse("[credentials]\nusername = \"student\"\npassword_file = \"/nonexistent/rvue/password\"").unwrap();
+        assert!(matches!(config.client(), Err(ConfigError::Io(_))));
+    }
+
+    #[test]
+    fn reports_an_audit_log_it_cannot_open() {
+        set_env("RVUE_CONFIG_TEST_MINIMAL_PASSWORD", "hunter2");
+        let config = Config::parse(&format!("audit_log = \"/nonexistent/rvue/audit.log\"\n{}", MINIMAL)).unwrap();
+        assert!(matches!(config.client(), Err(ConfigError::AuditLog(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_course_rules() {
+        let cases = [
+            "[[course_rules]]\ncourse = \"Chemistry\"\nperiod = 2\ndelivery = \"mute\"",
+            "[[course_rules]]\ndelivery = \"mute\"",
+            "[[course_rules]]\nperiod = 2\ndelivery = \"hourly\"",
+        ];
+
+        for toml in cases.iter() {
+            assert!(matches!(Config::parse(toml).unwrap().rules(), Err(ConfigError::InvalidCourseRule(_))), "{}", toml);
+        }
+        let goal = Config::parse("[[goals]]\ntarget = 90.0").unwrap();
+        assert!(matches!(goal.goals(), Err(ConfigError::InvalidCourseRule(_))));
+    }
+
+    #[test]
+    fn rejects_an_unknown_rounding_mode() {
+        let config = Config::parse("rounding = \"bankers\"").unwrap();
+        match config.rounding() {
+            Err(ConfigError::InvalidRounding(ref mode)) => assert_eq!(mode, "bankers"),
+            other => panic!("expected an invalid rounding mode, got {:?}", other),
+        }
+        assert!(matches!(config.goals(), Err(ConfigError::InvalidRounding(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let base = Config::parse("[template]\nbase = \"html\"").unwrap();
+        assert!(matches!(base.template(), Err(ConfigError::InvalidTemplateBase(_))));
+
+        let placeholder = Config::parse("[template]\nsubject = \"{student}\"").unwrap();
+        assert!(matches!(placeholder.template(), Err(ConfigError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_turn_in_markers() {
+        let cases = [
+            "[[turn_in_markers]]\ncode = \"LT\"\nstatus = \"tardy\"",
+            "[[turn_in_markers]]\ncode = \"LT\"\nphrase = \"late\"\nstatus = \"late\"",
+            "[[turn_in_markers]]\nstatus = \"late\"",
+        ];
+
+        for toml in cases.iter() {
+            let config = Config::parse(toml).unwrap();
+            assert!(matches!(config.turn_in_markers(), Err(ConfigError::InvalidTurnInMarker(_))), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn rejects_an_out_of_range_utc_offset() {
+        let config = Config::parse("utc_offset_minutes = 1500").unwrap();
+        assert!(matches!(config.clock(), Err(ConfigError::InvalidUtcOffset(1500))));
+    }
+
+    #[test]
+    fn rejects_an_unknown_time_zone() {
+        let config = Config::parse("time_zone = \"America/Springfield\"").unwrap();
+        match config.clock() {
+            Err(ConfigError::UnknownTimeZone(ref name)) => assert_eq!(name, "America/Springfield"),
+            other => panic!("expected an unknown time zone, got {:?}", other.map(|c| c.offset())),
+        }
+    }
+}
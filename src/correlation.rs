@@ -0,0 +1,182 @@
+// How absences line up with grades, course by course. Each graded assignment is paired with the
+// number of times the student missed that course's period in the days leading up to its due date,
+// and the two are correlated. It's a rough signal (a handful of assignments per term says little),
+// meant to point counselors and parents at where to look rather than to prove anything.
+//
+// rvue can't retrieve attendance yet, so absences come in as (date, period) pairs from wherever
+// the caller gets them.
+use gradebook::{Course, CourseTitle, Gradebook};
+
+use chrono::{Duration, NaiveDate};
+
+pub const DEFAULT_WINDOW_DAYS: i64 = 14;
+
+#[derive(Clone, Debug)]
+pub struct CourseCorrelation {
+    pub course: CourseTitle,
+    pub period: i8,
+    // absences in the course's period over the whole gradebook
+    pub absences: usize,
+    // graded assignments that went into the numbers below
+    pub assignments: usize,
+    // Pearson's r between absences before an assignment and its score; None with fewer than three
+    // assignments or when either side doesn't vary (e.g. no absences at all)
+    pub correlation: Option<f64>,
+    // average percentage on assignments with at least one absence in the window before them
+    pub average_after_absence: Option<f64>,
+    pub average_otherwise: Option<f64>,
+}
+
+impl CourseCorrelation {
+    // how much lower scores were after absences, in percentage points; positive is worse
+    pub fn drop_after_absence(&self) -> Option<f64> {
+        match (self.average_after_absence, self.average_otherwise) {
+            (Some(after), Some(otherwise)) => Some(otherwise - after),
+            _ => None,
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 3 || xs.len() != ys.len() {
+        return None;
+    }
+
+    let (mx, my) = match (mean(xs), mean(ys)) {
+        (Some(mx), Some(my)) => (mx, my),
+        _ => { return None; }
+    };
+    let cov: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+    let vx: f64 = xs.iter().map(|x| (x - mx) * (x - mx)).sum();
+    let vy: f64 = ys.iter().map(|y| (y - my) * (y - my)).sum();
+
+    if vx <= 0.0 || vy <= 0.0 {
+        None
+    } else {
+        Some(cov / (vx * vy).sqrt())
+    }
+}
+
+pub fn course_correlation(course: &Course, absences: &[(NaiveDate, i8)], window: Duration) -> CourseCorrelation {
+    let missed: Vec<NaiveDate> = absences.iter()
+        .filter(|&&(_, period)| period == course.period)
+        .map(|&(date, _)| date)
+        .collect();
+
+    let mut counts = Vec::new();
+    let mut scores = Vec::new();
+    for a in course.marks().iter().flat_map(|m| m.assignments().iter()) {
+        let score = match a.points.percentage() {
+            Some(score) => score,
+            None => { continue; }
+        };
+        let before = missed.iter()
+            .filter(|&&d| d <= a.due_date && d > a.due_date - window)
+            .count();
+
+        counts.push(before as f64);
+        scores.push(score);
+    }
+
+    let after: Vec<f64> = counts.iter().zip(&scores).filter(|&(c, _)| *c > 0.0).map(|(_, s)| *s).collect();
+    let otherwise: Vec<f64> = counts.iter().zip(&scores).filter(|&(c, _)| *c == 0.0).map(|(_, s)| *s).collect();
+
+    CourseCorrelation {
+        course: course.title.clone(),
+        period: course.period,
+        absences: missed.len(),
+        assignments: scores.len(),
+        correlation: pearson(&counts, &scores),
+        average_after_absence: mean(&after),
+        average_otherwise: mean(&otherwise),
+    }
+}
+
+// one summary per course, in gradebook order
+pub fn correlations(gradebook: &Gradebook, absences: &[(NaiveDate, i8)], window: Duration) -> Vec<CourseCorrelation> {
+    gradebook.courses().iter()
+        .map(|c| course_correlation(c, absences, window))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::{AssignmentPoints, Points};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2018, month, day).unwrap()
+    }
+
+    // English with one assignment per (due date, score out of 100)
+    fn english(graded: &[(NaiveDate, f64)]) -> Course {
+        let gradebook = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+        let mut course = gradebook.courses[0].clone();
+        let template = course.marks[0].assignments[0].clone();
+
+        course.marks[0].assignments = graded.iter()
+            .map(|&(due, score)| {
+                let mut a = template.clone();
+                a.due_date = due;
+                a.points = AssignmentPoints::Graded(Points(score), Points(100.0));
+                a
+            })
+            .collect();
+        course
+    }
+
+    #[test]
+    fn correlates_only_when_both_sides_vary() {
+        assert_eq!(pearson(&[0.0, 1.0, 2.0], &[90.0, 80.0, 70.0]).map(|r| r.round()), Some(-1.0));
+        assert_eq!(pearson(&[0.0, 1.0, 2.0], &[70.0, 80.0, 90.0]).map(|r| r.round()), Some(1.0));
+        assert_eq!(pearson(&[0.0, 0.0, 0.0], &[70.0, 80.0, 90.0]), None);
+        assert_eq!(pearson(&[0.0, 1.0], &[90.0, 80.0]), None);
+    }
+
+    #[test]
+    fn counts_absences_in_the_window_before_each_due_date() {
+        let course = english(&[(date(9, 10), 95.0), (date(9, 20), 90.0), (date(10, 1), 70.0), (date(10, 10), 60.0)]);
+        let absences = [
+            (date(9, 28), 2),
+            (date(9, 30), 2),
+            (date(10, 10), 2),
+            // Chemistry's period
+            (date(9, 19), 3),
+            // in the window for 9/10, and just outside the one for 9/20
+            (date(9, 6), 2),
+        ];
+
+        let c = course_correlation(&course, &absences, Duration::days(DEFAULT_WINDOW_DAYS));
+
+        assert_eq!(c.period, 2);
+        assert_eq!(c.absences, 4);
+        assert_eq!(c.assignments, 4);
+        // 1, 0, 2 and 3 absences before each due date
+        assert_eq!(c.average_otherwise, Some(90.0));
+        assert_eq!(c.average_after_absence, Some((95.0 + 70.0 + 60.0) / 3.0));
+        assert!(c.drop_after_absence().unwrap() > 0.0);
+        assert!(c.correlation.unwrap() < -0.5);
+    }
+
+    #[test]
+    fn leaves_out_ungraded_work_and_says_nothing_without_absences() {
+        let gradebook = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+
+        let all = correlations(&gradebook, &[], Duration::days(DEFAULT_WINDOW_DAYS));
+
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].assignments, 1);
+        assert_eq!(all[0].correlation, None);
+        assert_eq!(all[0].drop_after_absence(), None);
+        assert_eq!(all[1].assignments, 0);
+        assert_eq!(all[1].average_otherwise, None);
+    }
+}
@@ -0,0 +1,59 @@
+// A StudentVUE login. The password never shows up in `Debug` output (and so can't end up in logs
+// or error context by accident) and is overwritten with zeros when the credentials are dropped.
+// That only goes for rvue's own copies: each request body holds it too, and once the body is
+// handed to the HTTP client (see `SVUERequest::send_with`) it's freed without being zeroed.
+// There's deliberately no `Clone`: a copy would be one more place the password lives, so share
+// credentials behind a reference or an `Arc` instead.
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+pub struct Credentials {
+    password: Zeroizing<String>,
+    username: String,
+}
+
+impl Credentials {
+    pub fn new(username: &str, password: &str) -> Credentials {
+        Self::from_owned(username.to_string(), password.to_string())
+    }
+
+    // takes the strings as they are, so no other copy of the password is left behind
+    pub fn from_owned(username: String, password: String) -> Credentials {
+        Credentials {
+            password: Zeroizing::new(password),
+            username,
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_password() {
+        let creds = Credentials::new("student", "hunter2-secret");
+        let debug = format!("{:?}", creds);
+
+        assert!(debug.contains("student"));
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
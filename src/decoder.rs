@@ -1,13 +1,14 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::num::{ParseFloatError, ParseIntError};
 use std::str::ParseBoolError;
 
-use chrono::{self, NaiveDate};
+use chrono::{self, NaiveDate, NaiveDateTime};
 use xml::attribute::OwnedAttribute;
 use xml::reader::{Error as ReaderError, Events, XmlEvent as ReaderEvent};
 
 pub trait SVUEDecodeable {
-    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>)
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits)
         -> DecoderResult<Self> where Self: Sized;
 }
 
@@ -17,6 +18,7 @@ pub type DecoderResult<T> = Result<T, DecodingError>;
 pub enum DecodingError {
     BoolParseError(String, ParseBoolError),
     DateParseError(String, chrono::ParseError),
+    ElementLimitExceeded(usize),
     SVUEErrorParsingFailed(String),
     EventError(ReaderError),
     FloatParseError(String, ParseFloatError),
@@ -26,23 +28,146 @@ pub enum DecodingError {
     UnexpectedEvent(ReaderEvent),
 }
 
+// caps on the size of what a decode produces: how many of the decoded types (courses, marks,
+// assignments, ...) it builds, and how long their text can be. That bounds the output, not the
+// memory used along the way; the response is still read whole, and elements rvue doesn't decode
+// aren't counted. The default decodes everything
+#[derive(Debug, Default)]
+pub struct DecodeLimits {
+    elements: Cell<usize>,
+    max_elements: Option<usize>,
+    max_text_len: Option<usize>,
+    skip_standards: bool,
+}
+
+impl DecodeLimits {
+    pub fn new() -> DecodeLimits {
+        Default::default()
+    }
+
+    // a document that decodes to more than this many values fails with ElementLimitExceeded
+    pub fn max_elements(mut self, max: usize) -> DecodeLimits {
+        self.max_elements = Some(max);
+        self
+    }
+
+    // notes and descriptions longer than this many characters are cut short
+    pub fn max_text_len(mut self, max: usize) -> DecodeLimits {
+        self.max_text_len = Some(max);
+        self
+    }
+
+    // leaves every assignment and mark without its standards
+    pub fn skip_standards(mut self) -> DecodeLimits {
+        self.skip_standards = true;
+        self
+    }
+
+    pub fn skips_standards(&self) -> bool {
+        self.skip_standards
+    }
+
+    pub fn reset(&self) {
+        self.elements.set(0);
+    }
+
+    pub fn count_element(&self) -> DecoderResult<()> {
+        let count = self.elements.get() + 1;
+        self.elements.set(count);
+
+        match self.max_elements {
+            Some(max) if count > max => Err(DecodingError::ElementLimitExceeded(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn text(&self, s: &str) -> String {
+        match self.max_text_len.and_then(|max| s.char_indices().nth(max)) {
+            Some((end, _)) => s[..end].to_string(),
+            None => s.to_string(),
+        }
+    }
+}
+
+// consumes everything up to and including the end of the element whose start was just read
+pub fn skip_element(events_iter: &mut Events<&[u8]>) -> DecoderResult<()> {
+    let mut depth = 1;
+
+    loop {
+        match events_iter.next() {
+            Some(Ok(ReaderEvent::StartElement { .. })) => { depth += 1; }
+            Some(Ok(ReaderEvent::EndElement { .. })) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            }
+            Some(Ok(_)) => {}
+            Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+            None => { return Err(DecodingError::UnexpectedEnd); }
+        }
+    }
+}
+
 #[inline]
 pub fn parse_date(date: &str) -> Result<NaiveDate, chrono::ParseError>  {
     NaiveDate::parse_from_str(date, "%-m/%-d/%Y")
 }
 
+// the formats districts have been seen to use for timestamps, most specific first
+pub fn parse_timestamp(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim();
+
+    ["%m/%d/%Y %I:%M:%S %p", "%m/%d/%Y %H:%M:%S", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S"].iter()
+        .filter_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .next()
+        .or_else(|| NaiveDate::parse_from_str(s, "%m/%d/%Y").ok().map(|d| d.and_hms_opt(0, 0, 0).unwrap()))
+}
+
 #[inline]
-pub fn attributes_vec_to_map<'a>(attrs: &'a Vec<OwnedAttribute>) -> HashMap<&'a str, String> {
+pub fn attributes_vec_to_map(attrs: &[OwnedAttribute]) -> HashMap<&str, &str> {
     attrs.iter()
-        .map(|a| (a.name.local_name.as_str(), a.value.clone()))
+        .map(|a| (a.name.local_name.as_str(), a.value.as_str()))
         .fold(HashMap::new(), |mut acc, (k, v)| { acc.insert(k, v); acc })
 }
 
+// lets the child loops test an event's name without borrowing it past the match guard, so the
+// event itself can be handed to the child's decoder instead of a clone
+#[inline]
+pub fn is_start_of(event: &ReaderEvent, local_name: &str) -> bool {
+    match *event {
+        ReaderEvent::StartElement { ref name, .. } => name.local_name == local_name,
+        _ => false,
+    }
+}
+
+#[inline]
+pub fn is_end_of(event: &ReaderEvent, local_name: &str) -> bool {
+    match *event {
+        ReaderEvent::EndElement { ref name } => name.local_name == local_name,
+        _ => false,
+    }
+}
+
+// slice accessors for a decoded type's Vec fields
+macro_rules! field_slice_helpers {
+    ( $t:ty, { $($field:tt => $field_t:ty),+ } ) => {
+        impl $t {
+            $(
+                #[allow(dead_code)]
+                pub fn $field(&self) -> &[$field_t] {
+                    self.$field.as_slice()
+                }
+            )+
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! get_attr {
     ( $attrs:expr, $attr:expr ) => {
         match $attrs.get($attr) {
-            Some(val) => val,
+            Some(val) => *val,
             None => { return Err(DecodingError::MissingAttribute($attr.into())); }
         }
     };
@@ -51,7 +176,7 @@ macro_rules! get_attr {
 #[macro_export]
 macro_rules! get_attr_owned {
     ( $attrs:expr, $attr:expr ) => {
-        get_attr!($attrs, $attr).clone()
+        get_attr!($attrs, $attr).to_string()
     };
 }
 
@@ -59,13 +184,10 @@ macro_rules! get_attr_owned {
 macro_rules! parse_date {
     ( $attrs:expr, $attr:expr ) => {
         {
-            let date = parse_date(get_attr!($attrs, $attr));
-
-            if date.is_err() {
-                return Err(DecodingError::DateParseError($attr.into(), date.unwrap_err()));
+            match parse_date(get_attr!($attrs, $attr)) {
+                Ok(date) => date,
+                Err(e) => { return Err(DecodingError::DateParseError($attr.into(), e)); }
             }
-
-            date.unwrap()
         }
     };
 }
@@ -74,13 +196,10 @@ macro_rules! parse_date {
 macro_rules! parse_bool {
     ( $attrs:expr, $attr:expr ) => {
         {
-            let b = bool::from_str(get_attr!($attrs, $attr));
-
-            if b.is_err() {
-                return Err(DecodingError::BoolParseError($attr.into(), b.unwrap_err()));
+            match bool::from_str(get_attr!($attrs, $attr)) {
+                Ok(b) => b,
+                Err(e) => { return Err(DecodingError::BoolParseError($attr.into(), e)); }
             }
-
-            b.unwrap()
         }
     };
 }
@@ -89,13 +208,10 @@ macro_rules! parse_bool {
 macro_rules! parse_int {
     ( $ity:tt, $attrs:expr, $attr:expr ) => {
         {
-            let int = $ity::from_str_radix(get_attr!($attrs, $attr), 10);
-
-            if int.is_err() {
-                return Err(DecodingError::IntegerParseError($attr.into(), int.unwrap_err()));
+            match get_attr!($attrs, $attr).parse::<$ity>() {
+                Ok(int) => int,
+                Err(e) => { return Err(DecodingError::IntegerParseError($attr.into(), e)); }
             }
-
-            int.unwrap()
         }
     };
 }
@@ -104,13 +220,10 @@ macro_rules! parse_int {
 macro_rules! parse_float {
     ( $fty:tt, $attrs:expr, $attr:expr ) => {
         {
-            let f = $fty::from_str(get_attr!($attrs, $attr));
-
-            if f.is_err() {
-                return Err(DecodingError::FloatParseError($attr.into(), f.unwrap_err()));
+            match $fty::from_str(get_attr!($attrs, $attr)) {
+                Ok(f) => f,
+                Err(e) => { return Err(DecodingError::FloatParseError($attr.into(), e)); }
             }
-
-            f.unwrap()
         }
     };
 }
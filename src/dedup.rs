@@ -0,0 +1,563 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate};
+
+use diff::*;
+use gradebook::{Assignment, AssignmentGradeCalc, AssignmentGradeCalcWeight, AssignmentPoints, AssignmentScore,
+                ClassStats, Course, CourseTitle, Mark, ReportPeriod, ReportingPeriod, Standard,
+                StandardAssignmentView, StandardScreenAssignment, StandardView};
+
+pub type Fingerprint = u64;
+
+const FNV_OFFSET: Fingerprint = 0xcbf29ce484222325;
+const FNV_PRIME: Fingerprint = 0x100000001b3;
+
+// fingerprints get persisted between runs, so they can't come from `DefaultHasher`, whose
+// output isn't guaranteed to stay the same across Rust releases
+pub fn fnv1a(s: &str) -> Fingerprint {
+    s.bytes().fold(FNV_OFFSET, |h, b| (h ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+// FNV-1a fed one field at a time, rather than hashing `Debug` output, which isn't promised to stay
+// the same and would change every fingerprint whenever a field is added. Each field is followed by
+// a 0xff byte, which no UTF-8 string contains, so ("ab", "c") and ("a", "bc") don't collide; floats
+// go in by their bits and dates as a day count
+pub(crate) struct Fnv(Fingerprint);
+
+impl Fnv {
+    pub(crate) fn new(tag: &str) -> Fnv {
+        Fnv(FNV_OFFSET).str(tag)
+    }
+
+    fn bytes(mut self, bytes: &[u8]) -> Fnv {
+        for &b in bytes.iter().chain(&[0xff]) {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    fn str(self, s: &str) -> Fnv {
+        self.bytes(s.as_bytes())
+    }
+
+    fn int(self, n: i64) -> Fnv {
+        self.bytes(&n.to_le_bytes())
+    }
+
+    fn bool(self, b: bool) -> Fnv {
+        self.int(b as i64)
+    }
+
+    fn float(self, f: f64) -> Fnv {
+        self.bytes(&f.to_bits().to_le_bytes())
+    }
+
+    fn opt_float(self, f: Option<f64>) -> Fnv {
+        match f {
+            Some(f) => self.bool(true).float(f),
+            None => self.bool(false),
+        }
+    }
+
+    fn date(self, d: NaiveDate) -> Fnv {
+        self.int(d.num_days_from_ce() as i64)
+    }
+
+    fn hash(self, h: Fingerprint) -> Fnv {
+        self.bytes(&h.to_le_bytes())
+    }
+
+    // order-insensitive: the portal doesn't promise to list courses, marks or assignments the same
+    // way twice, and a reshuffle isn't a change worth diffing for
+    pub(crate) fn unordered<T, F: Fn(&T) -> Fingerprint>(self, items: &[T], f: F) -> Fnv {
+        let mut hashes: Vec<Fingerprint> = items.iter().map(f).collect();
+        hashes.sort();
+        hashes.into_iter().fold(self.int(items.len() as i64), |h, x| h.hash(x))
+    }
+
+    fn title(self, title: &CourseTitle) -> Fnv {
+        match *title {
+            CourseTitle::Parsed(ref name, ref id) => self.str("parsed").str(name).str(id),
+            CourseTitle::Unparseable(ref s) => self.str("unparseable").str(s),
+        }
+    }
+
+    fn score(self, score: &AssignmentScore) -> Fnv {
+        match *score {
+            AssignmentScore::NotDue => self.str("not_due"),
+            AssignmentScore::NotForGrading => self.str("not_for_grading"),
+            AssignmentScore::NotGraded => self.str("not_graded"),
+            AssignmentScore::Percentage(p) => self.str("percentage").float(p),
+            AssignmentScore::Score(s, o) => self.str("score").float(s.value()).float(o.value()),
+            AssignmentScore::SeeStandards => self.str("see_standards"),
+            AssignmentScore::Unparseable(ref s) => self.str("unparseable").str(s),
+        }
+    }
+
+    fn points(self, points: &AssignmentPoints) -> Fnv {
+        match *points {
+            AssignmentPoints::Ungraded(p) => self.str("ungraded").float(p.value()),
+            AssignmentPoints::Graded(s, o) => self.str("graded").float(s.value()).float(o.value()),
+            AssignmentPoints::Unparseable(ref s) => self.str("unparseable").str(s),
+        }
+    }
+
+    fn weight(self, weight: &AssignmentGradeCalcWeight) -> Fnv {
+        match *weight {
+            AssignmentGradeCalcWeight::Percentage(p) => self.str("percentage").float(p),
+            AssignmentGradeCalcWeight::Unparseable(ref s) => self.str("unparseable").str(s),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Fingerprint {
+        self.0
+    }
+}
+
+fn screen_assignment_hash(a: &StandardScreenAssignment) -> Fingerprint {
+    Fnv::new("standard_screen_assignment").str(&a._type).str(&a.assignment).date(a.due_date).str(&a.mark)
+        .opt_float(a.proficiency).float(a.proficiency_max_value)
+        .finish()
+}
+
+fn standard_hash(s: &Standard) -> Fingerprint {
+    Fnv::new("standard").str(&s.subject).str(&s.mark).str(&s.description).opt_float(s.proficiency)
+        .float(s.proficiency_max_value).unordered(&s.standard_screen_assignments, screen_assignment_hash)
+        .finish()
+}
+
+fn class_stats(h: Fnv, stats: &Option<ClassStats>) -> Fnv {
+    match *stats {
+        Some(ref s) => h.bool(true).opt_float(s.average).opt_float(s.high).opt_float(s.low).opt_float(s.median),
+        None => h.bool(false),
+    }
+}
+
+fn assignment_hash(a: &Assignment) -> Fingerprint {
+    let h = Fnv::new("assignment").str(&a._type).str(&a.gradebook_id).str(&a.measure).date(a.date)
+        .date(a.due_date).score(&a.score).str(&a.score_type).points(&a.points).str(&a.notes).str(&a.teacher_id)
+        .str(&a.student_id).bool(a.has_drop_box).date(a.drop_start_date).date(a.drop_end_date)
+        .unordered(&a.standards, standard_hash);
+
+    class_stats(h, &a.class_stats).finish()
+}
+
+fn grade_calc_hash(c: &AssignmentGradeCalc) -> Fingerprint {
+    Fnv::new("grade_calc").str(&c._type).str(&c.calculated_mark).float(c.points).float(c.points_possible)
+        .weight(&c.weight).weight(&c.weighted_pct)
+        .finish()
+}
+
+fn standard_assignment_view_hash(v: &StandardAssignmentView) -> Fingerprint {
+    Fnv::new("standard_assignment_view").str(&v._type).str(&v.assignment).float(v.cal_value).date(v.due_date)
+        .str(&v.gradebook_id).str(&v.mark).opt_float(v.proficiency).float(v.proficiency_max_value)
+        .finish()
+}
+
+fn standard_view_hash(v: &StandardView) -> Fingerprint {
+    Fnv::new("standard_view").float(v.cal_value).str(&v.description).str(&v.mark).opt_float(v.proficiency)
+        .float(v.proficiency_max_value).unordered(&v.standard_assignment_views, standard_assignment_view_hash)
+        .str(&v.subject).int(v.subject_id as i64)
+        .finish()
+}
+
+fn mark_hash(mark: &Mark) -> Fingerprint {
+    Fnv::new("mark").str(&mark.mark_name).float(mark.calculated_score_raw).str(&mark.calculated_score_string)
+        .unordered(&mark.assignments, assignment_hash)
+        .unordered(&mark.grade_calculation_summary, grade_calc_hash)
+        .unordered(&mark.standard_views, standard_view_hash)
+        .finish()
+}
+
+pub(crate) fn course_hash(course: &Course) -> Fingerprint {
+    Fnv::new("course").title(&course.title).int(course.period as i64).str(&course.room).str(&course.staff)
+        .str(&course.staff_email).int(course.highlight_percentage_cut_off_for_progress_bar as i64)
+        .unordered(&course.marks, mark_hash)
+        .finish()
+}
+
+pub(crate) fn report_period_hash(p: &ReportPeriod) -> Fingerprint {
+    Fnv::new("report_period").date(p.end_date).str(&p.grade_period).int(p.index as i64).date(p.start_date)
+        .finish()
+}
+
+pub(crate) fn reporting_period(h: Fnv, p: &ReportingPeriod) -> Fnv {
+    h.date(p.end_date).str(&p.grade_period).date(p.start_date)
+}
+
+pub fn course_change_fingerprint(course: &CourseTitle, change: &CourseChange) -> Fingerprint {
+    let h = Fnv::new("course").title(course).str(change.kind());
+
+    match *change {
+        CourseChange::Added | CourseChange::Dropped => h,
+        CourseChange::CalculatedGradeChange { ref old, ref new } |
+        CourseChange::RoomChange { ref old, ref new } |
+        CourseChange::StaffChange { ref old, ref new } |
+        CourseChange::StaffEmailChange { ref old, ref new } => h.str(old).str(new),
+        CourseChange::HighlightCutoffChange { old, new } |
+        CourseChange::PeriodChange { old, new } => h.int(old as i64).int(new as i64),
+        CourseChange::TitleChange { ref old, ref new } => h.title(old).title(new),
+    }.finish()
+}
+
+pub fn assignment_change_fingerprint(course: &CourseTitle, gradebook_id: &str, change: &AssignmentChange)
+    -> Fingerprint {
+
+    let h = Fnv::new("assignment").title(course).str(gradebook_id).str(change.kind());
+
+    match *change {
+        AssignmentChange::Added | AssignmentChange::Removed => h,
+        AssignmentChange::DateChange { old, new } |
+        AssignmentChange::DueDateChange { old, new } => h.date(old).date(new),
+        AssignmentChange::NotesChange { ref old, ref new } |
+        AssignmentChange::ScoreTypeChange { ref old, ref new } |
+        AssignmentChange::TitleChange { ref old, ref new } => h.str(old).str(new),
+        AssignmentChange::PointsChange { ref old, ref new } => h.points(old).points(new),
+        AssignmentChange::ScoreChange { ref old, ref new } => h.score(old).score(new),
+        AssignmentChange::StandardAdded { ref standard, ref mark } |
+        AssignmentChange::StandardRemoved { ref standard, ref mark } => h.str(standard).str(mark),
+        AssignmentChange::StandardMarkChange { ref standard, ref old, ref new } => h.str(standard).str(old).str(new),
+        AssignmentChange::StandardProficiencyChange { ref standard, old, new } => {
+            h.str(standard).opt_float(old).opt_float(new)
+        }
+    }.finish()
+}
+
+// how many delivered changes a `Deduplicator` remembers by default
+pub const DEFAULT_DEDUP_CAPACITY: usize = 10000;
+
+#[derive(Clone, Debug)]
+pub struct Deduplicator {
+    capacity: usize,
+    // bumped every time a fingerprint is seen, so the smallest generation is the least recently seen
+    generation: u64,
+    // the fingerprints of the changes delivered, by the generation each was last seen in. They're
+    // keyed on the change alone (course, assignment, kind, old and new), not on the gradebooks it
+    // was diffed between, so a change that shows up again in a diff from some other baseline (the
+    // snapshot wasn't saved after a crash or a partly failed send, and the gradebook moved on
+    // again before the next poll) isn't sent twice
+    order: BTreeMap<u64, Fingerprint>,
+    seen: HashMap<Fingerprint, u64>,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Deduplicator {
+        Deduplicator {
+            capacity: DEFAULT_DEDUP_CAPACITY,
+            generation: 0,
+            order: BTreeMap::new(),
+            seen: HashMap::new(),
+        }
+    }
+}
+
+impl Deduplicator {
+    pub fn new() -> Deduplicator {
+        Default::default()
+    }
+
+    // `seen` as given by `Deduplicator::seen`, least recently seen first
+    pub fn from_seen<I: IntoIterator<Item=Fingerprint>>(seen: I) -> Deduplicator {
+        let mut dedup = Deduplicator::new();
+        for fingerprint in seen {
+            dedup.insert(fingerprint);
+        }
+        dedup
+    }
+
+    // once this many are remembered, the least recently seen are forgotten
+    pub fn capacity(mut self, capacity: usize) -> Deduplicator {
+        self.capacity = capacity;
+        self.evict();
+        self
+    }
+
+    // least recently seen first
+    pub fn seen(&self) -> impl Iterator<Item=&Fingerprint> {
+        self.order.values()
+    }
+
+    // drops every change that's already been delivered and marks the rest as seen; returns None
+    // if nothing new is left
+    pub fn filter(&mut self, changeset: Changeset) -> Option<Changeset> {
+        self.filter_for(0, changeset)
+    }
+
+    // the same, for one sink of a notifier with several (see `notify::Notifier::sink_count`), so
+    // each one is sent what it hasn't had yet; sink 0's fingerprints are the ones `filter` uses
+    pub fn filter_for(&mut self, sink: usize, changeset: Changeset) -> Option<Changeset> {
+        retain_changes(changeset, &mut |fingerprint, inverse| self.deliver(sink, fingerprint, inverse))
+    }
+
+    // what `filter_for` would let through, without marking any of it as seen; once it's been
+    // delivered, `mark_for` does that
+    pub fn unseen_for(&mut self, sink: usize, changeset: Changeset) -> Option<Changeset> {
+        let mut fresh = HashSet::new();
+
+        retain_changes(changeset, &mut |fingerprint, _| {
+            let key = sink_key(sink, fingerprint);
+            !self.touch(key) && fresh.insert(key)
+        })
+    }
+
+    pub fn mark_for(&mut self, sink: usize, changeset: &Changeset) {
+        retain_changes(changeset.clone(), &mut |fingerprint, inverse| self.deliver(sink, fingerprint, inverse));
+    }
+
+    // true if the change is new to `sink`. Its inverse is forgotten, so a score that goes back to
+    // what it was and then changes again is sent every time it moves
+    fn deliver(&mut self, sink: usize, fingerprint: Fingerprint, inverse: Fingerprint) -> bool {
+        let fresh = self.insert(sink_key(sink, fingerprint));
+        if fresh {
+            self.forget(sink_key(sink, inverse));
+        }
+        fresh
+    }
+
+    // whether `key` has been seen, making it the most recently seen if so
+    fn touch(&mut self, key: Fingerprint) -> bool {
+        self.generation += 1;
+        match self.seen.get_mut(&key) {
+            Some(generation) => {
+                self.order.remove(generation);
+                *generation = self.generation;
+                self.order.insert(self.generation, key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // true if `key` is new
+    fn insert(&mut self, key: Fingerprint) -> bool {
+        if self.touch(key) {
+            return false;
+        }
+
+        self.seen.insert(key, self.generation);
+        self.order.insert(self.generation, key);
+        self.evict();
+        true
+    }
+
+    fn forget(&mut self, key: Fingerprint) {
+        if let Some(generation) = self.seen.remove(&key) {
+            self.order.remove(&generation);
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.seen.len() > self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(&generation) => generation,
+                None => { break; }
+            };
+            if let Some(key) = self.order.remove(&oldest) {
+                self.seen.remove(&key);
+            }
+        }
+    }
+}
+
+fn sink_key(sink: usize, fingerprint: Fingerprint) -> Fingerprint {
+    match sink {
+        0 => fingerprint,
+        _ => Fnv::new("sink").int(sink as i64).hash(fingerprint).finish(),
+    }
+}
+
+// keeps the changes `keep` says yes to, given their fingerprints and the fingerprints of their
+// inverses; None if that's none of them
+fn retain_changes(changeset: Changeset, keep: &mut dyn FnMut(Fingerprint, Fingerprint) -> bool) -> Option<Changeset> {
+    let Changeset { old, new, changes } = changeset;
+    let changes: Vec<CourseChanges> = changes.into_iter()
+        .filter_map(|ccs| retain_course_changes(ccs, keep))
+        .collect();
+
+    if changes.is_empty() {
+        None
+    } else {
+        Some(Changeset {
+            old,
+            new,
+            changes,
+        })
+    }
+}
+
+fn retain_course_changes(mut ccs: CourseChanges, keep: &mut dyn FnMut(Fingerprint, Fingerprint) -> bool)
+    -> Option<CourseChanges> {
+
+    let title = match ccs.new.as_ref().or(ccs.old.as_ref()) {
+        Some(c) => c.title.clone(),
+        None => { return None; }
+    };
+
+    ccs.changes = ccs.changes.take()
+        .map(|cs| {
+            cs.into_iter()
+                .filter(|c| keep(course_change_fingerprint(&title, c), course_change_fingerprint(&title, &c.invert())))
+                .collect::<Vec<_>>()
+        })
+        .and_then(|cs| if cs.is_empty() { None } else { Some(cs) });
+
+    ccs.assignment_changes = ccs.assignment_changes.take()
+        .map(|acs| {
+            acs.into_iter()
+                .filter_map(|ac| retain_assignment_changes(&title, ac, keep))
+                .collect::<Vec<_>>()
+        })
+        .and_then(|acs| if acs.is_empty() { None } else { Some(acs) });
+
+    if ccs.changes.is_none() && ccs.assignment_changes.is_none() {
+        None
+    } else {
+        Some(ccs)
+    }
+}
+
+fn retain_assignment_changes(title: &CourseTitle, mut acs: AssignmentChanges,
+                             keep: &mut dyn FnMut(Fingerprint, Fingerprint) -> bool) -> Option<AssignmentChanges> {
+
+    let gradebook_id = match acs.new.as_ref().or(acs.old.as_ref()) {
+        Some(a) => a.gradebook_id.clone(),
+        None => { return None; }
+    };
+
+    acs.changes.retain(|c| {
+        keep(assignment_change_fingerprint(title, &gradebook_id, c),
+             assignment_change_fingerprint(title, &gradebook_id, &c.invert()))
+    });
+
+    if acs.changes.is_empty() {
+        None
+    } else {
+        Some(acs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::{Gradebook, Points};
+
+    const NO_STANDARDS: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    // the fixture, and the fixture with its reading log graded
+    fn gradebooks() -> (Gradebook, Gradebook) {
+        let old = Gradebook::from_xml(NO_STANDARDS).unwrap();
+        let mut new = old.clone();
+        {
+            let log = &mut new.courses[0].marks[0].assignments[1];
+            log.score = AssignmentScore::Score(Points(18.0), Points(20.0));
+            log.points = AssignmentPoints::Graded(Points(18.0), Points(20.0));
+        }
+        (old, new)
+    }
+
+    // the graded reading log, and the vocabulary check graded too
+    fn graded_further(new: &Gradebook) -> Gradebook {
+        let mut newer = new.clone();
+        newer.courses[0].marks[0].assignments[2].score = AssignmentScore::Score(Points(9.0), Points(10.0));
+        newer
+    }
+
+    fn assignment_kinds(changeset: Option<Changeset>) -> Vec<(String, &'static str)> {
+        changeset.map(|changeset| {
+            changeset.changes.iter()
+                .flat_map(|ccs| ccs.assignment_changes.iter().flat_map(|acs| acs.iter()))
+                .flat_map(|acs| {
+                    let id = acs.new.as_ref().or(acs.old.as_ref()).unwrap().gradebook_id.clone();
+                    acs.changes.iter().map(move |c| (id.clone(), c.kind()))
+                })
+                .collect()
+        }).unwrap_or_default()
+    }
+
+    #[test]
+    fn does_not_resend_when_the_same_gradebooks_are_diffed_again() {
+        let (a, b) = gradebooks();
+        let mut dedup = Deduplicator::new();
+
+        assert!(dedup.filter(Changeset::diff(&a, &b).unwrap()).is_some());
+        assert!(dedup.filter(Changeset::diff(&a, &b).unwrap()).is_none());
+    }
+
+    #[test]
+    fn does_not_resend_a_change_diffed_from_a_baseline_that_was_never_moved() {
+        // delivered a -> b, but the snapshot wasn't saved (a crash, or a sink that failed), and by
+        // the next poll the gradebook had moved on to c
+        let (a, b) = gradebooks();
+        let c = graded_further(&b);
+        let mut dedup = Deduplicator::new();
+
+        dedup.filter(Changeset::diff(&a, &b).unwrap());
+        assert_eq!(assignment_kinds(dedup.filter(Changeset::diff(&a, &c).unwrap())), vec![
+            ("2003".to_string(), "score_change"),
+        ]);
+    }
+
+    #[test]
+    fn does_not_resend_to_a_sink_that_already_took_a_change() {
+        let (a, b) = gradebooks();
+        let c = graded_further(&b);
+        let mut dedup = Deduplicator::new();
+
+        // sink 0 took a -> b, sink 1 failed, so the baseline stayed at a
+        let first = Changeset::diff(&a, &b).unwrap();
+        dedup.mark_for(0, &first);
+
+        let retry = Changeset::diff(&a, &c).unwrap();
+        assert_eq!(assignment_kinds(dedup.unseen_for(0, retry.clone())), vec![("2003".to_string(), "score_change")]);
+        assert_eq!(assignment_kinds(dedup.unseen_for(1, retry)).len(), 3);
+    }
+
+    #[test]
+    fn resends_a_change_once_it_has_been_undone() {
+        let (a, b) = gradebooks();
+        let mut dedup = Deduplicator::new();
+
+        assert!(dedup.filter(Changeset::diff(&a, &b).unwrap()).is_some());
+        // the grade is taken back out, which is news once
+        assert!(dedup.filter(Changeset::diff(&b, &a).unwrap()).is_some());
+        assert!(dedup.filter(Changeset::diff(&b, &a).unwrap()).is_none());
+        // and put back in, which is news again
+        assert!(dedup.filter(Changeset::diff(&a, &b).unwrap()).is_some());
+        assert!(dedup.filter(Changeset::diff(&a, &b).unwrap()).is_none());
+    }
+
+    #[test]
+    fn keeps_sinks_apart() {
+        let (a, b) = gradebooks();
+        let mut dedup = Deduplicator::new();
+        let changeset = Changeset::diff(&a, &b).unwrap();
+
+        assert!(dedup.unseen_for(1, changeset.clone()).is_some());
+        dedup.mark_for(1, &changeset);
+        assert!(dedup.unseen_for(1, changeset.clone()).is_none());
+        assert!(dedup.unseen_for(0, changeset).is_some());
+    }
+
+    #[test]
+    fn forgets_the_least_recently_seen_past_its_capacity() {
+        let mut dedup = Deduplicator::new().capacity(2);
+
+        assert!(dedup.insert(1));
+        assert!(dedup.insert(2));
+        // 1 is now the most recently seen, so 2 goes first
+        assert!(!dedup.insert(1));
+        assert!(dedup.insert(3));
+        assert_eq!(dedup.seen().cloned().collect::<Vec<_>>(), vec![1, 3]);
+        assert!(dedup.insert(2));
+    }
+
+    #[test]
+    fn round_trips_through_from_seen() {
+        let (a, b) = gradebooks();
+        let mut dedup = Deduplicator::new();
+        dedup.filter(Changeset::diff(&a, &b).unwrap());
+
+        let mut restored = Deduplicator::from_seen(dedup.seen().cloned());
+        assert!(restored.filter(Changeset::diff(&a, &b).unwrap()).is_none());
+    }
+}
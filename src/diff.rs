@@ -2,14 +2,26 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use std::iter::IntoIterator;
 
+use attendance::{Absence, AbsencePeriod, Attendance};
 use gradebook::*;
+use messages::{Message, Messages};
+use schedule::{ClassListing, Schedule};
 
 use chrono::NaiveDate;
 
+// how far apart two percentages can be and still be the same score. Districts print percentages to
+// one decimal place, so one worked out from points (7 out of 30 is 23.333...) can be up to half of
+// that off from the printed one ("23.3")
+const PERCENTAGE_EPSILON: f64 = 0.05;
+
 pub trait Pairable<'a, K> {
     fn unique_key(&'a self) -> &'a K;
 }
 
+pub trait FallbackPairable {
+    fn fallback_eq(&self, other: &Self) -> bool;
+}
+
 pub trait IntoHashMap<'a, K, V> {
     fn into_hash_map(self) -> HashMap<&'a K, &'a V>;
 }
@@ -49,6 +61,31 @@ fn pair_values<'a, O, N, K, V>(old: O, new: N) -> Vec<(Option<&'a V>, Option<&'a
     pairs
 }
 
+// values whose unique keys changed between snapshots end up as an unpaired (old, None) and
+// (None, new); give those a second chance using the looser fallback comparison
+pub fn repair_pairs<'a, V>(pairs: Vec<(Option<&'a V>, Option<&'a V>)>) -> Vec<(Option<&'a V>, Option<&'a V>)>
+    where V: 'a + FallbackPairable {
+
+    let mut unpaired_new: Vec<&'a V> = pairs.iter()
+        .filter_map(|&(o, n)| if o.is_none() { n } else { None })
+        .collect();
+    let mut repaired = pairs.into_iter().fold(Vec::new(), |mut acc, (o, n)| {
+        match (o, n) {
+            (Some(ov), None) => {
+                let matched = unpaired_new.iter()
+                    .position(|nv| ov.fallback_eq(nv))
+                    .map(|i| unpaired_new.remove(i));
+                acc.push((o, matched));
+            }
+            (Some(_), Some(_)) => { acc.push((o, n)); }
+            _ => {}
+        }
+        acc
+    });
+    repaired.extend(unpaired_new.into_iter().map(|nv| (None, Some(nv))));
+    repaired
+}
+
 impl<'a, K, V> PairableCollection<'a, &'a [V], K, V> for [V]
     where K: 'a + Eq + Hash,
           V: 'a + Pairable<'a, K> {
@@ -67,6 +104,84 @@ impl<'a, K, V> PairableCollection<'a, &'a Vec<V>, K, V> for Vec<V>
     }
 }
 
+// every comparison the diff makes goes through one of these, so overriding a method changes what
+// counts as a change for that field
+pub trait DiffStrategy {
+    fn calculated_grade_eq(&self, old: &Mark, new: &Mark) -> bool {
+        old.calculated_grade() == new.calculated_grade()
+    }
+
+    fn course_title_eq(&self, old: &CourseTitle, new: &CourseTitle) -> bool {
+        old == new
+    }
+
+    fn date_eq(&self, old: &NaiveDate, new: &NaiveDate) -> bool {
+        old == new
+    }
+
+    fn due_date_eq(&self, old: &NaiveDate, new: &NaiveDate) -> bool {
+        old == new
+    }
+
+    fn highlight_cutoff_eq(&self, old: &i8, new: &i8) -> bool {
+        old == new
+    }
+
+    fn notes_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+
+    fn period_eq(&self, old: &i8, new: &i8) -> bool {
+        old == new
+    }
+
+    fn points_eq(&self, old: &AssignmentPoints, new: &AssignmentPoints) -> bool {
+        old == new
+    }
+
+    fn room_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+
+    // a score that's only written differently ("18 out of 20" becoming "90") hasn't changed; scores
+    // that aren't both percentages (ungraded, or out of nothing) are compared as they are
+    fn score_eq(&self, old: &AssignmentScore, new: &AssignmentScore) -> bool {
+        match (old.percentage(), new.percentage()) {
+            (Some(o), Some(n)) => (o - n).abs() < PERCENTAGE_EPSILON,
+            _ => old == new,
+        }
+    }
+
+    fn score_type_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+
+    fn staff_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+
+    fn staff_email_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+
+    fn standard_mark_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+
+    fn standard_proficiency_eq(&self, old: &Option<f64>, new: &Option<f64>) -> bool {
+        old == new
+    }
+
+    fn title_eq(&self, old: &str, new: &str) -> bool {
+        old == new
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultDiffStrategy;
+
+impl DiffStrategy for DefaultDiffStrategy {}
+
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
 pub struct Changeset {
@@ -77,9 +192,13 @@ pub struct Changeset {
 
 impl Changeset {
     pub fn diff(old: &Gradebook, new: &Gradebook) -> Option<Changeset> {
-        let pairs = old.courses().pair_with(new.courses());
+        Self::diff_with(old, new, &DefaultDiffStrategy)
+    }
+
+    pub fn diff_with<S: DiffStrategy>(old: &Gradebook, new: &Gradebook, strategy: &S) -> Option<Changeset> {
+        let pairs = repair_pairs(old.courses().pair_with(new.courses()));
         let changes = pairs.iter().fold(Vec::new(), |mut acc, &(o, n)| {
-            if let Some(ccs) = CourseChanges::diff(o, n) {
+            if let Some(ccs) = CourseChanges::diff(o, n, strategy) {
                 acc.push(ccs);
             }
             acc
@@ -91,10 +210,18 @@ impl Changeset {
             Some(Changeset {
                 old: old.clone(),
                 new: new.clone(),
-                changes: changes,
+                changes,
             })
         }
     }
+
+    pub fn invert(&self) -> Changeset {
+        Changeset {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
 }
 
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
@@ -112,11 +239,57 @@ pub enum CourseChange {
     Added,
     Dropped,
     CalculatedGradeChange { old: String, new: String },
+    HighlightCutoffChange { old: i8, new: i8 },
     PeriodChange { old: i8, new: i8 },
+    RoomChange { old: String, new: String },
     StaffChange { old: String, new: String },
     StaffEmailChange { old: String, new: String },
-    //we don't have a course title change because we pair courses by their title; if the title
-    //changes, rvue assumes it's a different course
+    // courses are paired by title, so this only comes up when the course ID's base code stayed
+    // the same (see `CourseTitle::same_course`); any other new title is a different course
+    TitleChange { old: CourseTitle, new: CourseTitle },
+}
+
+impl CourseChange {
+    // the same names the json export uses for "kind"
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            CourseChange::Added => "added",
+            CourseChange::Dropped => "dropped",
+            CourseChange::CalculatedGradeChange { .. } => "grade_change",
+            CourseChange::HighlightCutoffChange { .. } => "highlight_cutoff_change",
+            CourseChange::PeriodChange { .. } => "period_change",
+            CourseChange::RoomChange { .. } => "room_change",
+            CourseChange::StaffChange { .. } => "teacher_change",
+            CourseChange::StaffEmailChange { .. } => "teacher_email_change",
+            CourseChange::TitleChange { .. } => "title_change",
+        }
+    }
+
+    pub fn invert(&self) -> CourseChange {
+        match *self {
+            CourseChange::Added => CourseChange::Dropped,
+            CourseChange::Dropped => CourseChange::Added,
+            CourseChange::CalculatedGradeChange { ref old, ref new } => {
+                CourseChange::CalculatedGradeChange { old: new.clone(), new: old.clone() }
+            }
+            CourseChange::HighlightCutoffChange { old, new } => {
+                CourseChange::HighlightCutoffChange { old: new, new: old }
+            }
+            CourseChange::PeriodChange { old, new } => CourseChange::PeriodChange { old: new, new: old },
+            CourseChange::RoomChange { ref old, ref new } => {
+                CourseChange::RoomChange { old: new.clone(), new: old.clone() }
+            }
+            CourseChange::StaffChange { ref old, ref new } => {
+                CourseChange::StaffChange { old: new.clone(), new: old.clone() }
+            }
+            CourseChange::StaffEmailChange { ref old, ref new } => {
+                CourseChange::StaffEmailChange { old: new.clone(), new: old.clone() }
+            }
+            CourseChange::TitleChange { ref old, ref new } => {
+                CourseChange::TitleChange { old: new.clone(), new: old.clone() }
+            }
+        }
+    }
 }
 
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
@@ -138,38 +311,109 @@ pub enum AssignmentChange {
     PointsChange { old: AssignmentPoints, new: AssignmentPoints },
     ScoreChange { old: AssignmentScore, new: AssignmentScore },
     ScoreTypeChange { old: String, new: String },
+    // a standard, or one of its screen assignments, that only one of the two snapshots has; `mark`
+    // is the mark it has there
+    StandardAdded { standard: String, mark: String },
+    StandardRemoved { standard: String, mark: String },
+    StandardMarkChange { standard: String, old: String, new: String },
+    StandardProficiencyChange { standard: String, old: Option<f64>, new: Option<f64> },
     TitleChange { old: String, new: String },
 }
 
+impl AssignmentChange {
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            AssignmentChange::Added => "added",
+            AssignmentChange::Removed => "removed",
+            AssignmentChange::DateChange { .. } => "date_change",
+            AssignmentChange::DueDateChange { .. } => "due_date_change",
+            AssignmentChange::NotesChange { .. } => "notes_change",
+            AssignmentChange::PointsChange { .. } => "points_change",
+            AssignmentChange::ScoreChange { .. } => "score_change",
+            AssignmentChange::ScoreTypeChange { .. } => "score_type_change",
+            AssignmentChange::StandardAdded { .. } => "standard_added",
+            AssignmentChange::StandardRemoved { .. } => "standard_removed",
+            AssignmentChange::StandardMarkChange { .. } => "standard_mark_change",
+            AssignmentChange::StandardProficiencyChange { .. } => "standard_proficiency_change",
+            AssignmentChange::TitleChange { .. } => "title_change",
+        }
+    }
+
+    pub fn invert(&self) -> AssignmentChange {
+        match *self {
+            AssignmentChange::Added => AssignmentChange::Removed,
+            AssignmentChange::Removed => AssignmentChange::Added,
+            AssignmentChange::DateChange { old, new } => AssignmentChange::DateChange { old: new, new: old },
+            AssignmentChange::DueDateChange { old, new } => AssignmentChange::DueDateChange { old: new, new: old },
+            AssignmentChange::NotesChange { ref old, ref new } => {
+                AssignmentChange::NotesChange { old: new.clone(), new: old.clone() }
+            }
+            AssignmentChange::PointsChange { ref old, ref new } => {
+                AssignmentChange::PointsChange { old: new.clone(), new: old.clone() }
+            }
+            AssignmentChange::ScoreChange { ref old, ref new } => {
+                AssignmentChange::ScoreChange { old: new.clone(), new: old.clone() }
+            }
+            AssignmentChange::ScoreTypeChange { ref old, ref new } => {
+                AssignmentChange::ScoreTypeChange { old: new.clone(), new: old.clone() }
+            }
+            AssignmentChange::StandardAdded { ref standard, ref mark } => {
+                AssignmentChange::StandardRemoved { standard: standard.clone(), mark: mark.clone() }
+            }
+            AssignmentChange::StandardRemoved { ref standard, ref mark } => {
+                AssignmentChange::StandardAdded { standard: standard.clone(), mark: mark.clone() }
+            }
+            AssignmentChange::StandardMarkChange { ref standard, ref old, ref new } => {
+                AssignmentChange::StandardMarkChange { standard: standard.clone(), old: new.clone(), new: old.clone() }
+            }
+            AssignmentChange::StandardProficiencyChange { ref standard, old, new } => {
+                AssignmentChange::StandardProficiencyChange { standard: standard.clone(), old: new, new: old }
+            }
+            AssignmentChange::TitleChange { ref old, ref new } => {
+                AssignmentChange::TitleChange { old: new.clone(), new: old.clone() }
+            }
+        }
+    }
+}
+
 macro_rules! add_change {
-    ( $change_t:tt, $variant:tt, $field:tt, $changes:expr, $old:expr, $new:expr ) => {
-        if $old.$field != $new.$field {
+    ( $change_t:tt, $variant:tt, $field:tt, $eq:ident, $changes:expr, $strategy:expr, $old:expr, $new:expr ) => {
+        if !$strategy.$eq(&$old.$field, &$new.$field) {
             $changes.push($change_t::$variant { old: $old.$field.clone(), new: $new.$field.clone() });
         }
     };
 }
 
 macro_rules! diff {
-    ( [$( $field:tt: $variant:tt ),+], $change_t:tt, $changes:expr, $old:expr, $new:expr ) => {
+    ( [$( $field:tt: $variant:tt => $eq:ident ),+], $change_t:tt, $changes:expr, $strategy:expr, $old:expr, $new:expr ) => {
         $(
-            add_change!($change_t, $variant, $field, $changes, $old, $new);
+            add_change!($change_t, $variant, $field, $eq, $changes, $strategy, $old, $new);
         )+
     };
 }
 
 impl AssignmentChanges {
-    fn diff(old: &Assignment, new: &Assignment) -> Option<AssignmentChanges> {
+    pub fn invert(&self) -> AssignmentChanges {
+        AssignmentChanges {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+
+    fn diff<S: DiffStrategy>(old: &Assignment, new: &Assignment, strategy: &S) -> Option<AssignmentChanges> {
         let mut changes = Vec::new();
 
         diff!([
-            date: DateChange,
-            due_date: DueDateChange,
-            notes: NotesChange,
-            points: PointsChange,
-            score: ScoreChange,
-            score_type: ScoreTypeChange,
-            measure: TitleChange
-        ], AssignmentChange, changes, &old, &new);
+            date: DateChange => date_eq,
+            due_date: DueDateChange => due_date_eq,
+            notes: NotesChange => notes_eq,
+            points: PointsChange => points_eq,
+            score: ScoreChange => score_eq,
+            score_type: ScoreTypeChange => score_type_eq,
+            measure: TitleChange => title_eq
+        ], AssignmentChange, changes, strategy, &old, &new);
+        changes.append(&mut Self::diff_standards(old, new, strategy));
 
         if changes.is_empty() {
             None
@@ -177,14 +421,74 @@ impl AssignmentChanges {
             Some(AssignmentChanges {
                 old: Some(old.clone()),
                 new: Some(new.clone()),
-                changes: changes,
+                changes,
             })
         }
     }
+
+    // in standards-based grading, teachers often update the rubric mark on an assignment without
+    // touching its score, so look at the per-assignment standard marks too
+    fn diff_standards<S: DiffStrategy>(old: &Assignment, new: &Assignment, strategy: &S) -> Vec<AssignmentChange> {
+        let added_or_removed = |standard: &Standard, mark: &str, added: bool| {
+            let (standard, mark) = (standard.description.clone(), mark.to_string());
+            if added {
+                AssignmentChange::StandardAdded { standard, mark }
+            } else {
+                AssignmentChange::StandardRemoved { standard, mark }
+            }
+        };
+
+        let pairs = old.standards().pair_with(new.standards());
+        pairs.iter().fold(Vec::new(), |mut acc, &(o, n)| {
+            match (o, n) {
+                (Some(o), Some(n)) => {
+                    let screen_pairs = o.standard_screen_assignments().pair_with(n.standard_screen_assignments());
+
+                    for &(so, sn) in screen_pairs.iter() {
+                        match (so, sn) {
+                            (Some(so), Some(sn)) => {
+                                if !strategy.standard_mark_eq(&so.mark, &sn.mark) {
+                                    acc.push(AssignmentChange::StandardMarkChange {
+                                        standard: n.description.clone(),
+                                        old: so.mark.clone(),
+                                        new: sn.mark.clone(),
+                                    });
+                                }
+                                if !strategy.standard_proficiency_eq(&so.proficiency, &sn.proficiency) {
+                                    acc.push(AssignmentChange::StandardProficiencyChange {
+                                        standard: n.description.clone(),
+                                        old: so.proficiency,
+                                        new: sn.proficiency,
+                                    });
+                                }
+                            }
+                            (None, Some(sn)) => { acc.push(added_or_removed(n, &sn.mark, true)); }
+                            (Some(so), None) => { acc.push(added_or_removed(o, &so.mark, false)); }
+                            (None, None) => {}
+                        }
+                    }
+                }
+                (None, Some(n)) => { acc.push(added_or_removed(n, &n.mark, true)); }
+                (Some(o), None) => { acc.push(added_or_removed(o, &o.mark, false)); }
+                (None, None) => {}
+            }
+            acc
+        })
+    }
 }
 
 impl CourseChanges {
-    fn diff(old: Option<&Course>, new: Option<&Course>) -> Option<CourseChanges> {
+    pub fn invert(&self) -> CourseChanges {
+        CourseChanges {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            assignment_changes: self.assignment_changes.as_ref()
+                .map(|acs| acs.iter().map(|ac| ac.invert()).collect()),
+            changes: self.changes.as_ref().map(|cs| cs.iter().map(|c| c.invert()).collect()),
+        }
+    }
+
+    fn diff<S: DiffStrategy>(old: Option<&Course>, new: Option<&Course>, strategy: &S) -> Option<CourseChanges> {
         if old.is_none() && new.is_none() {
             return None;
         }
@@ -201,16 +505,31 @@ impl CourseChanges {
                 let mut changes = Vec::new();
 
                 diff!([
-                    period: PeriodChange,
-                    staff: StaffChange,
-                    staff_email: StaffEmailChange
-                ], CourseChange, changes, &c1, &c2);
+                    highlight_percentage_cut_off_for_progress_bar: HighlightCutoffChange => highlight_cutoff_eq,
+                    period: PeriodChange => period_eq,
+                    room: RoomChange => room_eq,
+                    staff: StaffChange => staff_eq,
+                    staff_email: StaffEmailChange => staff_email_eq
+                ], CourseChange, changes, strategy, &c1, &c2);
 
-                if let Some(grade_change) = Self::diff_overall_grades(&c1.marks[0], &c2.marks[0]) {
-                    changes.push(grade_change);
+                if !strategy.course_title_eq(&c1.title, &c2.title) {
+                    changes.push(CourseChange::TitleChange { old: c1.title.clone(), new: c2.title.clone() });
                 }
 
-                let assignment_changes = Self::diff_assignments(&c1.marks[0], &c2.marks[0]);
+                let assignment_changes = match (c1.official_mark(), c2.official_mark()) {
+                    (Some(m1), Some(m2)) => {
+                        if let Some(grade_change) = Self::diff_overall_grades(m1, m2, strategy) {
+                            changes.push(grade_change);
+                        }
+
+                        Self::diff_assignments(m1, m2, strategy)
+                    }
+                    // courses are listed before they have a mark (and after their last one is
+                    // gone) around the start and end of a term
+                    (None, Some(_)) => Self::whole_course_assignments(c2, true),
+                    (Some(_), None) => Self::whole_course_assignments(c1, false),
+                    (None, None) => Vec::new(),
+                };
 
                 match (changes.is_empty(), assignment_changes.is_empty()) {
                     (true, true) => { return None; }
@@ -222,12 +541,17 @@ impl CourseChanges {
                     }
                 }
             }
-            (Some(_), _) | (None, Some(_)) => {
+            (Some(c), None) | (None, Some(c)) => {
                 course_changes.changes = Some(vec![if old.is_none() {
                     CourseChange::Added
                 } else {
                     CourseChange::Dropped
-                }])
+                }]);
+
+                let assignment_changes = Self::whole_course_assignments(c, old.is_none());
+                if !assignment_changes.is_empty() {
+                    course_changes.assignment_changes = Some(assignment_changes);
+                }
             }
             (None, None) => { return None; }
         }
@@ -239,26 +563,44 @@ impl CourseChanges {
         }
     }
 
-    fn diff_overall_grades(old: &Mark, new: &Mark) -> Option<CourseChange> {
-        let old_grade = old.calculated_grade();
-        let new_grade = new.calculated_grade();
+    // when a whole course comes or goes, so do all of its assignments
+    fn whole_course_assignments(course: &Course, added: bool) -> Vec<AssignmentChanges> {
+        let assignments = course.official_mark().map(|m| m.assignments()).unwrap_or(&[]);
+
+        assignments.iter().map(|a| {
+            if added {
+                AssignmentChanges {
+                    old: None,
+                    new: Some(a.clone()),
+                    changes: vec![AssignmentChange::Added],
+                }
+            } else {
+                AssignmentChanges {
+                    old: Some(a.clone()),
+                    new: None,
+                    changes: vec![AssignmentChange::Removed],
+                }
+            }
+        }).collect()
+    }
 
-        if old_grade != new_grade {
+    fn diff_overall_grades<S: DiffStrategy>(old: &Mark, new: &Mark, strategy: &S) -> Option<CourseChange> {
+        if !strategy.calculated_grade_eq(old, new) {
             Some(CourseChange::CalculatedGradeChange {
-                old: old_grade,
-                new: new_grade,
+                old: old.calculated_grade(),
+                new: new.calculated_grade(),
             })
         } else {
             None
         }
     }
 
-    fn diff_assignments(old: &Mark, new: &Mark) -> Vec<AssignmentChanges> {
-        let pairs = old.assignments().pair_with(new.assignments());
+    fn diff_assignments<S: DiffStrategy>(old: &Mark, new: &Mark, strategy: &S) -> Vec<AssignmentChanges> {
+        let pairs = repair_pairs(old.assignments().pair_with(new.assignments()));
         pairs.iter().fold(Vec::new(), |mut acc, &(o, n)| {
             match (o, n) {
-                (Some(ref a1), Some(ref a2)) => {
-                    if let Some(acs) = AssignmentChanges::diff(a1, a2) {
+                (Some(a1), Some(a2)) => {
+                    if let Some(acs) = AssignmentChanges::diff(a1, a2, strategy) {
                         acc.push(acs);
                     }
                     acc
@@ -280,3 +622,1441 @@ impl CourseChanges {
         })
     }
 }
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct PeriodComparison {
+    pub previous_period: ReportingPeriod,
+    pub current_period: ReportingPeriod,
+    pub courses: Vec<CourseComparison>,
+}
+
+impl PeriodComparison {
+    // unlike `Changeset::diff`, this is meant for two gradebooks from different reporting periods,
+    // so every course shows up in the result even if nothing about it changed
+    pub fn compare(previous: &Gradebook, current: &Gradebook) -> PeriodComparison {
+        let pairs = previous.courses().pair_with(current.courses());
+        let courses = pairs.iter().fold(Vec::new(), |mut acc, &(p, c)| {
+            if let Some(cc) = CourseComparison::compare(p, c) {
+                acc.push(cc);
+            }
+            acc
+        });
+
+        PeriodComparison {
+            previous_period: previous.reporting_period.clone(),
+            current_period: current.reporting_period.clone(),
+            courses,
+        }
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct CourseComparison {
+    pub previous: Option<Course>,
+    pub current: Option<Course>,
+    pub changes: Vec<PeriodChange>,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub enum PeriodChange {
+    Added,
+    Dropped,
+    CategoryAdded { category: String, weight: AssignmentGradeCalcWeight },
+    CategoryRemoved { category: String, weight: AssignmentGradeCalcWeight },
+    CategoryWeightChange { category: String, old: AssignmentGradeCalcWeight, new: AssignmentGradeCalcWeight },
+    GradeDelta { old: String, new: String, delta: f64 },
+}
+
+impl CourseComparison {
+    fn compare(previous: Option<&Course>, current: Option<&Course>) -> Option<CourseComparison> {
+        let changes = match (previous, current) {
+            (Some(p), Some(c)) => {
+                match (p.official_mark(), c.official_mark()) {
+                    (Some(pm), Some(cm)) => {
+                        let mut changes = vec![PeriodChange::GradeDelta {
+                            old: pm.calculated_grade(),
+                            new: cm.calculated_grade(),
+                            delta: cm.calculated_score_raw - pm.calculated_score_raw,
+                        }];
+                        changes.append(&mut Self::compare_categories(pm, cm));
+                        changes
+                    }
+                    _ => Vec::new()
+                }
+            }
+            (None, Some(_)) => vec![PeriodChange::Added],
+            (Some(_), None) => vec![PeriodChange::Dropped],
+            (None, None) => { return None; }
+        };
+
+        Some(CourseComparison {
+            previous: previous.cloned(),
+            current: current.cloned(),
+            changes,
+        })
+    }
+
+    fn compare_categories(previous: &Mark, current: &Mark) -> Vec<PeriodChange> {
+        let pairs = previous.grade_calculation_summary().pair_with(current.grade_calculation_summary());
+        pairs.iter().fold(Vec::new(), |mut acc, &(p, c)| {
+            match (p, c) {
+                (Some(p), Some(c)) => {
+                    if p.weight != c.weight {
+                        acc.push(PeriodChange::CategoryWeightChange {
+                            category: c._type.clone(),
+                            old: p.weight.clone(),
+                            new: c.weight.clone(),
+                        });
+                    }
+                }
+                (None, Some(c)) => {
+                    acc.push(PeriodChange::CategoryAdded { category: c._type.clone(), weight: c.weight.clone() });
+                }
+                (Some(p), None) => {
+                    acc.push(PeriodChange::CategoryRemoved { category: p._type.clone(), weight: p.weight.clone() });
+                }
+                (None, None) => {}
+            }
+            acc
+        })
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct StudentChangeset {
+    pub student: String,
+    pub changeset: Changeset,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct HouseholdChangeset {
+    pub changesets: Vec<StudentChangeset>,
+}
+
+impl HouseholdChangeset {
+    // takes (label, old, new) for each child; children without changes are left out, and if no
+    // child has changes there's nothing to report at all
+    pub fn diff<'a, I>(snapshots: I) -> Option<HouseholdChangeset>
+        where I: IntoIterator<Item=(&'a str, &'a Gradebook, &'a Gradebook)> {
+
+        let changesets = snapshots.into_iter().fold(Vec::new(), |mut acc, (student, old, new)| {
+            if let Some(changeset) = Changeset::diff(old, new) {
+                acc.push(StudentChangeset {
+                    student: student.to_string(),
+                    changeset,
+                });
+            }
+            acc
+        });
+
+        if changesets.is_empty() {
+            None
+        } else {
+            Some(HouseholdChangeset {
+                changesets,
+            })
+        }
+    }
+
+    pub fn for_student(&self, student: &str) -> Option<&Changeset> {
+        self.changesets.iter()
+            .find(|sc| sc.student == student)
+            .map(|sc| &sc.changeset)
+    }
+}
+
+// new absences and tardies, and ones the school went back and changed (most often a reason
+// filled in once a parent called in), between two fetches of the attendance document
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct AttendanceChangeset {
+    pub old: Attendance,
+    pub new: Attendance,
+    // oldest first
+    pub changes: Vec<AbsenceChanges>,
+}
+
+impl AttendanceChangeset {
+    pub fn diff(old: &Attendance, new: &Attendance) -> Option<AttendanceChangeset> {
+        let mut changes = old.absences().pair_with(new.absences()).iter()
+            .fold(Vec::new(), |mut acc, &(o, n)| {
+                if let Some(acs) = AbsenceChanges::diff(o, n) {
+                    acc.push(acs);
+                }
+                acc
+            });
+        changes.sort_by_key(|acs| acs.date());
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(AttendanceChangeset {
+                old: old.clone(),
+                new: new.clone(),
+                changes,
+            })
+        }
+    }
+
+    pub fn invert(&self) -> AttendanceChangeset {
+        AttendanceChangeset {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct AbsenceChanges {
+    pub old: Option<Absence>,
+    pub new: Option<Absence>,
+    pub changes: Vec<AbsenceChange>,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub enum AbsenceChange {
+    Added,
+    // usually one entered by mistake
+    Removed,
+    NoteChange { old: String, new: String },
+    // the whole day's reason
+    ReasonChange { old: String, new: String },
+    PeriodAdded { period: i8, name: String, reason: String },
+    PeriodRemoved { period: i8, name: String, reason: String },
+    // e.g. "Absent" to "Tardy"
+    PeriodNameChange { period: i8, old: String, new: String },
+    PeriodReasonChange { period: i8, old: String, new: String },
+}
+
+impl AbsenceChange {
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            AbsenceChange::Added => "added",
+            AbsenceChange::Removed => "removed",
+            AbsenceChange::NoteChange { .. } => "note_change",
+            AbsenceChange::ReasonChange { .. } => "reason_change",
+            AbsenceChange::PeriodAdded { .. } => "period_added",
+            AbsenceChange::PeriodRemoved { .. } => "period_removed",
+            AbsenceChange::PeriodNameChange { .. } => "period_name_change",
+            AbsenceChange::PeriodReasonChange { .. } => "period_reason_change",
+        }
+    }
+
+    pub fn invert(&self) -> AbsenceChange {
+        match *self {
+            AbsenceChange::Added => AbsenceChange::Removed,
+            AbsenceChange::Removed => AbsenceChange::Added,
+            AbsenceChange::NoteChange { ref old, ref new } => {
+                AbsenceChange::NoteChange { old: new.clone(), new: old.clone() }
+            }
+            AbsenceChange::ReasonChange { ref old, ref new } => {
+                AbsenceChange::ReasonChange { old: new.clone(), new: old.clone() }
+            }
+            AbsenceChange::PeriodAdded { period, ref name, ref reason } => {
+                AbsenceChange::PeriodRemoved { period, name: name.clone(), reason: reason.clone() }
+            }
+            AbsenceChange::PeriodRemoved { period, ref name, ref reason } => {
+                AbsenceChange::PeriodAdded { period, name: name.clone(), reason: reason.clone() }
+            }
+            AbsenceChange::PeriodNameChange { period, ref old, ref new } => {
+                AbsenceChange::PeriodNameChange { period, old: new.clone(), new: old.clone() }
+            }
+            AbsenceChange::PeriodReasonChange { period, ref old, ref new } => {
+                AbsenceChange::PeriodReasonChange { period, old: new.clone(), new: old.clone() }
+            }
+        }
+    }
+}
+
+impl AbsenceChanges {
+    pub fn date(&self) -> NaiveDate {
+        match (self.new.as_ref(), self.old.as_ref()) {
+            (Some(a), _) | (None, Some(a)) => a.date,
+            (None, None) => NaiveDate::MIN,
+        }
+    }
+
+    pub fn invert(&self) -> AbsenceChanges {
+        AbsenceChanges {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+
+    fn diff(old: Option<&Absence>, new: Option<&Absence>) -> Option<AbsenceChanges> {
+        let changes = match (old, new) {
+            (Some(a1), Some(a2)) => {
+                let mut changes = Vec::new();
+
+                if a1.reason != a2.reason {
+                    changes.push(AbsenceChange::ReasonChange { old: a1.reason.clone(), new: a2.reason.clone() });
+                }
+                if a1.note != a2.note {
+                    changes.push(AbsenceChange::NoteChange { old: a1.note.clone(), new: a2.note.clone() });
+                }
+                changes.append(&mut Self::diff_periods(a1, a2));
+
+                changes
+            }
+            (None, Some(_)) => vec![AbsenceChange::Added],
+            (Some(_), None) => vec![AbsenceChange::Removed],
+            (None, None) => Vec::new(),
+        };
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(AbsenceChanges {
+                old: old.cloned(),
+                new: new.cloned(),
+                changes,
+            })
+        }
+    }
+
+    fn diff_periods(old: &Absence, new: &Absence) -> Vec<AbsenceChange> {
+        let mut pairs: Vec<(Option<&AbsencePeriod>, Option<&AbsencePeriod>)> = old.periods().pair_with(new.periods());
+        pairs.sort_by_key(|&(o, n)| o.or(n).map(|p| p.number));
+
+        pairs.iter().fold(Vec::new(), |mut acc, &(o, n)| {
+            match (o, n) {
+                (Some(p1), Some(p2)) => {
+                    if p1.name != p2.name {
+                        acc.push(AbsenceChange::PeriodNameChange {
+                            period: p2.number,
+                            old: p1.name.clone(),
+                            new: p2.name.clone(),
+                        });
+                    }
+                    if p1.reason != p2.reason {
+                        acc.push(AbsenceChange::PeriodReasonChange {
+                            period: p2.number,
+                            old: p1.reason.clone(),
+                            new: p2.reason.clone(),
+                        });
+                    }
+                }
+                (None, Some(p)) => {
+                    acc.push(AbsenceChange::PeriodAdded {
+                        period: p.number,
+                        name: p.name.clone(),
+                        reason: p.reason.clone(),
+                    });
+                }
+                (Some(p), None) => {
+                    acc.push(AbsenceChange::PeriodRemoved {
+                        period: p.number,
+                        name: p.name.clone(),
+                        reason: p.reason.clone(),
+                    });
+                }
+                (None, None) => {}
+            }
+            acc
+        })
+    }
+}
+
+// classes added and dropped, and rooms, periods and teachers that moved, between two fetches of a
+// term's schedule; mostly seen at semester turnover
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct ScheduleChangeset {
+    pub old: Schedule,
+    pub new: Schedule,
+    pub changes: Vec<ClassChanges>,
+}
+
+impl ScheduleChangeset {
+    pub fn diff(old: &Schedule, new: &Schedule) -> Option<ScheduleChangeset> {
+        let pairs = repair_pairs(old.classes().pair_with(new.classes()));
+        let changes = pairs.iter().fold(Vec::new(), |mut acc, &(o, n)| {
+            if let Some(ccs) = ClassChanges::diff(o, n) {
+                acc.push(ccs);
+            }
+            acc
+        });
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(ScheduleChangeset {
+                old: old.clone(),
+                new: new.clone(),
+                changes,
+            })
+        }
+    }
+
+    pub fn invert(&self) -> ScheduleChangeset {
+        ScheduleChangeset {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct ClassChanges {
+    pub old: Option<ClassListing>,
+    pub new: Option<ClassListing>,
+    pub changes: Vec<ClassChange>,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub enum ClassChange {
+    Added,
+    Dropped,
+    PeriodChange { old: String, new: String },
+    RoomChange { old: String, new: String },
+    TeacherChange { old: String, new: String },
+    TeacherEmailChange { old: String, new: String },
+    // only for a class paired by its section (see `ClassListing`'s `FallbackPairable`)
+    TitleChange { old: String, new: String },
+}
+
+impl ClassChange {
+    // the names `CourseChange::kind` uses for the same changes
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            ClassChange::Added => "added",
+            ClassChange::Dropped => "dropped",
+            ClassChange::PeriodChange { .. } => "period_change",
+            ClassChange::RoomChange { .. } => "room_change",
+            ClassChange::TeacherChange { .. } => "teacher_change",
+            ClassChange::TeacherEmailChange { .. } => "teacher_email_change",
+            ClassChange::TitleChange { .. } => "title_change",
+        }
+    }
+
+    pub fn invert(&self) -> ClassChange {
+        match *self {
+            ClassChange::Added => ClassChange::Dropped,
+            ClassChange::Dropped => ClassChange::Added,
+            ClassChange::PeriodChange { ref old, ref new } => {
+                ClassChange::PeriodChange { old: new.clone(), new: old.clone() }
+            }
+            ClassChange::RoomChange { ref old, ref new } => {
+                ClassChange::RoomChange { old: new.clone(), new: old.clone() }
+            }
+            ClassChange::TeacherChange { ref old, ref new } => {
+                ClassChange::TeacherChange { old: new.clone(), new: old.clone() }
+            }
+            ClassChange::TeacherEmailChange { ref old, ref new } => {
+                ClassChange::TeacherEmailChange { old: new.clone(), new: old.clone() }
+            }
+            ClassChange::TitleChange { ref old, ref new } => {
+                ClassChange::TitleChange { old: new.clone(), new: old.clone() }
+            }
+        }
+    }
+}
+
+impl ClassChanges {
+    pub fn invert(&self) -> ClassChanges {
+        ClassChanges {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+
+    fn diff(old: Option<&ClassListing>, new: Option<&ClassListing>) -> Option<ClassChanges> {
+        let changes = match (old, new) {
+            (Some(c1), Some(c2)) => {
+                let mut changes = Vec::new();
+                // districts pad these inconsistently
+                let changed = |old: &String, new: &String| old.trim() != new.trim();
+
+                if changed(&c1.course_title, &c2.course_title) {
+                    changes.push(ClassChange::TitleChange { old: c1.course_title.clone(), new: c2.course_title.clone() });
+                }
+                if changed(&c1.period, &c2.period) {
+                    changes.push(ClassChange::PeriodChange { old: c1.period.clone(), new: c2.period.clone() });
+                }
+                if changed(&c1.room, &c2.room) {
+                    changes.push(ClassChange::RoomChange { old: c1.room.clone(), new: c2.room.clone() });
+                }
+                if changed(&c1.teacher, &c2.teacher) {
+                    changes.push(ClassChange::TeacherChange { old: c1.teacher.clone(), new: c2.teacher.clone() });
+                }
+                if changed(&c1.teacher_email, &c2.teacher_email) {
+                    changes.push(ClassChange::TeacherEmailChange {
+                        old: c1.teacher_email.clone(),
+                        new: c2.teacher_email.clone(),
+                    });
+                }
+
+                changes
+            }
+            (None, Some(_)) => vec![ClassChange::Added],
+            (Some(_), None) => vec![ClassChange::Dropped],
+            (None, None) => Vec::new(),
+        };
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(ClassChanges {
+                old: old.cloned(),
+                new: new.cloned(),
+                changes,
+            })
+        }
+    }
+}
+
+// messages that arrived, went away or were marked read or unread between two fetches of the
+// inbox, paired by the district's message ID
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct MessageChangeset {
+    pub old: Messages,
+    pub new: Messages,
+    pub changes: Vec<MessageChanges>,
+}
+
+impl MessageChangeset {
+    pub fn diff(old: &Messages, new: &Messages) -> Option<MessageChangeset> {
+        let changes = old.messages().pair_with(new.messages()).iter()
+            .fold(Vec::new(), |mut acc, &(o, n)| {
+                if let Some(mcs) = MessageChanges::diff(o, n) {
+                    acc.push(mcs);
+                }
+                acc
+            });
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(MessageChangeset {
+                old: old.clone(),
+                new: new.clone(),
+                changes,
+            })
+        }
+    }
+
+    pub fn invert(&self) -> MessageChangeset {
+        MessageChangeset {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+
+    // the messages that came in since the old inbox and haven't been read yet
+    pub fn new_unread(&self) -> Vec<&Message> {
+        self.changes.iter()
+            .filter(|mcs| mcs.changes.iter().any(|c| matches!(*c, MessageChange::Added)))
+            .filter_map(|mcs| mcs.new.as_ref())
+            .filter(|m| !m.read)
+            .collect()
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct MessageChanges {
+    pub old: Option<Message>,
+    pub new: Option<Message>,
+    pub changes: Vec<MessageChange>,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub enum MessageChange {
+    Added,
+    // deleted, or aged out of the inbox
+    Removed,
+    ReadChange { old: bool, new: bool },
+    SubjectChange { old: String, new: String },
+}
+
+impl MessageChange {
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            MessageChange::Added => "added",
+            MessageChange::Removed => "removed",
+            MessageChange::ReadChange { .. } => "read_change",
+            MessageChange::SubjectChange { .. } => "subject_change",
+        }
+    }
+
+    pub fn invert(&self) -> MessageChange {
+        match *self {
+            MessageChange::Added => MessageChange::Removed,
+            MessageChange::Removed => MessageChange::Added,
+            MessageChange::ReadChange { old, new } => MessageChange::ReadChange { old: new, new: old },
+            MessageChange::SubjectChange { ref old, ref new } => {
+                MessageChange::SubjectChange { old: new.clone(), new: old.clone() }
+            }
+        }
+    }
+}
+
+impl MessageChanges {
+    pub fn invert(&self) -> MessageChanges {
+        MessageChanges {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            changes: self.changes.iter().map(|c| c.invert()).collect(),
+        }
+    }
+
+    fn diff(old: Option<&Message>, new: Option<&Message>) -> Option<MessageChanges> {
+        let changes = match (old, new) {
+            (Some(m1), Some(m2)) => {
+                let mut changes = Vec::new();
+
+                if m1.read != m2.read {
+                    changes.push(MessageChange::ReadChange { old: m1.read, new: m2.read });
+                }
+                if m1.subject != m2.subject {
+                    changes.push(MessageChange::SubjectChange { old: m1.subject.clone(), new: m2.subject.clone() });
+                }
+
+                changes
+            }
+            (None, Some(_)) => vec![MessageChange::Added],
+            (Some(_), None) => vec![MessageChange::Removed],
+            (None, None) => Vec::new(),
+        };
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(MessageChanges {
+                old: old.cloned(),
+                new: new.cloned(),
+                changes,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STANDARDS: &str = include_str!("../fixtures/gradebook_standards.xml");
+    const NO_STANDARDS: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+    const INBOX: &str = include_str!("../fixtures/messages.xml");
+
+    fn gradebook() -> Gradebook {
+        Gradebook::from_xml(NO_STANDARDS).unwrap()
+    }
+
+    fn assignment_changes(changeset: &Changeset) -> Vec<&AssignmentChanges> {
+        changeset.changes.iter()
+            .filter_map(|ccs| ccs.assignment_changes.as_ref())
+            .flat_map(|acs| acs.iter())
+            .collect()
+    }
+
+    fn kinds(acs: &AssignmentChanges) -> Vec<&'static str> {
+        acs.changes.iter().map(|c| c.kind()).collect()
+    }
+
+    fn standard_changes(acs: &AssignmentChanges) -> Vec<(&'static str, &str, &str)> {
+        acs.changes.iter()
+            .filter_map(|c| match *c {
+                AssignmentChange::StandardAdded { ref standard, ref mark } |
+                AssignmentChange::StandardRemoved { ref standard, ref mark } => Some((c.kind(), &standard[..], &mark[..])),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // what a changeset says, one line per course or assignment, in an order that doesn't depend on
+    // which side it was diffed from; the change types aren't PartialEq, so they're compared as text
+    fn summary(changeset: &Changeset) -> Vec<String> {
+        let mut lines: Vec<String> = changeset.changes.iter()
+            .flat_map(|ccs| {
+                let course = format!("{:?} -> {:?}",
+                                     ccs.old.as_ref().map(|c| c.title.to_string()),
+                                     ccs.new.as_ref().map(|c| c.title.to_string()));
+                let mut lines = vec![format!("{}: {:?}", course, ccs.changes)];
+
+                lines.extend(ccs.assignment_changes.iter().flat_map(|acs| acs.iter()).map(|acs| {
+                    format!("{} / {:?} -> {:?}: {:?}", course,
+                            acs.old.as_ref().map(|a| &a.gradebook_id),
+                            acs.new.as_ref().map(|a| &a.gradebook_id),
+                            acs.changes)
+                }));
+                lines
+            })
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    // a bit of everything: course and assignment field changes, an assignment and a course each
+    // added and removed
+    fn changed(old: &Gradebook) -> Gradebook {
+        let mut new = old.clone();
+        {
+            let english = &mut new.courses[0];
+            english.room = "110".to_string();
+
+            let mark = &mut english.marks[0];
+            mark.calculated_score_string = "B".to_string();
+            mark.calculated_score_raw = 85.0;
+            mark.assignments[0].score = AssignmentScore::Score(Points(40.0), Points(50.0));
+            mark.assignments[0].points = AssignmentPoints::Graded(Points(40.0), Points(50.0));
+            mark.assignments[0].notes = "see me".to_string();
+            mark.assignments.remove(2);
+
+            let mut essay = mark.assignments[1].clone();
+            essay.gradebook_id = "2004".to_string();
+            essay.measure = "Poetry Essay".to_string();
+            mark.assignments.push(essay);
+        }
+
+        let mut physics = new.courses[0].clone();
+        physics.title = CourseTitle::Parsed("Physics".to_string(), "SC3010".to_string());
+        physics.period = 4;
+        new.courses.remove(1);
+        new.courses.push(physics);
+        new
+    }
+
+    #[test]
+    fn inverting_a_diff_gives_the_diff_the_other_way() {
+        let old = gradebook();
+        let new = changed(&old);
+
+        let forward = Changeset::diff(&old, &new).unwrap();
+        let backward = Changeset::diff(&new, &old).unwrap();
+        let inverted = forward.invert();
+
+        assert_eq!(summary(&inverted), summary(&backward));
+        assert!(Changeset::diff(&inverted.old, &new).is_none());
+        assert!(Changeset::diff(&inverted.new, &old).is_none());
+        assert_eq!(summary(&inverted.invert()), summary(&forward));
+    }
+
+    #[test]
+    fn inverts_each_change() {
+        let date = NaiveDate::from_ymd_opt(2018, 9, 20).unwrap();
+        let later = NaiveDate::from_ymd_opt(2018, 9, 27).unwrap();
+        let standard = "Solves systems of linear equations".to_string();
+
+        let assignment_cases = vec![
+            (AssignmentChange::Added, AssignmentChange::Removed),
+            (AssignmentChange::DateChange { old: date, new: later }, AssignmentChange::DateChange { old: later, new: date }),
+            (AssignmentChange::DueDateChange { old: date, new: later },
+             AssignmentChange::DueDateChange { old: later, new: date }),
+            (AssignmentChange::NotesChange { old: "".to_string(), new: "late".to_string() },
+             AssignmentChange::NotesChange { old: "late".to_string(), new: "".to_string() }),
+            (AssignmentChange::PointsChange { old: AssignmentPoints::Ungraded(Points(20.0)), new: AssignmentPoints::Graded(Points(18.0), Points(20.0)) },
+             AssignmentChange::PointsChange { old: AssignmentPoints::Graded(Points(18.0), Points(20.0)), new: AssignmentPoints::Ungraded(Points(20.0)) }),
+            (AssignmentChange::ScoreChange { old: AssignmentScore::NotDue, new: AssignmentScore::Percentage(90.0) },
+             AssignmentChange::ScoreChange { old: AssignmentScore::Percentage(90.0), new: AssignmentScore::NotDue }),
+            (AssignmentChange::ScoreTypeChange { old: "Raw Score".to_string(), new: "Percentage".to_string() },
+             AssignmentChange::ScoreTypeChange { old: "Percentage".to_string(), new: "Raw Score".to_string() }),
+            (AssignmentChange::StandardAdded { standard: standard.clone(), mark: "3".to_string() },
+             AssignmentChange::StandardRemoved { standard: standard.clone(), mark: "3".to_string() }),
+            (AssignmentChange::StandardMarkChange { standard: standard.clone(), old: "2".to_string(), new: "3".to_string() },
+             AssignmentChange::StandardMarkChange { standard: standard.clone(), old: "3".to_string(), new: "2".to_string() }),
+            (AssignmentChange::StandardProficiencyChange { standard: standard.clone(), old: None, new: Some(3.0) },
+             AssignmentChange::StandardProficiencyChange { standard: standard.clone(), old: Some(3.0), new: None }),
+            (AssignmentChange::TitleChange { old: "Quiz".to_string(), new: "Systems Quiz".to_string() },
+             AssignmentChange::TitleChange { old: "Systems Quiz".to_string(), new: "Quiz".to_string() }),
+        ];
+        for (change, expected) in assignment_cases {
+            assert_eq!(format!("{:?}", change.invert()), format!("{:?}", expected));
+            assert_eq!(format!("{:?}", change.invert().invert()), format!("{:?}", change));
+        }
+
+        let english = CourseTitle::Parsed("English 10".to_string(), "EN1002".to_string());
+        let honors = CourseTitle::Parsed("English 10".to_string(), "EN1002-03".to_string());
+        let course_cases = vec![
+            (CourseChange::Added, CourseChange::Dropped),
+            (CourseChange::CalculatedGradeChange { old: "A (91.2)".to_string(), new: "B (85)".to_string() },
+             CourseChange::CalculatedGradeChange { old: "B (85)".to_string(), new: "A (91.2)".to_string() }),
+            (CourseChange::HighlightCutoffChange { old: 50, new: 60 }, CourseChange::HighlightCutoffChange { old: 60, new: 50 }),
+            (CourseChange::PeriodChange { old: 2, new: 3 }, CourseChange::PeriodChange { old: 3, new: 2 }),
+            (CourseChange::RoomChange { old: "108".to_string(), new: "110".to_string() },
+             CourseChange::RoomChange { old: "110".to_string(), new: "108".to_string() }),
+            (CourseChange::StaffChange { old: "Okafor, Chris".to_string(), new: "Nguyen, Sam".to_string() },
+             CourseChange::StaffChange { old: "Nguyen, Sam".to_string(), new: "Okafor, Chris".to_string() }),
+            (CourseChange::StaffEmailChange { old: "a@example.org".to_string(), new: "b@example.org".to_string() },
+             CourseChange::StaffEmailChange { old: "b@example.org".to_string(), new: "a@example.org".to_string() }),
+            (CourseChange::TitleChange { old: english.clone(), new: honors.clone() },
+             CourseChange::TitleChange { old: honors.clone(), new: english.clone() }),
+        ];
+        for (change, expected) in course_cases {
+            assert_eq!(format!("{:?}", change.invert()), format!("{:?}", expected));
+            assert_eq!(format!("{:?}", change.invert().invert()), format!("{:?}", change));
+        }
+    }
+
+    // (gradebook ID, kinds) for each assignment change of the one course that changed
+    fn whole_course(changeset: &Changeset) -> Vec<(String, Vec<&'static str>)> {
+        assert_eq!(changeset.changes.len(), 1);
+        changeset.changes[0].assignment_changes.iter()
+            .flat_map(|acs| acs.iter())
+            .map(|acs| {
+                let assignment = acs.new.as_ref().or(acs.old.as_ref()).unwrap();
+                (assignment.gradebook_id.clone(), kinds(acs))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_every_assignment_of_a_course_that_comes_or_goes() {
+        let with = gradebook();
+        let mut without = with.clone();
+        without.courses.remove(0);
+
+        let added = Changeset::diff(&without, &with).unwrap();
+        assert_eq!(added.changes[0].changes.as_ref().map(|cs| cs.iter().map(|c| c.kind()).collect()), Some(vec!["added"]));
+        assert_eq!(whole_course(&added), vec![
+            ("2001".to_string(), vec!["added"]),
+            ("2002".to_string(), vec!["added"]),
+            ("2003".to_string(), vec!["added"]),
+        ]);
+
+        let dropped = Changeset::diff(&with, &without).unwrap();
+        assert_eq!(dropped.changes[0].changes.as_ref().map(|cs| cs.iter().map(|c| c.kind()).collect()), Some(vec!["dropped"]));
+        assert_eq!(whole_course(&dropped), vec![
+            ("2001".to_string(), vec!["removed"]),
+            ("2002".to_string(), vec!["removed"]),
+            ("2003".to_string(), vec!["removed"]),
+        ]);
+    }
+
+    #[test]
+    fn lists_no_assignments_for_a_course_without_any() {
+        let with = gradebook();
+        let mut without = with.clone();
+        without.courses.remove(1);
+
+        let added = Changeset::diff(&without, &with).unwrap();
+        assert!(added.changes[0].assignment_changes.is_none());
+        let dropped = Changeset::diff(&with, &without).unwrap();
+        assert!(dropped.changes[0].assignment_changes.is_none());
+    }
+
+    #[test]
+    fn lists_every_assignment_when_a_course_gets_or_loses_its_only_mark() {
+        let with = gradebook();
+        let mut without = with.clone();
+        without.courses[0].marks.clear();
+
+        let graded = Changeset::diff(&without, &with).unwrap();
+        assert!(graded.changes[0].changes.is_none());
+        assert_eq!(whole_course(&graded), vec![
+            ("2001".to_string(), vec!["added"]),
+            ("2002".to_string(), vec!["added"]),
+            ("2003".to_string(), vec!["added"]),
+        ]);
+
+        let ungraded = Changeset::diff(&with, &without).unwrap();
+        assert_eq!(whole_course(&ungraded), vec![
+            ("2001".to_string(), vec!["removed"]),
+            ("2002".to_string(), vec!["removed"]),
+            ("2003".to_string(), vec!["removed"]),
+        ]);
+    }
+
+    // the default comparisons, except that the field named (the hook without its `_eq`) never
+    // counts as changed
+    struct Ignoring(&'static str);
+
+    impl Ignoring {
+        fn eq(&self, field: &str, eq: bool) -> bool {
+            self.0 == field || eq
+        }
+    }
+
+    impl DiffStrategy for Ignoring {
+        fn calculated_grade_eq(&self, old: &Mark, new: &Mark) -> bool {
+            self.eq("calculated_grade", DefaultDiffStrategy.calculated_grade_eq(old, new))
+        }
+
+        fn course_title_eq(&self, old: &CourseTitle, new: &CourseTitle) -> bool {
+            self.eq("course_title", DefaultDiffStrategy.course_title_eq(old, new))
+        }
+
+        fn date_eq(&self, old: &NaiveDate, new: &NaiveDate) -> bool {
+            self.eq("date", DefaultDiffStrategy.date_eq(old, new))
+        }
+
+        fn due_date_eq(&self, old: &NaiveDate, new: &NaiveDate) -> bool {
+            self.eq("due_date", DefaultDiffStrategy.due_date_eq(old, new))
+        }
+
+        fn highlight_cutoff_eq(&self, old: &i8, new: &i8) -> bool {
+            self.eq("highlight_cutoff", DefaultDiffStrategy.highlight_cutoff_eq(old, new))
+        }
+
+        fn notes_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("notes", DefaultDiffStrategy.notes_eq(old, new))
+        }
+
+        fn period_eq(&self, old: &i8, new: &i8) -> bool {
+            self.eq("period", DefaultDiffStrategy.period_eq(old, new))
+        }
+
+        fn points_eq(&self, old: &AssignmentPoints, new: &AssignmentPoints) -> bool {
+            self.eq("points", DefaultDiffStrategy.points_eq(old, new))
+        }
+
+        fn room_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("room", DefaultDiffStrategy.room_eq(old, new))
+        }
+
+        fn score_eq(&self, old: &AssignmentScore, new: &AssignmentScore) -> bool {
+            self.eq("score", DefaultDiffStrategy.score_eq(old, new))
+        }
+
+        fn score_type_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("score_type", DefaultDiffStrategy.score_type_eq(old, new))
+        }
+
+        fn staff_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("staff", DefaultDiffStrategy.staff_eq(old, new))
+        }
+
+        fn staff_email_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("staff_email", DefaultDiffStrategy.staff_email_eq(old, new))
+        }
+
+        fn standard_mark_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("standard_mark", DefaultDiffStrategy.standard_mark_eq(old, new))
+        }
+
+        fn standard_proficiency_eq(&self, old: &Option<f64>, new: &Option<f64>) -> bool {
+            self.eq("standard_proficiency", DefaultDiffStrategy.standard_proficiency_eq(old, new))
+        }
+
+        fn title_eq(&self, old: &str, new: &str) -> bool {
+            self.eq("title", DefaultDiffStrategy.title_eq(old, new))
+        }
+    }
+
+    fn all_kinds(changeset: Option<Changeset>) -> Vec<&'static str> {
+        changeset.map(|changeset| {
+            changeset.changes.iter()
+                .flat_map(|ccs| {
+                    let course = ccs.changes.iter().flat_map(|cs| cs.iter()).map(|c| c.kind());
+                    let assignments = ccs.assignment_changes.iter().flat_map(|acs| acs.iter()).flat_map(|acs| kinds(acs));
+                    course.chain(assignments).collect::<Vec<_>>()
+                })
+                .collect()
+        }).unwrap_or_default()
+    }
+
+    #[test]
+    fn compares_each_field_through_its_strategy_hook() {
+        fn first(gradebook: &mut Gradebook) -> &mut Assignment {
+            &mut gradebook.courses[0].marks[0].assignments[0]
+        }
+        fn screen(gradebook: &mut Gradebook) -> &mut StandardScreenAssignment {
+            &mut first(gradebook).standards[0].standard_screen_assignments[0]
+        }
+
+        type Change = fn(&mut Gradebook);
+
+        // (hook, fixture, change, the kind of change it makes)
+        let cases: Vec<(&str, &str, Change, &str)> = vec![
+            ("calculated_grade", NO_STANDARDS, |g| g.courses[0].marks[0].calculated_score_string = "B".to_string(), "grade_change"),
+            ("course_title", NO_STANDARDS,
+             |g| g.courses[0].title = CourseTitle::Parsed("English 10".to_string(), "EN1002-03".to_string()), "title_change"),
+            ("date", NO_STANDARDS, |g| first(g).date = first(g).date.succ_opt().unwrap(), "date_change"),
+            ("due_date", NO_STANDARDS, |g| first(g).due_date = first(g).due_date.succ_opt().unwrap(), "due_date_change"),
+            ("highlight_cutoff", NO_STANDARDS,
+             |g| g.courses[0].highlight_percentage_cut_off_for_progress_bar = 60, "highlight_cutoff_change"),
+            ("notes", NO_STANDARDS, |g| first(g).notes = "see me".to_string(), "notes_change"),
+            ("period", NO_STANDARDS, |g| g.courses[0].period = 5, "period_change"),
+            ("points", NO_STANDARDS, |g| first(g).points = AssignmentPoints::Graded(Points(40.0), Points(50.0)), "points_change"),
+            ("room", NO_STANDARDS, |g| g.courses[0].room = "110".to_string(), "room_change"),
+            ("score", NO_STANDARDS, |g| first(g).score = AssignmentScore::Score(Points(40.0), Points(50.0)), "score_change"),
+            ("score_type", NO_STANDARDS, |g| first(g).score_type = "Percentage".to_string(), "score_type_change"),
+            ("staff", NO_STANDARDS, |g| g.courses[0].staff = "Nguyen, Sam".to_string(), "teacher_change"),
+            ("staff_email", NO_STANDARDS, |g| g.courses[0].staff_email = "snguyen@example.org".to_string(), "teacher_email_change"),
+            ("standard_mark", STANDARDS, |g| screen(g).mark = "4".to_string(), "standard_mark_change"),
+            ("standard_proficiency", STANDARDS, |g| screen(g).proficiency = Some(4.0), "standard_proficiency_change"),
+            ("title", NO_STANDARDS, |g| first(g).measure = "Narrative".to_string(), "title_change"),
+        ];
+
+        for &(hook, fixture, change, kind) in cases.iter() {
+            let old = Gradebook::from_xml(fixture).unwrap();
+            let mut new = old.clone();
+            change(&mut new);
+
+            assert_eq!(all_kinds(Changeset::diff(&old, &new)), vec![kind], "{} by default", hook);
+            for &(ignored, ..) in cases.iter() {
+                let expected = if ignored == hook { Vec::new() } else { vec![kind] };
+                assert_eq!(all_kinds(Changeset::diff_with(&old, &new, &Ignoring(ignored))), expected,
+                           "{} when ignoring {}", hook, ignored);
+            }
+        }
+    }
+
+    #[test]
+    fn compares_scores_by_percentage() {
+        let cases = [
+            (AssignmentScore::Score(Points(18.0), Points(20.0)), AssignmentScore::Percentage(90.0), true),
+            (AssignmentScore::Score(Points(2.0), Points(3.0)), AssignmentScore::Score(Points(4.0), Points(6.0)), true),
+            (AssignmentScore::Score(Points(1.0), Points(3.0)), AssignmentScore::Percentage(100.0 / 3.0), true),
+            (AssignmentScore::Percentage(90.0), AssignmentScore::Percentage(90.0 + 1e-10), true),
+            // rounded to one decimal place by the district
+            (AssignmentScore::Score(Points(7.0), Points(30.0)), AssignmentScore::Percentage(23.3), true),
+            (AssignmentScore::Score(Points(2.0), Points(3.0)), AssignmentScore::Percentage(66.7), true),
+            (AssignmentScore::Percentage(90.0), AssignmentScore::Percentage(90.1), false),
+            (AssignmentScore::Percentage(90.0), AssignmentScore::Percentage(90.5), false),
+            (AssignmentScore::Score(Points(18.0), Points(20.0)), AssignmentScore::Score(Points(18.0), Points(25.0)), false),
+            // out of nothing, so there's no percentage to compare
+            (AssignmentScore::Score(Points(0.0), Points(0.0)), AssignmentScore::Score(Points(0.0), Points(0.0)), true),
+            (AssignmentScore::Score(Points(5.0), Points(0.0)), AssignmentScore::Score(Points(0.0), Points(0.0)), false),
+            (AssignmentScore::NotDue, AssignmentScore::NotDue, true),
+            (AssignmentScore::NotDue, AssignmentScore::Percentage(0.0), false),
+            (AssignmentScore::NotGraded, AssignmentScore::NotForGrading, false),
+            (AssignmentScore::Unparseable("MSG".to_string()), AssignmentScore::Unparseable("MSG".to_string()), true),
+        ];
+
+        for &(ref old, ref new, expected) in cases.iter() {
+            assert_eq!(DefaultDiffStrategy.score_eq(old, new), expected, "{:?} and {:?}", old, new);
+            assert_eq!(DefaultDiffStrategy.score_eq(new, old), expected, "{:?} and {:?}", new, old);
+        }
+    }
+
+    #[test]
+    fn re_pairs_an_assignment_graded_while_its_id_changed() {
+        let old = gradebook();
+        let mut new = old.clone();
+        {
+            let log = &mut new.courses[0].marks[0].assignments[1];
+            log.gradebook_id = "9999".to_string();
+            log.score = AssignmentScore::Score(Points(18.0), Points(20.0));
+            log.points = AssignmentPoints::Graded(Points(18.0), Points(20.0));
+        }
+
+        let changeset = Changeset::diff(&old, &new).unwrap();
+        let changes = assignment_changes(&changeset);
+
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].old.is_some() && changes[0].new.is_some());
+        assert_eq!(kinds(changes[0]), vec!["points_change", "score_change"]);
+    }
+
+    #[test]
+    fn does_not_re_pair_an_assignment_whose_points_possible_changed() {
+        let old = gradebook();
+        let mut new = old.clone();
+        {
+            let log = &mut new.courses[0].marks[0].assignments[1];
+            log.gradebook_id = "9999".to_string();
+            log.points = AssignmentPoints::Ungraded(Points(25.0));
+        }
+
+        let changeset = Changeset::diff(&old, &new).unwrap();
+        let mut kinds = assignment_changes(&changeset).iter().flat_map(|acs| kinds(acs)).collect::<Vec<_>>();
+        kinds.sort();
+
+        assert_eq!(kinds, vec!["added", "removed"]);
+    }
+
+    #[test]
+    fn reports_standards_only_one_snapshot_has() {
+        let old = Gradebook::from_xml(STANDARDS).unwrap();
+        let mut new = old.clone();
+        {
+            let quiz = &mut new.courses[0].marks[0].assignments[0];
+            let mut standard = quiz.standards[0].clone();
+            standard.description = "Graphs linear inequalities".to_string();
+            standard.mark = "3".to_string();
+            quiz.standards.push(standard);
+        }
+
+        let changeset = Changeset::diff(&old, &new).unwrap();
+        let changes = assignment_changes(&changeset);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(standard_changes(changes[0]), vec![("standard_added", "Graphs linear inequalities", "3")]);
+
+        let changeset = Changeset::diff(&new, &old).unwrap();
+        let changes = assignment_changes(&changeset);
+        assert_eq!(standard_changes(changes[0]), vec![("standard_removed", "Graphs linear inequalities", "3")]);
+    }
+
+    #[test]
+    fn reports_standard_assignments_only_one_snapshot_has() {
+        let old = Gradebook::from_xml(STANDARDS).unwrap();
+        let mut new = old.clone();
+        {
+            let standard = &mut new.courses[0].marks[0].assignments[0].standards[0];
+            let mut screen = standard.standard_screen_assignments[0].clone();
+            screen.assignment = "Systems Retake".to_string();
+            screen.mark = "4".to_string();
+            standard.standard_screen_assignments.push(screen);
+        }
+
+        let changeset = Changeset::diff(&old, &new).unwrap();
+        let changes = assignment_changes(&changeset);
+        assert_eq!(standard_changes(changes[0]), vec![("standard_added", "Solves systems of linear equations", "4")]);
+
+        let changeset = Changeset::diff(&new, &old).unwrap();
+        let changes = assignment_changes(&changeset);
+        assert_eq!(standard_changes(changes[0]), vec![("standard_removed", "Solves systems of linear equations", "4")]);
+    }
+
+    fn absence(date: (i32, u32, u32), reason: &str, periods: &[(i8, &str, &str)]) -> Absence {
+        Absence {
+            date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+            note: String::new(),
+            periods: periods.iter()
+                .map(|&(number, name, reason)| AbsencePeriod {
+                    course: "English 10".to_string(),
+                    name: name.to_string(),
+                    number,
+                    reason: reason.to_string(),
+                    staff: "Okafor, Chris".to_string(),
+                    staff_email: "cokafor@example.org".to_string(),
+                })
+                .collect(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn attendance(absences: Vec<Absence>) -> Attendance {
+        Attendance { absences, kind: "Period".to_string(), ..Default::default() }
+    }
+
+    // (date, kinds) for each absence that changed
+    fn absence_kinds(changeset: &AttendanceChangeset) -> Vec<(String, Vec<&'static str>)> {
+        changeset.changes.iter()
+            .map(|acs| (acs.date().to_string(), acs.changes.iter().map(|c| c.kind()).collect()))
+            .collect()
+    }
+
+    fn attendance_snapshots() -> (Attendance, Attendance) {
+        let old = attendance(vec![
+            absence((2018, 9, 24), "", &[(1, "Absent", "Unverified")]),
+            absence((2018, 9, 20), "Illness", &[(1, "Absent", "Illness"), (2, "Absent", "Illness")]),
+        ]);
+        let mut called_in = absence((2018, 9, 20), "Illness", &[(1, "Tardy", "Illness"), (3, "Absent", "Illness")]);
+        called_in.note = "parent called".to_string();
+        let new = attendance(vec![
+            called_in,
+            absence((2018, 10, 1), "", &[(4, "Tardy", "Unexcused Tardy")]),
+        ]);
+        (old, new)
+    }
+
+    #[test]
+    fn diffs_added_removed_and_changed_absences_oldest_first() {
+        let (old, new) = attendance_snapshots();
+        let changeset = AttendanceChangeset::diff(&old, &new).unwrap();
+
+        assert_eq!(absence_kinds(&changeset), vec![
+            ("2018-09-20".to_string(), vec!["note_change", "period_name_change", "period_removed", "period_added"]),
+            ("2018-09-24".to_string(), vec!["removed"]),
+            ("2018-10-01".to_string(), vec!["added"]),
+        ]);
+        assert_eq!(format!("{:?}", changeset.changes[0].changes[1]),
+                   format!("{:?}", AbsenceChange::PeriodNameChange { period: 1, old: "Absent".to_string(), new: "Tardy".to_string() }));
+        assert!(changeset.changes[1].new.is_none());
+        assert!(changeset.changes[2].old.is_none());
+    }
+
+    #[test]
+    fn diffs_a_changed_reason() {
+        let old = attendance(vec![absence((2018, 9, 24), "Unverified", &[])]);
+        let new = attendance(vec![absence((2018, 9, 24), "Excused", &[])]);
+
+        let changeset = AttendanceChangeset::diff(&old, &new).unwrap();
+        assert_eq!(format!("{:?}", changeset.changes[0].changes),
+                   format!("{:?}", vec![AbsenceChange::ReasonChange { old: "Unverified".to_string(), new: "Excused".to_string() }]));
+    }
+
+    #[test]
+    fn finds_nothing_between_the_same_attendance() {
+        let (old, _) = attendance_snapshots();
+        assert!(AttendanceChangeset::diff(&old, &old.clone()).is_none());
+    }
+
+    #[test]
+    fn inverting_an_attendance_diff_gives_the_diff_the_other_way() {
+        let (old, new) = attendance_snapshots();
+
+        let forward = AttendanceChangeset::diff(&old, &new).unwrap();
+        let backward = AttendanceChangeset::diff(&new, &old).unwrap();
+        let inverted = forward.invert();
+
+        assert_eq!(format!("{:?}", inverted.changes), format!("{:?}", backward.changes));
+        assert_eq!(absence_kinds(&inverted), vec![
+            ("2018-09-20".to_string(), vec!["note_change", "period_name_change", "period_added", "period_removed"]),
+            ("2018-09-24".to_string(), vec!["added"]),
+            ("2018-10-01".to_string(), vec!["removed"]),
+        ]);
+        assert_eq!(format!("{:?}", inverted.invert().changes), format!("{:?}", forward.changes));
+    }
+
+    fn class(title: &str, period: &str, room: &str, section_gu: &str) -> ClassListing {
+        ClassListing {
+            course_title: title.to_string(),
+            period: period.to_string(),
+            room: room.to_string(),
+            section_gu: section_gu.to_string(),
+            teacher: "Okafor, Chris".to_string(),
+            teacher_email: "cokafor@example.org".to_string(),
+        }
+    }
+
+    fn schedule(classes: Vec<ClassListing>) -> Schedule {
+        Schedule { classes, ..Default::default() }
+    }
+
+    // (old title, new title, kinds) for each class that changed, sorted
+    fn class_kinds(changeset: &ScheduleChangeset) -> Vec<(Option<String>, Option<String>, Vec<&'static str>)> {
+        let mut kinds: Vec<_> = changeset.changes.iter()
+            .map(|ccs| (ccs.old.as_ref().map(|c| c.course_title.clone()),
+                        ccs.new.as_ref().map(|c| c.course_title.clone()),
+                        ccs.changes.iter().map(|c| c.kind()).collect()))
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    #[test]
+    fn diffs_added_dropped_and_moved_classes() {
+        let old = schedule(vec![
+            class("English 10", "1", "108", "A1"),
+            class("Chemistry", "2", "Lab 2", "B2"),
+            class("Band", "3", "Music", "C3"),
+        ]);
+        let mut moved = class("Chemistry", " 3", "Lab 4", "B2");
+        moved.teacher = "Nguyen, Sam".to_string();
+        moved.teacher_email = "snguyen@example.org".to_string();
+        let new = schedule(vec![
+            // padded differently, which isn't a change
+            class("English 10", "1 ", " 108", "A1"),
+            moved,
+            class("Physics", "4", "Lab 1", "D4"),
+        ]);
+
+        let changeset = ScheduleChangeset::diff(&old, &new).unwrap();
+        assert_eq!(class_kinds(&changeset), vec![
+            (None, Some("Physics".to_string()), vec!["added"]),
+            (Some("Band".to_string()), None, vec!["dropped"]),
+            (Some("Chemistry".to_string()), Some("Chemistry".to_string()),
+             vec!["period_change", "room_change", "teacher_change", "teacher_email_change"]),
+        ]);
+        assert!(ScheduleChangeset::diff(&old, &old.clone()).is_none());
+    }
+
+    #[test]
+    fn pairs_a_retitled_class_by_its_section() {
+        let old = schedule(vec![class("Chemistry", "2", "Lab 2", "B2")]);
+        let new = schedule(vec![class("Chemistry Honors", "2", "Lab 2", "B2")]);
+
+        let changeset = ScheduleChangeset::diff(&old, &new).unwrap();
+        assert_eq!(class_kinds(&changeset), vec![
+            (Some("Chemistry".to_string()), Some("Chemistry Honors".to_string()), vec!["title_change"]),
+        ]);
+    }
+
+    #[test]
+    fn does_not_pair_retitled_classes_without_a_section() {
+        let old = schedule(vec![class("Chemistry", "2", "Lab 2", "")]);
+        let new = schedule(vec![class("Chemistry Honors", "2", "Lab 2", "")]);
+
+        let changeset = ScheduleChangeset::diff(&old, &new).unwrap();
+        assert_eq!(class_kinds(&changeset), vec![
+            (None, Some("Chemistry Honors".to_string()), vec!["added"]),
+            (Some("Chemistry".to_string()), None, vec!["dropped"]),
+        ]);
+    }
+
+    #[test]
+    fn inverting_a_schedule_diff_gives_the_diff_the_other_way() {
+        let old = schedule(vec![class("Chemistry", "2", "Lab 2", "B2"), class("Band", "3", "Music", "C3")]);
+        let new = schedule(vec![class("Chemistry Honors", "3", "Lab 4", "B2"), class("Physics", "4", "Lab 1", "D4")]);
+
+        let forward = ScheduleChangeset::diff(&old, &new).unwrap();
+        let backward = ScheduleChangeset::diff(&new, &old).unwrap();
+
+        assert_eq!(class_kinds(&forward.invert()), class_kinds(&backward));
+        assert_eq!(class_kinds(&forward.invert().invert()), class_kinds(&forward));
+    }
+
+    // (id, kinds) for each message that changed, sorted by id
+    fn message_kinds(changeset: &MessageChangeset) -> Vec<(String, Vec<&'static str>)> {
+        let mut kinds: Vec<_> = changeset.changes.iter()
+            .map(|mcs| {
+                let message = mcs.new.as_ref().or(mcs.old.as_ref()).unwrap();
+                (message.id.clone(), mcs.changes.iter().map(|c| c.kind()).collect())
+            })
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    fn next_inbox(old: &Messages) -> Messages {
+        let mut new = old.clone();
+        new.messages.remove(0);
+        new.messages[0].read = true;
+        new.messages[1].subject = "Picture day moved to Monday".to_string();
+
+        let mut reminder = old.messages[1].clone();
+        reminder.id = "M-4104".to_string();
+        reminder.subject = "Permission slips due".to_string();
+        new.messages.push(reminder);
+        new
+    }
+
+    #[test]
+    fn diffs_the_inbox_by_message_id() {
+        let old = Messages::from_xml(INBOX).unwrap();
+        let new = next_inbox(&old);
+
+        let changeset = MessageChangeset::diff(&old, &new).unwrap();
+        assert_eq!(message_kinds(&changeset), vec![
+            ("M-4101".to_string(), vec!["removed"]),
+            ("M-4102".to_string(), vec!["read_change"]),
+            ("M-4103".to_string(), vec!["subject_change"]),
+            ("M-4104".to_string(), vec!["added"]),
+        ]);
+        assert!(MessageChangeset::diff(&old, &old.clone()).is_none());
+    }
+
+    #[test]
+    fn lists_new_messages_that_are_still_unread() {
+        let old = Messages::from_xml(INBOX).unwrap();
+        let mut new = next_inbox(&old);
+
+        let changeset = MessageChangeset::diff(&old, &new).unwrap();
+        let unread: Vec<&str> = changeset.new_unread().iter().map(|m| &m.subject[..]).collect();
+        assert_eq!(unread, vec!["Permission slips due"]);
+
+        new.messages.last_mut().unwrap().read = true;
+        assert!(MessageChangeset::diff(&old, &new).unwrap().new_unread().is_empty());
+    }
+
+    #[test]
+    fn inverting_an_inbox_diff_gives_the_diff_the_other_way() {
+        let old = Messages::from_xml(INBOX).unwrap();
+        let new = next_inbox(&old);
+
+        let forward = MessageChangeset::diff(&old, &new).unwrap();
+        let backward = MessageChangeset::diff(&new, &old).unwrap();
+
+        assert_eq!(message_kinds(&forward.invert()), message_kinds(&backward));
+        assert_eq!(message_kinds(&forward.invert().invert()), message_kinds(&forward));
+        assert_eq!(forward.invert().new, old);
+    }
+
+    fn period_kinds(comparison: &PeriodComparison) -> Vec<(String, Vec<String>)> {
+        comparison.courses.iter()
+            .map(|cc| {
+                let course = cc.current.as_ref().or(cc.previous.as_ref()).unwrap();
+                (course.title.to_string(), cc.changes.iter().map(|c| format!("{:?}", c)).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compares_two_periods_course_by_course() {
+        let previous = gradebook();
+        let mut current = previous.clone();
+        current.reporting_period.grade_period = "Quarter 2".to_string();
+        {
+            let mark = &mut current.courses[0].marks[0];
+            mark.mark_name = "Quarter 2".to_string();
+            mark.calculated_score_string = "B".to_string();
+            mark.calculated_score_raw = 85.2;
+
+            let mut participation = mark.grade_calculation_summary[0].clone();
+            participation._type = "Participation".to_string();
+            participation.weight = AssignmentGradeCalcWeight::Percentage(20.0);
+            mark.grade_calculation_summary[0].weight = AssignmentGradeCalcWeight::Percentage(80.0);
+            mark.grade_calculation_summary.push(participation);
+        }
+        let mut physics = current.courses[1].clone();
+        physics.title = CourseTitle::Parsed("Physics".to_string(), "SC3010".to_string());
+        current.courses.remove(1);
+        current.courses.push(physics);
+
+        let comparison = PeriodComparison::compare(&previous, &current);
+        assert_eq!(comparison.previous_period.grade_period, "Quarter 1");
+        assert_eq!(comparison.current_period.grade_period, "Quarter 2");
+
+        let english = period_kinds(&comparison).remove(0);
+        assert_eq!(english.0, previous.courses[0].title.to_string());
+        assert_eq!(english.1, vec![
+            format!("{:?}", PeriodChange::GradeDelta { old: "A- (91.2)".to_string(), new: "B (85.2)".to_string(), delta: 85.2 - 91.2 }),
+            format!("{:?}", PeriodChange::CategoryWeightChange {
+                category: "Essays".to_string(),
+                old: AssignmentGradeCalcWeight::Percentage(100.0),
+                new: AssignmentGradeCalcWeight::Percentage(80.0),
+            }),
+            format!("{:?}", PeriodChange::CategoryAdded {
+                category: "Participation".to_string(),
+                weight: AssignmentGradeCalcWeight::Percentage(20.0),
+            }),
+        ]);
+
+        let rest: Vec<(String, Vec<String>)> = period_kinds(&comparison).into_iter().skip(1).collect();
+        assert_eq!(rest, vec![
+            (previous.courses[1].title.to_string(), vec!["Dropped".to_string()]),
+            ("Physics (SC3010)".to_string(), vec!["Added".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn lists_every_course_between_periods_even_if_nothing_changed() {
+        let previous = gradebook();
+        let comparison = PeriodComparison::compare(&previous, &previous.clone());
+
+        assert_eq!(comparison.courses.len(), previous.courses.len());
+        for cc in comparison.courses.iter() {
+            match cc.changes[..] {
+                [PeriodChange::GradeDelta { delta, .. }] => assert_eq!(delta, 0.0),
+                ref changes => panic!("expected only a zero grade delta, got {:?}", changes),
+            }
+        }
+    }
+
+    #[test]
+    fn labels_each_childs_changes_and_leaves_out_unchanged_children() {
+        let old = gradebook();
+        let new = changed(&old);
+        let unchanged = old.clone();
+
+        let household = HouseholdChangeset::diff(vec![
+            ("Ada", &old, &new),
+            ("Ben", &old, &unchanged),
+            ("Cy", &new, &old),
+        ]).unwrap();
+
+        let students: Vec<&str> = household.changesets.iter().map(|sc| &sc.student[..]).collect();
+        assert_eq!(students, vec!["Ada", "Cy"]);
+        assert_eq!(summary(household.for_student("Ada").unwrap()), summary(&Changeset::diff(&old, &new).unwrap()));
+        assert_eq!(summary(household.for_student("Cy").unwrap()), summary(&Changeset::diff(&new, &old).unwrap()));
+        assert!(household.for_student("Ben").is_none());
+    }
+
+    #[test]
+    fn finds_nothing_for_a_household_without_changes() {
+        let old = gradebook();
+        let other = Gradebook::from_xml(STANDARDS).unwrap();
+
+        assert!(HouseholdChangeset::diff(vec![("Ada", &old, &old), ("Ben", &other, &other)]).is_none());
+        assert!(HouseholdChangeset::diff(Vec::new()).is_none());
+    }
+}
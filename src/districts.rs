@@ -0,0 +1,268 @@
+// Finding a district's server by zip code, the way the StudentVUE apps do: Edupoint runs a directory
+// (HDInfoServices) that lists the districts near a zip code along with their portal addresses.
+use api::{district_endpoint, SVUEResponse, DISTRICT_LOOKUP_ENDPOINT, DISTRICT_LOOKUP_KEY, DISTRICT_LOOKUP_PASSWORD,
+          DISTRICT_LOOKUP_USER};
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUERequest, SVUERequestError};
+#[cfg(feature="client")]
+use credentials::Credentials;
+use decoder::*;
+
+use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct District {
+    pub id: String,
+    pub name: String,
+    pub address: String,
+    // the portal's address, e.g. "https://student.district.org/"
+    pub url: String,
+}
+
+impl District {
+    // the web service URL to point an `SVUEClient` at (see `SVUEClient::district`)
+    pub fn endpoint(&self) -> String {
+        district_endpoint(&self.url)
+    }
+}
+
+// where lookups go and what they log in with: Edupoint's directory with the apps' key and account
+// unless told otherwise, e.g. after an app update changes them or to point at a stand-in
+#[derive(Clone, Debug)]
+pub struct DistrictDirectory {
+    endpoint: String,
+    key: String,
+    user: String,
+    password: String,
+}
+
+impl Default for DistrictDirectory {
+    fn default() -> DistrictDirectory {
+        DistrictDirectory {
+            endpoint: DISTRICT_LOOKUP_ENDPOINT.to_string(),
+            key: DISTRICT_LOOKUP_KEY.to_string(),
+            user: DISTRICT_LOOKUP_USER.to_string(),
+            password: DISTRICT_LOOKUP_PASSWORD.to_string(),
+        }
+    }
+}
+
+impl DistrictDirectory {
+    pub fn new() -> DistrictDirectory {
+        Default::default()
+    }
+
+    pub fn endpoint(mut self, endpoint: &str) -> DistrictDirectory {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    pub fn key(mut self, key: &str) -> DistrictDirectory {
+        self.key = key.to_string();
+        self
+    }
+
+    pub fn account(mut self, user: &str, password: &str) -> DistrictDirectory {
+        self.user = user.to_string();
+        self.password = password.to_string();
+        self
+    }
+
+    pub fn endpoint_url(&self) -> &str {
+        &self.endpoint
+    }
+
+    // the districts this directory lists for `zip`; see `DistrictList::lookup`
+    #[cfg(feature="client")]
+    pub fn lookup(&self, zip: &str) -> Result<DistrictList, SVUERequestError> {
+        let credentials = Credentials::new(&self.user, &self.password);
+        let action = SVUEAPIAction::LookupDistricts(zip.trim().to_string(), self.key.clone());
+        let resp = SVUERequest::new(action, &credentials)
+            .endpoint(&self.endpoint)
+            .run()?;
+
+        DistrictList::from_response(&resp).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default)]
+pub struct DistrictList {
+    pub districts: Vec<District>,
+}
+
+impl DistrictList {
+    // the districts Edupoint lists for `zip`, nearest first as it orders them; the directory wants
+    // at least the first few digits, and answers an RT_ERROR otherwise
+    #[cfg(feature="client")]
+    pub fn lookup(zip: &str) -> Result<DistrictList, SVUERequestError> {
+        DistrictDirectory::new().lookup(zip)
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<DistrictList> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<DistrictList> {
+        let limits = DecodeLimits::new();
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+
+        match events_iter.next() {
+            Some(Ok(event)) => Self::from_event(event, &mut events_iter, &limits),
+            Some(Err(e)) => Err(DecodingError::EventError(e)),
+            None => Err(DecodingError::UnexpectedEnd),
+        }
+    }
+
+    // districts whose name contains `query`, ignoring case
+    pub fn matching(&self, query: &str) -> Vec<&District> {
+        let query = query.trim().to_lowercase();
+
+        self.districts.iter()
+            .filter(|d| d.name.to_lowercase().contains(&query))
+            .collect()
+    }
+}
+
+impl SVUEDecodeable for DistrictList {
+    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<DistrictList> {
+        let mut districts = Vec::new();
+
+        loop {
+            match events_iter.next() {
+                Some(Ok(event)) => {
+                    match event {
+                        _ if is_start_of(&event, "DistrictInfo") => {
+                            districts.push(District::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_end_of(&event, "DistrictLists") => {
+                            return Ok(DistrictList {
+                                districts,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                None => { return Err(DecodingError::UnexpectedEnd); }
+            }
+        }
+    }
+}
+
+impl SVUEDecodeable for District {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<District> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "DistrictInfo" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        Ok(District {
+                            id: get_attr_owned!(attrs, "DistrictID"),
+                            name: get_attr_owned!(attrs, "Name"),
+                            address: attrs.get("Address").map(|a| a.to_string()).unwrap_or_default(),
+                            url: get_attr_owned!(attrs, "PvueURL"),
+                        })
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::{SVUEAPIAction, SVUERequest, SVUERequestError};
+    use credentials::Credentials;
+
+    const DISTRICTS: &str = concat!(
+        "<DistrictLists xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\"><DistrictInfos>",
+        "<DistrictInfo DistrictID=\"3f2a\" Name=\"Portland Public Schools\" Address=\"Portland OR 97227\" ",
+        "PvueURL=\"https://sis.pps.example.org\" />",
+        "<DistrictInfo DistrictID=\"9c41\" Name=\"Parkrose School District\" ",
+        "PvueURL=\"https://student.parkrose.example.org/\" />",
+        "</DistrictInfos></DistrictLists>");
+
+    #[test]
+    fn decodes_the_directory_listing() {
+        let list = DistrictList::from_xml(DISTRICTS).unwrap();
+
+        assert_eq!(list.districts.len(), 2);
+        assert_eq!(list.districts[0], District {
+            id: "3f2a".to_string(),
+            name: "Portland Public Schools".to_string(),
+            address: "Portland OR 97227".to_string(),
+            url: "https://sis.pps.example.org".to_string(),
+        });
+        assert_eq!(list.districts[1].address, "");
+        assert_eq!(list.districts[1].endpoint(), "https://student.parkrose.example.org/Service/PXPCommunication.asmx");
+    }
+
+    #[test]
+    fn finds_districts_by_part_of_the_name() {
+        let list = DistrictList::from_xml(DISTRICTS).unwrap();
+
+        let names: Vec<&str> = list.matching(" PARK").iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["Parkrose School District"]);
+        assert_eq!(list.matching("school").len(), 2);
+        assert!(list.matching("salem").is_empty());
+    }
+
+    #[test]
+    fn rejects_a_listing_without_the_required_attributes() {
+        let xml = "<DistrictLists><DistrictInfos><DistrictInfo DistrictID=\"1\" /></DistrictInfos></DistrictLists>";
+
+        assert!(DistrictList::from_xml(xml).is_err());
+        assert!(DistrictList::from_xml("<DistrictLists><DistrictInfos>").is_err());
+    }
+
+    #[test]
+    fn sends_the_directory_key_and_turns_away_unsafe_ones() {
+        let credentials = Credentials::new(DISTRICT_LOOKUP_USER, DISTRICT_LOOKUP_PASSWORD);
+        let lookup = |key: &str| {
+            let action = SVUEAPIAction::LookupDistricts("97227".to_string(), key.to_string());
+            SVUERequest::new(action, &credentials).build_body().map(|b| String::from_utf8(b.to_vec()).unwrap())
+        };
+
+        let body = lookup(DISTRICT_LOOKUP_KEY).unwrap();
+        assert!(body.contains(&format!("&lt;Key&gt;{}&lt;/Key&gt;", DISTRICT_LOOKUP_KEY)));
+        assert!(body.contains("&lt;MatchToDistrictZipCode&gt;97227&lt;/MatchToDistrictZipCode&gt;"));
+
+        for key in ["", "A</Key><Key>B"].iter() {
+            match lookup(key) {
+                Err(SVUERequestError::InvalidParameter("Key")) => {}
+                other => panic!("{:?} was accepted: {:?}", key, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn looks_up_districts_in_another_directory() {
+        use mock::{self, MockServer};
+
+        let server = MockServer::new()
+            .credentials("directory", "s3cret")
+            .document("GetMatchingDistrictList", DISTRICTS)
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let directory = DistrictDirectory::new()
+            .endpoint(&mock::endpoint_url(&server))
+            .key("0000-TEST")
+            .account("directory", "s3cret");
+
+        let list = directory.lookup(" 97227 ").unwrap();
+        assert_eq!(list.districts.len(), 2);
+
+        // the built-in account isn't the one this directory accepts
+        let wrong = DistrictDirectory::new().endpoint(directory.endpoint_url());
+        assert!(wrong.lookup("97227").is_err());
+        server.close();
+    }
+}
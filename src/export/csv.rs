@@ -0,0 +1,234 @@
+use std::io::{self, Write};
+
+use super::timeseries::GradePoint;
+
+use completion::CompletionOverlay;
+use gradebook::*;
+use tags::CourseTags;
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_row<W: Write>(w: &mut W, fields: &[&str]) -> io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+    write!(w, "{}\r\n", fields.join(","))
+}
+
+fn course_title(title: &CourseTitle) -> (&str, &str) {
+    match *title {
+        CourseTitle::Parsed(ref name, ref id) => (name, id),
+        CourseTitle::Unparseable(ref raw) => (raw, ""),
+    }
+}
+
+fn points_cells(points: &AssignmentPoints) -> (String, String) {
+    match *points {
+        AssignmentPoints::Ungraded(p) => ("".to_string(), p.to_string()),
+        AssignmentPoints::Graded(e, p) => (e.to_string(), p.to_string()),
+        AssignmentPoints::Unparseable(_) => ("".to_string(), "".to_string()),
+    }
+}
+
+// one row per assignment, across every mark of every course
+pub fn write_assignments<W: Write>(w: &mut W, gradebook: &Gradebook) -> io::Result<()> {
+    assignment_rows(w, gradebook, None)
+}
+
+// the same, with a "done" column from `overlay` (the time it was marked done, or empty)
+pub fn write_assignments_with<W: Write>(w: &mut W, gradebook: &Gradebook, overlay: &CompletionOverlay)
+    -> io::Result<()> {
+
+    assignment_rows(w, gradebook, Some(overlay))
+}
+
+fn assignment_rows<W: Write>(w: &mut W, gradebook: &Gradebook, overlay: Option<&CompletionOverlay>)
+    -> io::Result<()> {
+
+    let mut header = vec!["course", "course_id", "period", "mark", "assignment_id", "assignment", "type", "date",
+        "due_date", "score", "score_type", "points_earned", "points_possible", "notes"];
+    if overlay.is_some() {
+        header.push("done");
+    }
+    write_row(w, &header)?;
+
+    for course in gradebook.courses() {
+        let (name, id) = course_title(&course.title);
+        let period = course.period.to_string();
+
+        for mark in course.marks() {
+            for a in mark.assignments() {
+                let (earned, possible) = points_cells(&a.points);
+                let date = a.date.format("%Y-%m-%d").to_string();
+                let due_date = a.due_date.format("%Y-%m-%d").to_string();
+                let score = a.score.to_string();
+                let done = overlay.and_then(|o| o.done_at(&a.gradebook_id))
+                    .map(|at| at.to_rfc3339())
+                    .unwrap_or_default();
+
+                let mut row = vec![
+                    name,
+                    id,
+                    &period,
+                    &mark.mark_name,
+                    &a.gradebook_id,
+                    &a.measure,
+                    &a._type,
+                    &date,
+                    &due_date,
+                    &score,
+                    &a.score_type,
+                    &earned,
+                    &possible,
+                    &a.notes,
+                ];
+                if overlay.is_some() {
+                    row.push(&done);
+                }
+                write_row(w, &row)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// one row per course mark
+pub fn write_course_summaries<W: Write>(w: &mut W, gradebook: &Gradebook) -> io::Result<()> {
+    course_summary_rows(w, gradebook, None)
+}
+
+// the same, with "tags" (separated by semicolons) and "color" columns from `tags`
+pub fn write_course_summaries_with<W: Write>(w: &mut W, gradebook: &Gradebook, tags: &CourseTags)
+    -> io::Result<()> {
+
+    course_summary_rows(w, gradebook, Some(tags))
+}
+
+fn course_summary_rows<W: Write>(w: &mut W, gradebook: &Gradebook, tags: Option<&CourseTags>) -> io::Result<()> {
+    let mut header = vec!["course", "course_id", "period", "room", "teacher", "teacher_email", "mark", "grade",
+        "score", "assignments"];
+    if tags.is_some() {
+        header.push("tags");
+        header.push("color");
+    }
+    write_row(w, &header)?;
+
+    for course in gradebook.courses() {
+        let (name, id) = course_title(&course.title);
+        let period = course.period.to_string();
+        let course_tags = tags.map(|t| t.tags_for(&course.title).join(";")).unwrap_or_default();
+        let color = tags.and_then(|t| t.color_for(&course.title)).unwrap_or("");
+
+        for mark in course.marks() {
+            let score = mark.calculated_score_raw.to_string();
+            let assignments = mark.assignments.len().to_string();
+
+            let mut row = vec![
+                name,
+                id,
+                &period,
+                &course.room,
+                &course.staff,
+                &course.staff_email,
+                &mark.mark_name,
+                &mark.calculated_score_string,
+                &score,
+                &assignments,
+            ];
+            if tags.is_some() {
+                row.push(&course_tags);
+                row.push(color);
+            }
+            write_row(w, &row)?;
+        }
+    }
+
+    Ok(())
+}
+
+// timestamp, course, percentage and friends, one row per `GradePoint`
+pub fn write_time_series<W: Write>(w: &mut W, points: &[GradePoint]) -> io::Result<()> {
+    write_row(w, &["timestamp", "course", "course_id", "period", "mark", "grade", "percentage"])?;
+
+    for p in points {
+        let (name, id) = course_title(&p.course);
+
+        write_row(w, &[
+            &p.captured_at.to_rfc3339(),
+            name,
+            id,
+            &p.period.to_string(),
+            &p.mark,
+            &p.grade,
+            &p.percentage.to_string(),
+        ])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRADEBOOK: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    #[test]
+    fn quotes_only_the_fields_that_need_it() {
+        let cases = [
+            ("Okafor, Chris", "\"Okafor, Chris\""),
+            ("the \"hook\"", "\"the \"\"hook\"\"\""),
+            ("two\nlines", "\"two\nlines\""),
+            ("ends\r", "\"ends\r\""),
+            ("\"", "\"\"\"\""),
+            ("plain text", "plain text"),
+            ("", ""),
+        ];
+
+        for &(field, expected) in cases.iter() {
+            assert_eq!(escape(field), expected, "{:?}", field);
+        }
+    }
+
+    #[test]
+    fn writes_commas_and_quotes_in_assignment_rows() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses.truncate(1);
+        gradebook.courses[0].marks[0].assignments.truncate(1);
+        {
+            let a = &mut gradebook.courses[0].marks[0].assignments[0];
+            a.measure = "Essay: \"Why, and how\"".to_string();
+            a.notes = "Late, see me".to_string();
+        }
+
+        let mut out = Vec::new();
+        write_assignments(&mut out, &gradebook).unwrap();
+        let rows: Vec<String> = String::from_utf8(out).unwrap().split("\r\n").map(|r| r.to_string()).collect();
+
+        assert_eq!(rows, vec![
+            "course,course_id,period,mark,assignment_id,assignment,type,date,due_date,score,score_type,points_earned,\
+             points_possible,notes".to_string(),
+            "English 10,EN1002,2,Quarter 1,2001,\"Essay: \"\"Why, and how\"\"\",Essays,2018-09-20,2018-09-27,45.6 out of 50,\
+             Raw Score,45.6,50,\"Late, see me\"".to_string(),
+            String::new(),
+        ]);
+    }
+
+    #[test]
+    fn quotes_a_teacher_name_in_course_summaries() {
+        let gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+
+        let mut out = Vec::new();
+        write_course_summaries(&mut out, &gradebook).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // "Okafor, Chris" would otherwise be split over two columns
+        let english = out.split("\r\n").nth(1).unwrap();
+        assert!(english.starts_with("English 10,EN1002,2,108,\"Okafor, Chris\",cokafor@example.org,"), "{}", english);
+    }
+}
@@ -0,0 +1,532 @@
+// A stable JSON representation of rvue's types. This is deliberately independent of the structs'
+// layout (and of the `serde-serialize` derives), so fields can be renamed or restructured in Rust
+// without breaking consumers. The contract:
+//
+// * dates are `"YYYY-MM-DD"` strings
+// * numbers that aren't finite are written as `null`
+// * values that rvue couldn't parse keep the original text under `"raw"`
+// * every change is an object with a snake_case `"kind"` and, where it applies, `"old"`/`"new"`
+// * keys are only ever added, never removed or renamed
+use std::io::{self, Write};
+
+use super::timeseries::GradePoint;
+
+use attendance::*;
+use diff::*;
+use gradebook::*;
+use history::{ChangesetHistory, TimestampedChangeset};
+
+use chrono::NaiveDate;
+
+pub trait ToJson {
+    fn to_json(&self) -> String;
+}
+
+pub fn to_writer<W: Write, T: ToJson>(w: &mut W, value: &T) -> io::Result<()> {
+    w.write_all(value.to_json().as_bytes())
+}
+
+fn escape(s: &str) -> String {
+    s.chars().fold(String::with_capacity(s.len() + 2), |mut acc, c| {
+        match c {
+            '"' => acc.push_str("\\\""),
+            '\\' => acc.push_str("\\\\"),
+            '\n' => acc.push_str("\\n"),
+            '\r' => acc.push_str("\\r"),
+            '\t' => acc.push_str("\\t"),
+            c if (c as u32) < 0x20 => acc.push_str(&format!("\\u{:04x}", c as u32)),
+            c => acc.push(c),
+        }
+        acc
+    })
+}
+
+pub fn string(s: &str) -> String {
+    format!("\"{}\"", escape(s))
+}
+
+pub fn number(n: f64) -> String {
+    if n.is_finite() {
+        n.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+pub fn date(d: &NaiveDate) -> String {
+    string(&d.format("%Y-%m-%d").to_string())
+}
+
+pub fn optional<T, F: Fn(&T) -> String>(value: Option<&T>, f: F) -> String {
+    value.map(f).unwrap_or("null".to_string())
+}
+
+pub fn array<T: ToJson>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_json()).collect();
+    format!("[{}]", values.join(","))
+}
+
+pub struct Object {
+    fields: Vec<String>,
+}
+
+impl Default for Object {
+    fn default() -> Object {
+        Object::new()
+    }
+}
+
+impl Object {
+    pub fn new() -> Object {
+        Object {
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn field(mut self, key: &str, value: String) -> Object {
+        self.fields.push(format!("{}:{}", string(key), value));
+        self
+    }
+
+    pub fn build(self) -> String {
+        format!("{{{}}}", self.fields.join(","))
+    }
+}
+
+fn change<T, F: Fn(&T) -> String>(kind: &str, old: &T, new: &T, f: F) -> String {
+    Object::new()
+        .field("kind", string(kind))
+        .field("old", f(old))
+        .field("new", f(new))
+        .build()
+}
+
+fn kind(kind: &str) -> String {
+    Object::new().field("kind", string(kind)).build()
+}
+
+impl ToJson for Gradebook {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("reporting_period", self.reporting_period.to_json())
+            .field("reporting_periods", array(self.reporting_periods()))
+            .field("courses", array(self.courses()))
+            .field("last_activity", optional(self.last_activity().as_ref(),
+                |t| string(&t.format("%Y-%m-%dT%H:%M:%S").to_string())))
+            .build()
+    }
+}
+
+impl ToJson for ReportingPeriod {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("name", string(&self.grade_period))
+            .field("start_date", date(&self.start_date))
+            .field("end_date", date(&self.end_date))
+            .build()
+    }
+}
+
+impl ToJson for ReportPeriod {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("index", self.index.to_string())
+            .field("name", string(&self.grade_period))
+            .field("start_date", date(&self.start_date))
+            .field("end_date", date(&self.end_date))
+            .build()
+    }
+}
+
+impl ToJson for CourseTitle {
+    fn to_json(&self) -> String {
+        match *self {
+            CourseTitle::Parsed(ref name, ref id) => {
+                Object::new()
+                    .field("name", string(name))
+                    .field("id", string(id))
+                    .build()
+            }
+            CourseTitle::Unparseable(ref raw) => {
+                Object::new()
+                    .field("name", "null".to_string())
+                    .field("id", "null".to_string())
+                    .field("raw", string(raw))
+                    .build()
+            }
+        }
+    }
+}
+
+impl ToJson for Course {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("title", self.title.to_json())
+            .field("period", self.period.to_string())
+            .field("room", string(&self.room))
+            .field("teacher", string(&self.staff))
+            .field("teacher_email", string(&self.staff_email))
+            .field("marks", array(self.marks()))
+            .build()
+    }
+}
+
+impl ToJson for Mark {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("name", string(&self.mark_name))
+            .field("score", number(self.calculated_score_raw))
+            .field("grade", string(&self.calculated_score_string))
+            .field("categories", array(self.grade_calculation_summary()))
+            .field("assignments", array(self.assignments()))
+            .build()
+    }
+}
+
+impl ToJson for AssignmentGradeCalcWeight {
+    fn to_json(&self) -> String {
+        match *self {
+            AssignmentGradeCalcWeight::Percentage(p) => {
+                Object::new().field("percentage", number(p)).build()
+            }
+            AssignmentGradeCalcWeight::Unparseable(ref raw) => {
+                Object::new()
+                    .field("percentage", "null".to_string())
+                    .field("raw", string(raw))
+                    .build()
+            }
+        }
+    }
+}
+
+impl ToJson for AssignmentGradeCalc {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("name", string(&self._type))
+            .field("mark", string(&self.calculated_mark))
+            .field("points", number(self.points))
+            .field("points_possible", number(self.points_possible))
+            .field("weight", self.weight.to_json())
+            .field("weighted_percentage", self.weighted_pct.to_json())
+            .build()
+    }
+}
+
+impl ToJson for AssignmentScore {
+    fn to_json(&self) -> String {
+        match *self {
+            AssignmentScore::NotDue => kind("not_due"),
+            AssignmentScore::NotForGrading => kind("not_for_grading"),
+            AssignmentScore::NotGraded => kind("not_graded"),
+            AssignmentScore::Percentage(p) => {
+                Object::new()
+                    .field("kind", string("percentage"))
+                    .field("percentage", number(p))
+                    .build()
+            }
+            AssignmentScore::Score(s, p) => {
+                Object::new()
+                    .field("kind", string("score"))
+                    .field("score", number(s.value()))
+                    .field("possible", number(p.value()))
+                    .build()
+            }
+            AssignmentScore::SeeStandards => kind("see_standards"),
+            AssignmentScore::Unparseable(ref raw) => {
+                Object::new()
+                    .field("kind", string("unparseable"))
+                    .field("raw", string(raw))
+                    .build()
+            }
+        }
+    }
+}
+
+impl ToJson for AssignmentPoints {
+    fn to_json(&self) -> String {
+        match *self {
+            AssignmentPoints::Ungraded(p) => {
+                Object::new()
+                    .field("kind", string("ungraded"))
+                    .field("possible", number(p.value()))
+                    .build()
+            }
+            AssignmentPoints::Graded(e, p) => {
+                Object::new()
+                    .field("kind", string("graded"))
+                    .field("earned", number(e.value()))
+                    .field("possible", number(p.value()))
+                    .build()
+            }
+            AssignmentPoints::Unparseable(ref raw) => {
+                Object::new()
+                    .field("kind", string("unparseable"))
+                    .field("raw", string(raw))
+                    .build()
+            }
+        }
+    }
+}
+
+impl ToJson for Assignment {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("id", string(&self.gradebook_id))
+            .field("title", string(&self.measure))
+            .field("type", string(&self._type))
+            .field("date", date(&self.date))
+            .field("due_date", date(&self.due_date))
+            .field("score", self.score.to_json())
+            .field("score_type", string(&self.score_type))
+            .field("points", self.points.to_json())
+            .field("notes", string(&self.notes))
+            .field("class_stats", optional(self.class_stats.as_ref(), |s| s.to_json()))
+            .build()
+    }
+}
+
+impl ToJson for ClassStats {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("average", optional(self.average.as_ref(), |n| number(*n)))
+            .field("high", optional(self.high.as_ref(), |n| number(*n)))
+            .field("low", optional(self.low.as_ref(), |n| number(*n)))
+            .field("median", optional(self.median.as_ref(), |n| number(*n)))
+            .build()
+    }
+}
+
+impl ToJson for Attendance {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("school", string(&self.school_name))
+            .field("type", string(&self.kind))
+            .field("period_count", optional(self.period_count.as_ref(), |c| c.to_string()))
+            .field("absences", array(self.absences()))
+            .field("total_excused", array(&self.total_excused))
+            .field("total_unexcused", array(&self.total_unexcused))
+            .field("total_tardies", array(&self.total_tardies))
+            .field("total_unexcused_tardies", array(&self.total_unexcused_tardies))
+            .field("total_activities", array(&self.total_activities))
+            .build()
+    }
+}
+
+impl ToJson for AbsenceReason {
+    fn to_json(&self) -> String {
+        match *self {
+            AbsenceReason::Activity => string("activity"),
+            AbsenceReason::Excused => string("excused"),
+            AbsenceReason::Tardy => string("tardy"),
+            AbsenceReason::Unexcused => string("unexcused"),
+            AbsenceReason::Other(_) => string("other"),
+        }
+    }
+}
+
+impl ToJson for Absence {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("date", date(&self.date))
+            .field("reason", string(&self.reason))
+            .field("reason_kind", self.reason_kind().to_json())
+            .field("note", string(&self.note))
+            .field("periods", array(self.periods()))
+            .build()
+    }
+}
+
+impl ToJson for AbsencePeriod {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("number", self.number.to_string())
+            .field("name", string(&self.name))
+            .field("reason", string(&self.reason))
+            .field("reason_kind", self.reason_kind().to_json())
+            .field("course", string(&self.course))
+            .field("teacher", string(&self.staff))
+            .field("teacher_email", string(&self.staff_email))
+            .build()
+    }
+}
+
+impl ToJson for PeriodTotal {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("number", self.number.to_string())
+            .field("total", self.total.to_string())
+            .build()
+    }
+}
+
+impl ToJson for Changeset {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("reporting_period", self.new.reporting_period.to_json())
+            .field("courses", array(&self.changes))
+            .build()
+    }
+}
+
+impl ToJson for CourseChanges {
+    fn to_json(&self) -> String {
+        let title = self.new.as_ref().or(self.old.as_ref()).map(|c| &c.title);
+
+        Object::new()
+            .field("course", optional(title, |t| t.to_json()))
+            .field("changes", self.changes.as_ref().map(|cs| array(cs)).unwrap_or("[]".to_string()))
+            .field("assignments", self.assignment_changes.as_ref().map(|acs| array(acs)).unwrap_or("[]".to_string()))
+            .build()
+    }
+}
+
+impl ToJson for CourseChange {
+    fn to_json(&self) -> String {
+        match *self {
+            CourseChange::Added => kind("added"),
+            CourseChange::Dropped => kind("dropped"),
+            CourseChange::CalculatedGradeChange { ref old, ref new } => {
+                change("grade_change", old, new, |s| string(s))
+            }
+            CourseChange::HighlightCutoffChange { ref old, ref new } => {
+                change("highlight_cutoff_change", old, new, |i| i.to_string())
+            }
+            CourseChange::PeriodChange { ref old, ref new } => change("period_change", old, new, |i| i.to_string()),
+            CourseChange::RoomChange { ref old, ref new } => change("room_change", old, new, |s| string(s)),
+            CourseChange::StaffChange { ref old, ref new } => change("teacher_change", old, new, |s| string(s)),
+            CourseChange::StaffEmailChange { ref old, ref new } => {
+                change("teacher_email_change", old, new, |s| string(s))
+            }
+            // "old" and "new" were the titles as written before there was "old_title" and "new_title"
+            CourseChange::TitleChange { ref old, ref new } => {
+                Object::new()
+                    .field("kind", string("title_change"))
+                    .field("old", string(&old.to_string()))
+                    .field("new", string(&new.to_string()))
+                    .field("old_title", old.to_json())
+                    .field("new_title", new.to_json())
+                    .build()
+            }
+        }
+    }
+}
+
+impl ToJson for AssignmentChanges {
+    fn to_json(&self) -> String {
+        let assignment = self.new.as_ref().or(self.old.as_ref());
+
+        Object::new()
+            .field("id", optional(assignment, |a| string(&a.gradebook_id)))
+            .field("title", optional(assignment, |a| string(&a.measure)))
+            .field("changes", array(&self.changes))
+            .build()
+    }
+}
+
+impl ToJson for AssignmentChange {
+    fn to_json(&self) -> String {
+        match *self {
+            AssignmentChange::Added => kind("added"),
+            AssignmentChange::Removed => kind("removed"),
+            AssignmentChange::DateChange { ref old, ref new } => change("date_change", old, new, date),
+            AssignmentChange::DueDateChange { ref old, ref new } => change("due_date_change", old, new, date),
+            AssignmentChange::NotesChange { ref old, ref new } => change("notes_change", old, new, |s| string(s)),
+            AssignmentChange::PointsChange { ref old, ref new } => {
+                change("points_change", old, new, |p| p.to_json())
+            }
+            AssignmentChange::ScoreChange { ref old, ref new } => change("score_change", old, new, |s| s.to_json()),
+            AssignmentChange::ScoreTypeChange { ref old, ref new } => {
+                change("score_type_change", old, new, |s| string(s))
+            }
+            AssignmentChange::StandardAdded { ref standard, ref mark } |
+            AssignmentChange::StandardRemoved { ref standard, ref mark } => {
+                Object::new()
+                    .field("kind", string(self.kind()))
+                    .field("standard", string(standard))
+                    .field("mark", string(mark))
+                    .build()
+            }
+            AssignmentChange::StandardMarkChange { ref standard, ref old, ref new } => {
+                Object::new()
+                    .field("kind", string("standard_mark_change"))
+                    .field("standard", string(standard))
+                    .field("old", string(old))
+                    .field("new", string(new))
+                    .build()
+            }
+            AssignmentChange::StandardProficiencyChange { ref standard, ref old, ref new } => {
+                Object::new()
+                    .field("kind", string("standard_proficiency_change"))
+                    .field("standard", string(standard))
+                    .field("old", optional(old.as_ref(), |p| number(*p)))
+                    .field("new", optional(new.as_ref(), |p| number(*p)))
+                    .build()
+            }
+            AssignmentChange::TitleChange { ref old, ref new } => change("title_change", old, new, |s| string(s)),
+        }
+    }
+}
+
+impl ToJson for TimestampedChangeset {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("captured_at", string(&self.captured_at.to_rfc3339()))
+            .field("changeset", self.changeset.to_json())
+            .build()
+    }
+}
+
+impl ToJson for ChangesetHistory {
+    fn to_json(&self) -> String {
+        array(self.entries())
+    }
+}
+
+impl ToJson for GradePoint {
+    fn to_json(&self) -> String {
+        Object::new()
+            .field("timestamp", string(&self.captured_at.to_rfc3339()))
+            .field("course", self.course.to_json())
+            .field("period", self.period.to_string())
+            .field("mark", string(&self.mark))
+            .field("grade", string(&self.grade))
+            .field("percentage", number(self.percentage))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRADEBOOK: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    // the fixture, with a note that has to be escaped
+    fn gradebook() -> Gradebook {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses[0].marks[0].assignments[0].notes = "Revise the \"hook\"\n\tby Friday \\ see rubric".to_string();
+        gradebook
+    }
+
+    // a change to each of the fixture's courses: the essay regraded, and chemistry moved out of lab 3
+    fn changeset() -> Changeset {
+        let new = Gradebook::from_xml(GRADEBOOK).unwrap();
+        let mut old = new.clone();
+        old.courses[0].marks[0].assignments[0].score = AssignmentScore::Percentage(12.5);
+        old.courses[1].room = "Lab 3".to_string();
+
+        Changeset::diff(&old, &new).unwrap()
+    }
+
+    // the documents are a contract (see above), so any difference at all from the golden files
+    // has to be on purpose
+    #[test]
+    fn writes_a_gradebook_as_the_golden_file() {
+        assert_eq!(gradebook().to_json(), include_str!("../../fixtures/gradebook_no_standards.json").trim_end());
+    }
+
+    #[test]
+    fn writes_a_changeset_as_the_golden_file() {
+        assert_eq!(changeset().to_json(), include_str!("../../fixtures/changeset.json").trim_end());
+    }
+}
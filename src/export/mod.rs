@@ -0,0 +1,7 @@
+pub mod csv;
+pub mod json;
+#[cfg(feature="schemars")]
+pub mod schema;
+pub mod timeseries;
+#[cfg(feature="xlsx")]
+pub mod xlsx;
@@ -0,0 +1,614 @@
+// JSON Schemas (draft-07) describing the documents written by `export::json`, for consumers that
+// want to validate or generate code from rvue's output. The JSON contract is deliberately separate
+// from the structs' layout (see json.rs), so the schemas are generated with schemars from the
+// types below, which spell out that contract field for field; the tests check json.rs's output
+// against what they generate. Objects may gain properties over time, so the schemas never forbid
+// additional ones.
+use std::marker::PhantomData;
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
+use serde_json;
+
+// a value that's always present but may be `null`; schemars takes an `Option` field to be one that
+// can be left out
+struct Nullable<T>(PhantomData<T>);
+
+impl<T: JsonSchema> JsonSchema for Nullable<T> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        format!("Nullable_{}", T::schema_name())
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        gen.subschema_for::<Option<T>>()
+    }
+}
+
+fn string_schema(pattern: Option<&str>, format: Option<&str>) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        format: format.map(|f| f.to_string()),
+        string: pattern.map(|p| Box::new(StringValidation { pattern: Some(p.to_string()), ..Default::default() })),
+        ..Default::default()
+    }.into()
+}
+
+// "YYYY-MM-DD"
+struct Date;
+
+impl JsonSchema for Date {
+    fn schema_name() -> String {
+        "date".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        string_schema(Some("^[0-9]{4}-[0-9]{2}-[0-9]{2}$"), None)
+    }
+}
+
+// a gradebook's last activity, in the school's local time
+struct LocalTimestamp;
+
+impl JsonSchema for LocalTimestamp {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "local_timestamp".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        string_schema(Some("^[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}$"), None)
+    }
+}
+
+// RFC 3339, in UTC
+struct Timestamp;
+
+impl JsonSchema for Timestamp {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "timestamp".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        string_schema(None, Some("date-time"))
+    }
+}
+
+// Only ever used as types to generate the schemas from, never built. Names are the ones json.rs
+// writes; `Nullable` fields are always written, `Option` ones only sometimes.
+#[allow(dead_code)]
+mod documents {
+    use super::{Date, LocalTimestamp, Nullable, Timestamp};
+
+    use schemars::JsonSchema;
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "reporting_period")]
+    pub struct ReportingPeriod {
+        name: String,
+        start_date: Date,
+        end_date: Date,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "report_period")]
+    pub struct ReportPeriod {
+        index: i8,
+        name: String,
+        start_date: Date,
+        end_date: Date,
+    }
+
+    // `raw` is only there when the title couldn't be split into a name and an ID
+    #[derive(JsonSchema)]
+    #[schemars(rename = "course_title")]
+    pub struct CourseTitle {
+        name: Nullable<String>,
+        id: Nullable<String>,
+        raw: Option<String>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "gradebook")]
+    pub struct Gradebook {
+        reporting_period: ReportingPeriod,
+        reporting_periods: Vec<ReportPeriod>,
+        courses: Vec<Course>,
+        last_activity: Nullable<LocalTimestamp>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "course")]
+    pub struct Course {
+        title: CourseTitle,
+        period: i8,
+        room: String,
+        teacher: String,
+        teacher_email: String,
+        marks: Vec<Mark>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "mark")]
+    pub struct Mark {
+        name: String,
+        score: Nullable<f64>,
+        grade: String,
+        categories: Vec<Category>,
+        assignments: Vec<Assignment>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "weight")]
+    pub struct Weight {
+        percentage: Nullable<f64>,
+        raw: Option<String>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "category")]
+    pub struct Category {
+        name: String,
+        mark: String,
+        points: Nullable<f64>,
+        points_possible: Nullable<f64>,
+        weight: Weight,
+        weighted_percentage: Weight,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "score", tag = "kind", rename_all = "snake_case")]
+    pub enum AssignmentScore {
+        NotDue,
+        NotForGrading,
+        NotGraded,
+        SeeStandards,
+        Percentage { percentage: Nullable<f64> },
+        Score { score: Nullable<f64>, possible: Nullable<f64> },
+        Unparseable { raw: String },
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "points", tag = "kind", rename_all = "snake_case")]
+    pub enum AssignmentPoints {
+        Ungraded { possible: Nullable<f64> },
+        Graded { earned: Nullable<f64>, possible: Nullable<f64> },
+        Unparseable { raw: String },
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "class_stats")]
+    pub struct ClassStats {
+        average: Nullable<f64>,
+        high: Nullable<f64>,
+        low: Nullable<f64>,
+        median: Nullable<f64>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "assignment")]
+    pub struct Assignment {
+        id: String,
+        title: String,
+        #[schemars(rename = "type")]
+        kind: String,
+        date: Date,
+        due_date: Date,
+        score: AssignmentScore,
+        score_type: String,
+        points: AssignmentPoints,
+        notes: String,
+        class_stats: Option<ClassStats>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "changeset")]
+    pub struct Changeset {
+        reporting_period: ReportingPeriod,
+        courses: Vec<CourseChanges>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "course_changes")]
+    pub struct CourseChanges {
+        course: Nullable<CourseTitle>,
+        changes: Vec<CourseChange>,
+        assignments: Vec<AssignmentChanges>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "course_change", tag = "kind", rename_all = "snake_case")]
+    pub enum CourseChange {
+        Added,
+        Dropped,
+        GradeChange { old: String, new: String },
+        RoomChange { old: String, new: String },
+        TeacherChange { old: String, new: String },
+        TeacherEmailChange { old: String, new: String },
+        TitleChange { old: String, new: String, old_title: CourseTitle, new_title: CourseTitle },
+        HighlightCutoffChange { old: i8, new: i8 },
+        PeriodChange { old: i8, new: i8 },
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "assignment_changes")]
+    pub struct AssignmentChanges {
+        id: Nullable<String>,
+        title: Nullable<String>,
+        changes: Vec<AssignmentChange>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "assignment_change", tag = "kind", rename_all = "snake_case")]
+    pub enum AssignmentChange {
+        Added,
+        Removed,
+        DateChange { old: Date, new: Date },
+        DueDateChange { old: Date, new: Date },
+        NotesChange { old: String, new: String },
+        ScoreTypeChange { old: String, new: String },
+        TitleChange { old: String, new: String },
+        PointsChange { old: AssignmentPoints, new: AssignmentPoints },
+        ScoreChange { old: AssignmentScore, new: AssignmentScore },
+        StandardAdded { standard: String, mark: String },
+        StandardRemoved { standard: String, mark: String },
+        StandardMarkChange { standard: String, old: String, new: String },
+        StandardProficiencyChange { standard: String, old: Nullable<f64>, new: Nullable<f64> },
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "grade_point")]
+    pub struct GradePoint {
+        timestamp: Timestamp,
+        course: CourseTitle,
+        period: i8,
+        mark: String,
+        grade: String,
+        percentage: Nullable<f64>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "absence_reason", rename_all = "snake_case")]
+    pub enum AbsenceReason {
+        Activity,
+        Excused,
+        Tardy,
+        Unexcused,
+        Other,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "absence_period")]
+    pub struct AbsencePeriod {
+        number: i8,
+        name: String,
+        reason: String,
+        reason_kind: AbsenceReason,
+        course: String,
+        teacher: String,
+        teacher_email: String,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "absence")]
+    pub struct Absence {
+        date: Date,
+        reason: String,
+        reason_kind: AbsenceReason,
+        note: String,
+        periods: Vec<AbsencePeriod>,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "period_total")]
+    pub struct PeriodTotal {
+        number: i8,
+        total: u32,
+    }
+
+    #[derive(JsonSchema)]
+    #[schemars(rename = "attendance")]
+    pub struct Attendance {
+        school: String,
+        #[schemars(rename = "type")]
+        kind: String,
+        period_count: Nullable<i8>,
+        absences: Vec<Absence>,
+        total_excused: Vec<PeriodTotal>,
+        total_unexcused: Vec<PeriodTotal>,
+        total_tardies: Vec<PeriodTotal>,
+        total_unexcused_tardies: Vec<PeriodTotal>,
+        total_activities: Vec<PeriodTotal>,
+    }
+}
+
+fn document<T: JsonSchema>(title: &str) -> String {
+    let mut root = SchemaSettings::draft07().into_generator().into_root_schema_for::<T>();
+    root.schema.metadata().title = Some(title.to_string());
+
+    serde_json::to_string_pretty(&root).expect("a schema is always serializable")
+}
+
+pub fn gradebook() -> String {
+    document::<documents::Gradebook>("Gradebook")
+}
+
+pub fn changeset() -> String {
+    document::<documents::Changeset>("Changeset")
+}
+
+pub fn attendance() -> String {
+    document::<documents::Attendance>("Attendance")
+}
+
+// for an array of time-series records
+pub fn grade_points() -> String {
+    document::<Vec<documents::GradePoint>>("Grade time series")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use attendance::*;
+    use diff::*;
+    use export::json::ToJson;
+    use export::timeseries::GradePoint;
+    use gradebook::*;
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use jsonschema::{Draft, JSONSchema};
+    use serde_json::{self, Value};
+
+    const STANDARDS: &str = include_str!("../../fixtures/gradebook_standards.xml");
+    const NO_STANDARDS: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    fn assert_valid(schema: &str, json: &str) {
+        let schema: Value = serde_json::from_str(schema).unwrap();
+        let instance: Value = serde_json::from_str(json).unwrap();
+        let compiled = JSONSchema::options()
+            .with_draft(Draft::Draft7)
+            .should_validate_formats(true)
+            .compile(&schema)
+            .unwrap();
+
+        if let Err(errors) = compiled.validate(&instance) {
+            let errors: Vec<String> = errors.map(|e| format!("{} at {}", e, e.instance_path)).collect();
+            panic!("{} doesn't match its schema:\n{}", json, errors.join("\n"));
+        };
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    // every kind of course and assignment change, with every kind of score and points among them
+    fn every_change() -> Changeset {
+        let old = Gradebook::from_xml(STANDARDS).unwrap();
+        let new = Gradebook::from_xml(NO_STANDARDS).unwrap();
+        let course = old.courses[0].clone();
+        let assignment = course.marks[0].assignments[0].clone();
+        let title = |name: &str, id: &str| CourseTitle::Parsed(name.to_string(), id.to_string());
+        let text = |s: &str| s.to_string();
+
+        let course_changes = vec![
+            CourseChange::Added,
+            CourseChange::Dropped,
+            CourseChange::CalculatedGradeChange { old: text("B+ (88.5)"), new: text("A- (90.1)") },
+            CourseChange::HighlightCutoffChange { old: 50, new: 60 },
+            CourseChange::PeriodChange { old: 1, new: 2 },
+            CourseChange::RoomChange { old: text("214"), new: text("216") },
+            CourseChange::StaffChange { old: text("Rivera, Ana"), new: text("Okafor, Chris") },
+            CourseChange::StaffEmailChange { old: text("arivera@example.org"), new: text("") },
+            CourseChange::TitleChange { old: title("Algebra 2", "MA3021-01"), new: title("Algebra 2 Honors", "MA3021-02") },
+            CourseChange::TitleChange { old: title("Algebra 2", "MA3021"), new: CourseTitle::Unparseable(text("ALG 2")) },
+        ];
+
+        let scores = [
+            AssignmentScore::NotDue,
+            AssignmentScore::NotForGrading,
+            AssignmentScore::NotGraded,
+            AssignmentScore::Percentage(92.5),
+            AssignmentScore::Percentage(f64::NAN),
+            AssignmentScore::Score(Points(17.0), Points(20.0)),
+            AssignmentScore::Score(Points(1.0), Points(f64::INFINITY)),
+            AssignmentScore::SeeStandards,
+            AssignmentScore::Unparseable(text("MSG")),
+        ];
+        let points = [
+            AssignmentPoints::Ungraded(Points(20.0)),
+            AssignmentPoints::Graded(Points(17.0), Points(20.0)),
+            AssignmentPoints::Graded(Points(f64::NAN), Points(20.0)),
+            AssignmentPoints::Unparseable(text("")),
+        ];
+
+        let mut assignment_changes = vec![
+            AssignmentChange::Added,
+            AssignmentChange::Removed,
+            AssignmentChange::DateChange { old: date(2018, 9, 14), new: date(2018, 9, 17) },
+            AssignmentChange::DueDateChange { old: date(2018, 9, 14), new: date(2018, 9, 21) },
+            AssignmentChange::NotesChange { old: text(""), new: text("turned in late") },
+            AssignmentChange::ScoreTypeChange { old: text("Raw Score"), new: text("Percentage") },
+            AssignmentChange::StandardAdded { standard: text("Graphs linear inequalities"), mark: text("3") },
+            AssignmentChange::StandardRemoved { standard: text("Graphs linear inequalities"), mark: text("") },
+            AssignmentChange::StandardMarkChange {
+                standard: text("Solves systems of linear equations"),
+                old: text("2"),
+                new: text("3"),
+            },
+            AssignmentChange::StandardProficiencyChange {
+                standard: text("Solves systems of linear equations"),
+                old: None,
+                new: Some(3.0),
+            },
+            AssignmentChange::TitleChange { old: text("Systems Quiz"), new: text("Systems Quiz (retake)") },
+        ];
+        assignment_changes.extend(scores.iter().zip(scores.iter().rev())
+            .map(|(o, n)| AssignmentChange::ScoreChange { old: o.clone(), new: n.clone() }));
+        assignment_changes.extend(points.iter().zip(points.iter().rev())
+            .map(|(o, n)| AssignmentChange::PointsChange { old: o.clone(), new: n.clone() }));
+
+        let mut unparseable = course.clone();
+        unparseable.title = CourseTitle::Unparseable(text("ALG 2"));
+
+        Changeset {
+            old,
+            new,
+            changes: vec![
+                CourseChanges {
+                    old: Some(course.clone()),
+                    new: Some(course.clone()),
+                    assignment_changes: Some(vec![
+                        AssignmentChanges {
+                            old: Some(assignment.clone()),
+                            new: Some(assignment.clone()),
+                            changes: assignment_changes,
+                        },
+                        AssignmentChanges {
+                            old: None,
+                            new: Some(assignment),
+                            changes: vec![AssignmentChange::Added],
+                        },
+                    ]),
+                    changes: Some(course_changes),
+                },
+                CourseChanges {
+                    old: None,
+                    new: Some(unparseable),
+                    assignment_changes: None,
+                    changes: Some(vec![CourseChange::Added]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn gradebooks_match_the_schema() {
+        for xml in [STANDARDS, NO_STANDARDS].iter() {
+            assert_valid(&gradebook(), &Gradebook::from_xml(xml).unwrap().to_json());
+        }
+
+        // no `LastUpdated`, so the last activity is worked out from the assignments
+        let mut edited = Gradebook::from_xml(STANDARDS).unwrap();
+        edited.last_updated = None;
+        edited.courses[0].marks[0].calculated_score_raw = f64::NAN;
+        edited.courses[0].marks[0].assignments[0].class_stats = Some(ClassStats {
+            average: Some(84.3),
+            high: None,
+            low: Some(f64::NEG_INFINITY),
+            median: None,
+        });
+        edited.courses[0].marks[0].grade_calculation_summary[0].weight =
+            AssignmentGradeCalcWeight::Unparseable("n/a".to_string());
+        assert_valid(&gradebook(), &edited.to_json());
+    }
+
+    #[test]
+    fn changesets_match_the_schema() {
+        let changes = every_change();
+
+        assert_valid(&changeset(), &changes.to_json());
+        assert_valid(&changeset(), &changes.invert().to_json());
+    }
+
+    #[test]
+    fn attendance_matches_the_schema() {
+        let period = |number: i8, reason: &str| AbsencePeriod {
+            course: "Algebra 2".to_string(),
+            name: "Absent".to_string(),
+            number,
+            reason: reason.to_string(),
+            staff: "Rivera, Ana".to_string(),
+            staff_email: "arivera@example.org".to_string(),
+        };
+        let total = |number: i8, total: u32| PeriodTotal { number, total };
+
+        let by_period = Attendance {
+            absences: vec![
+                Absence {
+                    date: date(2018, 9, 14),
+                    note: "".to_string(),
+                    periods: vec![period(1, "Excused"), period(2, "Tardy"), period(3, "Field Trip")],
+                    reason: "".to_string(),
+                },
+                Absence {
+                    date: date(2018, 9, 21),
+                    note: "doctor's note \"received\"".to_string(),
+                    periods: vec![period(4, "Unexcused"), period(5, "Something else")],
+                    reason: "Illness".to_string(),
+                },
+            ],
+            kind: "Period".to_string(),
+            period_count: Some(7),
+            school_name: "Example High School".to_string(),
+            total_activities: vec![total(3, 1)],
+            total_excused: vec![total(1, 1), total(2, 0)],
+            total_tardies: vec![total(2, 1)],
+            total_unexcused: vec![total(4, 1)],
+            total_unexcused_tardies: Vec::new(),
+        };
+        assert_valid(&attendance(), &by_period.to_json());
+
+        let daily = Attendance { period_count: None, kind: "Daily".to_string(), ..Default::default() };
+        assert_valid(&attendance(), &daily.to_json());
+    }
+
+    #[test]
+    fn grade_points_match_the_schema() {
+        let points = [
+            GradePoint {
+                captured_at: Utc.with_ymd_and_hms(2018, 9, 28, 15, 12, 45).unwrap(),
+                course: CourseTitle::Parsed("Algebra 2".to_string(), "MA3021".to_string()),
+                period: 1,
+                mark: "1st Qtr Progress".to_string(),
+                grade: "B+".to_string(),
+                percentage: 88.5,
+            },
+            GradePoint {
+                captured_at: Utc.with_ymd_and_hms(2018, 10, 5, 8, 0, 0).unwrap(),
+                course: CourseTitle::Unparseable("ALG 2".to_string()),
+                period: 1,
+                mark: "1st Qtr Progress".to_string(),
+                grade: "N/A".to_string(),
+                percentage: f64::NAN,
+            },
+        ];
+        let json: Vec<String> = points.iter().map(|p| p.to_json()).collect();
+
+        assert_valid(&grade_points(), &format!("[{}]", json.join(",")));
+    }
+
+    fn is_valid(schema: &str, instance: &Value) -> bool {
+        let schema: Value = serde_json::from_str(schema).unwrap();
+        JSONSchema::options().with_draft(Draft::Draft7).compile(&schema).unwrap().is_valid(instance)
+    }
+
+    #[test]
+    fn rejects_documents_that_break_the_contract() {
+        let gradebook_json: Value = serde_json::from_str(&Gradebook::from_xml(NO_STANDARDS).unwrap().to_json()).unwrap();
+        assert!(is_valid(&gradebook(), &gradebook_json));
+
+        let mut missing = gradebook_json.clone();
+        missing.as_object_mut().unwrap().remove("last_activity");
+        assert!(!is_valid(&gradebook(), &missing));
+
+        let mut bad_date = gradebook_json.clone();
+        bad_date["reporting_period"]["start_date"] = Value::from("9/4/2018");
+        assert!(!is_valid(&gradebook(), &bad_date));
+
+        let mut bad_score = gradebook_json;
+        bad_score["courses"][0]["marks"][0]["assignments"][0]["score"] = serde_json::json!({ "kind": "perfect" });
+        assert!(!is_valid(&gradebook(), &bad_score));
+    }
+}
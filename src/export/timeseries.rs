@@ -0,0 +1,40 @@
+// Long-format ("tidy") grade history: one record per course mark per snapshot, which is what
+// most plotting libraries want. Write the records with `csv::write_time_series` or
+// `json::array`.
+use gradebook::{CourseTitle, Gradebook};
+
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct GradePoint {
+    pub captured_at: DateTime<Utc>,
+    pub course: CourseTitle,
+    pub period: i8,
+    pub mark: String,
+    pub grade: String,
+    pub percentage: f64,
+}
+
+// `history` doesn't have to be sorted; the records come out in the same order as the snapshots
+pub fn grade_points<'a, I>(history: I) -> Vec<GradePoint>
+    where I: IntoIterator<Item=(DateTime<Utc>, &'a Gradebook)> {
+
+    let mut points = Vec::new();
+
+    for (captured_at, gradebook) in history {
+        for course in gradebook.courses() {
+            for mark in course.marks() {
+                points.push(GradePoint {
+                    captured_at,
+                    course: course.title.clone(),
+                    period: course.period,
+                    mark: mark.mark_name.clone(),
+                    grade: mark.calculated_score_string.clone(),
+                    percentage: mark.calculated_score_raw,
+                });
+            }
+        }
+    }
+
+    points
+}
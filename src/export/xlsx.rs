@@ -0,0 +1,431 @@
+// An .xlsx workbook is a zip of SpreadsheetML parts. Only what's needed for plain tables is
+// written: strings are stored inline (no shared string table) and there are three cell styles
+// (normal, bold header, date).
+use std::collections::HashSet;
+use std::io::{self, Seek, Write};
+
+use goals::Goals;
+use gradebook::*;
+
+use chrono::NaiveDate;
+use zip::{CompressionMethod, ZipWriter};
+use zip::write::FileOptions;
+use zip::result::ZipError;
+
+#[derive(Debug)]
+pub enum XlsxError {
+    Io(io::Error),
+    ZipError(ZipError),
+}
+
+enum Cell {
+    Date(NaiveDate),
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+struct Sheet {
+    name: String,
+    widths: Vec<u8>,
+    header: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+const STYLE_HEADER: u8 = 1;
+const STYLE_DATE: u8 = 2;
+
+fn escape(s: &str) -> String {
+    s.chars().fold(String::with_capacity(s.len()), |mut acc, c| {
+        match c {
+            '&' => acc.push_str("&amp;"),
+            '<' => acc.push_str("&lt;"),
+            '>' => acc.push_str("&gt;"),
+            '"' => acc.push_str("&quot;"),
+            // control characters aren't allowed in XML 1.0 at all
+            c if (c as u32) < 0x20 && c != '\t' && c != '\n' && c != '\r' => {}
+            c => acc.push(c),
+        }
+        acc
+    })
+}
+
+// A1-style column letters
+fn column_name(mut idx: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push((b'A' + (idx % 26) as u8) as char);
+        if idx < 26 { break; }
+        idx = idx / 26 - 1;
+    }
+    name.into_iter().rev().collect()
+}
+
+// Excel stores dates as days since 1899-12-30
+fn date_serial(d: &NaiveDate) -> i64 {
+    d.signed_duration_since(NaiveDate::from_ymd_opt(1899, 12, 30).unwrap()).num_days()
+}
+
+// sheet names are limited to 31 characters, can't contain `[]:*?/\` and must be unique
+// (case-insensitively) within the workbook
+fn sheet_name(title: &str, used: &mut HashSet<String>) -> String {
+    let base: String = title.chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .take(31)
+        .collect();
+    let base = if base.trim().is_empty() { "Course".to_string() } else { base };
+
+    let mut name = base.clone();
+    let mut n = 2;
+    while !used.insert(name.to_lowercase()) {
+        let suffix = format!(" ({})", n);
+        let truncated: String = base.chars().take(31 - suffix.len()).collect();
+        name = format!("{}{}", truncated, suffix);
+        n += 1;
+    }
+
+    name
+}
+
+fn course_name(title: &CourseTitle) -> &str {
+    match *title {
+        CourseTitle::Parsed(ref name, _) => name,
+        CourseTitle::Unparseable(ref raw) => raw,
+    }
+}
+
+fn points_cells(points: &AssignmentPoints) -> (Cell, Cell) {
+    match *points {
+        AssignmentPoints::Ungraded(p) => (Cell::Empty, Cell::Number(p.value())),
+        AssignmentPoints::Graded(e, p) => (Cell::Number(e.value()), Cell::Number(p.value())),
+        AssignmentPoints::Unparseable(_) => (Cell::Empty, Cell::Empty),
+    }
+}
+
+// with goals, the current mark's row also has the course's target and the percentage needed on
+// remaining work to reach it
+fn summary_sheet(gradebook: &Gradebook, goals: Option<&Goals>) -> Sheet {
+    let mut rows = Vec::new();
+
+    for course in gradebook.courses() {
+        let progress = goals.and_then(|g| g.progress(course));
+        // the current mark is the last one
+        let current = course.marks().len().saturating_sub(1);
+
+        for (i, mark) in course.marks().iter().enumerate() {
+            let mut row = vec![
+                Cell::Text(course.title.to_string()),
+                Cell::Number(course.period as f64),
+                Cell::Text(course.room.clone()),
+                Cell::Text(course.staff.clone()),
+                Cell::Text(course.staff_email.clone()),
+                Cell::Text(mark.mark_name.clone()),
+                Cell::Text(mark.calculated_score_string.clone()),
+                Cell::Number(mark.calculated_score_raw),
+                Cell::Number(mark.assignments.len() as f64),
+            ];
+
+            if goals.is_some() {
+                match progress {
+                    Some(ref p) if i == current => {
+                        row.push(Cell::Number(p.target));
+                        row.push(p.required.map(Cell::Number).unwrap_or(Cell::Empty));
+                    }
+                    _ => {
+                        row.push(Cell::Empty);
+                        row.push(Cell::Empty);
+                    }
+                }
+            }
+
+            rows.push(row);
+        }
+    }
+
+    let mut widths = vec![36, 8, 10, 24, 30, 12, 8, 8, 12];
+    let mut header = vec!["Course", "Period", "Room", "Teacher", "Teacher Email", "Mark", "Grade", "Score",
+        "Assignments"];
+    if goals.is_some() {
+        widths.extend(&[8, 10]);
+        header.extend(&["Target", "Needed"]);
+    }
+
+    Sheet {
+        name: "Summary".to_string(),
+        widths,
+        header,
+        rows,
+    }
+}
+
+fn course_sheet(course: &Course, name: String) -> Sheet {
+    let mut rows = Vec::new();
+
+    for mark in course.marks() {
+        for a in mark.assignments() {
+            let (earned, possible) = points_cells(&a.points);
+
+            rows.push(vec![
+                Cell::Text(mark.mark_name.clone()),
+                Cell::Text(a.measure.clone()),
+                Cell::Text(a._type.clone()),
+                Cell::Date(a.date),
+                Cell::Date(a.due_date),
+                Cell::Text(a.score.to_string()),
+                Cell::Text(a.score_type.clone()),
+                earned,
+                possible,
+                Cell::Text(a.notes.clone()),
+            ]);
+        }
+    }
+
+    Sheet {
+        name,
+        widths: vec![12, 36, 16, 12, 12, 14, 14, 8, 8, 40],
+        header: vec!["Mark", "Assignment", "Type", "Date", "Due Date", "Score", "Score Type", "Earned",
+            "Possible", "Notes"],
+        rows,
+    }
+}
+
+fn cell_xml(reference: &str, cell: &Cell) -> String {
+    match *cell {
+        Cell::Date(ref d) => format!("<c r=\"{}\" s=\"{}\"><v>{}</v></c>", reference, STYLE_DATE, date_serial(d)),
+        Cell::Empty => String::new(),
+        Cell::Number(n) if n.is_finite() => format!("<c r=\"{}\"><v>{}</v></c>", reference, n),
+        Cell::Number(_) => String::new(),
+        Cell::Text(ref s) => {
+            format!("<c r=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>", reference, escape(s))
+        }
+    }
+}
+
+fn sheet_xml(sheet: &Sheet) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>",
+        "<worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">",
+        // keep the header row visible while scrolling
+        "<sheetViews><sheetView workbookViewId=\"0\">",
+        "<pane ySplit=\"1\" topLeftCell=\"A2\" activePane=\"bottomLeft\" state=\"frozen\"/>",
+        "</sheetView></sheetViews>"));
+
+    xml.push_str("<cols>");
+    for (i, width) in sheet.widths.iter().enumerate() {
+        xml.push_str(&format!("<col min=\"{0}\" max=\"{0}\" width=\"{1}\" customWidth=\"1\"/>", i + 1, width));
+    }
+    xml.push_str("</cols><sheetData>");
+
+    xml.push_str("<row r=\"1\">");
+    for (i, h) in sheet.header.iter().enumerate() {
+        xml.push_str(&format!("<c r=\"{}1\" s=\"{}\" t=\"inlineStr\"><is><t>{}</t></is></c>", column_name(i),
+            STYLE_HEADER, escape(h)));
+    }
+    xml.push_str("</row>");
+
+    for (r, row) in sheet.rows.iter().enumerate() {
+        let r = r + 2;
+        xml.push_str(&format!("<row r=\"{}\">", r));
+        for (i, cell) in row.iter().enumerate() {
+            xml.push_str(&cell_xml(&format!("{}{}", column_name(i), r), cell));
+        }
+        xml.push_str("</row>");
+    }
+
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+fn content_types_xml(sheets: usize) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>",
+        "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">",
+        "<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>",
+        "<Default Extension=\"xml\" ContentType=\"application/xml\"/>",
+        "<Override PartName=\"/xl/workbook.xml\" ",
+        "ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>",
+        "<Override PartName=\"/xl/styles.xml\" ",
+        "ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>"));
+
+    for i in 1..sheets + 1 {
+        xml.push_str(&format!(concat!("<Override PartName=\"/xl/worksheets/sheet{}.xml\" ",
+            "ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>"), i));
+    }
+
+    xml.push_str("</Types>");
+    xml
+}
+
+const ROOT_RELS: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>",
+    "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    "<Relationship Id=\"rId1\" ",
+    "Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" ",
+    "Target=\"xl/workbook.xml\"/>",
+    "</Relationships>");
+
+const STYLES: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>",
+    "<styleSheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">",
+    "<numFmts count=\"1\"><numFmt numFmtId=\"164\" formatCode=\"yyyy-mm-dd\"/></numFmts>",
+    "<fonts count=\"2\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font>",
+    "<font><b/><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>",
+    "<fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill>",
+    "<fill><patternFill patternType=\"gray125\"/></fill></fills>",
+    "<borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>",
+    "<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>",
+    "<cellXfs count=\"3\">",
+    "<xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>",
+    "<xf numFmtId=\"0\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyFont=\"1\"/>",
+    "<xf numFmtId=\"164\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "</cellXfs></styleSheet>");
+
+fn workbook_xml(sheets: &[Sheet]) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>",
+        "<workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" ",
+        "xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\"><sheets>"));
+
+    for (i, sheet) in sheets.iter().enumerate() {
+        xml.push_str(&format!("<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>", escape(&sheet.name), i + 1, i + 1));
+    }
+
+    xml.push_str("</sheets></workbook>");
+    xml
+}
+
+fn workbook_rels_xml(sheets: usize) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>",
+        "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">"));
+
+    for i in 1..sheets + 1 {
+        xml.push_str(&format!(concat!("<Relationship Id=\"rId{0}\" ",
+            "Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" ",
+            "Target=\"worksheets/sheet{0}.xml\"/>"), i));
+    }
+    xml.push_str(&format!(concat!("<Relationship Id=\"rId{}\" ",
+        "Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" ",
+        "Target=\"styles.xml\"/>"), sheets + 1));
+
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn write_part<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &str, contents: &str) -> Result<(), XlsxError> {
+    zip.start_file(name, FileOptions::default().compression_method(CompressionMethod::Deflated)).map_err(XlsxError::ZipError)?;
+    zip.write_all(contents.as_bytes()).map_err(XlsxError::Io)
+}
+
+// a "Summary" sheet with one row per course mark, followed by one sheet per course listing its
+// assignments
+pub fn write_workbook<W: Write + Seek>(w: W, gradebook: &Gradebook) -> Result<W, XlsxError> {
+    workbook(w, gradebook, None)
+}
+
+pub fn write_workbook_with_goals<W: Write + Seek>(w: W, gradebook: &Gradebook, goals: &Goals) -> Result<W, XlsxError> {
+    workbook(w, gradebook, Some(goals))
+}
+
+fn workbook<W: Write + Seek>(w: W, gradebook: &Gradebook, goals: Option<&Goals>) -> Result<W, XlsxError> {
+    let mut used = HashSet::new();
+    used.insert("summary".to_string());
+
+    let mut sheets = vec![summary_sheet(gradebook, goals)];
+    for course in gradebook.courses() {
+        let name = sheet_name(course_name(&course.title), &mut used);
+        sheets.push(course_sheet(course, name));
+    }
+
+    let mut zip = ZipWriter::new(w);
+
+    write_part(&mut zip, "[Content_Types].xml", &content_types_xml(sheets.len()))?;
+    write_part(&mut zip, "_rels/.rels", ROOT_RELS)?;
+    write_part(&mut zip, "xl/workbook.xml", &workbook_xml(&sheets))?;
+    write_part(&mut zip, "xl/_rels/workbook.xml.rels", &workbook_rels_xml(sheets.len()))?;
+    write_part(&mut zip, "xl/styles.xml", STYLES)?;
+
+    for (i, sheet) in sheets.iter().enumerate() {
+        write_part(&mut zip, &format!("xl/worksheets/sheet{}.xml", i + 1), &sheet_xml(sheet))?;
+    }
+
+    zip.finish().map_err(XlsxError::ZipError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    const GRADEBOOK: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    fn part(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut contents = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn names_columns_in_a1_style() {
+        let cases = [(0, "A"), (25, "Z"), (26, "AA"), (27, "AB"), (701, "ZZ"), (702, "AAA")];
+
+        for &(idx, expected) in cases.iter() {
+            assert_eq!(column_name(idx), expected, "{}", idx);
+        }
+    }
+
+    #[test]
+    fn counts_dates_from_the_excel_epoch() {
+        assert_eq!(date_serial(&NaiveDate::from_ymd_opt(1900, 1, 1).unwrap()), 2);
+        assert_eq!(date_serial(&NaiveDate::from_ymd_opt(2018, 9, 20).unwrap()), 43363);
+    }
+
+    #[test]
+    fn keeps_sheet_names_valid_and_unique() {
+        let mut used = HashSet::new();
+        used.insert("summary".to_string());
+
+        assert_eq!(sheet_name("Summary", &mut used), "Summary (2)");
+        assert_eq!(sheet_name("AP Bio: Lab [A/B]?", &mut used), "AP Bio_ Lab _A_B__");
+        assert_eq!(sheet_name("   ", &mut used), "Course");
+        assert_eq!(sheet_name("course", &mut used), "course (2)");
+
+        let long = "Advanced Placement United States History";
+        assert_eq!(sheet_name(long, &mut used), "Advanced Placement United State");
+        assert_eq!(sheet_name(long, &mut used), "Advanced Placement United S (2)");
+        assert!(used.iter().all(|n| n.chars().count() <= 31));
+    }
+
+    #[test]
+    fn writes_a_sheet_per_course_after_the_summary() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses[0].marks[0].assignments[0].notes = "Revise <intro> & \"hook\"\u{7}".to_string();
+
+        let buf = write_workbook(Cursor::new(Vec::new()), &gradebook).unwrap().into_inner();
+        let mut archive = ZipArchive::new(Cursor::new(buf)).unwrap();
+
+        let workbook = part(&mut archive, "xl/workbook.xml");
+        assert!(workbook.contains("<sheet name=\"Summary\" sheetId=\"1\" r:id=\"rId1\"/>"));
+        assert!(workbook.contains("<sheet name=\"English 10\" sheetId=\"2\" r:id=\"rId2\"/>"));
+        assert!(workbook.contains("<sheet name=\"Chemistry\" sheetId=\"3\" r:id=\"rId3\"/>"));
+        assert!(part(&mut archive, "[Content_Types].xml").contains("/xl/worksheets/sheet3.xml"));
+        assert!(part(&mut archive, "xl/_rels/workbook.xml.rels").contains("Id=\"rId4\""));
+
+        let summary = part(&mut archive, "xl/worksheets/sheet1.xml");
+        assert!(summary.contains("<c r=\"D2\" t=\"inlineStr\"><is><t xml:space=\"preserve\">Okafor, Chris</t></is></c>"));
+        assert!(summary.contains("<c r=\"B2\"><v>2</v></c>"));
+
+        let english = part(&mut archive, "xl/worksheets/sheet2.xml");
+        assert!(english.contains("<c r=\"B2\" t=\"inlineStr\"><is><t xml:space=\"preserve\">Personal Narrative</t></is></c>"));
+        assert!(english.contains("<c r=\"D2\" s=\"2\"><v>43363</v></c>"));
+        assert!(english.contains("<c r=\"H2\"><v>45.6</v></c><c r=\"I2\"><v>50</v></c>"));
+        assert!(english.contains("Revise &lt;intro&gt; &amp; &quot;hook&quot;</t>"));
+
+        let chemistry = part(&mut archive, "xl/worksheets/sheet3.xml");
+        assert!(!chemistry.contains("<row r=\"2\">"));
+    }
+}
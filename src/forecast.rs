@@ -0,0 +1,196 @@
+// End-of-term projections: each category's ungraded assignments are assumed to score what the
+// category has averaged so far. The bounds assume one standard deviation of the category's past
+// scores either way, which is rough but makes it obvious when a projection rests on two quizzes.
+use goals::{categories, CategoryProgress};
+use gradebook::{Mark, Points};
+use grading::RoundingPolicy;
+
+#[derive(Clone, Copy, Debug)]
+pub struct GradeProjection {
+    pub estimate: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl GradeProjection {
+    pub fn rounded(&self, policy: &RoundingPolicy) -> GradeProjection {
+        GradeProjection {
+            estimate: policy.round(self.estimate),
+            low: policy.round(self.low),
+            high: policy.round(self.high),
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+
+    mean(values).map(|m| {
+        let variance = values.iter().map(|v| (v - m) * (v - m)).sum::<f64>() / (values.len() - 1) as f64;
+        variance.sqrt()
+    })
+}
+
+// percentages of the graded assignments in `category`, or in the whole mark for None
+fn scores(mark: &Mark, category: Option<&str>) -> Vec<f64> {
+    mark.assignments().iter()
+        .filter(|a| category.map(|c| a._type == c).unwrap_or(true))
+        .filter_map(|a| a.points.percentage())
+        .collect()
+}
+
+// the weighted grade if remaining work in each category scores `pct(category)`
+fn grade_with<F: Fn(&CategoryProgress) -> f64>(categories: &[CategoryProgress], pct: F) -> Option<f64> {
+    let counted: Vec<&CategoryProgress> = categories.iter().filter(|c| c.total() > Points(0.0)).collect();
+    let total_weight: f64 = counted.iter().map(|c| c.weight).sum();
+
+    if total_weight <= 0.0 {
+        return None;
+    }
+
+    let weighted: f64 = counted.iter()
+        .filter_map(|c| (c.earned + c.remaining * (pct(c) / 100.0)).percentage_of(c.total()).map(|p| c.weight * p))
+        .sum();
+
+    Some(weighted / total_weight)
+}
+
+impl Mark {
+    // None if nothing has been graded yet
+    pub fn projected_grade(&self) -> Option<GradeProjection> {
+        let categories = categories(self);
+        let overall = scores(self, None);
+        let overall_mean = match mean(&overall) {
+            Some(m) => m,
+            None => { return None; }
+        };
+        let overall_dev = std_dev(&overall);
+
+        // a category with nothing graded yet is assumed to go like the rest of the mark
+        let average = |c: &CategoryProgress| {
+            c.earned.percentage_of(c.possible).unwrap_or(overall_mean)
+        };
+        let spread = |c: &CategoryProgress| {
+            let category = if c.category == "Total" { None } else { Some(c.category.as_str()) };
+            // with fewer than two scores there's nothing to go on, so anything is possible
+            std_dev(&scores(self, category)).or(overall_dev).unwrap_or(100.0)
+        };
+
+        let estimate = grade_with(&categories, |c| average(c));
+        let low = grade_with(&categories, |c| (average(c) - spread(c)).max(0.0));
+        // capped at 100% (or the average itself, for a category already over it on extra credit)
+        // so a wide spread can't project a grade no one could earn
+        let high = grade_with(&categories, |c| (average(c) + spread(c)).min(average(c).max(100.0)));
+
+        match (estimate, low, high) {
+            (Some(estimate), Some(low), Some(high)) => Some(GradeProjection {
+                estimate,
+                low,
+                high,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::{Assignment, AssignmentGradeCalc, AssignmentGradeCalcWeight, AssignmentPoints, AssignmentScore,
+                    Gradebook};
+
+    // the fixture's first mark, with `assignments` as (category, earned, possible) and None for
+    // ungraded work
+    fn mark(assignments: &[(&str, Option<f64>, f64)]) -> Mark {
+        let gradebook = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+        let mut mark = gradebook.courses[0].marks[0].clone();
+        let template = mark.assignments[0].clone();
+
+        mark.grade_calculation_summary.clear();
+        mark.assignments = assignments.iter().map(|&(category, earned, possible)| Assignment {
+            _type: category.to_string(),
+            score: match earned {
+                Some(e) => AssignmentScore::Score(Points(e), Points(possible)),
+                None => AssignmentScore::NotGraded,
+            },
+            points: match earned {
+                Some(e) => AssignmentPoints::Graded(Points(e), Points(possible)),
+                None => AssignmentPoints::Ungraded(Points(possible)),
+            },
+            ..template.clone()
+        }).collect();
+        mark
+    }
+
+    fn weighted(mut mark: Mark, weights: &[(&str, f64)]) -> Mark {
+        mark.grade_calculation_summary = weights.iter().map(|&(category, weight)| {
+            let (earned, possible) = mark.assignments.iter()
+                .filter(|a| a._type == category)
+                .fold((0.0, 0.0), |acc, a| match (a.points.earned(), a.points.possible()) {
+                    (Some(e), Some(p)) => (acc.0 + e.0, acc.1 + p.0),
+                    _ => acc,
+                });
+
+            AssignmentGradeCalc {
+                _type: category.to_string(),
+                calculated_mark: String::new(),
+                points: earned,
+                points_possible: possible,
+                weight: AssignmentGradeCalcWeight::Percentage(weight),
+                weighted_pct: AssignmentGradeCalcWeight::Percentage(0.0),
+            }
+        }).collect();
+        mark
+    }
+
+    #[test]
+    fn no_projection_without_graded_work() {
+        let mark = mark(&[("Homework", None, 10.0), ("Tests", None, 100.0)]);
+
+        assert!(mark.projected_grade().is_none());
+    }
+
+    #[test]
+    fn projects_a_single_category_by_its_average() {
+        let projection = mark(&[("Homework", Some(8.0), 10.0), ("Homework", Some(10.0), 10.0), ("Homework", None, 10.0)])
+            .projected_grade()
+            .unwrap();
+
+        assert!((projection.estimate - 90.0).abs() < 1e-9, "{:?}", projection);
+        assert!(projection.low < projection.estimate && projection.estimate < projection.high, "{:?}", projection);
+        assert!(projection.high <= 100.0, "{:?}", projection);
+    }
+
+    #[test]
+    fn caps_a_wide_spread_at_100() {
+        // one score to go on, so the spread is the full 100 points either way: the remaining 90
+        // points score somewhere between 0% and 100%, not the 190% the spread alone would give
+        let projection = mark(&[("Homework", Some(9.0), 10.0), ("Homework", None, 90.0)]).projected_grade().unwrap();
+
+        assert!((projection.low - 9.0).abs() < 1e-9, "{:?}", projection);
+        assert!((projection.high - 99.0).abs() < 1e-9, "{:?}", projection);
+    }
+
+    #[test]
+    fn an_ungraded_category_goes_like_the_rest_of_the_mark() {
+        let mark = weighted(
+            mark(&[("Homework", Some(8.0), 10.0), ("Homework", Some(6.0), 10.0), ("Tests", None, 100.0)]),
+            &[("Homework", 50.0), ("Tests", 50.0)],
+        );
+        let projection = mark.projected_grade().unwrap();
+
+        // both categories at the homework average of 70%
+        assert!((projection.estimate - 70.0).abs() < 1e-9, "{:?}", projection);
+        assert!(projection.high <= 100.0, "{:?}", projection);
+    }
+}
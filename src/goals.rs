@@ -0,0 +1,247 @@
+// Target grades per course, and what it takes to reach them. "What it takes" assumes the same
+// percentage on every assignment that hasn't been graded yet, weighted the way the mark's grade
+// calculation summary weights categories (or by points, for marks without one).
+use gradebook::{AssignmentGradeCalcWeight, Course, Mark, Points};
+use grading::RoundingPolicy;
+use report::current_mark;
+use rules::CourseMatcher;
+
+#[derive(Clone, Debug, Default)]
+pub struct Goals {
+    rounding: RoundingPolicy,
+    targets: Vec<(CourseMatcher, f64)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CategoryProgress {
+    pub category: String,
+    pub weight: f64,
+    pub earned: Points,
+    pub possible: Points,
+    // possible points of the category's ungraded assignments
+    pub remaining: Points,
+}
+
+impl CategoryProgress {
+    // what the category will be out of once everything is graded
+    pub fn total(&self) -> Points {
+        self.possible + self.remaining
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GoalProgress {
+    pub target: f64,
+    // rounded the way the portal shows it, so a goal counts as met when the portal says it is
+    pub current: f64,
+    pub categories: Vec<CategoryProgress>,
+    // the percentage needed on all remaining work to finish at `target`; over 100 means it's out of
+    // reach, and None means there's no remaining work left to change the grade
+    pub required: Option<f64>,
+}
+
+impl GoalProgress {
+    // positive while below the target
+    pub fn distance(&self) -> f64 {
+        self.target - self.current
+    }
+
+    pub fn is_met(&self) -> bool {
+        self.current >= self.target
+    }
+
+    pub fn is_reachable(&self) -> bool {
+        self.is_met() || self.required.map(|r| r <= 100.0).unwrap_or(false)
+    }
+}
+
+fn weight(w: &AssignmentGradeCalcWeight) -> Option<f64> {
+    match *w {
+        AssignmentGradeCalcWeight::Percentage(p) => Some(p),
+        AssignmentGradeCalcWeight::Unparseable(_) => None,
+    }
+}
+
+fn remaining_points(mark: &Mark, category: Option<&str>) -> Points {
+    mark.assignments().iter()
+        .filter(|a| category.map(|c| a._type == c).unwrap_or(true))
+        .filter(|a| a.points.earned().is_none() && !a.score.is_graded())
+        .filter_map(|a| a.points.possible())
+        .sum()
+}
+
+pub fn categories(mark: &Mark) -> Vec<CategoryProgress> {
+    let weighted: Vec<CategoryProgress> = mark.grade_calculation_summary().iter()
+        // the portal adds a row for the whole mark
+        .filter(|c| !c._type.eq_ignore_ascii_case("total"))
+        .filter_map(|c| weight(&c.weight).map(|w| CategoryProgress {
+            category: c._type.clone(),
+            weight: w,
+            earned: Points(c.points),
+            possible: Points(c.points_possible),
+            remaining: remaining_points(mark, Some(&c._type)),
+        }))
+        .collect();
+
+    if !weighted.is_empty() {
+        return weighted;
+    }
+
+    let (earned, possible) = mark.assignments().iter()
+        .fold((Points(0.0), Points(0.0)), |acc, a| match (a.points.earned(), a.points.possible()) {
+            (Some(e), Some(p)) => (acc.0 + e, acc.1 + p),
+            _ => acc,
+        });
+
+    vec![CategoryProgress {
+        category: "Total".to_string(),
+        weight: 100.0,
+        earned,
+        possible,
+        remaining: remaining_points(mark, None),
+    }]
+}
+
+// the final grade is linear in the percentage scored on remaining work: `base + slope * pct`
+fn required_percentage(categories: &[CategoryProgress], target: f64) -> Option<f64> {
+    let counted: Vec<&CategoryProgress> = categories.iter().filter(|c| c.total() > Points(0.0)).collect();
+    let total_weight: f64 = counted.iter().map(|c| c.weight).sum();
+
+    if total_weight <= 0.0 {
+        return None;
+    }
+
+    let base: f64 = counted.iter()
+        .filter_map(|c| c.earned.percentage_of(c.total()).map(|p| c.weight * p))
+        .sum::<f64>() / total_weight;
+    let slope: f64 = counted.iter()
+        .filter_map(|c| c.remaining.percentage_of(c.total()).map(|p| c.weight * p / 100.0))
+        .sum::<f64>() / total_weight;
+
+    if slope > 0.0 {
+        Some((target - base) / slope)
+    } else {
+        None
+    }
+}
+
+impl Goals {
+    pub fn new() -> Goals {
+        Default::default()
+    }
+
+    // a target percentage for matching courses; the first matching target wins
+    pub fn target(mut self, matcher: CourseMatcher, target: f64) -> Goals {
+        self.targets.push((matcher, target));
+        self
+    }
+
+    pub fn rounding(mut self, policy: RoundingPolicy) -> Goals {
+        self.rounding = policy;
+        self
+    }
+
+    pub fn target_for(&self, course: &Course) -> Option<f64> {
+        self.targets.iter()
+            .find(|&(matcher, _)| matcher.matches(course))
+            .map(|&(_, target)| target)
+    }
+
+    // None for courses without a target or a current mark
+    pub fn progress(&self, course: &Course) -> Option<GoalProgress> {
+        let target = match self.target_for(course) {
+            Some(t) => t,
+            None => { return None; }
+        };
+
+        current_mark(course).map(|mark| {
+            let categories = categories(mark);
+
+            GoalProgress {
+                target,
+                current: self.rounding.round(mark.calculated_score_raw),
+                required: required_percentage(&categories, target),
+                categories,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::Gradebook;
+    use grading::RoundingMode;
+
+    fn courses() -> Vec<Course> {
+        Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap().courses
+    }
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.map(|a| (a - b).abs() < 1e-9).unwrap_or(false)
+    }
+
+    #[test]
+    fn takes_the_first_matching_target() {
+        let courses = courses();
+        let goals = Goals::new()
+            .target(CourseMatcher::Title("english".to_string()), 95.0)
+            .target(CourseMatcher::Period(2), 80.0);
+
+        assert_eq!(goals.target_for(&courses[0]), Some(95.0));
+        assert_eq!(goals.target_for(&courses[1]), None);
+        assert!(goals.progress(&courses[1]).is_none());
+    }
+
+    #[test]
+    fn works_out_the_score_needed_on_remaining_work() {
+        let courses = courses();
+        // Essays is 91.2 of 100 points, with 30 more possible on the ungraded Reading Log and
+        // Vocabulary Check
+        let progress = Goals::new().target(CourseMatcher::Period(2), 95.0).progress(&courses[0]).unwrap();
+
+        assert_eq!(progress.categories.len(), 1);
+        assert_eq!(progress.categories[0].remaining, Points(30.0));
+        assert_eq!(progress.categories[0].total(), Points(130.0));
+        assert!(close(progress.required, (123.5 - 91.2) / 30.0 * 100.0));
+        assert!(!progress.is_met());
+        assert!(!progress.is_reachable());
+
+        let progress = Goals::new().target(CourseMatcher::Period(2), 75.0).progress(&courses[0]).unwrap();
+        assert!(close(progress.required, (97.5 - 91.2) / 30.0 * 100.0));
+        assert!(progress.is_met());
+        assert!(progress.distance() < 0.0);
+    }
+
+    #[test]
+    fn compares_against_the_score_rounded_like_the_portal() {
+        let courses = courses();
+        let goals = Goals::new()
+            .target(CourseMatcher::Period(2), 91.5)
+            .rounding(RoundingPolicy::new(RoundingMode::HalfUp, 0));
+
+        let progress = goals.progress(&courses[0]).unwrap();
+        assert_eq!(progress.current, 91.0);
+        assert!(!progress.is_met());
+    }
+
+    #[test]
+    fn weighs_by_points_without_a_calculation_summary() {
+        let mut courses = courses();
+        courses[0].marks[0].grade_calculation_summary.clear();
+        let goals = Goals::new().target(CourseMatcher::Period(2), 90.0);
+
+        let progress = goals.progress(&courses[0]).unwrap();
+        assert_eq!(progress.categories[0].category, "Total");
+        assert_eq!(progress.categories[0].earned, Points(45.6));
+        assert_eq!(progress.categories[0].possible, Points(50.0));
+        // 90% of 80 points is 72, and 45.6 are in already
+        assert!(close(progress.required, (72.0 - 45.6) / 30.0 * 100.0));
+
+        // nothing left to grade
+        courses[0].marks[0].assignments.truncate(1);
+        let progress = goals.progress(&courses[0]).unwrap();
+        assert_eq!(progress.required, None);
+        assert!(progress.is_reachable());
+    }
+}
@@ -1,30 +1,32 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Mul, Sub};
 use std::str::FromStr;
 
-use api::{SVUEAPIAction, SVUERequest, SVUERequestError};
+use api::SVUEResponse;
+#[cfg(feature="client")]
+use api::{Account, SVUEAPIAction, SVUEClient, SVUEErrorKind, SVUERequestError};
+use clock::Clock;
 use decoder::*;
-use diff::Pairable;
-
-use chrono::NaiveDate;
-use regex::Regex;
+use dedup::{self, Fingerprint, Fnv};
+#[cfg(feature="client")]
+use diff::Changeset;
+use diff::{FallbackPairable, Pairable};
+use locale;
+use turnin::{TurnInMarkers, TurnInStatus};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use regex::{Captures, Regex};
 use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
 
-macro_rules! field_slice_helpers {
-    ( $t:ty, { $($field:tt => $field_t:ty),+ } ) => {
-        impl $t {
-            $(
-                #[allow(dead_code)]
-                pub fn $field(&self) -> &[$field_t] {
-                    self.$field.as_slice()
-                }
-            )+
-        }
-    };
-}
-
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Gradebook {
     pub courses: Vec<Course>,
+    // when the district says the gradebook last changed; most don't say
+    pub last_updated: Option<NaiveDateTime>,
     pub reporting_period: ReportingPeriod,
     pub reporting_periods: Vec<ReportPeriod>,
 }
@@ -34,66 +36,234 @@ field_slice_helpers!(Gradebook, {
     reporting_periods => ReportPeriod
 });
 
+#[cfg(feature="client")]
+#[derive(Clone, Debug)]
+pub struct FetchedGradebook {
+    pub gradebook: Gradebook,
+    // what changed since the previous gradebook, diffed locally; None if nothing did, or if there's
+    // no data
+    pub changes: Option<Changeset>,
+    // the district said there's nothing for the period (see `Gradebook::retrieve_if_any`), so
+    // `gradebook` is empty rather than what the student's courses really look like
+    pub no_data: bool,
+}
+
+#[cfg(feature="client")]
+impl FetchedGradebook {
+    // what `Gradebook::retrieve_if_any` gave, against `previous`
+    pub(crate) fn since(previous: &Gradebook, fetched: Option<Gradebook>) -> FetchedGradebook {
+        match fetched {
+            Some(gradebook) => {
+                FetchedGradebook {
+                    changes: Changeset::diff(previous, &gradebook),
+                    gradebook,
+                    no_data: false,
+                }
+            }
+            None => {
+                FetchedGradebook {
+                    gradebook: Gradebook::default(),
+                    changes: None,
+                    no_data: true,
+                }
+            }
+        }
+    }
+}
+
 impl Gradebook {
+    // these connect anew on every call; anything fetching more than once should keep an
+    // `SVUEClient` around instead, which reuses its connections
+    #[cfg(feature="client")]
     pub fn retrieve<'a>(user: &'a str, password: &'a str) -> Result<Gradebook, SVUERequestError> {
         Self::retrieve_for_grade_period(user, password, -1)
     }
 
+    #[cfg(feature="client")]
     pub fn retrieve_for_grade_period<'a>(user: &'a str, password: &'a str, period: i8) -> Result<Gradebook, SVUERequestError> {
-        let action = if period < 0 {
-            SVUEAPIAction::RetrieveGrades(None)
+        let client = SVUEClient::new(user, password);
+
+        if period < 0 {
+            client.gradebook()
         } else {
-            SVUEAPIAction::RetrieveGrades(Some(period))
+            client.gradebook_for_period(period)
+        }
+    }
+
+    // one child's gradebook through a parent's account; `child_int_id` is from
+    // `students::Student`, and `period` is as in `retrieve_with`
+    #[cfg(feature="client")]
+    pub fn retrieve_for_child(user: &str, password: &str, child_int_id: &str, period: Option<i8>)
+        -> Result<Gradebook, SVUERequestError> {
+
+        Self::retrieve_with(&SVUEClient::new(user, password).account(Account::child(child_int_id)), period)
+    }
+
+    // for districts other than Portland; `district_url` is the address of the district's portal
+    #[cfg(feature="client")]
+    pub fn retrieve_from_district(district_url: &str, user: &str, password: &str) -> Result<Gradebook, SVUERequestError> {
+        Self::retrieve_with(&SVUEClient::new(user, password).district(district_url), None)
+    }
+
+    // an empty gradebook where `retrieve_if_any` gives `None`
+    #[cfg(feature="client")]
+    pub fn retrieve_with(client: &SVUEClient, period: Option<i8>) -> Result<Gradebook, SVUERequestError> {
+        Self::retrieve_if_any(client, period).map(|gradebook| gradebook.unwrap_or_default())
+    }
+
+    // between terms some districts answer with an error saying there's nothing to show rather than
+    // an empty gradebook, which comes back as `None`; anything comparing gradebooks should keep
+    // the last one it had rather than treat every course as dropped
+    #[cfg(feature="client")]
+    pub fn retrieve_if_any(client: &SVUEClient, period: Option<i8>) -> Result<Option<Gradebook>, SVUERequestError> {
+        Self::from_result(client.perform(SVUEAPIAction::RetrieveGrades(period)))
+    }
+
+    // the result of a `RetrieveGrades` request, as `retrieve_if_any` gives it
+    #[cfg(feature="client")]
+    pub(crate) fn from_result(result: Result<SVUEResponse, SVUERequestError>) -> Result<Option<Gradebook>, SVUERequestError> {
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(SVUERequestError::SVUEError(ref e)) if e.kind() == SVUEErrorKind::NoDataForPeriod => {
+                return Ok(None);
+            }
+            Err(e) => { return Err(e); }
         };
-        let resp = SVUERequest::perform(action, (user, password))?;
-        let mut events_iter = EventReader::new(resp.xml.as_bytes()).into_iter();
 
-        Self::from_event(events_iter.next().unwrap().unwrap(), &mut events_iter)
-            .map_err(|e| SVUERequestError::DecodingError(e))
+        Self::from_response(&resp).map(Some).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+
+    // true for the gradebooks returned over the summer and before a term starts, which have no
+    // courses, or courses without any marks yet
+    pub fn is_empty(&self) -> bool {
+        self.courses.iter().all(|c| c.marks.is_empty())
+    }
+
+    // the gradebook along with what's changed since `previous`. StudentVUE has no standard method
+    // for only the recent activity, so this fetches the whole gradebook and diffs it locally
+    #[cfg(feature="client")]
+    pub fn retrieve_since(client: &SVUEClient, period: Option<i8>, previous: &Gradebook)
+        -> Result<FetchedGradebook, SVUERequestError> {
+
+        Self::retrieve_if_any(client, period).map(|gradebook| FetchedGradebook::since(previous, gradebook))
+    }
+
+    // the district's last-updated time if it gives one, otherwise the date of the newest
+    // assignment; None for a gradebook without either
+    pub fn last_activity(&self) -> Option<NaiveDateTime> {
+        self.last_updated.or_else(|| {
+            self.courses.iter()
+                .flat_map(|c| c.marks().iter())
+                .flat_map(|m| m.assignments().iter())
+                .map(|a| a.date)
+                .max()
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+        })
+    }
+
+    // only true when the district's last-updated time says so; the newest assignment date can't
+    // tell, since regrading an old assignment doesn't change it
+    pub fn unchanged_since(&self, previous: &Gradebook) -> bool {
+        match (self.last_updated, previous.last_updated) {
+            (Some(current), Some(previous)) => current <= previous,
+            _ => false,
+        }
+    }
+
+    // equal for gradebooks with the same content, whatever order it came in; stable across runs,
+    // so it can be compared against a stored snapshot's. `last_updated` is left out since a bumped
+    // timestamp on its own changes nothing worth diffing
+    pub fn content_hash(&self) -> Fingerprint {
+        dedup::reporting_period(Fnv::new("gradebook"), &self.reporting_period)
+            .unordered(&self.reporting_periods, dedup::report_period_hash)
+            .unordered(&self.courses, dedup::course_hash)
+            .finish()
+    }
+
+    // a cheap check before a full diff
+    pub fn same_content(&self, other: &Gradebook) -> bool {
+        self.content_hash() == other.content_hash()
+    }
+
+    // `reporting_period` as an entry of `reporting_periods`, whose index is what the API wants
+    // when asking for a period
+    pub fn reporting_period_index(&self) -> Option<i8> {
+        self.reporting_periods.iter()
+            .find(|p| p.is(&self.reporting_period))
+            .map(|p| p.index)
+    }
+
+    // the report period `clock` is in, if any
+    pub fn current_report_period<C: Clock>(&self, clock: &C) -> Option<&ReportPeriod> {
+        let today = clock.today();
+
+        self.reporting_periods.iter().find(|p| p.start_date <= today && today <= p.end_date)
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<Gradebook> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_response_with(resp: &SVUEResponse, limits: &DecodeLimits) -> DecoderResult<Gradebook> {
+        Self::from_xml_with(&resp.xml, limits)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<Gradebook> {
+        Self::from_xml_with(xml, &DecodeLimits::new())
+    }
+
+    pub fn from_xml_with(xml: &str, limits: &DecodeLimits) -> DecoderResult<Gradebook> {
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+        limits.reset();
+
+        match events_iter.next() {
+            Some(Ok(event)) => Self::from_event(event, &mut events_iter, limits),
+            Some(Err(e)) => Err(DecodingError::EventError(e)),
+            None => Err(DecodingError::UnexpectedEnd),
+        }
     }
 }
 
 impl SVUEDecodeable for Gradebook {
-    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>) -> DecoderResult<Gradebook> {
+    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Gradebook> {
         let mut courses = Vec::new();
+        let mut last_updated = None;
         let mut reporting_period: ReportingPeriod = Default::default();
         let mut reporting_periods = Vec::new();
 
         loop {
             match events_iter.next() {
                 Some(Ok(event)) => {
-                    match event.clone() {
-                        ReaderEvent::StartElement { ref name, .. } => {
-                            match name.local_name.as_str() {
-                                "Course" => {
-                                    let course = Course::from_event(event, events_iter)?;
+                    match event {
+                        ReaderEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "Gradebook" => {
+                            let attrs = attributes_vec_to_map(attributes);
+
+                            last_updated = ["LastUpdated", "LastModified", "LastUpdate"].iter()
+                                .filter_map(|a| attrs.get(a))
+                                .filter_map(|v| parse_timestamp(v))
+                                .next();
+                        }
+                        _ if is_start_of(&event, "Course") => {
+                            let course = Course::from_event(event, events_iter, limits)?;
 
-                                    courses.push(course);
-                                }
-                                "ReportPeriod" => {
-                                    let report_period = ReportPeriod::from_event(event, events_iter)?;
+                            courses.push(course);
+                        }
+                        _ if is_start_of(&event, "ReportPeriod") => {
+                            let report_period = ReportPeriod::from_event(event, events_iter, limits)?;
 
-                                    reporting_periods.push(report_period);
-                                }
-                                "ReportingPeriod" => {
-                                    reporting_period = ReportingPeriod::from_event(event, events_iter)?;
-                                }
-                                _ => {}
-                            }
+                            reporting_periods.push(report_period);
                         }
-                        ReaderEvent::EndElement { name } => {
-                            match name.local_name.as_str() {
-                                "Gradebook" => {
-                                    return Ok(Gradebook {
-                                        courses: courses,
-                                        reporting_period: reporting_period,
-                                        reporting_periods: reporting_periods,
-                                    });
-                                }
-                                _ => {}
-                            }
+                        _ if is_start_of(&event, "ReportingPeriod") => {
+                            reporting_period = ReportingPeriod::from_event(event, events_iter, limits)?;
+                        }
+                        _ if is_end_of(&event, "Gradebook") => {
+                            return Ok(Gradebook {
+                                courses,
+                                last_updated,
+                                reporting_period,
+                                reporting_periods,
+                            });
                         }
-                        ReaderEvent::Whitespace(_) => {},
                         _ => {}
                     }
                 }
@@ -113,22 +283,30 @@ pub struct ReportPeriod {
     pub start_date: NaiveDate,
 }
 
+impl ReportPeriod {
+    // whether `period` (which has no index) is this one
+    pub fn is(&self, period: &ReportingPeriod) -> bool {
+        self.grade_period == period.grade_period && self.start_date == period.start_date
+    }
+}
+
 impl SVUEDecodeable for ReportPeriod {
-    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>) -> DecoderResult<ReportPeriod> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<ReportPeriod> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "ReportPeriod" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         Ok(ReportPeriod {
                             end_date: parse_date!(attrs, "EndDate"),
-                            grade_period: get_attr_owned!(attrs, "GradePeriod").clone(),
+                            grade_period: get_attr_owned!(attrs, "GradePeriod"),
                             index: parse_int!(i8, attrs, "Index"),
                             start_date: parse_date!(attrs, "StartDate"),
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -147,20 +325,21 @@ pub struct ReportingPeriod {
 impl Default for ReportingPeriod {
     fn default() -> ReportingPeriod {
         ReportingPeriod {
-            end_date: NaiveDate::from_ymd(1970, 1, 1),
+            end_date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
             grade_period: "".to_string(),
-            start_date: NaiveDate::from_ymd(1970, 1, 1),
+            start_date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
         }
     }
 }
 
 impl SVUEDecodeable for ReportingPeriod {
-    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>) -> DecoderResult<ReportingPeriod> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<ReportingPeriod> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "ReportingPeriod" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         Ok(ReportingPeriod {
                             end_date: parse_date!(attrs, "EndDate"),
@@ -168,7 +347,7 @@ impl SVUEDecodeable for ReportingPeriod {
                             start_date: parse_date!(attrs, "StartDate"),
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -185,8 +364,11 @@ pub enum CourseTitle {
 
 impl CourseTitle {
     fn parse(title: &str) -> CourseTitle {
-        let r = Regex::new(r"(.+)\s+\((.+?)\)").unwrap();
-        let captures = r.captures(title);
+        lazy_static! {
+            static ref TITLE: Regex = Regex::new(r"(.+)\s+\((.+?)\)").unwrap();
+        }
+
+        let captures = TITLE.captures(title);
 
         match captures {
             Some(cs) => {
@@ -204,6 +386,75 @@ impl CourseTitle {
             None => CourseTitle::Unparseable(title.to_string())
         }
     }
+
+    pub fn id(&self) -> Option<&str> {
+        match *self {
+            CourseTitle::Parsed(_, ref id) => Some(id),
+            CourseTitle::Unparseable(_) => None,
+        }
+    }
+
+    pub fn course_id(&self) -> Option<CourseId> {
+        self.id().map(CourseId::parse)
+    }
+
+    // the ID without its section, e.g. "MAT401" for "MAT401-03"
+    pub fn base_code(&self) -> Option<String> {
+        self.course_id().map(|id| id.code)
+    }
+
+    // both are sections of the same course, going by their IDs
+    pub fn same_course(&self, other: &CourseTitle) -> bool {
+        match (self.base_code(), other.base_code()) {
+            (Some(a), Some(b)) => !a.is_empty() && a == b,
+            _ => false,
+        }
+    }
+}
+
+// a course ID split into the course's code and the section, for IDs that have one. Districts
+// write these as "MAT401-03", "MAT401.3", "MAT401/03" or "MAT401 03"; the code is uppercased with
+// spaces dropped so the same course matches however it's written
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CourseId {
+    pub code: String,
+    pub section: Option<String>,
+}
+
+impl CourseId {
+    pub fn parse(id: &str) -> CourseId {
+        lazy_static! {
+            static ref SECTION: Regex = Regex::new(r"^(.*?[A-Za-z].*?)\s*[-./_ ]\s*([A-Za-z]?\d{1,3}[A-Za-z]?)$").unwrap();
+        }
+
+        let id = id.trim();
+        let split = SECTION.captures(id).and_then(|cs| match (cs.get(1), cs.get(2)) {
+            (Some(c), Some(s)) => Some((c.as_str(), s.as_str())),
+            _ => None,
+        });
+        let (code, section) = match split {
+            // only a code with a number of its own has a section after it: "SCI-101", "ENG 9" and
+            // "SPN-2" are courses in their own right, not sections of "SCI", "ENG" and "SPN"
+            Some((code, section)) if code.chars().any(|c| c.is_ascii_digit()) => {
+                (code, Some(section.to_string()))
+            }
+            _ => (id, None),
+        };
+
+        CourseId {
+            code: code.split_whitespace().collect::<Vec<_>>().join("").to_uppercase(),
+            section,
+        }
+    }
+}
+
+impl fmt::Display for CourseTitle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CourseTitle::Parsed(ref name, ref id) => write!(f, "{} ({})", name, id),
+            CourseTitle::Unparseable(ref title) => write!(f, "{}", title),
+        }
+    }
 }
 
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
@@ -222,19 +473,52 @@ field_slice_helpers!(Course, {
     marks => Mark
 });
 
+impl Course {
+    // the mark that counts: one that none of the course's other marks roll into (a semester mark
+    // over the quarter it includes, a quarter over its progress report), and the last listed of
+    // those. districts list different subsets of marks, in no particular order, so the first isn't
+    // reliably it; marks rvue can't classify only count if there's nothing else, and then it's the
+    // last one, which the portal lists as current
+    pub fn official_mark(&self) -> Option<&Mark> {
+        let kinds: Vec<MarkKind> = self.marks.iter().map(|m| m.kind()).collect();
+        let tops: Vec<(&Mark, MarkKind)> = self.marks.iter().zip(kinds.iter())
+            .filter(|&(_, kind)| !kinds.iter().any(|other| kind.rolls_into(other)))
+            .map(|(m, kind)| (m, *kind))
+            .collect();
+
+        tops.iter().rev()
+            .find(|&&(_, kind)| kind != MarkKind::Other)
+            .or(tops.last())
+            .map(|&(m, _)| m)
+    }
+
+    pub fn marks_of(&self, kind: MarkKind) -> Vec<&Mark> {
+        self.marks.iter().filter(|m| m.kind() == kind).collect()
+    }
+}
+
 impl<'a> Pairable<'a, CourseTitle> for Course {
     fn unique_key(&'a self) -> &'a CourseTitle {
         &self.title
     }
 }
 
+// a course whose title changed but whose ID still has the same base code (e.g. a section change,
+// or the district renaming it) is still the same course
+impl FallbackPairable for Course {
+    fn fallback_eq(&self, other: &Course) -> bool {
+        self.title.same_course(&other.title)
+    }
+}
+
 impl SVUEDecodeable for Course {
-    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>) -> DecoderResult<Course> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Course> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "Course" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let cutoff = parse_int!(i8, attrs, "HighlightPercentageCutOffForProgressBar");
                         let mut marks = Vec::new();
@@ -242,27 +526,17 @@ impl SVUEDecodeable for Course {
                         loop {
                             match events_iter.next() {
                                 Some(Ok(event)) => {
-                                    match event.clone() {
-                                        ReaderEvent::StartElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "Mark" => {
-                                                    let mark = Mark::from_event(event, events_iter)?;
-
-                                                    marks.push(mark);
-                                                }
-                                                "Marks" => {},
-                                                _ => { return Err(DecodingError::UnexpectedEvent(event)); }
-                                            }
+                                    match event {
+                                        _ if is_start_of(&event, "Mark") => {
+                                            let mark = Mark::from_event(event, events_iter, limits)?;
+
+                                            marks.push(mark);
                                         }
-                                        ReaderEvent::EndElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "Course" => {
-                                                    break;
-                                                }
-                                                _ => {}
-                                            }
+                                        _ if is_start_of(&event, "Marks") => {}
+                                        _ if is_end_of(&event, "Course") => {
+                                            break;
                                         }
-                                        ReaderEvent::Whitespace(_) => {},
+                                        ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
                                         _ => { return Err(DecodingError::UnexpectedEvent(event)); }
                                     }
                                 }
@@ -279,15 +553,15 @@ impl SVUEDecodeable for Course {
 
                         Ok(Course {
                             highlight_percentage_cut_off_for_progress_bar: cutoff,
-                            marks: marks,
-                            period: period,
-                            room: room,
-                            staff: staff,
-                            staff_email: staff_email,
-                            title: title,
+                            marks,
+                            period,
+                            room,
+                            staff,
+                            staff_email,
+                            title,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -295,6 +569,61 @@ impl SVUEDecodeable for Course {
     }
 }
 
+// how much of the term a mark covers; progress reports roll into quarters (or trimesters), which
+// roll into semesters, which roll into the final mark. variants are ordered from the least to the
+// most official, with marks that can't be classified below the rest, but that isn't a hierarchy:
+// a school has quarters or trimesters, never both (see `rolls_into`)
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MarkKind {
+    Other,
+    Progress,
+    Quarter,
+    Trimester,
+    Semester,
+    Final,
+}
+
+impl MarkKind {
+    // classifies a mark by its name, e.g. "Progress Report 1", "1st Qtr Progress", "Q2", "S1",
+    // "Semester 2" or "Final"; districts name marks however they like, so anything else is `Other`
+    pub fn of(mark_name: &str) -> MarkKind {
+        // "Q1", "1st" and "PR2" all come down to their letters
+        let words: Vec<String> = mark_name.split(|c: char| !c.is_alphanumeric())
+            .map(|w| w.chars().filter(|c| !c.is_numeric()).collect::<String>().to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let has = |names: &[&str]| words.iter().any(|w| names.contains(&w.as_str()));
+
+        // progress comes first since progress reports are often named after their quarter
+        if has(&["progress", "prog", "pr", "ipr", "interim", "mid"]) {
+            MarkKind::Progress
+        } else if has(&["semester", "sem", "s"]) {
+            MarkKind::Semester
+        } else if has(&["trimester", "tri", "t"]) {
+            MarkKind::Trimester
+        } else if has(&["quarter", "qtr", "q"]) {
+            MarkKind::Quarter
+        } else if has(&["final", "fin", "year", "yr"]) {
+            MarkKind::Final
+        } else {
+            MarkKind::Other
+        }
+    }
+
+    // whether a `self` mark counts towards an `other` one
+    pub fn rolls_into(&self, other: &MarkKind) -> bool {
+        let into: &[MarkKind] = match *self {
+            MarkKind::Progress => &[MarkKind::Quarter, MarkKind::Trimester, MarkKind::Semester, MarkKind::Final],
+            MarkKind::Quarter => &[MarkKind::Semester, MarkKind::Final],
+            MarkKind::Trimester | MarkKind::Semester => &[MarkKind::Final],
+            MarkKind::Final | MarkKind::Other => &[],
+        };
+
+        into.contains(other)
+    }
+}
+
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
 pub struct Mark {
@@ -316,15 +645,39 @@ impl Mark {
     pub fn calculated_grade(&self) -> String {
         format!("{} ({})", self.calculated_score_string, self.calculated_score_raw)
     }
+
+    pub fn kind(&self) -> MarkKind {
+        MarkKind::of(&self.mark_name)
+    }
+
+    // the mark-level view of a standard an assignment is graded on, with its overall proficiency
+    pub fn standard_view(&self, standard: &Standard) -> Option<&StandardView> {
+        self.standard_views.iter().find(|v| standard.is_part_of(v))
+    }
+
+    // each of the assignment's standards with its view, if the mark has one
+    pub fn standards_of<'a>(&'a self, assignment: &'a Assignment) -> Vec<(&'a Standard, Option<&'a StandardView>)> {
+        assignment.standards().iter()
+            .map(|s| (s, self.standard_view(s)))
+            .collect()
+    }
+
+    // the assignments graded on `view`'s standard
+    pub fn assignments_for(&self, view: &StandardView) -> Vec<&Assignment> {
+        self.assignments.iter()
+            .filter(|a| a.standards().iter().any(|s| s.is_part_of(view)))
+            .collect()
+    }
 }
 
 impl SVUEDecodeable for Mark {
-    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>) -> DecoderResult<Mark> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Mark> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "Mark" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let mut assignments = Vec::new();
                         let mut grade_calculation_summary = Vec::new();
@@ -333,36 +686,29 @@ impl SVUEDecodeable for Mark {
                         loop {
                             match events_iter.next() {
                                 Some(Ok(event)) => {
-                                    match event.clone() {
-                                        ReaderEvent::StartElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "Assignment" => {
-                                                    let a = Assignment::from_event(event, events_iter)?;
-                                                    assignments.push(a);
-                                                }
-                                                "Assignments" => {}
-                                                "AssignmentGradeCalc" => {
-                                                    let agc = AssignmentGradeCalc::from_event(event, events_iter)?;
-                                                    grade_calculation_summary.push(agc);
-                                                }
-                                                "GradeCalculationSummary" => {}
-                                                "StandardView" => {
-                                                    let sv = StandardView::from_event(event, events_iter)?;
-                                                    standard_views.push(sv);
-                                                }
-                                                "StandardViews" => {}
-                                                _ => { return Err(DecodingError::UnexpectedEvent(event)); }
-                                            }
+                                    match event {
+                                        _ if is_start_of(&event, "Assignment") => {
+                                            let a = Assignment::from_event(event, events_iter, limits)?;
+                                            assignments.push(a);
+                                        }
+                                        _ if is_start_of(&event, "Assignments") => {}
+                                        _ if is_start_of(&event, "AssignmentGradeCalc") => {
+                                            let agc = AssignmentGradeCalc::from_event(event, events_iter, limits)?;
+                                            grade_calculation_summary.push(agc);
+                                        }
+                                        _ if is_start_of(&event, "GradeCalculationSummary") => {}
+                                        _ if is_start_of(&event, "StandardView") => {
+                                            let sv = StandardView::from_event(event, events_iter, limits)?;
+                                            standard_views.push(sv);
                                         }
-                                        ReaderEvent::EndElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "Mark" => {
-                                                    break;
-                                                }
-                                                _ => {}
-                                            }
+                                        _ if is_start_of(&event, "StandardViews") && limits.skips_standards() => {
+                                            skip_element(events_iter)?;
                                         }
-                                        ReaderEvent::Whitespace(_) => {},
+                                        _ if is_start_of(&event, "StandardViews") => {}
+                                        _ if is_end_of(&event, "Mark") => {
+                                            break;
+                                        }
+                                        ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
                                         _ => { return Err(DecodingError::UnexpectedEvent(event)); }
                                     }
                                 }
@@ -376,15 +722,15 @@ impl SVUEDecodeable for Mark {
                         let mark_name = get_attr_owned!(attrs, "MarkName");
 
                         Ok(Mark {
-                            assignments: assignments,
-                            mark_name: mark_name,
-                            calculated_score_raw: calculated_score_raw,
-                            calculated_score_string: calculated_score_string,
-                            grade_calculation_summary: grade_calculation_summary,
-                            standard_views: standard_views,
+                            assignments,
+                            mark_name,
+                            calculated_score_raw,
+                            calculated_score_string,
+                            grade_calculation_summary,
+                            standard_views,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -410,15 +756,16 @@ field_slice_helpers!(StandardView, {
 });
 
 impl SVUEDecodeable for StandardView {
-    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>) -> DecoderResult<StandardView> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<StandardView> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "StandardView" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let cal_value = parse_float!(f64, attrs, "CalValue");
-                        let description = get_attr_owned!(attrs, "Description");
+                        let description = limits.text(get_attr!(attrs, "Description"));
                         let mark = get_attr_owned!(attrs, "Mark");
                         let proficiency = f64::from_str(get_attr!(attrs, "Proficiency")).ok();
                         let proficiency_max_value = parse_float!(f64, attrs, "ProfciencyMaxValue");
@@ -427,26 +774,17 @@ impl SVUEDecodeable for StandardView {
                         loop {
                             match events_iter.next() {
                                 Some(Ok(event)) => {
-                                    match event.clone() {
-                                        ReaderEvent::StartElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "StandardAssignmentView" => {
-                                                    let sav = StandardAssignmentView::from_event(event, events_iter)?;
-                                                    standard_assignment_views.push(sav);
-                                                }
-                                                "StandardAssignmentViews" => {},
-                                                _ => { return Err(DecodingError::UnexpectedEvent(event)); }
-                                            }
+                                    match event {
+                                        _ if is_start_of(&event, "StandardAssignmentView") => {
+                                            let sav = StandardAssignmentView::from_event(event, events_iter, limits)?;
+                                            standard_assignment_views.push(sav);
                                         }
-                                        ReaderEvent::EndElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "StandardAssignmentViews" => {
-                                                    break;
-                                                }
-                                                _ => {}
-                                            }
+                                        _ if is_start_of(&event, "StandardAssignmentViews") => {}
+                                        // as with `Standard`, the list of assignments may be missing
+                                        _ if is_end_of(&event, "StandardView") => {
+                                            break;
                                         }
-                                        ReaderEvent::Whitespace(_) => {},
+                                        ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
                                         _ => { return Err(DecodingError::UnexpectedEvent(event)); }
                                     }
                                 }
@@ -459,17 +797,17 @@ impl SVUEDecodeable for StandardView {
                         let subject_id = parse_int!(i8, attrs, "SubjectID");
 
                         Ok(StandardView {
-                            cal_value: cal_value,
-                            description: description,
-                            mark: mark,
-                            proficiency: proficiency,
-                            proficiency_max_value: proficiency_max_value,
-                            standard_assignment_views: standard_assignment_views,
-                            subject: subject,
-                            subject_id: subject_id,
+                            cal_value,
+                            description,
+                            mark,
+                            proficiency,
+                            proficiency_max_value,
+                            standard_assignment_views,
+                            subject,
+                            subject_id,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -491,12 +829,13 @@ pub struct StandardAssignmentView {
 }
 
 impl SVUEDecodeable for StandardAssignmentView {
-    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>) -> DecoderResult<StandardAssignmentView> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<StandardAssignmentView> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "StandardAssignmentView" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let _type = get_attr_owned!(attrs, "Type");
                         let assignment = get_attr_owned!(attrs, "Assignment");
@@ -509,17 +848,17 @@ impl SVUEDecodeable for StandardAssignmentView {
                         let proficiency_max_value = parse_float!(f64, attrs, "ProfciencyMaxValue");
 
                         Ok(StandardAssignmentView {
-                            _type: _type,
-                            assignment: assignment,
-                            cal_value: cal_value,
-                            due_date: due_date,
-                            gradebook_id: gradebook_id,
-                            mark: mark,
-                            proficiency: proficiency,
-                            proficiency_max_value: proficiency_max_value,
+                            _type,
+                            assignment,
+                            cal_value,
+                            due_date,
+                            gradebook_id,
+                            mark,
+                            proficiency,
+                            proficiency_max_value,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -539,13 +878,20 @@ pub struct AssignmentGradeCalc {
     pub weighted_pct: AssignmentGradeCalcWeight,
 }
 
+impl<'a> Pairable<'a, String> for AssignmentGradeCalc {
+    fn unique_key(&'a self) -> &'a String {
+        &self._type
+    }
+}
+
 impl SVUEDecodeable for AssignmentGradeCalc {
-    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>) -> DecoderResult<AssignmentGradeCalc> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<AssignmentGradeCalc> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "AssignmentGradeCalc" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let _type = get_attr_owned!(attrs, "Type");
                         let calculated_mark = get_attr_owned!(attrs, "CalculatedMark");
@@ -555,15 +901,15 @@ impl SVUEDecodeable for AssignmentGradeCalc {
                         let weighted_pct = AssignmentGradeCalcWeight::parse(get_attr!(attrs, "WeightedPct"));
 
                         Ok(AssignmentGradeCalc {
-                            _type: _type,
-                            calculated_mark: calculated_mark,
-                            points: points,
-                            points_possible: points_possible,
-                            weight: weight,
-                            weighted_pct: weighted_pct,
+                            _type,
+                            calculated_mark,
+                            points,
+                            points_possible,
+                            weight,
+                            weighted_pct,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -572,7 +918,7 @@ impl SVUEDecodeable for AssignmentGradeCalc {
 }
 
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AssignmentGradeCalcWeight {
     Percentage(f64),
     Unparseable(String),
@@ -583,10 +929,10 @@ impl AssignmentGradeCalcWeight {
         let weight = weight.trim();
 
         if weight.ends_with('%') {
-            let w = weight.trim_right_matches('%');
+            let w = weight.trim_end_matches('%');
 
             f64::from_str(w)
-                .map(|i| AssignmentGradeCalcWeight::Percentage(i))
+                .map(AssignmentGradeCalcWeight::Percentage)
                 .unwrap_or(AssignmentGradeCalcWeight::Unparseable(weight.to_string()))
         } else {
             AssignmentGradeCalcWeight::Unparseable(weight.to_string())
@@ -612,25 +958,123 @@ pub struct Assignment {
     pub drop_start_date: NaiveDate,
     pub drop_end_date: NaiveDate,
     pub standards: Vec<Standard>,
+    // only some districts share these
+    pub class_stats: Option<ClassStats>,
+}
+
+// how the whole class did on an assignment, in the same units as the score (usually percentages);
+// each figure is optional since districts share different subsets of them
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClassStats {
+    pub average: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub median: Option<f64>,
+}
+
+impl ClassStats {
+    // None if the assignment carries none of the attributes
+    fn from_attributes(attrs: &HashMap<&str, &str>) -> Option<ClassStats> {
+        let number = |names: &[&str]| {
+            names.iter()
+                .filter_map(|n| attrs.get(n))
+                .filter_map(|v| f64::from_str(v.trim().trim_end_matches('%')).ok())
+                .next()
+        };
+
+        let stats = ClassStats {
+            average: number(&["ClassAverage", "ClassAvg"]),
+            high: number(&["ClassHigh", "HighScore"]),
+            low: number(&["ClassLow", "LowScore"]),
+            median: number(&["ClassMedian", "MedianScore"]),
+        };
+
+        if stats == Default::default() { None } else { Some(stats) }
+    }
+
+    // how far the student's percentage is above (or below) the class average
+    pub fn versus_average(&self, score: &AssignmentScore) -> Option<f64> {
+        match (score.percentage(), self.average) {
+            (Some(p), Some(avg)) => Some(p - avg),
+            _ => None,
+        }
+    }
 }
 
 field_slice_helpers!(Assignment, {
     standards => Standard
 });
 
+impl Assignment {
+    // ungraded and due before today; pass a `SchoolClock` so "today" is the school's
+    pub fn is_overdue<C: Clock>(&self, clock: &C) -> bool {
+        !self.score.is_graded() && self.due_date < clock.today()
+    }
+
+    pub fn is_due_today<C: Clock>(&self, clock: &C) -> bool {
+        !self.score.is_graded() && self.due_date == clock.today()
+    }
+
+    // ungraded and due between today and `within` from now
+    pub fn is_due_within<C: Clock>(&self, clock: &C, within: Duration) -> bool {
+        let today = clock.today();
+
+        !self.score.is_graded() && self.due_date >= today && self.due_date <= today + within
+    }
+
+    // teachers flag missing work in the notes; a zero on something that's worth points usually
+    // means the same thing
+    pub fn is_missing(&self) -> bool {
+        if self.notes.to_lowercase().contains("missing") {
+            return true;
+        }
+
+        match self.score {
+            AssignmentScore::Percentage(p) => p == 0.0,
+            AssignmentScore::Score(s, p) => s == Points(0.0) && p > Points(0.0),
+            _ => false,
+        }
+    }
+
+    // late, missing and so on, going by the markers most districts use; see `turnin`
+    pub fn turn_in_status(&self) -> TurnInStatus {
+        self.turn_in_status_with(&TurnInMarkers::default())
+    }
+
+    pub fn turn_in_status_with(&self, markers: &TurnInMarkers) -> TurnInStatus {
+        markers.classify(self)
+    }
+}
+
 impl<'a> Pairable<'a, String> for Assignment {
     fn unique_key(&'a self) -> &'a String {
         &self.gradebook_id
     }
 }
 
+// some districts regenerate gradebook ids mid-term, so fall back to what the student actually
+// sees when pairing assignments. Only the points possible count: grading an assignment changes
+// its earned points, and that often happens in the same poll as the id change.
+impl FallbackPairable for Assignment {
+    fn fallback_eq(&self, other: &Assignment) -> bool {
+        let same_points = match (self.points.possible(), other.points.possible()) {
+            (Some(p1), Some(p2)) => p1 == p2,
+            _ => self.points == other.points,
+        };
+
+        self.measure == other.measure && self.due_date == other.due_date && same_points
+    }
+}
+
 impl SVUEDecodeable for Assignment {
-    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>) -> DecoderResult<Assignment> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Assignment> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "Assignment" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let _type = get_attr_owned!(attrs, "Type");
                         let gradebook_id = get_attr_owned!(attrs, "GradebookID");
@@ -640,38 +1084,38 @@ impl SVUEDecodeable for Assignment {
                         let score = AssignmentScore::parse(get_attr!(attrs, "Score"));
                         let score_type = get_attr_owned!(attrs, "ScoreType");
                         let points = AssignmentPoints::parse(get_attr!(attrs, "Points"));
-                        let notes = get_attr_owned!(attrs, "Notes");
+                        let notes = limits.text(get_attr!(attrs, "Notes"));
                         let teacher_id = get_attr_owned!(attrs, "TeacherID");
                         let student_id = get_attr_owned!(attrs, "StudentID");
                         let has_drop_box = parse_bool!(attrs, "HasDropBox");
                         let drop_start_date = parse_date!(attrs, "DropStartDate");
                         let drop_end_date = parse_date!(attrs, "DropEndDate");
+                        let class_stats = ClassStats::from_attributes(&attrs);
                         let mut standards = Vec::new();
 
+                        // districts that don't grade on standards leave out `Standards` altogether
+                        // (and some send `<Assignment ... />` with no children at all), so only the
+                        // assignment's own end tag says it's done
                         loop {
                             match events_iter.next() {
                                 Some(Ok(event)) => {
-                                    match event.clone() {
-                                        ReaderEvent::StartElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "Standard" => {
-                                                    let s = Standard::from_event(event, events_iter)?;
-                                                    standards.push(s);
-                                                }
-                                                "Standards" => {},
-                                                "Resources" => {},
-                                                _ => { return Err(DecodingError::UnexpectedEvent(event)); }
-                                            }
+                                    match event {
+                                        _ if is_start_of(&event, "Standard") => {
+                                            let s = Standard::from_event(event, events_iter, limits)?;
+                                            standards.push(s);
+                                        }
+                                        _ if is_start_of(&event, "Standards") && limits.skips_standards() => {
+                                            skip_element(events_iter)?;
+                                        }
+                                        _ if is_start_of(&event, "Standards") => {}
+                                        _ if is_end_of(&event, "Assignment") => {
+                                            break;
                                         }
-                                        ReaderEvent::EndElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "Standards" => {
-                                                    break;
-                                                }
-                                                _ => {}
-                                            }
+                                        // `Resources` and anything newer than this decoder
+                                        ReaderEvent::StartElement { .. } => {
+                                            skip_element(events_iter)?;
                                         }
-                                        ReaderEvent::Whitespace(_) => {},
+                                        ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
                                         _ => { return Err(DecodingError::UnexpectedEvent(event)); }
                                     }
                                 }
@@ -681,24 +1125,25 @@ impl SVUEDecodeable for Assignment {
                         }
 
                         Ok(Assignment {
-                            _type: _type,
-                            gradebook_id: gradebook_id,
-                            measure: measure,
-                            date: date,
-                            due_date: due_date,
-                            score: score,
-                            score_type: score_type,
-                            points: points,
-                            notes: notes,
-                            teacher_id: teacher_id,
-                            student_id: student_id,
-                            has_drop_box: has_drop_box,
-                            drop_start_date: drop_start_date,
-                            drop_end_date: drop_end_date,
-                            standards: standards,
+                            _type,
+                            gradebook_id,
+                            measure,
+                            date,
+                            due_date,
+                            score,
+                            score_type,
+                            points,
+                            notes,
+                            teacher_id,
+                            student_id,
+                            has_drop_box,
+                            drop_start_date,
+                            drop_end_date,
+                            standards,
+                            class_stats,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -713,41 +1158,116 @@ pub enum AssignmentScore {
     NotForGrading,
     NotGraded,
     Percentage(f64),
-    Score(f64, f64),
+    // earned out of possible
+    Score(Points, Points),
     // this seems to be equivalent to not graded? not really sure; standards based grading (with
     // svue) is very confusing
     SeeStandards,
     Unparseable(String),
 }
 
+// `[\d\.]+` also matches things like "1.2.3" or ".", so the capture can still fail to parse;
+// callers fall back to `Unparseable` instead of panicking mid-decode
+fn parse_capture(cs: &Captures, i: usize) -> Option<f64> {
+    cs.get(i)
+        .and_then(|m| f64::from_str(m.as_str()).ok())
+        .and_then(|n| if n.is_finite() { Some(n) } else { None })
+}
+
+// graded marks without a percentage (nothing possible) sort below the rest; NaNs, which the
+// decoder never produces, compare as equal rather than poisoning a sort
+fn cmp_percentages(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
+
 impl AssignmentScore {
+    pub fn is_graded(&self) -> bool {
+        matches!(*self, AssignmentScore::Percentage(_) | AssignmentScore::Score(_, _))
+    }
+
+    // a score out of zero possible points (extra credit) has no percentage
+    pub fn percentage(&self) -> Option<f64> {
+        match *self {
+            AssignmentScore::Percentage(p) => Some(p),
+            AssignmentScore::Score(s, p) => s.percentage_of(p),
+            _ => None,
+        }
+    }
+
+    // only scores given as points have these; a bare percentage doesn't say out of what
+    pub fn earned(&self) -> Option<Points> {
+        match *self {
+            AssignmentScore::Score(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn possible(&self) -> Option<Points> {
+        match *self {
+            AssignmentScore::Score(_, p) => Some(p),
+            _ => None,
+        }
+    }
+
+    // where each kind of score sorts relative to the others; graded scores then compare by
+    // percentage
+    fn rank(&self) -> u8 {
+        match *self {
+            AssignmentScore::Unparseable(_) => 0,
+            AssignmentScore::NotForGrading => 1,
+            AssignmentScore::SeeStandards => 2,
+            AssignmentScore::NotGraded => 3,
+            AssignmentScore::NotDue => 4,
+            AssignmentScore::Score(_, _) | AssignmentScore::Percentage(_) => 5,
+        }
+    }
+
+    // a total order by performance: every ungraded state sorts below every graded score, in a
+    // fixed order, and graded scores compare by percentage
+    pub fn cmp_performance(&self, other: &AssignmentScore) -> Ordering {
+        self.rank().cmp(&other.rank())
+            .then_with(|| cmp_percentages(self.percentage(), other.percentage()))
+    }
+
+    pub fn is_better_than(&self, other: &AssignmentScore) -> bool {
+        self.cmp_performance(other) == Ordering::Greater
+    }
+
+    // change in percentage points from `other` to `self`, when both are graded
+    pub fn delta(&self, other: &AssignmentScore) -> Option<f64> {
+        match (self.percentage(), other.percentage()) {
+            (Some(a), Some(b)) => Some(a - b),
+            _ => None,
+        }
+    }
+
+    // the words come from every registered `locale::ScoreLocale`
     fn parse(score: &str) -> AssignmentScore {
         match score {
-            "Not Due" => AssignmentScore::NotDue,
             "" => AssignmentScore::NotForGrading,
-            "Not Graded" => AssignmentScore::NotGraded,
-            "See Standards" => AssignmentScore::SeeStandards,
+            s if locale::matches_any(s, |l| &l.not_due) => AssignmentScore::NotDue,
+            s if locale::matches_any(s, |l| &l.not_graded) => AssignmentScore::NotGraded,
+            s if locale::matches_any(s, |l| &l.see_standards) => AssignmentScore::SeeStandards,
             _ => {
                 // probably a better way to do this than to try two regexes
-                let score_regex = Regex::new(r"([\d\.]+)\s*out\s*of\s*([\d\.]+)").unwrap();
+                lazy_static! {
+                    static ref PERCENTAGE: Regex = Regex::new(r"^([\d\.]+)\s*(?:\(\))?$").unwrap();
+                }
 
-                match score_regex.captures(score) {
+                match locale::patterns().score.captures(score) {
                     Some(cs) => {
-                        let score = f64::from_str(cs.get(1).unwrap().as_str()).unwrap();
-                        let possible_score = f64::from_str(cs.get(2).unwrap().as_str()).unwrap();
-
-                        AssignmentScore::Score(score, possible_score)
+                        match (parse_capture(&cs, 1), parse_capture(&cs, 2)) {
+                            (Some(score), Some(possible_score)) => AssignmentScore::Score(Points(score), Points(possible_score)),
+                            _ => AssignmentScore::Unparseable(score.to_string()),
+                        }
                     }
                     None => {
-                        let pct_regex = Regex::new(r"^([\d\.]+)\s*(?:\(\))?$").unwrap();
-                        let captures = pct_regex.captures(score);
-
-                        if captures.is_some() {
-                            let pct = f64::from_str(captures.unwrap().get(1).unwrap().as_str()).unwrap();
-
-                            AssignmentScore::Percentage(pct)
-                        } else {
-                            AssignmentScore::Unparseable(score.to_string())
+                        match PERCENTAGE.captures(score).and_then(|cs| parse_capture(&cs, 1)) {
+                            Some(pct) => AssignmentScore::Percentage(pct),
+                            None => AssignmentScore::Unparseable(score.to_string()),
                         }
                     }
                 }
@@ -757,43 +1277,177 @@ impl AssignmentScore {
     }
 }
 
+// these mirror the strings svue sends, so a displayed score reads the way it does in the portal
+impl fmt::Display for AssignmentScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AssignmentScore::NotDue => write!(f, "Not Due"),
+            AssignmentScore::NotForGrading => Ok(()),
+            AssignmentScore::NotGraded => write!(f, "Not Graded"),
+            AssignmentScore::Percentage(p) => write!(f, "{}", p),
+            AssignmentScore::Score(s, p) => write!(f, "{} out of {}", s, p),
+            AssignmentScore::SeeStandards => write!(f, "See Standards"),
+            AssignmentScore::Unparseable(ref score) => write!(f, "{}", score),
+        }
+    }
+}
+
+// a number of points, earned or possible, as opposed to a percentage; keeping them apart in the
+// types means the only way from points to a percentage is `percentage_of`
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Points(pub f64);
+
+impl Points {
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    // None out of zero (or fewer) possible points, e.g. extra credit
+    pub fn percentage_of(&self, possible: Points) -> Option<f64> {
+        if possible.0 > 0.0 {
+            Some(self.0 / possible.0 * 100.0)
+        } else {
+            None
+        }
+    }
+}
+
+impl Add for Points {
+    type Output = Points;
+
+    fn add(self, other: Points) -> Points {
+        Points(self.0 + other.0)
+    }
+}
+
+impl Sub for Points {
+    type Output = Points;
+
+    fn sub(self, other: Points) -> Points {
+        Points(self.0 - other.0)
+    }
+}
+
+impl Mul<f64> for Points {
+    type Output = Points;
+
+    fn mul(self, factor: f64) -> Points {
+        Points(self.0 * factor)
+    }
+}
+
+impl Sum for Points {
+    fn sum<I: Iterator<Item=Points>>(iter: I) -> Points {
+        iter.fold(Points(0.0), |acc, p| acc + p)
+    }
+}
+
+impl<'a> Sum<&'a Points> for Points {
+    fn sum<I: Iterator<Item=&'a Points>>(iter: I) -> Points {
+        iter.fold(Points(0.0), |acc, p| acc + *p)
+    }
+}
+
+impl fmt::Display for Points {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub enum AssignmentPoints {
-    Ungraded(f64),
-    Graded(f64, f64),
+    // what it's worth
+    Ungraded(Points),
+    // earned out of possible
+    Graded(Points, Points),
     Unparseable(String),
 }
 
 impl AssignmentPoints {
-    fn parse(points: &str) -> AssignmentPoints {
-        if points.contains("Points Possible") {
-            let regex = Regex::new(r"([\d\.]+)\s*Points\s*Possible").unwrap();
+    pub fn percentage(&self) -> Option<f64> {
+        match (self.earned(), self.possible()) {
+            (Some(e), Some(p)) => e.percentage_of(p),
+            _ => None,
+        }
+    }
+
+    pub fn earned(&self) -> Option<Points> {
+        match *self {
+            AssignmentPoints::Graded(e, _) => Some(e),
+            _ => None,
+        }
+    }
 
-            match regex.captures(points) {
-                Some(cs) => {
-                    let possible_points = f64::from_str(cs.get(1).unwrap().as_str()).unwrap();
+    // ungraded assignments still say what they're worth
+    pub fn possible(&self) -> Option<Points> {
+        match *self {
+            AssignmentPoints::Ungraded(p) | AssignmentPoints::Graded(_, p) => Some(p),
+            AssignmentPoints::Unparseable(_) => None,
+        }
+    }
 
-                    AssignmentPoints::Ungraded(possible_points)
-                }
+    fn rank(&self) -> u8 {
+        match *self {
+            AssignmentPoints::Unparseable(_) => 0,
+            AssignmentPoints::Ungraded(_) => 1,
+            AssignmentPoints::Graded(_, _) => 2,
+        }
+    }
+
+    // same scheme as `AssignmentScore::cmp_performance`
+    pub fn cmp_performance(&self, other: &AssignmentPoints) -> Ordering {
+        self.rank().cmp(&other.rank())
+            .then_with(|| cmp_percentages(self.percentage(), other.percentage()))
+    }
+
+    pub fn is_better_than(&self, other: &AssignmentPoints) -> bool {
+        self.cmp_performance(other) == Ordering::Greater
+    }
+
+    pub fn delta(&self, other: &AssignmentPoints) -> Option<f64> {
+        match (self.percentage(), other.percentage()) {
+            (Some(a), Some(b)) => Some(a - b),
+            _ => None,
+        }
+    }
+
+    fn parse(points: &str) -> AssignmentPoints {
+        lazy_static! {
+            static ref GRADED: Regex = Regex::new(r"([\d\.]+)\s*/\s*([\d\.]+)").unwrap();
+        }
+        let patterns = locale::patterns();
+
+        if patterns.points_possible_word.is_match(points) {
+            match patterns.points_possible.captures(points).and_then(|cs| parse_capture(&cs, 1)) {
+                Some(possible_points) => AssignmentPoints::Ungraded(Points(possible_points)),
                 None => AssignmentPoints::Unparseable(points.to_string())
             }
         } else {
-            let regex = Regex::new(r"([\d\.]+)\s*/\s*([\d\.]+)").unwrap();
-
-            match regex.captures(points) {
-                Some(cs) => {
-                    let points_scored = f64::from_str(cs.get(1).unwrap().as_str()).unwrap();
-                    let possible_points = f64::from_str(cs.get(2).unwrap().as_str()).unwrap();
+            let parsed = GRADED.captures(points)
+                .map(|cs| (parse_capture(&cs, 1), parse_capture(&cs, 2)));
 
-                    AssignmentPoints::Graded(points_scored, possible_points)
+            match parsed {
+                Some((Some(points_scored), Some(possible_points))) => {
+                    AssignmentPoints::Graded(Points(points_scored), Points(possible_points))
                 }
-                None => AssignmentPoints::Unparseable(points.to_string())
+                _ => AssignmentPoints::Unparseable(points.to_string())
             }
         }
     }
 }
 
+impl fmt::Display for AssignmentPoints {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AssignmentPoints::Ungraded(p) => write!(f, "{} Points Possible", p),
+            AssignmentPoints::Graded(e, p) => write!(f, "{} / {}", e, p),
+            AssignmentPoints::Unparseable(ref points) => write!(f, "{}", points),
+        }
+    }
+}
+
 #[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
 pub struct Standard {
@@ -809,17 +1463,33 @@ field_slice_helpers!(Standard, {
     standard_screen_assignments => StandardScreenAssignment
 });
 
+impl Standard {
+    // views and assignments don't share an ID for a standard, only its subject and description,
+    // which aren't always spaced or capitalized the same way in both
+    pub fn is_part_of(&self, view: &StandardView) -> bool {
+        self.subject.trim().eq_ignore_ascii_case(view.subject.trim())
+            && self.description.trim().eq_ignore_ascii_case(view.description.trim())
+    }
+}
+
+impl<'a> Pairable<'a, String> for Standard {
+    fn unique_key(&'a self) -> &'a String {
+        &self.description
+    }
+}
+
 impl SVUEDecodeable for Standard {
-    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>) -> DecoderResult<Standard> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Standard> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "Standard" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let subject = get_attr_owned!(attrs, "Subject");
                         let mark = get_attr_owned!(attrs, "Mark");
-                        let description = get_attr_owned!(attrs, "Description");
+                        let description = limits.text(get_attr!(attrs, "Description"));
                         let proficiency = f64::from_str(get_attr!(attrs, "Proficiency")).ok();
                         let proficiency_max_value = parse_float!(f64, attrs, "ProfciencyMaxValue");
                         let mut standard_screen_assignments = Vec::new();
@@ -827,26 +1497,17 @@ impl SVUEDecodeable for Standard {
                         loop {
                             match events_iter.next() {
                                 Some(Ok(event)) => {
-                                    match event.clone() {
-                                        ReaderEvent::StartElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "StandardScreenAssignment" => {
-                                                    let ssa = StandardScreenAssignment::from_event(event, events_iter)?;
-                                                    standard_screen_assignments.push(ssa);
-                                                }
-                                                "StandardScreenAssignments" => {},
-                                                _ => { return Err(DecodingError::UnexpectedEvent(event)); }
-                                            }
+                                    match event {
+                                        _ if is_start_of(&event, "StandardScreenAssignment") => {
+                                            let ssa = StandardScreenAssignment::from_event(event, events_iter, limits)?;
+                                            standard_screen_assignments.push(ssa);
                                         }
-                                        ReaderEvent::EndElement { name, .. } => {
-                                            match name.local_name.as_str() {
-                                                "StandardScreenAssignments" => {
-                                                    break;
-                                                }
-                                                _ => {}
-                                            }
+                                        _ if is_start_of(&event, "StandardScreenAssignments") => {}
+                                        // a standard with no assignments may leave the list out
+                                        _ if is_end_of(&event, "Standard") => {
+                                            break;
                                         }
-                                        ReaderEvent::Whitespace(_) => {},
+                                        ReaderEvent::EndElement { .. } | ReaderEvent::Whitespace(_) => {}
                                         _ => { return Err(DecodingError::UnexpectedEvent(event)); }
                                     }
                                 }
@@ -856,15 +1517,15 @@ impl SVUEDecodeable for Standard {
                         }
 
                         Ok(Standard {
-                            subject: subject,
-                            mark: mark,
-                            description: description,
-                            proficiency: proficiency,
-                            proficiency_max_value: proficiency_max_value,
-                            standard_screen_assignments: standard_screen_assignments,
+                            subject,
+                            mark,
+                            description,
+                            proficiency,
+                            proficiency_max_value,
+                            standard_screen_assignments,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
@@ -883,13 +1544,20 @@ pub struct StandardScreenAssignment {
     pub proficiency_max_value: f64,
 }
 
+impl<'a> Pairable<'a, String> for StandardScreenAssignment {
+    fn unique_key(&'a self) -> &'a String {
+        &self.assignment
+    }
+}
+
 impl SVUEDecodeable for StandardScreenAssignment {
-    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>) -> DecoderResult<StandardScreenAssignment> {
-        match event.clone() {
-            ReaderEvent::StartElement { name, attributes, .. } => {
+    fn from_event(event: ReaderEvent, _: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<StandardScreenAssignment> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
                 match name.local_name.as_str() {
                     "StandardScreenAssignment" => {
-                        let attrs = attributes_vec_to_map(&attributes);
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
 
                         let _type = get_attr_owned!(attrs, "Type");
                         let assignment = get_attr_owned!(attrs, "Assignment");
@@ -899,18 +1567,380 @@ impl SVUEDecodeable for StandardScreenAssignment {
                         let proficiency_max_value = parse_float!(f64, attrs, "ProfciencyMaxValue");
 
                         Ok(StandardScreenAssignment {
-                            _type: _type,
-                            assignment: assignment,
-                            due_date: due_date,
-                            mark: mark,
-                            proficiency: proficiency,
-                            proficiency_max_value: proficiency_max_value,
+                            _type,
+                            assignment,
+                            due_date,
+                            mark,
+                            proficiency,
+                            proficiency_max_value,
                         })
                     }
-                    _ => Err(DecodingError::UnexpectedEvent(event))
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
                 }
             }
             _ => Err(DecodingError::UnexpectedEvent(event))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use regex::Regex;
+
+    // a fixed xorshift, so a failure can be reproduced
+    struct Inputs(u64);
+
+    impl Inputs {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn string(&mut self, pieces: &[&str]) -> String {
+            let len = self.next() % 8;
+            (0..len).map(|_| pieces[(self.next() % pieces.len() as u64) as usize]).collect()
+        }
+    }
+
+    const PIECES: &[&str] = &[
+        "0", "1", "7", "10", "99999999999999999999", ".", "..", "-", "+", "e", "E", "1e999999", "1e-999999",
+        "NaN", "nan", "inf", "-inf", "Infinity", " ", "\t", "\n", "()", "(", ")", "/", "%", "out of", "OUT  OF",
+        "de", "Points Possible", "Puntos Posibles", "Not Due", "See Standards", "é", "٣", "１０", "½", "\u{0}",
+        "\u{feff}", "💯", "<", "&amp;",
+    ];
+
+    const MALFORMED: &[&str] = &[
+        "", " ", ".", "...", "1.2.3", "1..2", ". out of .", "1.2.3 out of 4", "8 out of", "out of 10", "8 out of 1.2.3",
+        "1e999999 out of 10", "10 out of 1e999999", "NaN", "inf", "-inf", "NaN out of NaN", "inf / inf", "1e999999",
+        "1e999999 Points Possible", ". Points Possible", "Points Possible", "/", "1 /", "/ 1", "1.2.3 / 4",
+        "٣ out of ١٠", "１０ out of １０", "8 de 10", "8de10", "8 dé 10", "💯", "\u{0}", "10 ()", "() 10",
+    ];
+
+    fn huge() -> Vec<String> {
+        vec![
+            "9".repeat(400),
+            format!("{}.{}", "9".repeat(400), "9".repeat(400)),
+            format!("{} out of {}", "9".repeat(400), "1".repeat(400)),
+            format!("{} / {}", "9".repeat(400), "9".repeat(400)),
+            format!("{} Points Possible", "9".repeat(400)),
+            ".".repeat(10000),
+            "out of ".repeat(2000),
+            "é".repeat(10000),
+        ]
+    }
+
+    fn inputs() -> Vec<String> {
+        let mut inputs: Vec<String> = MALFORMED.iter().map(|s| s.to_string()).chain(huge()).collect();
+        let mut gen = Inputs(0x2545f4914f6cdd1d);
+        inputs.extend((0..5000).map(|_| gen.string(PIECES)));
+        inputs
+    }
+
+    fn assert_score_sane(input: &str, score: &AssignmentScore) {
+        match *score {
+            AssignmentScore::Percentage(p) => assert!(p.is_finite(), "{:?} gave {:?}", input, score),
+            AssignmentScore::Score(s, p) => assert!(s.0.is_finite() && p.0.is_finite(), "{:?} gave {:?}", input, score),
+            AssignmentScore::Unparseable(ref s) => assert_eq!(s, input),
+            _ => {}
+        }
+    }
+
+    fn assert_points_sane(input: &str, points: &AssignmentPoints) {
+        match *points {
+            AssignmentPoints::Ungraded(p) => assert!(p.0.is_finite(), "{:?} gave {:?}", input, points),
+            AssignmentPoints::Graded(s, p) => assert!(s.0.is_finite() && p.0.is_finite(), "{:?} gave {:?}", input, points),
+            AssignmentPoints::Unparseable(ref s) => assert_eq!(s, input),
+        }
+    }
+
+    #[test]
+    fn parse_capture_never_panics_or_returns_non_finite() {
+        let whole = Regex::new(r"(?s)^(.*)$").unwrap();
+        let number = Regex::new(r"([\d\.]+)").unwrap();
+
+        for input in inputs() {
+            for regex in &[&whole, &number] {
+                if let Some(cs) = regex.captures(&input) {
+                    if let Some(n) = parse_capture(&cs, 1) {
+                        assert!(n.is_finite(), "{:?} parsed to {}", input, n);
+                    }
+                    assert_eq!(parse_capture(&cs, 7), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn score_parse_never_panics() {
+        for input in inputs() {
+            assert_score_sane(&input, &AssignmentScore::parse(&input));
+        }
+    }
+
+    #[test]
+    fn points_parse_never_panics() {
+        for input in inputs() {
+            assert_points_sane(&input, &AssignmentPoints::parse(&input));
+        }
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_unparseable() {
+        for input in &["1.2.3 out of 4", "8 out of 1.2.3", ". out of .", "NaN", "inf", "1e999999"] {
+            assert_eq!(AssignmentScore::parse(input), AssignmentScore::Unparseable(input.to_string()));
+        }
+        for input in &["1.2.3 Points Possible", ". Points Possible", "1.2.3 / 4", "inf / inf"] {
+            assert_eq!(AssignmentPoints::parse(input), AssignmentPoints::Unparseable(input.to_string()));
+        }
+    }
+
+    #[test]
+    fn well_formed_values_still_parse() {
+        assert_eq!(AssignmentScore::parse("8 out of 10"), AssignmentScore::Score(Points(8.0), Points(10.0)));
+        assert_eq!(AssignmentScore::parse("8 de 10"), AssignmentScore::Score(Points(8.0), Points(10.0)));
+        assert_eq!(AssignmentScore::parse("92.5"), AssignmentScore::Percentage(92.5));
+        assert_eq!(AssignmentScore::parse("Not Due"), AssignmentScore::NotDue);
+        assert_eq!(AssignmentScore::parse("8de10"), AssignmentScore::Unparseable("8de10".to_string()));
+        assert_eq!(AssignmentPoints::parse("10 Points Possible"), AssignmentPoints::Ungraded(Points(10.0)));
+        assert_eq!(AssignmentPoints::parse("8 / 10"), AssignmentPoints::Graded(Points(8.0), Points(10.0)));
+    }
+
+    #[test]
+    fn parses_scores_and_points_in_every_registered_locale() {
+        for l in locale::locales() {
+            for word in &l.out_of {
+                let score = format!("8 {} 10", word);
+                assert_eq!(AssignmentScore::parse(&score), AssignmentScore::Score(Points(8.0), Points(10.0)), "{}", l.name);
+            }
+            for word in &l.points_possible {
+                let points = format!("10 {}", word);
+                assert_eq!(AssignmentPoints::parse(&points), AssignmentPoints::Ungraded(Points(10.0)), "{}", l.name);
+            }
+        }
+    }
+
+    // a standards-based district: every assignment has `Resources` and `Standards`, every mark has
+    // `StandardViews`
+    const STANDARDS: &str = include_str!("../fixtures/gradebook_standards.xml");
+    // a traditional one: no standards anywhere, assignments that are self-closing or have other
+    // children, and a course with no assignments at all
+    const NO_STANDARDS: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    fn measures(mark: &Mark) -> Vec<&str> {
+        mark.assignments.iter().map(|a| a.measure.as_str()).collect()
+    }
+
+    #[test]
+    fn decodes_assignments_with_standards() {
+        let gradebook = Gradebook::from_xml(STANDARDS).unwrap();
+
+        assert_eq!(gradebook.courses.len(), 1);
+        assert_eq!(gradebook.reporting_periods.len(), 2);
+        assert_eq!(gradebook.reporting_period.grade_period, "1st Qtr Progress");
+        assert!(gradebook.last_updated.is_some());
+
+        let mark = &gradebook.courses[0].marks[0];
+        assert_eq!(measures(mark), vec!["Systems Quiz", "Quadratics Worksheet"]);
+        assert_eq!(mark.grade_calculation_summary.len(), 3);
+        assert_eq!(mark.standard_views.len(), 2);
+        assert_eq!(mark.standard_views[0].standard_assignment_views.len(), 1);
+        assert!(mark.standard_views[1].standard_assignment_views.is_empty());
+
+        let quiz = &mark.assignments[0];
+        assert_eq!(quiz.score, AssignmentScore::Score(Points(17.0), Points(20.0)));
+        assert_eq!(quiz.points, AssignmentPoints::Graded(Points(17.0), Points(20.0)));
+        assert_eq!(quiz.standards.len(), 1);
+        assert_eq!(quiz.standards[0].description, "Solves systems of linear equations");
+        assert_eq!(quiz.standards[0].standard_screen_assignments.len(), 1);
+        assert!(mark.standard_view(&quiz.standards[0]).is_some());
+
+        // a standard with no assignment list of its own
+        let worksheet = &mark.assignments[1];
+        assert_eq!(worksheet.score, AssignmentScore::NotGraded);
+        assert_eq!(worksheet.standards.len(), 1);
+        assert!(worksheet.standards[0].standard_screen_assignments.is_empty());
+        assert_eq!(worksheet.standards[0].proficiency, None);
+    }
+
+    #[test]
+    fn decodes_assignments_with_standards_skipped() {
+        let gradebook = Gradebook::from_xml_with(STANDARDS, &DecodeLimits::new().skip_standards()).unwrap();
+        let mark = &gradebook.courses[0].marks[0];
+
+        assert_eq!(measures(mark), vec!["Systems Quiz", "Quadratics Worksheet"]);
+        assert!(mark.standard_views.is_empty());
+        assert!(mark.assignments.iter().all(|a| a.standards.is_empty()));
+        assert_eq!(mark.grade_calculation_summary.len(), 3);
+    }
+
+    #[test]
+    fn decodes_assignments_without_standards() {
+        let gradebook = Gradebook::from_xml(NO_STANDARDS).unwrap();
+
+        assert_eq!(gradebook.courses.len(), 2);
+        assert_eq!(gradebook.last_updated, None);
+
+        let mark = &gradebook.courses[0].marks[0];
+        assert_eq!(measures(mark), vec!["Personal Narrative", "Reading Log", "Vocabulary Check"]);
+        assert!(mark.standard_views.is_empty());
+        assert!(mark.assignments.iter().all(|a| a.standards.is_empty()));
+
+        // `Resources` is skipped without losing the attributes around it
+        let narrative = &mark.assignments[0];
+        assert_eq!(narrative.score, AssignmentScore::Score(Points(45.6), Points(50.0)));
+        assert!(narrative.has_drop_box);
+        assert_eq!(narrative.class_stats.as_ref().and_then(|s| s.average), Some(84.3));
+
+        // self-closing, so there's no end tag of its own to stop at
+        assert_eq!(mark.assignments[1].score, AssignmentScore::NotDue);
+        assert_eq!(mark.assignments[1].points, AssignmentPoints::Ungraded(Points(20.0)));
+        assert_eq!(mark.assignments[2].score, AssignmentScore::Unparseable("MSG".to_string()));
+
+        let empty = &gradebook.courses[1].marks[0];
+        assert!(empty.assignments.is_empty());
+        assert!(empty.grade_calculation_summary.is_empty());
+    }
+
+    #[test]
+    fn parses_course_ids() {
+        let cases = [
+            ("MAT401-03", "MAT401", Some("03")),
+            ("MAT401.3", "MAT401", Some("3")),
+            ("MAT401/03", "MAT401", Some("03")),
+            ("MAT401 03", "MAT401", Some("03")),
+            ("MAT401_03", "MAT401", Some("03")),
+            ("MAT401-03A", "MAT401", Some("03A")),
+            ("mat 401 - 03", "MAT401", Some("03")),
+            ("  MAT401-03  ", "MAT401", Some("03")),
+            ("MAT401", "MAT401", None),
+            // numbered courses rather than sections
+            ("SCI-101", "SCI-101", None),
+            ("ENG 9", "ENG9", None),
+            ("SPN-2", "SPN-2", None),
+            // nothing that looks like a section
+            ("MAT401-0003", "MAT401-0003", None),
+            ("401-03", "401-03", None),
+            ("12345", "12345", None),
+            ("", "", None),
+        ];
+
+        for &(id, code, section) in cases.iter() {
+            let expected = CourseId { code: code.to_string(), section: section.map(|s| s.to_string()) };
+            assert_eq!(CourseId::parse(id), expected, "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn only_compares_course_ids_of_parsed_titles() {
+        let cases = [
+            ("Algebra 2 (MAT401-03)", Some("MAT401")),
+            ("Algebra 2 (mat401.4)", Some("MAT401")),
+            ("Advisory", None),
+            ("Advisory ()", None),
+            ("(MAT401-03)", None),
+        ];
+
+        let algebra = CourseTitle::parse("Algebra 2 (MAT401-01)");
+        for &(title, code) in cases.iter() {
+            let title = CourseTitle::parse(title);
+            assert_eq!(title.base_code().as_deref(), code, "{:?}", title);
+            assert_eq!(title.same_course(&algebra), code.is_some(), "{:?}", title);
+            assert!(!title.same_course(&CourseTitle::Parsed("Study Hall".to_string(), " ".to_string())), "{:?}", title);
+        }
+    }
+
+    fn course_with_marks(names: &[&str]) -> Course {
+        let mut course = Gradebook::from_xml(NO_STANDARDS).unwrap().courses[0].clone();
+        let mark = course.marks[0].clone();
+        course.marks = names.iter().map(|n| Mark { mark_name: n.to_string(), ..mark.clone() }).collect();
+        course
+    }
+
+    #[test]
+    fn rolls_marks_into_the_ones_that_include_them() {
+        let cases = [
+            (MarkKind::Progress, MarkKind::Quarter, true),
+            (MarkKind::Progress, MarkKind::Trimester, true),
+            (MarkKind::Quarter, MarkKind::Semester, true),
+            (MarkKind::Quarter, MarkKind::Final, true),
+            (MarkKind::Trimester, MarkKind::Final, true),
+            (MarkKind::Semester, MarkKind::Final, true),
+            (MarkKind::Quarter, MarkKind::Trimester, false),
+            (MarkKind::Trimester, MarkKind::Quarter, false),
+            (MarkKind::Trimester, MarkKind::Semester, false),
+            (MarkKind::Semester, MarkKind::Quarter, false),
+            (MarkKind::Quarter, MarkKind::Quarter, false),
+            (MarkKind::Other, MarkKind::Final, false),
+            (MarkKind::Progress, MarkKind::Other, false),
+        ];
+
+        for &(kind, other, expected) in cases.iter() {
+            assert_eq!(kind.rolls_into(&other), expected, "{:?} into {:?}", kind, other);
+        }
+    }
+
+    #[test]
+    fn picks_the_official_mark() {
+        let cases: &[(&[&str], &str)] = &[
+            (&["Q2", "S1"], "S1"),
+            (&["S1", "Q2"], "S1"),
+            (&["Progress Report 2", "Quarter 2", "Semester 1"], "Semester 1"),
+            (&["T1", "Progress Report 1"], "T1"),
+            (&["Trimester 2", "Final"], "Final"),
+            // not part of each other, so the one listed last
+            (&["Semester 1", "Trimester 1"], "Trimester 1"),
+            (&["Trimester 1", "Semester 1"], "Semester 1"),
+            (&["Grading Period", "Q1"], "Q1"),
+            (&["Q1", "Grading Period"], "Q1"),
+            (&["Grading Period A", "Grading Period B"], "Grading Period B"),
+        ];
+
+        for &(names, expected) in cases.iter() {
+            let course = course_with_marks(names);
+            assert_eq!(course.official_mark().map(|m| m.mark_name.as_str()), Some(expected), "{:?}", names);
+        }
+        assert!(course_with_marks(&[]).official_mark().is_none());
+    }
+
+    #[cfg(feature="client")]
+    #[test]
+    fn diffs_a_fetched_gradebook_against_the_previous_one() {
+        let previous = Gradebook::from_xml(NO_STANDARDS).unwrap();
+
+        let unchanged = FetchedGradebook::since(&previous, Some(previous.clone()));
+        assert!(unchanged.changes.is_none());
+        assert!(!unchanged.no_data);
+
+        let mut gradebook = previous.clone();
+        gradebook.courses.pop();
+        let fetched = FetchedGradebook::since(&previous, Some(gradebook));
+        assert!(fetched.changes.is_some());
+        assert_eq!(fetched.gradebook.courses.len(), previous.courses.len() - 1);
+
+        let nothing = FetchedGradebook::since(&previous, None);
+        assert!(nothing.no_data);
+        assert!(nothing.changes.is_none());
+        assert!(nothing.gradebook.courses.is_empty());
+    }
+
+    #[test]
+    fn hashes_content_the_same_whatever_the_order() {
+        let gradebook = Gradebook::from_xml(NO_STANDARDS).unwrap();
+        // content hashes are stored with snapshots, so they mustn't move between releases
+        assert_eq!(gradebook.content_hash(), 0x8bfb198c0708247d);
+        assert_eq!(Gradebook::from_xml(STANDARDS).unwrap().content_hash(), 0xe61f110a763f049a);
+
+        let mut shuffled = gradebook.clone();
+        shuffled.courses.reverse();
+        shuffled.courses[1].marks[0].assignments.reverse();
+        shuffled.last_updated = NaiveDate::from_ymd_opt(2018, 10, 1).unwrap().and_hms_opt(8, 0, 0);
+        assert!(shuffled.same_content(&gradebook));
+
+        shuffled.courses[0].room = "Lab 3".to_string();
+        assert!(!shuffled.same_content(&gradebook));
+    }
+}
@@ -0,0 +1,118 @@
+// Turning raw percentages into what the portal shows. Districts round differently (an 89.5 is an
+// A in one and a B+ in the next), so anything that computes a grade itself, like goals and
+// projections, takes a policy to match the district's.
+use std::cmp::Ordering;
+
+use gradebook::Mark;
+
+// values within this of a half are treated as exactly a half, so 89.45 * 10 (which is
+// 894.4999...) still rounds as 894.5
+const HALF_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundingMode {
+    // leaves the value as is
+    Exact,
+    // halves round up (89.5 -> 90)
+    HalfUp,
+    // halves round down (89.5 -> 89)
+    HalfDown,
+    // halves round to the even neighbor (88.5 -> 88, 89.5 -> 90)
+    HalfEven,
+    // drops the extra digits (89.9 -> 89)
+    Truncate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoundingPolicy {
+    pub mode: RoundingMode,
+    // decimal places to keep
+    pub places: u32,
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> RoundingPolicy {
+        RoundingPolicy::new(RoundingMode::Exact, 0)
+    }
+}
+
+impl RoundingPolicy {
+    pub fn new(mode: RoundingMode, places: u32) -> RoundingPolicy {
+        RoundingPolicy {
+            mode,
+            places,
+        }
+    }
+
+    pub fn round(&self, value: f64) -> f64 {
+        let factor = 10f64.powi(self.places as i32);
+        let scaled = value * factor;
+        let floor = scaled.floor();
+        let frac = scaled - floor;
+
+        let rounded = match self.mode {
+            RoundingMode::Exact => { return value; }
+            RoundingMode::HalfUp => if frac >= 0.5 - HALF_EPSILON { floor + 1.0 } else { floor },
+            RoundingMode::HalfDown => if frac > 0.5 + HALF_EPSILON { floor + 1.0 } else { floor },
+            RoundingMode::HalfEven => {
+                if (frac - 0.5).abs() <= HALF_EPSILON {
+                    if floor % 2.0 == 0.0 { floor } else { floor + 1.0 }
+                } else {
+                    scaled.round()
+                }
+            }
+            RoundingMode::Truncate => scaled.trunc(),
+        };
+
+        rounded / factor
+    }
+
+    // rounded, with as many decimals as the policy keeps (or as `Display` gives for `Exact`)
+    pub fn format(&self, value: f64) -> String {
+        match self.mode {
+            RoundingMode::Exact => value.to_string(),
+            _ => format!("{:.*}", self.places as usize, self.round(value)),
+        }
+    }
+}
+
+// percentage cutoffs for letter grades, highest first; a percentage gets the first letter whose
+// cutoff it reaches after rounding
+#[derive(Clone, Debug)]
+pub struct GradeScale {
+    cutoffs: Vec<(f64, String)>,
+}
+
+impl Default for GradeScale {
+    fn default() -> GradeScale {
+        GradeScale::new(&[(93.0, "A"), (90.0, "A-"), (87.0, "B+"), (83.0, "B"), (80.0, "B-"), (77.0, "C+"),
+            (73.0, "C"), (70.0, "C-"), (67.0, "D+"), (63.0, "D"), (60.0, "D-"), (0.0, "F")])
+    }
+}
+
+impl GradeScale {
+    pub fn new(cutoffs: &[(f64, &str)]) -> GradeScale {
+        let mut cutoffs: Vec<(f64, String)> = cutoffs.iter().map(|&(c, l)| (c, l.to_string())).collect();
+        cutoffs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+        GradeScale {
+            cutoffs,
+        }
+    }
+
+    // None below the lowest cutoff
+    pub fn letter(&self, percentage: f64, policy: &RoundingPolicy) -> Option<&str> {
+        let rounded = policy.round(percentage);
+
+        self.cutoffs.iter()
+            .find(|&&(cutoff, _)| rounded >= cutoff)
+            .map(|(_, letter)| letter.as_str())
+    }
+}
+
+impl Mark {
+    // like `calculated_grade`, with the score rounded by `policy`
+    pub fn calculated_grade_with(&self, policy: &RoundingPolicy) -> String {
+        format!("{} ({})", self.calculated_score_string, policy.format(self.calculated_score_raw))
+    }
+}
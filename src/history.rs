@@ -0,0 +1,144 @@
+use std::cmp;
+
+use clock::{Clock, SystemClock};
+use diff::Changeset;
+
+use chrono::{DateTime, Utc};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct TimestampedChangeset {
+    pub captured_at: DateTime<Utc>,
+    pub changeset: Changeset,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default)]
+pub struct ChangesetHistory {
+    // kept sorted by capture time so range queries can slice
+    entries: Vec<TimestampedChangeset>,
+}
+
+impl ChangesetHistory {
+    pub fn new() -> ChangesetHistory {
+        Default::default()
+    }
+
+    pub fn entries(&self) -> &[TimestampedChangeset] {
+        self.entries.as_slice()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&TimestampedChangeset> {
+        self.entries.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn push(&mut self, captured_at: DateTime<Utc>, changeset: Changeset) {
+        let idx = self.entries.iter()
+            .position(|e| e.captured_at > captured_at)
+            .unwrap_or(self.entries.len());
+
+        self.entries.insert(idx, TimestampedChangeset {
+            captured_at,
+            changeset,
+        });
+    }
+
+    pub fn record(&mut self, changeset: Changeset) {
+        self.record_with(&SystemClock, changeset);
+    }
+
+    pub fn record_with<C: Clock>(&mut self, clock: &C, changeset: Changeset) {
+        self.push(clock.now(), changeset);
+    }
+
+    // changesets captured in [from, to)
+    pub fn range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> &[TimestampedChangeset] {
+        let start = self.index_of(from);
+        let end = cmp::max(start, self.index_of(to));
+
+        &self.entries[start..end]
+    }
+
+    pub fn since(&self, from: DateTime<Utc>) -> &[TimestampedChangeset] {
+        &self.entries[self.index_of(from)..]
+    }
+
+    fn index_of(&self, at: DateTime<Utc>) -> usize {
+        self.entries.iter()
+            .position(|e| e.captured_at >= at)
+            .unwrap_or(self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::FixedClock;
+    use gradebook::Gradebook;
+
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    // told apart by the room chemistry moved to
+    fn changeset(room: &str) -> Changeset {
+        let old = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+        let mut new = old.clone();
+        new.courses[1].room = room.to_string();
+
+        Changeset::diff(&old, &new).unwrap()
+    }
+
+    fn rooms(entries: &[TimestampedChangeset]) -> Vec<String> {
+        entries.iter().map(|e| e.changeset.new.courses[1].room.clone()).collect()
+    }
+
+    #[test]
+    fn keeps_changesets_in_capture_order() {
+        let mut history = ChangesetHistory::new();
+        history.push(at(3), changeset("C"));
+        history.push(at(1), changeset("A"));
+        history.push(at(2), changeset("B"));
+        // the same time as one already there goes after it
+        history.push(at(2), changeset("B2"));
+
+        assert_eq!(rooms(history.entries()), vec!["A", "B", "B2", "C"]);
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.latest().map(|e| e.captured_at), Some(at(3)));
+    }
+
+    #[test]
+    fn ranges_include_the_start_and_leave_out_the_end() {
+        let mut history = ChangesetHistory::new();
+        for (hour, room) in [(1, "A"), (2, "B"), (3, "C"), (4, "D")].iter() {
+            history.push(at(*hour), changeset(room));
+        }
+
+        assert_eq!(rooms(history.range(at(2), at(4))), vec!["B", "C"]);
+        assert_eq!(rooms(history.range(at(0), at(9))), vec!["A", "B", "C", "D"]);
+        assert!(history.range(at(3), at(3)).is_empty());
+        // backwards, so nothing rather than a panic
+        assert!(history.range(at(4), at(2)).is_empty());
+        assert_eq!(rooms(history.since(at(3))), vec!["C", "D"]);
+        assert!(history.since(at(5)).is_empty());
+    }
+
+    #[test]
+    fn records_at_the_time_the_clock_gives() {
+        let mut history = ChangesetHistory::new();
+        assert!(history.is_empty() && history.latest().is_none());
+
+        history.record_with(&FixedClock(at(7)), changeset("A"));
+        assert_eq!(history.latest().map(|e| e.captured_at), Some(at(7)));
+    }
+}
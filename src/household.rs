@@ -0,0 +1,53 @@
+// Families with more than one student, who may be at different districts with logins of their own,
+// or share a parent's login (see `api::Account`). Each member is a labeled client, so anything that
+// takes an `SVUEClient` works per child; the label is what notifications and household changesets
+// call them by.
+use api::{SVUEClient, SVUERequestError};
+use gradebook::Gradebook;
+
+#[derive(Clone)]
+pub struct Member {
+    pub client: SVUEClient,
+    pub label: String,
+}
+
+#[derive(Clone, Default)]
+pub struct Household {
+    members: Vec<Member>,
+}
+
+impl Household {
+    pub fn new() -> Household {
+        Default::default()
+    }
+
+    // a label names one child, so adding one again replaces the earlier member
+    pub fn member(mut self, label: &str, client: SVUEClient) -> Household {
+        self.members.retain(|m| m.label != label);
+        self.members.push(Member {
+            client,
+            label: label.to_string(),
+        });
+        self
+    }
+
+    pub fn members(&self) -> &[Member] {
+        self.members.as_slice()
+    }
+
+    pub fn find(&self, label: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.label == label)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    // every child's current gradebook, in the order they were added; one failing doesn't keep the
+    // others from being fetched
+    pub fn gradebooks(&self) -> Vec<(&str, Result<Gradebook, SVUERequestError>)> {
+        self.members.iter()
+            .map(|m| (m.label.as_str(), m.client.gradebook()))
+            .collect()
+    }
+}
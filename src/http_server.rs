@@ -0,0 +1,188 @@
+// The HTTP listener `mock`, `serve` and `trigger` are built on. Connections are accepted on a thread
+// of the listener's own and handled by hyper on a small tokio runtime, and each request is handed
+// to the (blocking) `Handler` on tokio's blocking pool, so handlers are free to make rvue's own
+// requests. The listener stops when its `Listening` is closed or dropped.
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use futures_util::future::{FutureExt, TryFutureExt};
+use http_body_util::{BodyExt, Full};
+use hyper;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use tokio;
+use tokio::runtime::{Builder, Runtime};
+
+pub use hyper::{HeaderMap, Method, StatusCode};
+
+pub struct Request {
+    pub method: Method,
+    pub path: String,
+    // everything after the `?`, still percent-encoded; empty if there isn't one
+    pub query: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+pub struct Response {
+    pub status: StatusCode,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+// compares every byte, so how long a wrong token takes to turn down says nothing about how much of
+// it was right
+fn same_token(given: &[u8], expected: &[u8]) -> bool {
+    if given.len() != expected.len() {
+        return false;
+    }
+
+    given.iter().zip(expected.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+impl Request {
+    // `Authorization: Bearer <token>`; an empty `token` matches nothing
+    pub fn has_bearer_token(&self, token: &str) -> bool {
+        if token.is_empty() {
+            return false;
+        }
+
+        let expected = format!("Bearer {}", token);
+        self.headers.get_all("Authorization").iter().any(|v| same_token(v.as_bytes(), expected.as_bytes()))
+    }
+}
+
+impl Response {
+    pub fn new(status: StatusCode, content_type: &'static str, body: String) -> Response {
+        Response {
+            status,
+            content_type,
+            body,
+        }
+    }
+}
+
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, req: &Request) -> Response;
+}
+
+pub struct Listening {
+    // the address actually bound, e.g. the port picked for "127.0.0.1:0"
+    pub socket: SocketAddr,
+    closed: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listening {
+    // stops accepting connections; requests already being handled are dropped
+    pub fn close(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => { return; }
+        };
+
+        self.closed.store(true, Ordering::SeqCst);
+        // the accept loop only looks at `closed` between connections, so give it one
+        let wake = match self.socket.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), self.socket.port()),
+            IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), self.socket.port()),
+            _ => self.socket,
+        };
+        let _ = TcpStream::connect(wake);
+        let _ = thread.join();
+    }
+}
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// `addr` can use port 0 to pick a free port; see `Listening::socket`
+pub fn listen<H: Handler>(handler: H, addr: &str) -> io::Result<Listening> {
+    let listener = TcpListener::bind(addr)?;
+    let socket = listener.local_addr()?;
+    let runtime = Builder::new_multi_thread().worker_threads(1).enable_all().build()?;
+    let handler = Arc::new(handler);
+    let closed = Arc::new(AtomicBool::new(false));
+
+    let stop = closed.clone();
+    let thread = thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            // a connection that failed before it was accepted only matters to its client
+            if let Ok(stream) = stream {
+                serve_connection(&runtime, stream, handler.clone());
+            }
+        }
+
+        runtime.shutdown_background();
+    });
+
+    Ok(Listening {
+        socket,
+        closed,
+        thread: Some(thread),
+    })
+}
+
+fn serve_connection<H: Handler>(runtime: &Runtime, stream: TcpStream, handler: Arc<H>) {
+    let _entered = runtime.enter();
+    let stream = match stream.set_nonblocking(true).and_then(|_| tokio::net::TcpStream::from_std(stream)) {
+        Ok(stream) => stream,
+        Err(_) => { return; }
+    };
+
+    let service = service_fn(move |req| respond(handler.clone(), req));
+    let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+    // an error here is the client hanging up or sending garbage, which only matters to the client
+    runtime.spawn(connection.map(|_| ()));
+}
+
+fn respond<H: Handler>(handler: Arc<H>, req: hyper::Request<Incoming>)
+    -> impl Future<Output=Result<hyper::Response<Full<Bytes>>, Infallible>> {
+
+    let (parts, body) = req.into_parts();
+    body.collect()
+        .map_ok(|collected| collected.to_bytes())
+        .then(move |body| {
+            tokio::task::spawn_blocking(move || {
+                match body {
+                    Ok(body) => {
+                        handler.handle(&Request {
+                            path: parts.uri.path().to_string(),
+                            query: parts.uri.query().unwrap_or("").to_string(),
+                            method: parts.method,
+                            headers: parts.headers,
+                            body: body.to_vec(),
+                        })
+                    }
+                    Err(_) => Response::new(StatusCode::BAD_REQUEST, "text/plain", String::new()),
+                }
+            })
+        })
+        .map(|handled| {
+            // the handler panicked
+            let res = handled.unwrap_or_else(|_| Response::new(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", String::new()));
+
+            let mut response = hyper::Response::new(Full::new(Bytes::from(res.body)));
+            *response.status_mut() = res.status;
+            response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(res.content_type));
+            Ok(response)
+        })
+}
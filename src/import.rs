@@ -0,0 +1,222 @@
+// Builds history out of gradebook XML saved by hand (e.g. with curl). Files can be either the full
+// SOAP response or just the `<Gradebook>` document. The capture time comes from a date in the
+// file name (`2017-03-04`, `20170304`, `2017-03-04T18-30-00`, ...) when there is one, and from
+// the file's modification time otherwise. A name with something that looks like a date but isn't
+// one (`2017-13-45`) is skipped rather than given the modification time.
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use api::{SVUEAPIAction, SVUERequestError, SVUEResponse};
+use decoder::DecodingError;
+use diff::Changeset;
+use gradebook::Gradebook;
+use history::ChangesetHistory;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use regex::Regex;
+
+#[derive(Debug)]
+pub enum ImportError {
+    DecodingError(Box<DecodingError>),
+    Io(io::Error),
+    // a date in the file name that isn't a real one
+    InvalidTimestamp(String),
+    NoTimestamp,
+    SOAPError(SVUERequestError),
+}
+
+#[derive(Debug)]
+pub struct ImportedHistory {
+    pub history: ChangesetHistory,
+    // files that couldn't be imported, and why; the rest of the directory is still used
+    pub skipped: Vec<(PathBuf, ImportError)>,
+    // oldest first
+    pub snapshots: Vec<(DateTime<Utc>, Gradebook)>,
+}
+
+// None if the name has nothing that looks like a date. A date has to stand apart from any other
+// digits around it, so a run of more than eight isn't taken for one
+fn timestamp_from_name(name: &str) -> Result<Option<DateTime<Utc>>, ImportError> {
+    lazy_static! {
+        static ref TIMESTAMP: Regex = Regex::new(
+            r"(?:^|\D)((?:19|20)\d{2})(?:-(\d{2})-(\d{2})|(\d{2})(\d{2}))(?:[T_ ]?(\d{2})[-:]?(\d{2})(?:[-:]?(\d{2}))?)?(?:\D|$)"
+        ).unwrap();
+    }
+
+    let cs = match TIMESTAMP.captures(name) {
+        Some(cs) => cs,
+        None => { return Ok(None); }
+    };
+    let num = |i: usize| cs.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+    let (month, day) = match num(2) {
+        Some(_) => (num(2), num(3)),
+        None => (num(4), num(5)),
+    };
+
+    let time = match (num(1), month, day) {
+        (Some(y), Some(m), Some(d)) => NaiveDate::from_ymd_opt(y as i32, m, d),
+        _ => None,
+    }.and_then(|date| date.and_hms_opt(num(6).unwrap_or(0), num(7).unwrap_or(0), num(8).unwrap_or(0)));
+
+    match time {
+        Some(time) => Ok(Some(Utc.from_utc_datetime(&time))),
+        None => {
+            let matched = cs.get(0).unwrap().as_str();
+            let matched = matched.trim_matches(|c: char| !c.is_ascii_digit());
+            Err(ImportError::InvalidTimestamp(matched.to_string()))
+        }
+    }
+}
+
+fn timestamp_from_mtime(path: &Path) -> Result<DateTime<Utc>, ImportError> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(ImportError::Io)?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).map_err(|_| ImportError::NoTimestamp)?;
+
+    Ok(Utc.timestamp_opt(since_epoch.as_secs() as i64, since_epoch.subsec_nanos()).unwrap())
+}
+
+pub fn decode_file(path: &Path) -> Result<Gradebook, ImportError> {
+    let mut raw = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut raw))
+        .map_err(ImportError::Io)?;
+
+    // the gradebook is escaped inside the envelope, so unwrap it first
+    if raw.contains("ProcessWebServiceRequestResult") {
+        let resp = SVUEResponse::from_soap(&raw, SVUEAPIAction::RetrieveGrades(None))
+            .map_err(ImportError::SOAPError)?;
+
+        Gradebook::from_response(&resp).map_err(|e| ImportError::DecodingError(Box::new(e)))
+    } else {
+        Gradebook::from_xml(&raw).map_err(|e| ImportError::DecodingError(Box::new(e)))
+    }
+}
+
+// every `.xml` file directly in `dir`; consecutive snapshots are diffed into the history, so the
+// first one only serves as the baseline
+pub fn import_dir<P: AsRef<Path>>(dir: P) -> Result<ImportedHistory, ImportError> {
+    let mut snapshots = Vec::new();
+    let mut skipped = Vec::new();
+
+    for entry in fs::read_dir(dir.as_ref()).map_err(ImportError::Io)? {
+        let path = entry.map_err(ImportError::Io)?.path();
+        let is_xml = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase() == "xml").unwrap_or(false);
+        if !path.is_file() || !is_xml {
+            continue;
+        }
+
+        let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+        let captured_at = match timestamp_from_name(name) {
+            Ok(Some(t)) => Ok(t),
+            Ok(None) => timestamp_from_mtime(&path),
+            Err(e) => Err(e),
+        };
+
+        match captured_at.and_then(|t| decode_file(&path).map(|g| (t, g))) {
+            Ok(s) => snapshots.push(s),
+            Err(e) => skipped.push((path, e)),
+        }
+    }
+
+    snapshots.sort_by_key(|a| a.0);
+
+    let mut history = ChangesetHistory::new();
+    for pair in snapshots.windows(2) {
+        if let Some(changeset) = Changeset::diff(&pair[0].1, &pair[1].1) {
+            history.push(pair[1].0, changeset);
+        }
+    }
+
+    Ok(ImportedHistory {
+        history,
+        skipped,
+        snapshots,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use xml::escape::escape_str_pcdata;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    #[test]
+    fn reads_the_capture_time_from_the_name() {
+        let cases = [
+            ("2017-03-04", at(2017, 3, 4, 0, 0, 0)),
+            ("20170304", at(2017, 3, 4, 0, 0, 0)),
+            ("2017-03-04T18-30-00", at(2017, 3, 4, 18, 30, 0)),
+            ("2017-03-04T18:30", at(2017, 3, 4, 18, 30, 0)),
+            ("2017-03-04_183000", at(2017, 3, 4, 18, 30, 0)),
+            ("20170304183000", at(2017, 3, 4, 18, 30, 0)),
+            ("gradebook-2017-03-04", at(2017, 3, 4, 0, 0, 0)),
+            ("gradebook_20170304_v2", at(2017, 3, 4, 0, 0, 0)),
+        ];
+
+        for &(name, expected) in cases.iter() {
+            assert_eq!(timestamp_from_name(name).unwrap(), Some(expected), "{}", name);
+        }
+    }
+
+    #[test]
+    fn only_takes_digits_standing_apart_for_a_date() {
+        for name in &["gradebook", "123456789", "order-99920170304", "2017030412", "backup-00001234"] {
+            assert_eq!(timestamp_from_name(name).unwrap(), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn turns_down_a_date_that_does_not_exist() {
+        for &(name, matched) in &[("2017-13-45", "2017-13-45"), ("gradebook-20170230", "20170230"),
+                                  ("2017-03-04T25-00-00", "2017-03-04T25-00-00")] {
+            match timestamp_from_name(name) {
+                Err(ImportError::InvalidTimestamp(ref m)) => assert_eq!(m, matched),
+                other => panic!("expected {:?} to be turned down, got {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn imports_envelopes_and_bare_documents_and_skips_the_rest() {
+        let dir = ::std::env::temp_dir().join(format!("rvue-import-test-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested.xml")).unwrap();
+
+        let moved = GRADEBOOK.replacen("Room=\"", "Room=\"B", 1);
+        assert!(moved != GRADEBOOK);
+        let envelope = format!("<soap:Envelope xmlns:soap=\"http://schemas.xmlsoap.org/soap/envelope/\"><soap:Body>\
+            <ProcessWebServiceRequestResponse xmlns=\"http://edupoint.com/webservices/\"><ProcessWebServiceRequestResult>\
+            {}</ProcessWebServiceRequestResult></ProcessWebServiceRequestResponse></soap:Body></soap:Envelope>",
+            escape_str_pcdata(&moved));
+
+        fs::write(dir.join("2017-03-04.xml"), GRADEBOOK).unwrap();
+        fs::write(dir.join("2017-03-05.XML"), envelope).unwrap();
+        fs::write(dir.join("notes.txt"), "not a gradebook").unwrap();
+        fs::write(dir.join("2017-03-06.xml"), "<Gradebook").unwrap();
+        fs::write(dir.join("2017-13-45.xml"), GRADEBOOK).unwrap();
+
+        let imported = import_dir(&dir).unwrap();
+        let captured: Vec<DateTime<Utc>> = imported.snapshots.iter().map(|s| s.0).collect();
+        assert_eq!(captured, vec![at(2017, 3, 4, 0, 0, 0), at(2017, 3, 5, 0, 0, 0)]);
+        assert_eq!(imported.history.entries().len(), 1);
+
+        let mut skipped: Vec<(String, bool)> = imported.skipped.iter()
+            .map(|(path, e)| (path.file_name().unwrap().to_str().unwrap().to_string(),
+                              matches!(*e, ImportError::InvalidTimestamp(_))))
+            .collect();
+        skipped.sort();
+        assert_eq!(skipped, vec![("2017-03-06.xml".to_string(), false), ("2017-13-45.xml".to_string(), true)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,115 @@
-#![cfg_attr(feature="serde-serialize", feature(proc_macro))]
 
+#[cfg(feature="binary")]
+extern crate bincode;
 extern crate chrono;
+#[cfg(feature="tz")]
+extern crate chrono_tz;
+#[cfg(feature="daemon")]
+extern crate ctrlc;
+#[cfg(any(feature="async", feature="http-server"))]
+extern crate futures_util;
+#[cfg(feature="webhook")]
+extern crate hmac;
+#[cfg(feature="http-server")]
+extern crate http_body_util;
+#[cfg(feature="http-server")]
+extern crate hyper;
+#[cfg(feature="http-server")]
+extern crate hyper_util;
+#[cfg(test)]
+extern crate jsonschema;
+#[macro_use] extern crate lazy_static;
+#[cfg(feature="email")]
+extern crate lettre;
+#[cfg(feature="arbitrary")]
+extern crate quickcheck;
+#[cfg(feature="redis-store")]
+extern crate redis;
 extern crate regex;
+#[cfg(feature="client")]
 extern crate reqwest;
+#[cfg(feature="sqlite")]
+extern crate rusqlite;
+#[cfg(feature="schemars")]
+extern crate schemars;
+#[cfg(feature="serde-serialize")]
+extern crate serde;
 #[cfg(feature="serde-serialize")]
 #[macro_use] extern crate serde_derive;
+#[cfg(any(test, feature="schemars", feature="serde-serialize"))]
+extern crate serde_json;
+#[cfg(feature="webhook")]
+extern crate sha2;
+#[cfg(feature="http-server")]
+extern crate tokio;
+#[cfg(feature="config")]
+extern crate toml;
 extern crate xml;
+extern crate zeroize;
+#[cfg(any(feature="xlsx", feature="archive"))]
+extern crate zip;
 
 #[macro_use]
 mod decoder;
 pub mod api;
+#[cfg(feature="arbitrary")]
+pub mod arbitrary;
+#[cfg(feature="archive")]
+pub mod archive;
+pub mod attendance;
+#[cfg(all(feature="client", feature="serde-serialize"))]
+pub mod audit;
+pub mod clock;
+pub mod compare;
+pub mod compat;
+pub mod completion;
+#[cfg(feature="config")]
+pub mod config;
+pub mod correlation;
+pub mod credentials;
+pub mod dedup;
 pub mod diff;
+pub mod districts;
+pub mod export;
+pub mod forecast;
+pub mod goals;
 pub mod gradebook;
+pub mod grading;
+pub mod history;
+#[cfg(feature="client")]
+pub mod household;
+#[cfg(feature="http-server")]
+pub mod http_server;
+pub mod import;
+pub mod locale;
+pub mod messages;
+#[cfg(feature="mock-server")]
+pub mod mock;
+#[cfg(feature="async")]
+pub mod nonblocking;
+pub mod notify;
+#[cfg(feature="client")]
+pub mod periods;
+pub mod portal;
+pub mod render;
+pub mod report;
+pub mod rules;
+pub mod sample;
+pub mod schedule;
+pub mod school;
+#[cfg(feature="serve")]
+pub mod serve;
+#[cfg(feature="serde-serialize")]
+pub mod store;
+pub mod students;
+pub mod tags;
+pub mod teachers;
+#[cfg(feature="client")]
+pub mod transport;
+#[cfg(feature="trigger")]
+pub mod trigger;
+pub mod turnin;
+#[cfg(all(feature="client", feature="serde-serialize"))]
+pub mod watcher;
+
+pub use decoder::{DecodeLimits, DecoderResult, DecodingError};
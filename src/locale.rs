@@ -0,0 +1,188 @@
+// The words StudentVUE uses in score and points strings, per portal language. Scores and points
+// are parsed against every registered locale, so a gradebook from a Spanish-language portal
+// decodes the same as an English one; more languages can be added with `register`.
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use regex::{self, Regex};
+
+#[derive(Clone, Debug)]
+pub struct ScoreLocale {
+    pub name: String,
+    pub not_due: Vec<String>,
+    pub not_graded: Vec<String>,
+    pub see_standards: Vec<String>,
+    // between the score and the possible score, e.g. "8 out of 10"
+    pub out_of: Vec<String>,
+    // after the possible points of an ungraded assignment, e.g. "10 Points Possible"
+    pub points_possible: Vec<String>,
+}
+
+fn strings(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| w.to_string()).collect()
+}
+
+pub fn english() -> ScoreLocale {
+    ScoreLocale {
+        name: "en".to_string(),
+        not_due: strings(&["Not Due"]),
+        not_graded: strings(&["Not Graded"]),
+        see_standards: strings(&["See Standards"]),
+        out_of: strings(&["out of"]),
+        points_possible: strings(&["Points Possible"]),
+    }
+}
+
+pub fn spanish() -> ScoreLocale {
+    ScoreLocale {
+        name: "es".to_string(),
+        not_due: strings(&["No Vencido", "No Pendiente"]),
+        not_graded: strings(&["No Calificado"]),
+        see_standards: strings(&["Ver Estándares", "Ver Estandares"]),
+        out_of: strings(&["de"]),
+        points_possible: strings(&["Puntos Posibles"]),
+    }
+}
+
+// the regexes built from the registered words; compiled when a locale is registered rather than
+// for every score or points string parsed
+#[derive(Debug)]
+pub struct Patterns {
+    // "8 out of 10"; words that start or end with a letter have to stand on their own there, so
+    // the Spanish "de" doesn't match inside anything else, while a separator like "/" needs no
+    // space around it
+    pub score: Regex,
+    // any of the points-possible words, e.g. "Points Possible"
+    pub points_possible_word: Regex,
+    // "10 Points Possible"
+    pub points_possible: Regex,
+}
+
+impl Patterns {
+    fn compile(locales: &[ScoreLocale]) -> Patterns {
+        let out_of = alternation_of(locales, |l| &l.out_of, true);
+        let possible = alternation_of(locales, |l| &l.points_possible, false);
+
+        // the words are escaped, so these always compile
+        Patterns {
+            score: Regex::new(&format!(r"([\d\.]+)\s*{}\s*([\d\.]+)", out_of)).unwrap(),
+            points_possible_word: Regex::new(&possible).unwrap(),
+            points_possible: Regex::new(&format!(r"([\d\.]+)\s*{}", possible)).unwrap(),
+        }
+    }
+}
+
+struct Registry {
+    locales: Vec<ScoreLocale>,
+    patterns: Arc<Patterns>,
+}
+
+impl Registry {
+    fn new(locales: Vec<ScoreLocale>) -> Registry {
+        Registry {
+            patterns: Arc::new(Patterns::compile(&locales)),
+            locales,
+        }
+    }
+}
+
+lazy_static! {
+    static ref REGISTRY: RwLock<Registry> = RwLock::new(Registry::new(vec![english(), spanish()]));
+}
+
+fn registry() -> RwLockReadGuard<'static, Registry> {
+    match REGISTRY.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+// a locale with the same name replaces the registered one
+pub fn register(locale: ScoreLocale) {
+    let mut registry = match REGISTRY.write() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    registry.locales.retain(|l| l.name != locale.name);
+    registry.locales.push(locale);
+    registry.patterns = Arc::new(Patterns::compile(&registry.locales));
+}
+
+pub fn locales() -> Vec<ScoreLocale> {
+    registry().locales.clone()
+}
+
+pub fn patterns() -> Arc<Patterns> {
+    registry().patterns.clone()
+}
+
+pub fn matches_any<F>(s: &str, words: F) -> bool where F: Fn(&ScoreLocale) -> &Vec<String> {
+    registry().locales.iter().any(|l| words(l).iter().any(|w| w == s))
+}
+
+// a regex alternation of every registered word, with runs of spaces matching any whitespace
+pub fn alternation<F>(words: F) -> String where F: Fn(&ScoreLocale) -> &Vec<String> {
+    alternation_of(&registry().locales, words, false)
+}
+
+// `word`'s pattern; when `bounded`, a `\b` goes at either end that's a word character, since one
+// next to anything else (the "/" in "8/10") would need a letter or digit beside it to match
+fn word_pattern(word: &str, bounded: bool) -> String {
+    let pattern = word.split_whitespace().map(regex::escape).collect::<Vec<_>>().join(r"\s*");
+    if !bounded {
+        return pattern;
+    }
+
+    let is_word = |c: Option<char>| c.map(|c| c.is_alphanumeric() || c == '_').unwrap_or(false);
+    let word = word.trim();
+    format!("{}{}{}", if is_word(word.chars().next()) { r"\b" } else { "" },
+            pattern,
+            if is_word(word.chars().last()) { r"\b" } else { "" })
+}
+
+fn alternation_of<F>(locales: &[ScoreLocale], words: F, bounded: bool) -> String
+    where F: Fn(&ScoreLocale) -> &Vec<String> {
+
+    let alternatives: Vec<String> = locales.iter()
+        .flat_map(|l| words(l).iter().filter(|w| !w.trim().is_empty()).map(|w| word_pattern(w, bounded))
+            .collect::<Vec<_>>())
+        .collect();
+
+    // with no words at all, something that never matches rather than the empty string, which
+    // always would
+    if alternatives.is_empty() {
+        return r"[^\s\S]".to_string();
+    }
+
+    format!("(?i:{})", alternatives.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(re: &Regex, s: &str) -> Option<Vec<String>> {
+        re.captures(s).map(|cs| cs.iter().skip(1).map(|c| c.unwrap().as_str().to_string()).collect())
+    }
+
+    #[test]
+    fn bounds_only_the_ends_of_a_word_that_are_letters() {
+        let locale = ScoreLocale {
+            name: "test".to_string(),
+            not_due: Vec::new(),
+            not_graded: Vec::new(),
+            see_standards: Vec::new(),
+            out_of: strings(&["/", "pts. of", "de"]),
+            points_possible: strings(&["pts. possible"]),
+        };
+        let patterns = Patterns::compile(&[locale]);
+
+        assert_eq!(captures(&patterns.score, "8/10"), Some(vec!["8".to_string(), "10".to_string()]));
+        assert_eq!(captures(&patterns.score, "8 / 10"), Some(vec!["8".to_string(), "10".to_string()]));
+        assert_eq!(captures(&patterns.score, "8 pts. of 10"), Some(vec!["8".to_string(), "10".to_string()]));
+        assert_eq!(captures(&patterns.score, "8 de 10"), Some(vec!["8".to_string(), "10".to_string()]));
+        assert_eq!(captures(&patterns.score, "8de10"), None);
+        assert_eq!(captures(&patterns.score, "8 dé 10"), None);
+        assert_eq!(captures(&patterns.points_possible, "10 pts. possible"), Some(vec!["10".to_string()]));
+    }
+}
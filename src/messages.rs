@@ -0,0 +1,195 @@
+// The `PXPMessagesData` document: what's in the portal's inbox, from teachers, counselors and the
+// school. Each message carries the district's ID for it, which is what `diff::MessageChangeset`
+// pairs messages by, so a message whose subject is edited isn't taken for a new one.
+use api::SVUEResponse;
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUEClient, SVUERequestError};
+use decoder::*;
+use diff::Pairable;
+
+use chrono::NaiveDateTime;
+use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    // when it was sent; None if the district's format isn't one we know
+    pub begin_date: Option<NaiveDateTime>,
+    // HTML, as the portal shows it
+    pub content: String,
+    pub from: String,
+    pub id: String,
+    // e.g. "StudentActivity" or "Synergy Mail"
+    pub kind: String,
+    pub read: bool,
+    // without the markup some districts put in subjects
+    pub subject: String,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Messages {
+    pub messages: Vec<Message>,
+}
+
+field_slice_helpers!(Messages, {
+    messages => Message
+});
+
+impl<'a> Pairable<'a, String> for Message {
+    fn unique_key(&'a self) -> &'a String {
+        &self.id
+    }
+}
+
+impl Messages {
+    #[cfg(feature="client")]
+    pub fn retrieve_with(client: &SVUEClient) -> Result<Messages, SVUERequestError> {
+        let resp = client.perform(SVUEAPIAction::RetrieveMessages)?;
+
+        Self::from_response(&resp).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<Messages> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<Messages> {
+        Self::from_xml_with(xml, &DecodeLimits::new())
+    }
+
+    pub fn from_xml_with(xml: &str, limits: &DecodeLimits) -> DecoderResult<Messages> {
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+        limits.reset();
+
+        match events_iter.next() {
+            Some(Ok(event)) => Self::from_event(event, &mut events_iter, limits),
+            Some(Err(e)) => Err(DecodingError::EventError(e)),
+            None => Err(DecodingError::UnexpectedEnd),
+        }
+    }
+
+    pub fn unread(&self) -> Vec<&Message> {
+        self.messages.iter().filter(|m| !m.read).collect()
+    }
+}
+
+impl SVUEDecodeable for Messages {
+    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Messages> {
+        let mut messages: Messages = Default::default();
+
+        loop {
+            match events_iter.next() {
+                Some(Ok(event)) => {
+                    match event {
+                        _ if is_start_of(&event, "MessageListing") => {
+                            messages.messages.push(Message::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_end_of(&event, "PXPMessagesData") => {
+                            return Ok(messages);
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                None => { return Err(DecodingError::UnexpectedEnd); }
+            }
+        }
+    }
+}
+
+impl SVUEDecodeable for Message {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Message> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "MessageListing" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+                        let attr = |name: &str| attrs.get(name).map(|v| v.trim().to_string()).unwrap_or_default();
+                        let subject = match attrs.get("SubjectNoHTML") {
+                            Some(s) if !s.trim().is_empty() => s.trim(),
+                            _ => attrs.get("Subject").map(|s| s.trim()).unwrap_or(""),
+                        };
+
+                        let message = Message {
+                            begin_date: attrs.get("BeginDate").and_then(|d| parse_timestamp(d)),
+                            content: limits.text(attrs.get("Content").cloned().unwrap_or("")),
+                            from: attr("From"),
+                            id: get_attr_owned!(attrs, "ID"),
+                            kind: attr("Type"),
+                            read: attr("Read").eq_ignore_ascii_case("true"),
+                            subject: limits.text(subject),
+                        };
+                        // attachments, which aren't kept
+                        skip_element(events_iter)?;
+
+                        Ok(message)
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+
+    const INBOX: &str = include_str!("../fixtures/messages.xml");
+
+    #[test]
+    fn decodes_the_inbox() {
+        let inbox = Messages::from_xml(INBOX).unwrap();
+        let ids: Vec<&str> = inbox.messages().iter().map(|m| &m.id[..]).collect();
+        assert_eq!(ids, vec!["M-4101", "M-4102", "M-4103"]);
+
+        let rubric = &inbox.messages[0];
+        assert_eq!(rubric.begin_date, NaiveDate::from_ymd_opt(2018, 9, 28).unwrap().and_hms_opt(14, 15, 0));
+        assert_eq!(rubric.content, "<p>The rubric is under Resources.</p>");
+        assert_eq!(rubric.from, "Okafor, Chris");
+        assert_eq!(rubric.kind, "StudentActivity");
+        assert!(rubric.read);
+        assert_eq!(rubric.subject, "Narrative rubric posted");
+    }
+
+    #[test]
+    fn falls_back_to_the_subject_when_there_is_no_plain_one() {
+        let inbox = Messages::from_xml(INBOX).unwrap();
+        assert_eq!(inbox.messages[1].subject, "Lab safety quiz Friday");
+        assert_eq!(inbox.messages[2].subject, "Picture day");
+    }
+
+    #[test]
+    fn leaves_out_dates_in_unknown_formats() {
+        let inbox = Messages::from_xml(INBOX).unwrap();
+        assert_eq!(inbox.messages[1].begin_date, NaiveDate::from_ymd_opt(2018, 10, 2).unwrap().and_hms_opt(8, 30, 0));
+        assert_eq!(inbox.messages[2].begin_date, None);
+    }
+
+    #[test]
+    fn lists_unread_messages() {
+        let inbox = Messages::from_xml(INBOX).unwrap();
+        let unread: Vec<&str> = inbox.unread().iter().map(|m| &m.id[..]).collect();
+        assert_eq!(unread, vec!["M-4102", "M-4103"]);
+    }
+
+    #[test]
+    fn requires_a_message_id() {
+        let xml = INBOX.replace(r#"ID="M-4102" "#, "");
+        match Messages::from_xml(&xml) {
+            Err(DecodingError::MissingAttribute(ref attr)) => assert_eq!(attr, "ID"),
+            other => panic!("expected a missing ID, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_an_empty_inbox() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?><PXPMessagesData><MessageListings /></PXPMessagesData>"#;
+        assert_eq!(Messages::from_xml(xml).unwrap(), Messages::default());
+    }
+}
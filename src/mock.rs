@@ -0,0 +1,192 @@
+// A local stand-in for a district's StudentVUE endpoint, so apps built on rvue can be tested end
+// to end without real credentials. Responses are canned per SOAP `methodName` (`Gradebook`,
+// `ChildList`, ...), e.g. recorded with curl:
+//
+//     let server = MockServer::new()
+//         .document("Gradebook", &recorded_gradebook)
+//         .rt_error("ChildList", "Invalid user id or password")
+//         .listen("127.0.0.1:0")?;
+//     let client = SVUEClient::new("user", "pass").endpoint(&endpoint_url(&server));
+use std::collections::HashMap;
+use std::io;
+
+use http_server::{self, Handler, Listening, Method, Request, Response, StatusCode};
+
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+const MAINTENANCE_PAGE: &str = "<html><head><title>Site Maintenance</title></head>\
+    <body><h1>StudentVUE is currently undergoing maintenance.</h1>\
+    <p>Please try again later.</p></body></html>";
+
+#[derive(Clone, Debug)]
+enum MockResponse {
+    Maintenance,
+    Soap(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MockServer {
+    credentials: Option<(String, String)>,
+    maintenance: bool,
+    responses: HashMap<String, MockResponse>,
+}
+
+// wraps a document the way the real endpoint does: escaped inside `ProcessWebServiceRequestResult`
+pub fn soap_envelope(document: &str) -> String {
+    format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+        <soap:Envelope xmlns:soap=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+        xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+        xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\
+        <soap:Body><ProcessWebServiceRequestResponse xmlns=\"http://edupoint.com/webservices/\">\
+        <ProcessWebServiceRequestResult>{}</ProcessWebServiceRequestResult>\
+        </ProcessWebServiceRequestResponse></soap:Body></soap:Envelope>", escape_str_pcdata(document))
+}
+
+pub fn rt_error_document(message: &str) -> String {
+    format!("<RT_ERROR ERROR_MESSAGE=\"{}\"><STACK_TRACE>mock</STACK_TRACE></RT_ERROR>",
+        escape_str_attribute(message))
+}
+
+// the URL to hand to `SVUEClient::endpoint`
+pub fn endpoint_url(listening: &Listening) -> String {
+    format!("http://{}/Service/PXPCommunication.asmx", listening.socket)
+}
+
+// the text of the first `<name>` element in the request body, still escaped
+fn element_text<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+
+    body.find(&open)
+        .map(|i| &body[i + open.len()..])
+        .and_then(|rest| rest.find(&close).map(|j| &rest[..j]))
+}
+
+impl MockServer {
+    pub fn new() -> MockServer {
+        Default::default()
+    }
+
+    // answers `method` with `document` (e.g. a recorded `<Gradebook>`)
+    pub fn document(self, method: &str, document: &str) -> MockServer {
+        self.soap(method, &soap_envelope(document))
+    }
+
+    // answers `method` with a full recorded SOAP response, as is
+    pub fn soap(mut self, method: &str, raw: &str) -> MockServer {
+        self.responses.insert(method.to_string(), MockResponse::Soap(raw.to_string()));
+        self
+    }
+
+    pub fn rt_error(self, method: &str, message: &str) -> MockServer {
+        self.document(method, &rt_error_document(message))
+    }
+
+    // `method` gets the HTML maintenance page districts put up during upgrades
+    pub fn maintenance_for(mut self, method: &str) -> MockServer {
+        self.responses.insert(method.to_string(), MockResponse::Maintenance);
+        self
+    }
+
+    // every request gets the maintenance page
+    pub fn maintenance(mut self) -> MockServer {
+        self.maintenance = true;
+        self
+    }
+
+    // logins other than this one get the invalid-password RT_ERROR; by default anything goes
+    pub fn credentials(mut self, username: &str, password: &str) -> MockServer {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    // `addr` can use port 0 to pick a free port; see `endpoint_url`
+    pub fn listen(self, addr: &str) -> io::Result<Listening> {
+        http_server::listen(self, addr)
+    }
+
+    fn respond(&self, body: &str) -> (StatusCode, bool, String) {
+        if self.maintenance {
+            return (StatusCode::SERVICE_UNAVAILABLE, false, MAINTENANCE_PAGE.to_string());
+        }
+
+        if let Some((ref username, ref password)) = self.credentials {
+            let valid = element_text(body, "userID") == Some(escape_str_pcdata(username).as_ref()) &&
+                element_text(body, "password") == Some(escape_str_pcdata(password).as_ref());
+            if !valid {
+                return (StatusCode::OK, true, soap_envelope(&rt_error_document("Invalid user id or password")));
+            }
+        }
+
+        let method = element_text(body, "methodName").unwrap_or("");
+        match self.responses.get(method) {
+            Some(MockResponse::Soap(raw)) => (StatusCode::OK, true, raw.clone()),
+            Some(&MockResponse::Maintenance) => (StatusCode::SERVICE_UNAVAILABLE, false, MAINTENANCE_PAGE.to_string()),
+            None => {
+                let message = format!("No mock response for methodName {}", method);
+                (StatusCode::OK, true, soap_envelope(&rt_error_document(&message)))
+            }
+        }
+    }
+}
+
+impl Handler for MockServer {
+    fn handle(&self, req: &Request) -> Response {
+        let (status, is_soap, reply) = if req.method != Method::POST {
+            (StatusCode::METHOD_NOT_ALLOWED, false, String::new())
+        } else {
+            match String::from_utf8(req.body.clone()) {
+                Ok(body) => self.respond(&body),
+                Err(_) => (StatusCode::BAD_REQUEST, false, String::new()),
+            }
+        };
+
+        let content_type = if is_soap { "text/xml; charset=utf-8" } else { "text/html; charset=utf-8" };
+        Response::new(status, content_type, reply)
+    }
+}
+
+#[cfg(all(test, feature="client"))]
+mod tests {
+    use super::*;
+    use api::{SVUEClient, SVUERequestError};
+    use gradebook::Gradebook;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    #[test]
+    fn answers_with_the_canned_document() {
+        let server = MockServer::new()
+            .document("Gradebook", GRADEBOOK)
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let client = SVUEClient::new("user", "pass").endpoint(&endpoint_url(&server));
+
+        let gradebook = client.gradebook().unwrap();
+        assert_eq!(gradebook.courses.len(), Gradebook::from_xml(GRADEBOOK).unwrap().courses.len());
+        server.close();
+    }
+
+    #[test]
+    fn answers_maintenance_with_a_503() {
+        let server = MockServer::new().maintenance().listen("127.0.0.1:0").unwrap();
+        let client = SVUEClient::new("user", "pass").endpoint(&endpoint_url(&server));
+
+        match client.gradebook() {
+            Err(SVUERequestError::ReqwestError(ref e)) => assert_eq!(e.status().map(|s| s.as_u16()), Some(503)),
+            other => panic!("expected a 503, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn accepts_a_password_that_needs_escaping() {
+        let server = MockServer::new()
+            .document("Gradebook", GRADEBOOK)
+            .credentials("user", "a&b<c>")
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let client = SVUEClient::new("user", "a&b<c>").endpoint(&endpoint_url(&server));
+
+        assert!(client.gradebook().is_ok());
+    }
+}
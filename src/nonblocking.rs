@@ -0,0 +1,168 @@
+// Futures-returning versions of the client's requests, for bots and servers polling many students
+// at once. They're sent with reqwest's async client, so they need a tokio runtime to run on; the
+// blocking API is unchanged. An `AsyncClient` wraps an `SVUEClient` for its credentials, endpoint,
+// account, failure count and audit log, but never sends through the client's `Transport`. Clones
+// share their connections, so one `AsyncClient` can be cloned into every task polling an account.
+use std::future::Future;
+use std::io::Cursor;
+use std::mem;
+use std::time::Instant;
+
+use api::{SOAP_ACTION, SVUEAPIAction, SVUEClient, SVUERequest, SVUERequestError, SVUEResponse};
+use gradebook::{FetchedGradebook, Gradebook};
+use transport::TransportResponse;
+
+use futures_util::future::{self, Either, FutureExt, TryFutureExt};
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+
+#[derive(Clone)]
+pub struct AsyncClient {
+    client: SVUEClient,
+    http: reqwest::Client,
+}
+
+impl AsyncClient {
+    pub fn new(client: SVUEClient) -> AsyncClient {
+        Self::with_http_client(client, reqwest::Client::new())
+    }
+
+    // for sharing one connection pool between clients, or configuring proxies, timeouts, ...
+    pub fn with_http_client(client: SVUEClient, http: reqwest::Client) -> AsyncClient {
+        AsyncClient {
+            client,
+            http,
+        }
+    }
+
+    pub fn client(&self) -> &SVUEClient {
+        &self.client
+    }
+
+    pub fn perform(&self, action: SVUEAPIAction) -> impl Future<Output=Result<SVUEResponse, SVUERequestError>> {
+        let started = Instant::now();
+        let name = action.as_str().to_string();
+        let period = action.report_period();
+
+        if let Err(e) = self.client.check_auth_failures(&name, period, started) {
+            return Either::Left(future::err(e));
+        }
+
+        let client = self.client.clone();
+        let sent = self.client.request(action).send_async(&self.http);
+        Either::Right(sent.map(move |result| client.finish(&name, period, started, result)))
+    }
+
+    pub fn gradebook(&self) -> impl Future<Output=Result<Gradebook, SVUERequestError>> {
+        self.retrieve_grades(None)
+    }
+
+    pub fn gradebook_for_period(&self, period: i8) -> impl Future<Output=Result<Gradebook, SVUERequestError>> {
+        self.retrieve_grades(Some(period))
+    }
+
+    // see `Gradebook::retrieve_since`
+    pub fn gradebook_since(&self, previous: Gradebook) -> impl Future<Output=Result<FetchedGradebook, SVUERequestError>> {
+        self.perform(SVUEAPIAction::RetrieveGrades(None))
+            .map(move |result| Gradebook::from_result(result).map(|gradebook| FetchedGradebook::since(&previous, gradebook)))
+    }
+
+    fn retrieve_grades(&self, period: Option<i8>) -> impl Future<Output=Result<Gradebook, SVUERequestError>> {
+        self.perform(SVUEAPIAction::RetrieveGrades(period))
+            .map(|result| Gradebook::from_result(result).map(|gradebook| gradebook.unwrap_or_default()))
+    }
+}
+
+impl<'a> SVUERequest<'a> {
+    // `send_with`, on reqwest's async client; the body is built before this returns, so the future
+    // doesn't borrow the request
+    pub fn send_async(&self, http: &reqwest::Client) -> impl Future<Output=Result<SVUEResponse, SVUERequestError>> {
+        let mut body = match self.build_body() {
+            Ok(body) => body,
+            Err(e) => { return Either::Left(future::err(e)); }
+        };
+        let action = self.action().clone();
+
+        let sent = http.post(self.endpoint_url())
+            .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+            .header("SOAPAction", &SOAP_ACTION[..])
+            // moved rather than copied, as in `send_with`
+            .body(mem::take(&mut *body))
+            .send()
+            // a 4xx or 5xx has no envelope to decode, so it's the status that's reported
+            .and_then(|resp| future::ready(resp.error_for_status()))
+            .and_then(|resp| {
+                let url = resp.url().as_str().to_string();
+                resp.bytes().map_ok(move |bytes| (url, bytes))
+            })
+            .map_err(SVUERequestError::ReqwestError);
+
+        Either::Right(sent.and_then(move |(url, bytes)| {
+            let resp = TransportResponse {
+                url,
+                body: Box::new(Cursor::new(bytes)),
+            };
+            future::ready(SVUEResponse::from_transport(resp, action))
+        }))
+    }
+}
+
+impl Gradebook {
+    // `Gradebook::retrieve`, as a future
+    pub fn retrieve_async(user: &str, password: &str) -> impl Future<Output=Result<Gradebook, SVUERequestError>> {
+        AsyncClient::new(SVUEClient::new(user, password)).gradebook()
+    }
+}
+
+#[cfg(all(test, feature="mock-server"))]
+mod tests {
+    use super::*;
+    use diff::Changeset;
+    use mock::{endpoint_url, MockServer};
+
+    use tokio::runtime::Builder;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        Builder::new_current_thread().enable_all().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn retrieves_a_gradebook_from_the_mock_server() {
+        let server = MockServer::new()
+            .document("Gradebook", GRADEBOOK)
+            .credentials("user", "pass")
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let client = AsyncClient::new(SVUEClient::new("user", "pass").endpoint(&endpoint_url(&server)));
+
+        let gradebook = block_on(client.gradebook()).unwrap();
+        let expected = Gradebook::from_xml(GRADEBOOK).unwrap();
+        assert_eq!(gradebook.courses.len(), expected.courses.len());
+        assert_eq!(gradebook.reporting_period.grade_period, expected.reporting_period.grade_period);
+        assert!(Changeset::diff(&expected, &gradebook).is_none());
+    }
+
+    #[test]
+    fn counts_rejected_logins_towards_the_breaker() {
+        let server = MockServer::new()
+            .document("Gradebook", GRADEBOOK)
+            .credentials("user", "pass")
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let client = SVUEClient::new("user", "wrong")
+            .endpoint(&endpoint_url(&server))
+            .max_auth_failures(1);
+        let client = AsyncClient::new(client);
+
+        match block_on(client.gradebook()) {
+            Err(SVUERequestError::SVUEError(_)) => {}
+            other => panic!("expected the invalid-password error, got {:?}", other.map(|_| ())),
+        }
+        match block_on(client.gradebook()) {
+            Err(SVUERequestError::LikelyBadCredentials(1)) => {}
+            other => panic!("expected the request to be held back, got {:?}", other.map(|_| ())),
+        }
+    }
+}
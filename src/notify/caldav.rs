@@ -0,0 +1,207 @@
+// Keeps a CalDAV calendar in step with assignment due dates: an all-day event is PUT when an
+// assignment shows up (or its title or due date changes) and DELETEd once it's graded or
+// removed. Each event lives at `<calendar>/rvue-<gradebook id>.ics`, so the same assignment
+// always maps to the same resource and updates simply overwrite it.
+use std::fmt;
+
+use super::{Notifier, NotifyError, RenderedChangeset};
+
+use diff::AssignmentChange;
+use gradebook::{Assignment, Course};
+
+use chrono::{Duration, Utc};
+use reqwest;
+use reqwest::Method;
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::CONTENT_TYPE;
+
+#[derive(Clone)]
+pub struct CalDavNotifier {
+    calendar_url: String,
+    credentials: Option<(String, String)>,
+}
+
+enum CalendarOp<'a> {
+    Delete(&'a Assignment),
+    Put(&'a Course, &'a Assignment),
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// content lines longer than 75 octets have to be folded onto continuation lines
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+
+    out.push_str("\r\n");
+    out
+}
+
+fn event_id(a: &Assignment) -> String {
+    format!("rvue-{}", a.gradebook_id)
+}
+
+pub fn ical_event(course: &Course, a: &Assignment) -> String {
+    let lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rvue//CalDAV//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@rvue", event_id(a)),
+        format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+        format!("DTSTART;VALUE=DATE:{}", a.due_date.format("%Y%m%d")),
+        format!("DTEND;VALUE=DATE:{}", (a.due_date + Duration::days(1)).format("%Y%m%d")),
+        format!("SUMMARY:{}", escape_text(&format!("{}: {}", course.title, a.measure))),
+        format!("DESCRIPTION:{}", escape_text(&format!("{} ({}). {}", a._type, a.points, a.notes))),
+        "TRANSP:TRANSPARENT".to_string(),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+    ];
+
+    lines.iter().map(|l| fold(l)).collect()
+}
+
+impl CalDavNotifier {
+    pub fn new(calendar_url: &str) -> CalDavNotifier {
+        CalDavNotifier {
+            calendar_url: calendar_url.trim_end_matches('/').to_string(),
+            credentials: None,
+        }
+    }
+
+    pub fn credentials(mut self, username: &str, password: &str) -> CalDavNotifier {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    fn resource_url(&self, a: &Assignment) -> String {
+        format!("{}/{}.ics", self.calendar_url, event_id(a))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.credentials {
+            Some((ref username, ref password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+
+    fn send(&self, client: &reqwest::blocking::Client, op: &CalendarOp) -> Result<(), NotifyError> {
+        let resp = match *op {
+            CalendarOp::Put(course, a) => {
+                self.authorize(client.request(Method::PUT, self.resource_url(a)))
+                    .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+                    .body(ical_event(course, a))
+                    .send()
+            }
+            CalendarOp::Delete(a) => {
+                self.authorize(client.request(Method::DELETE, self.resource_url(a)))
+                    .send()
+            }
+        }.map_err(NotifyError::HttpError)?;
+
+        let status = resp.status();
+        match *op {
+            // already gone is as good as deleted
+            CalendarOp::Delete(_) if status == reqwest::StatusCode::NOT_FOUND => Ok(()),
+            _ if status.is_success() => Ok(()),
+            _ => Err(NotifyError::HttpStatus(status)),
+        }
+    }
+}
+
+impl fmt::Debug for CalDavNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CalDavNotifier")
+            .field("calendar_url", &self.calendar_url)
+            .field("credentials", &self.credentials.as_ref().map(|(username, _)| (username, "<redacted>")))
+            .finish()
+    }
+}
+
+fn calendar_op<'a>(course: &'a Course, old: Option<&'a Assignment>, new: Option<&'a Assignment>,
+    changes: &[AssignmentChange]) -> Option<CalendarOp<'a>> {
+
+    let touches_event = changes.iter().any(|c| matches!(*c,
+        AssignmentChange::Added |
+        AssignmentChange::DueDateChange { .. } |
+        AssignmentChange::TitleChange { .. } |
+        AssignmentChange::ScoreChange { .. }));
+    if !touches_event {
+        return None;
+    }
+
+    match (old, new) {
+        (_, Some(a)) if a.score.is_graded() => Some(CalendarOp::Delete(a)),
+        (_, Some(a)) => Some(CalendarOp::Put(course, a)),
+        (Some(a), None) => Some(CalendarOp::Delete(a)),
+        (None, None) => None,
+    }
+}
+
+impl Notifier for CalDavNotifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let client = reqwest::blocking::Client::builder().build().map_err(NotifyError::HttpError)?;
+        let mut ops = Vec::new();
+
+        for ccs in rendered.changeset.changes.iter() {
+            let course = match ccs.new.as_ref().or(ccs.old.as_ref()) {
+                Some(c) => c,
+                None => { continue; }
+            };
+
+            for acs in ccs.assignment_changes.iter().flat_map(|acs| acs.iter()) {
+                // a graded assignment that was added (e.g. the whole course showed up) never
+                // needs an event in the first place
+                let already_graded = acs.old.is_none() && acs.new.as_ref().map(|a| a.score.is_graded()).unwrap_or(false);
+                if already_graded {
+                    continue;
+                }
+
+                if let Some(op) = calendar_op(course, acs.old.as_ref(), acs.new.as_ref(), &acs.changes) {
+                    ops.push(op);
+                }
+            }
+        }
+
+        let errors: Vec<NotifyError> = ops.iter()
+            .filter_map(|op| self.send(&client, op).err())
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifyError::Failed(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_the_password() {
+        let notifier = CalDavNotifier::new("https://dav.example.com/calendars/student/school/")
+            .credentials("student", "hunter2-secret");
+        let debug = format!("{:?}", notifier);
+
+        assert!(debug.contains("https://dav.example.com/calendars/student/school"));
+        assert!(debug.contains("student"));
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
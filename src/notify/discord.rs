@@ -0,0 +1,149 @@
+use std::fmt;
+
+use diff::{CourseChange, CourseChanges};
+use gradebook::Course;
+use export::json::{self, Object};
+use render::text;
+use super::{Notifier, NotifyError, RenderedChangeset};
+
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+
+// discord rejects messages with more than 10 embeds or embeds with more than 25 fields
+const MAX_EMBEDS: usize = 10;
+const MAX_FIELDS: usize = 25;
+
+const COLOR_DOWN: u32 = 0xe74c3c;
+const COLOR_NEUTRAL: u32 = 0x7289da;
+const COLOR_UP: u32 = 0x2ecc71;
+const COLOR_DROPPED: u32 = 0x95a5a6;
+
+#[derive(Clone)]
+pub struct DiscordNotifier {
+    webhook_url: String,
+    username: String,
+}
+
+fn color(ccs: &CourseChanges) -> u32 {
+    let dropped = ccs.changes.iter()
+        .flat_map(|cs| cs.iter())
+        .any(|c| matches!(*c, CourseChange::Dropped));
+    if dropped {
+        return COLOR_DROPPED;
+    }
+
+    let raw = |c: Option<&Course>| c.and_then(|c| c.official_mark()).map(|m| m.calculated_score_raw);
+    match (raw(ccs.old.as_ref()), raw(ccs.new.as_ref())) {
+        (Some(o), Some(n)) if n < o => COLOR_DOWN,
+        (Some(o), Some(n)) if n > o => COLOR_UP,
+        _ => COLOR_NEUTRAL,
+    }
+}
+
+fn field(name: &str, value: &str) -> String {
+    Object::new()
+        .field("name", json::string(name))
+        .field("value", json::string(value))
+        .field("inline", "false".to_string())
+        .build()
+}
+
+fn embed(ccs: &CourseChanges) -> Option<String> {
+    let course = match ccs.new.as_ref().or(ccs.old.as_ref()) {
+        Some(c) => c,
+        None => { return None; }
+    };
+
+    let course_fields = ccs.changes.iter()
+        .flat_map(|cs| cs.iter())
+        .map(|c| field("Course", &text::course_change(c)));
+    let assignment_fields = ccs.assignment_changes.iter()
+        .flat_map(|acs| acs.iter())
+        .map(|acs| {
+            let title = acs.new.as_ref().or(acs.old.as_ref()).map(|a| a.measure.as_str()).unwrap_or("");
+            let changes: Vec<String> = acs.changes.iter().map(text::assignment_change).collect();
+            field(title, &changes.join("\n"))
+        });
+    let fields: Vec<String> = course_fields.chain(assignment_fields).take(MAX_FIELDS).collect();
+
+    Some(Object::new()
+        .field("title", json::string(&course.title.to_string()))
+        .field("description", json::string(&format!("Period {} \u{b7} {}", course.period, course.staff)))
+        .field("color", color(ccs).to_string())
+        .field("fields", format!("[{}]", fields.join(",")))
+        .build())
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: &str) -> DiscordNotifier {
+        DiscordNotifier {
+            webhook_url: webhook_url.to_string(),
+            username: "rvue".to_string(),
+        }
+    }
+
+    pub fn username(mut self, username: &str) -> DiscordNotifier {
+        self.username = username.to_string();
+        self
+    }
+
+    fn payloads(&self, rendered: &RenderedChangeset) -> Vec<String> {
+        let embeds: Vec<String> = rendered.changeset.changes.iter().filter_map(embed).collect();
+        let content = match rendered.student {
+            Some(ref s) => json::string(&format!("Grade changes for **{}**", s)),
+            None => "null".to_string(),
+        };
+
+        embeds.chunks(MAX_EMBEDS)
+            .map(|chunk| {
+                Object::new()
+                    .field("username", json::string(&self.username))
+                    .field("content", content.clone())
+                    .field("embeds", format!("[{}]", chunk.join(",")))
+                    .build()
+            })
+            .collect()
+    }
+}
+
+// the webhook URL carries its own token, so anyone with it can post to the channel
+impl fmt::Debug for DiscordNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DiscordNotifier")
+            .field("webhook_url", &"<redacted>")
+            .field("username", &self.username)
+            .finish()
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let client = reqwest::blocking::Client::builder().build().map_err(NotifyError::HttpError)?;
+
+        for payload in self.payloads(rendered) {
+            let resp = client.post(&self.webhook_url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload)
+                .send()
+                .map_err(NotifyError::HttpError)?;
+
+            if !resp.status().is_success() {
+                return Err(NotifyError::HttpStatus(resp.status()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_the_webhook_url() {
+        let debug = format!("{:?}", DiscordNotifier::new("https://discord.example.org/api/webhooks/1/hunter2-secret"));
+
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
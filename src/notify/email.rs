@@ -0,0 +1,98 @@
+use std::fmt;
+
+use super::{Notifier, NotifyError, RenderedChangeset};
+
+use lettre::{Message, SmtpTransport, Transport};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub server: String,
+    pub port: u16,
+    // username and password
+    pub credentials: Option<(String, String)>,
+}
+
+// the password stays out of logs; `EmailNotifier`'s derived Debug goes through this too
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("server", &self.server)
+            .field("port", &self.port)
+            .field("credentials", &self.credentials.as_ref().map(|(user, _)| (user, "<redacted>")))
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmailNotifier {
+    from: String,
+    recipients: Vec<String>,
+    smtp: SmtpConfig,
+}
+
+impl EmailNotifier {
+    pub fn new(smtp: SmtpConfig, from: &str) -> EmailNotifier {
+        EmailNotifier {
+            from: from.to_string(),
+            recipients: Vec::new(),
+            smtp,
+        }
+    }
+
+    pub fn recipient(mut self, to: &str) -> EmailNotifier {
+        self.recipients.push(to.to_string());
+        self
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        if self.recipients.is_empty() {
+            return Ok(());
+        }
+
+        let mailbox = |address: &str| address.parse::<Mailbox>().map_err(NotifyError::EmailAddressError);
+
+        let mut builder = Message::builder()
+            .from(mailbox(&self.from)?)
+            .subject(rendered.subject.as_str());
+        for to in &self.recipients {
+            builder = builder.to(mailbox(to)?);
+        }
+        let email = builder.multipart(MultiPart::alternative_plain_html(rendered.text.clone(), rendered.html.clone()))
+            .map_err(NotifyError::EmailBuildError)?;
+
+        // STARTTLS, and refuse to send if the server doesn't offer it
+        let mut transport = SmtpTransport::starttls_relay(&self.smtp.server)
+            .map_err(NotifyError::SmtpError)?
+            .port(self.smtp.port);
+        if let Some((ref user, ref password)) = self.smtp.credentials {
+            transport = transport.credentials(Credentials::new(user.clone(), password.clone()));
+        }
+
+        transport.build()
+            .send(&email)
+            .map(|_| ())
+            .map_err(NotifyError::SmtpError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_the_smtp_password() {
+        let smtp = SmtpConfig {
+            server: "smtp.example.com".to_string(),
+            port: 587,
+            credentials: Some(("parent@example.com".to_string(), "hunter2-secret".to_string())),
+        };
+        let debug = format!("{:?}", EmailNotifier::new(smtp, "rvue@example.com"));
+
+        assert!(debug.contains("parent@example.com"));
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
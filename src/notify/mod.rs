@@ -0,0 +1,676 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use clock::{Clock, SystemClock, Zone};
+use diff::{Changeset, HouseholdChangeset};
+use render;
+use render::i18n::{self, Strings};
+use render::template::{self, ChangesetTemplate};
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, Utc};
+#[cfg(feature="tz")]
+use chrono_tz::Tz;
+
+#[cfg(feature="email")]
+use lettre;
+#[cfg(feature="client")]
+use reqwest;
+
+#[cfg(feature="caldav")]
+pub mod caldav;
+#[cfg(feature="client")]
+pub mod discord;
+#[cfg(feature="email")]
+pub mod email;
+#[cfg(feature="pushover")]
+pub mod pushover;
+#[cfg(feature="telegram")]
+pub mod telegram;
+#[cfg(feature="webhook")]
+pub mod webhook;
+
+#[derive(Clone, Debug)]
+pub struct RenderedChangeset {
+    pub changeset: Changeset,
+    pub html: String,
+    // the language and template it was rendered with, so it can be rendered again the same way
+    pub language: String,
+    pub template: ChangesetTemplate,
+    // set for household setups so sinks can tell children apart
+    pub student: Option<String>,
+    pub subject: String,
+    pub text: String,
+}
+
+impl RenderedChangeset {
+    pub fn render(changeset: Changeset) -> RenderedChangeset {
+        Self::render_in(changeset, &i18n::english())
+    }
+
+    pub fn render_in(changeset: Changeset, strings: &Strings) -> RenderedChangeset {
+        Self::render_with(changeset, template::text(), strings)
+    }
+
+    // the text and subject from `template` instead of the built-in wording
+    pub fn render_with(changeset: Changeset, template: ChangesetTemplate, strings: &Strings) -> RenderedChangeset {
+        RenderedChangeset {
+            html: render::html::changeset_in(&changeset, None, strings),
+            language: strings.name.clone(),
+            student: None,
+            subject: template.subject_for(&changeset, strings),
+            text: template.changeset(&changeset, None, strings),
+            template,
+            changeset,
+        }
+    }
+
+    pub fn render_for_student(student: &str, changeset: Changeset) -> RenderedChangeset {
+        Self::render_for_student_in(student, changeset, &i18n::english())
+    }
+
+    pub fn render_for_student_in(student: &str, changeset: Changeset, strings: &Strings) -> RenderedChangeset {
+        Self::render_for_student_with(student, changeset, template::text(), strings)
+    }
+
+    pub fn render_for_student_with(student: &str, changeset: Changeset, template: ChangesetTemplate,
+                                   strings: &Strings) -> RenderedChangeset {
+        let mut rendered = Self::render_with(changeset, template, strings);
+        rendered.subject = format!("[{}] {}", student, rendered.subject);
+        rendered.student = Some(student.to_string());
+        rendered
+    }
+}
+
+#[derive(Debug)]
+pub enum NotifyError {
+    // held back by `Throttled`; nothing was sent, and trying again later may succeed
+    Deferred,
+    #[cfg(feature="email")]
+    EmailAddressError(lettre::address::AddressError),
+    #[cfg(feature="email")]
+    EmailBuildError(lettre::error::Error),
+    Failed(Vec<NotifyError>),
+    #[cfg(feature="client")]
+    HttpError(reqwest::Error),
+    #[cfg(feature="client")]
+    HttpStatus(reqwest::StatusCode),
+    Io(io::Error),
+    #[cfg(feature="email")]
+    SmtpError(lettre::transport::smtp::Error),
+}
+
+pub trait Notifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError>;
+
+    // how many sinks this sends to; a `FanOut` has one per notifier it was given. The watcher
+    // keeps track of what each sink has been sent, so when only some of them fail, the retry goes
+    // to those alone
+    fn sink_count(&self) -> usize {
+        1
+    }
+
+    // sends `rendered[i]` to sink `i`, skipping the Nones, and returns each sink's result in the
+    // same order
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        rendered.iter()
+            .map(|r| match *r {
+                Some(ref r) => self.notify(r),
+                None => Ok(()),
+            })
+            .collect()
+    }
+}
+
+impl<N: Notifier + ?Sized> Notifier for &N {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        (**self).notify(rendered)
+    }
+
+    fn sink_count(&self) -> usize {
+        (**self).sink_count()
+    }
+
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        (**self).notify_sinks(rendered)
+    }
+}
+
+impl<N: Notifier + ?Sized> Notifier for Box<N> {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        (**self).notify(rendered)
+    }
+
+    fn sink_count(&self) -> usize {
+        (**self).sink_count()
+    }
+
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        (**self).notify_sinks(rendered)
+    }
+}
+
+// lets one notifier be shared, e.g. by a household's watchers
+impl<N: Notifier + ?Sized> Notifier for Arc<N> {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        (**self).notify(rendered)
+    }
+
+    fn sink_count(&self) -> usize {
+        (**self).sink_count()
+    }
+
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        (**self).notify_sinks(rendered)
+    }
+}
+
+// one notification per child, each with the child's name in the subject
+pub fn notify_household<N: Notifier>(notifier: &N, household: &HouseholdChangeset) -> Result<(), NotifyError> {
+    let errors: Vec<NotifyError> = household.changesets.iter()
+        .map(|sc| RenderedChangeset::render_for_student(&sc.student, sc.changeset.clone()))
+        .filter_map(|r| notifier.notify(&r).err())
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(NotifyError::Failed(errors))
+    }
+}
+
+// delivers to every sink even if some of them fail; the failures are collected into
+// `NotifyError::Failed`
+pub struct FanOut {
+    sinks: Vec<Box<dyn Notifier>>,
+}
+
+impl Default for FanOut {
+    fn default() -> FanOut {
+        FanOut::new()
+    }
+}
+
+impl FanOut {
+    pub fn new() -> FanOut {
+        FanOut {
+            sinks: Vec::new(),
+        }
+    }
+
+    pub fn sink<N: Notifier + 'static>(mut self, sink: N) -> FanOut {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+impl Notifier for FanOut {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let errors: Vec<NotifyError> = self.sinks.iter()
+            .filter_map(|s| s.notify(rendered).err())
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifyError::Failed(errors))
+        }
+    }
+
+    fn sink_count(&self) -> usize {
+        self.sinks.len()
+    }
+
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        self.sinks.iter()
+            .zip(rendered)
+            .map(|(s, r)| match *r {
+                Some(ref r) => s.notify(r),
+                None => Ok(()),
+            })
+            .collect()
+    }
+}
+
+// the filter can narrow the changeset down (e.g. to certain courses) or reject it outright by
+// returning None; a narrowed changeset is re-rendered before it reaches the sink
+pub struct Filtered<N, F> {
+    sink: N,
+    filter: F,
+}
+
+impl<N, F> Filtered<N, F>
+    where N: Notifier,
+          F: Fn(&Changeset) -> Option<Changeset> {
+
+    pub fn new(sink: N, filter: F) -> Filtered<N, F> {
+        Filtered {
+            sink,
+            filter,
+        }
+    }
+
+    fn filter(&self, rendered: &RenderedChangeset) -> Option<RenderedChangeset> {
+        (self.filter)(&rendered.changeset).map(|changeset| {
+            let strings = i18n::for_language(&rendered.language);
+            let template = rendered.template.clone();
+            match rendered.student {
+                Some(ref student) => RenderedChangeset::render_for_student_with(student, changeset, template, &strings),
+                None => RenderedChangeset::render_with(changeset, template, &strings),
+            }
+        })
+    }
+}
+
+impl<N, F> Notifier for Filtered<N, F>
+    where N: Notifier,
+          F: Fn(&Changeset) -> Option<Changeset> {
+
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        match self.filter(rendered) {
+            Some(filtered) => self.sink.notify(&filtered),
+            None => Ok(()),
+        }
+    }
+
+    fn sink_count(&self) -> usize {
+        self.sink.sink_count()
+    }
+
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        let filtered: Vec<Option<RenderedChangeset>> = rendered.iter()
+            .map(|r| r.as_ref().and_then(|r| self.filter(r)))
+            .collect();
+
+        self.sink.notify_sinks(&filtered)
+    }
+}
+
+// writes each notification out instead of sending it, for trying out filters and templates (see
+// `Watcher::dry_run`)
+pub struct Printer<W> {
+    out: Mutex<W>,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W) -> Printer<W> {
+        Printer {
+            out: Mutex::new(out),
+        }
+    }
+}
+
+impl Printer<io::Stdout> {
+    pub fn stdout() -> Printer<io::Stdout> {
+        Printer::new(io::stdout())
+    }
+}
+
+impl<W: Write> Notifier for Printer<W> {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let mut out = match self.out.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        writeln!(out, "Subject: {}\n\n{}", rendered.subject, rendered.text.trim_end())
+            .and_then(|_| writeln!(out))
+            .and_then(|_| out.flush())
+            .map_err(NotifyError::Io)
+    }
+}
+
+// no notifications from `start` until `end`, e.g. 22:00 to 07:00; the times are in UTC unless
+// given an offset or (with the `tz` feature) a time zone
+#[derive(Clone, Copy, Debug)]
+pub struct QuietHours {
+    end: NaiveTime,
+    start: NaiveTime,
+    zone: Zone,
+}
+
+impl QuietHours {
+    pub fn new(start: NaiveTime, end: NaiveTime) -> QuietHours {
+        QuietHours {
+            end,
+            start,
+            zone: Zone::utc(),
+        }
+    }
+
+    pub fn utc_offset(mut self, offset: FixedOffset) -> QuietHours {
+        self.zone = Zone::Fixed(offset);
+        self
+    }
+
+    // follows daylight saving time, unlike `utc_offset`
+    #[cfg(feature="tz")]
+    pub fn time_zone(mut self, tz: Tz) -> QuietHours {
+        self.zone = Zone::Named(tz);
+        self
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = self.zone.local(at).time();
+
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            // wraps past midnight
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+// holds notifications back during quiet hours and once `max_per_hour` have gone out in the last
+// hour, failing with `NotifyError::Deferred` instead. The watcher keeps deferred changes for its
+// next digest, or without one sends them (merged with anything newer) once a later poll gets
+// through. Wrap the whole notifier rather than single sinks of a `FanOut`, so a notification is
+// either sent everywhere or deferred everywhere.
+pub struct Throttled<N> {
+    clock: Box<dyn Clock + Send + Sync>,
+    max_per_hour: Option<usize>,
+    quiet_hours: Option<QuietHours>,
+    sent: Mutex<VecDeque<DateTime<Utc>>>,
+    sink: N,
+}
+
+impl<N: Notifier> Throttled<N> {
+    pub fn new(sink: N) -> Throttled<N> {
+        Throttled {
+            clock: Box::new(SystemClock),
+            max_per_hour: None,
+            quiet_hours: None,
+            sent: Mutex::new(VecDeque::new()),
+            sink,
+        }
+    }
+
+    pub fn quiet_hours(mut self, quiet_hours: QuietHours) -> Throttled<N> {
+        self.quiet_hours = Some(quiet_hours);
+        self
+    }
+
+    pub fn max_per_hour(mut self, max: usize) -> Throttled<N> {
+        self.max_per_hour = Some(max);
+        self
+    }
+
+    pub fn clock<C: Clock + Send + Sync + 'static>(mut self, clock: C) -> Throttled<N> {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    // decides once for the whole notification, then sends it through `send` and counts it if any
+    // of it went out
+    fn throttle<T, F>(&self, send: F) -> Result<T, NotifyError>
+        where F: FnOnce() -> (T, bool) {
+
+        let now = self.clock.now();
+
+        if self.quiet_hours.map(|q| q.contains(now)).unwrap_or(false) {
+            return Err(NotifyError::Deferred);
+        }
+
+        // a panic while holding the lock can't leave the queue half-written
+        let mut sent = match self.sent.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let hour_ago = now - Duration::hours(1);
+        while sent.front().map(|&t| t <= hour_ago).unwrap_or(false) {
+            sent.pop_front();
+        }
+
+        match self.max_per_hour {
+            Some(max) if sent.len() >= max => { return Err(NotifyError::Deferred); }
+            _ => {}
+        }
+
+        let (result, went_out) = send();
+        if went_out {
+            sent.push_back(now);
+        }
+
+        Ok(result)
+    }
+}
+
+impl<N: Notifier> Notifier for Throttled<N> {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        self.throttle(|| {
+            let result = self.sink.notify(rendered);
+            let went_out = result.is_ok();
+            (result, went_out)
+        })?
+    }
+
+    fn sink_count(&self) -> usize {
+        self.sink.sink_count()
+    }
+
+    // deferred everywhere or sent everywhere, as with `notify`
+    fn notify_sinks(&self, rendered: &[Option<RenderedChangeset>]) -> Vec<Result<(), NotifyError>> {
+        let sent = self.throttle(|| {
+            let results = self.sink.notify_sinks(rendered);
+            let went_out = results.iter().zip(rendered).any(|(r, s)| r.is_ok() && s.is_some());
+            (results, went_out)
+        });
+
+        match sent {
+            Ok(results) => results,
+            Err(_) => rendered.iter().map(|_| Err(NotifyError::Deferred)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use clock::FixedClock;
+    use diff::CourseChanges;
+    use gradebook::{AssignmentScore, Gradebook};
+
+    use chrono::TimeZone;
+
+    // remembers the subject of everything it's sent, or fails every time
+    #[derive(Clone, Default)]
+    struct Recorder {
+        fails: bool,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn failing() -> Recorder {
+            Recorder {
+                fails: true,
+                ..Default::default()
+            }
+        }
+
+        fn sent(&self) -> Vec<String> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    impl Notifier for Recorder {
+        fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+            if self.fails {
+                return Err(NotifyError::Io(io::Error::other("unreachable")));
+            }
+            self.sent.lock().unwrap().push(rendered.subject.clone());
+            Ok(())
+        }
+    }
+
+    // a clock the test moves forward by hand
+    #[derive(Clone)]
+    struct SteppedClock(Arc<Mutex<DateTime<Utc>>>);
+
+    impl SteppedClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for SteppedClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    // a change to each of the fixture's two courses: an English essay regraded, and chemistry
+    // moved to another lab
+    fn rendered() -> RenderedChangeset {
+        let old = Gradebook::from_xml(include_str!("../../fixtures/gradebook_no_standards.xml")).unwrap();
+        let mut new = old.clone();
+        new.courses[0].marks[0].assignments[0].score = AssignmentScore::Percentage(12.5);
+        new.courses[1].room = "Lab 3".to_string();
+
+        RenderedChangeset::render(Changeset::diff(&old, &new).unwrap())
+    }
+
+    fn course_titles(changeset: &Changeset) -> Vec<String> {
+        changeset.changes.iter()
+            .filter_map(|ccs| ccs.new.as_ref().map(|c| c.title.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fans_out_past_a_failing_sink() {
+        let (first, last) = (Recorder::default(), Recorder::default());
+        let fan_out = FanOut::new().sink(first.clone()).sink(Recorder::failing()).sink(last.clone());
+        let rendered = rendered();
+
+        match fan_out.notify(&rendered) {
+            Err(NotifyError::Failed(ref errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("expected the one failure, got {:?}", other),
+        }
+        assert_eq!(first.sent(), vec![rendered.subject.clone()]);
+        assert_eq!(last.sent(), vec![rendered.subject.clone()]);
+    }
+
+    #[test]
+    fn reports_each_sinks_result_in_order() {
+        let (first, last) = (Recorder::default(), Recorder::default());
+        let fan_out = FanOut::new().sink(first.clone()).sink(Recorder::failing()).sink(last.clone());
+        let rendered = rendered();
+
+        let results = fan_out.notify_sinks(&[Some(rendered.clone()), Some(rendered.clone()), None]);
+        assert_eq!(fan_out.sink_count(), 3);
+        assert_eq!(results.iter().map(|r| r.is_ok()).collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(first.sent().len(), 1);
+        // skipped rather than sent
+        assert!(last.sent().is_empty());
+    }
+
+    #[test]
+    fn filters_per_sink() {
+        let (everything, english, nothing) = (Recorder::default(), Recorder::default(), Recorder::default());
+        let only_english = |c: &Changeset| {
+            let changes: Vec<CourseChanges> = c.changes.iter()
+                .filter(|ccs| ccs.new.as_ref().map(|c| c.title.to_string().contains("English")).unwrap_or(false))
+                .cloned()
+                .collect();
+            if changes.is_empty() {
+                None
+            } else {
+                Some(Changeset {
+                    changes,
+                    ..c.clone()
+                })
+            }
+        };
+        let fan_out = FanOut::new()
+            .sink(everything.clone())
+            .sink(Filtered::new(english.clone(), only_english))
+            .sink(Filtered::new(nothing.clone(), |_: &Changeset| None));
+        let rendered = rendered();
+        assert_eq!(course_titles(&rendered.changeset).len(), 2);
+
+        assert!(fan_out.notify(&rendered).is_ok());
+        assert_eq!(everything.sent(), vec![rendered.subject.clone()]);
+        assert!(nothing.sent().is_empty());
+
+        // narrowed, and rendered again for what's left
+        let narrowed = only_english(&rendered.changeset).unwrap();
+        assert_eq!(course_titles(&narrowed).len(), 1);
+        assert_eq!(english.sent(), vec![RenderedChangeset::render(narrowed).subject]);
+    }
+
+    #[test]
+    fn defers_past_the_hourly_cap_until_the_hour_is_up() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let clock = SteppedClock(Arc::new(Mutex::new(start)));
+        let sink = Recorder::default();
+        let throttled = Throttled::new(sink.clone()).max_per_hour(2).clock(clock.clone());
+        let rendered = rendered();
+
+        assert!(throttled.notify(&rendered).is_ok());
+        clock.advance(Duration::minutes(10));
+        assert!(throttled.notify(&rendered).is_ok());
+        clock.advance(Duration::minutes(10));
+        assert!(matches!(throttled.notify(&rendered), Err(NotifyError::Deferred)));
+        assert_eq!(sink.sent().len(), 2);
+
+        // an hour after the first went out, there's room for one more
+        clock.advance(Duration::minutes(40));
+        assert!(throttled.notify(&rendered).is_ok());
+        assert!(matches!(throttled.notify(&rendered), Err(NotifyError::Deferred)));
+        clock.advance(Duration::minutes(10));
+        assert!(throttled.notify(&rendered).is_ok());
+        assert_eq!(sink.sent().len(), 4);
+    }
+
+    #[test]
+    fn defers_every_sink_together() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let sink = Recorder::default();
+        let throttled = Throttled::new(FanOut::new().sink(sink.clone()).sink(Recorder::failing()))
+            .max_per_hour(1)
+            .clock(FixedClock(start));
+        let rendered = vec![Some(rendered()), Some(rendered())];
+
+        // one sink took it, so it counts against the cap
+        let results = throttled.notify_sinks(&rendered);
+        assert!(results[0].is_ok() && results[1].is_err());
+        assert!(throttled.notify_sinks(&rendered).iter().all(|r| matches!(*r, Err(NotifyError::Deferred))));
+        assert_eq!(sink.sent().len(), 1);
+    }
+
+    #[test]
+    fn does_not_count_a_notification_that_failed_everywhere() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let throttled = Throttled::new(Recorder::failing()).max_per_hour(1).clock(FixedClock(start));
+        let rendered = rendered();
+
+        assert!(matches!(throttled.notify(&rendered), Err(NotifyError::Io(_))));
+        assert!(matches!(throttled.notify(&rendered), Err(NotifyError::Io(_))));
+    }
+
+    fn overnight() -> QuietHours {
+        QuietHours::new(NaiveTime::from_hms_opt(22, 0, 0).unwrap(), NaiveTime::from_hms_opt(7, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn wraps_quiet_hours_past_midnight() {
+        let quiet = overnight().utc_offset(FixedOffset::west_opt(5 * 3600).unwrap());
+
+        assert!(quiet.contains(Utc.with_ymd_and_hms(2026, 1, 15, 4, 0, 0).unwrap()));
+        assert!(!quiet.contains(Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap()));
+    }
+
+    #[cfg(feature="tz")]
+    #[test]
+    fn follows_daylight_saving_time_in_a_time_zone() {
+        let fixed = overnight().utc_offset(FixedOffset::west_opt(5 * 3600).unwrap());
+        let zoned = overnight().time_zone(chrono_tz::America::New_York);
+        // 7:30 EDT, but 6:30 at New York's standard offset
+        let at = Utc.with_ymd_and_hms(2026, 7, 1, 11, 30, 0).unwrap();
+
+        assert!(fixed.contains(at));
+        assert!(!zoned.contains(at));
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use super::{Notifier, NotifyError, RenderedChangeset};
+
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+
+const API_URL: &str = "https://api.pushover.net/1/messages.json";
+// pushover truncates anything longer server side; do it here so we control where it's cut
+const MAX_MESSAGE_LEN: usize = 1024;
+const MAX_TITLE_LEN: usize = 250;
+
+#[derive(Clone)]
+pub struct PushoverNotifier {
+    app_token: String,
+    user_key: String,
+    device: Option<String>,
+    priority: i8,
+}
+
+fn url_encode(s: &str) -> String {
+    s.bytes().fold(String::with_capacity(s.len()), |mut acc, b| {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => acc.push(b as char),
+            b' ' => acc.push('+'),
+            b => acc.push_str(&format!("%{:02X}", b)),
+        }
+        acc
+    })
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() > max {
+        let mut t: String = s.chars().take(max - 1).collect();
+        t.push('\u{2026}');
+        t
+    } else {
+        s.to_string()
+    }
+}
+
+impl PushoverNotifier {
+    pub fn new(app_token: &str, user_key: &str) -> PushoverNotifier {
+        PushoverNotifier {
+            app_token: app_token.to_string(),
+            user_key: user_key.to_string(),
+            device: None,
+            priority: 0,
+        }
+    }
+
+    pub fn device(mut self, device: &str) -> PushoverNotifier {
+        self.device = Some(device.to_string());
+        self
+    }
+
+    // pushover priorities range from -2 (silent) to 2 (emergency), but emergency needs retry and
+    // expiry parameters we don't send, so cap at 1
+    pub fn priority(mut self, priority: i8) -> PushoverNotifier {
+        self.priority = priority.clamp(-2, 1);
+        self
+    }
+}
+
+// the app token is all it takes to send as the app, so it stays out of logs
+impl fmt::Debug for PushoverNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PushoverNotifier")
+            .field("app_token", &"<redacted>")
+            .field("user_key", &self.user_key)
+            .field("device", &self.device)
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
+impl Notifier for PushoverNotifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let mut params = vec![
+            ("token", self.app_token.clone()),
+            ("user", self.user_key.clone()),
+            ("title", truncate(&rendered.subject, MAX_TITLE_LEN)),
+            ("message", truncate(&rendered.text, MAX_MESSAGE_LEN)),
+            ("priority", self.priority.to_string()),
+        ];
+        if let Some(ref device) = self.device {
+            params.push(("device", device.clone()));
+        }
+
+        let body: Vec<String> = params.iter()
+            .map(|&(k, ref v)| format!("{}={}", k, url_encode(v)))
+            .collect();
+
+        let client = reqwest::blocking::Client::builder().build().map_err(NotifyError::HttpError)?;
+        let resp = client.post(API_URL)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body.join("&"))
+            .send()
+            .map_err(NotifyError::HttpError)?;
+
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(NotifyError::HttpStatus(resp.status()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_the_app_token() {
+        let debug = format!("{:?}", PushoverNotifier::new("hunter2-secret", "uQiRzpo4DXghDmr9QzzfQu27cmVRsG"));
+
+        assert!(debug.contains("uQiRzpo4DXghDmr9QzzfQu27cmVRsG"));
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
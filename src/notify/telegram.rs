@@ -0,0 +1,105 @@
+use std::fmt;
+
+use export::json::{self, Object};
+use super::{Notifier, NotifyError, RenderedChangeset};
+
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+
+const API_BASE: &str = "https://api.telegram.org";
+// telegram's limit for a single message
+const MAX_MESSAGE_LEN: usize = 4096;
+
+#[derive(Clone)]
+pub struct TelegramNotifier {
+    bot_token: String,
+    chat_id: String,
+}
+
+// splits on line boundaries where possible so a course's changes aren't cut mid-line
+fn chunk_message(message: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in message.lines() {
+        if !current.is_empty() && current.chars().count() + line.chars().count() + 1 > max {
+            chunks.push(current);
+            current = String::new();
+        }
+
+        let mut line: String = line.to_string();
+        while line.chars().count() > max {
+            let rest = line.chars().skip(max).collect();
+            chunks.push(line.chars().take(max).collect());
+            line = rest;
+        }
+
+        current.push_str(&line);
+        current.push('\n');
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: &str, chat_id: &str) -> TelegramNotifier {
+        TelegramNotifier {
+            bot_token: bot_token.to_string(),
+            chat_id: chat_id.to_string(),
+        }
+    }
+}
+
+// the bot token is all it takes to send as the bot, so it stays out of logs
+impl fmt::Debug for TelegramNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TelegramNotifier")
+            .field("bot_token", &"<redacted>")
+            .field("chat_id", &self.chat_id)
+            .finish()
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let client = reqwest::blocking::Client::builder().build().map_err(NotifyError::HttpError)?;
+        let url = format!("{}/bot{}/sendMessage", API_BASE, self.bot_token);
+        let message = format!("{}\n\n{}", rendered.subject, rendered.text);
+
+        for chunk in chunk_message(&message, MAX_MESSAGE_LEN) {
+            let body = Object::new()
+                .field("chat_id", json::string(&self.chat_id))
+                .field("text", json::string(&chunk))
+                .field("disable_web_page_preview", "true".to_string())
+                .build();
+
+            let resp = client.post(&url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .map_err(NotifyError::HttpError)?;
+
+            if !resp.status().is_success() {
+                return Err(NotifyError::HttpStatus(resp.status()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_the_bot_token() {
+        let debug = format!("{:?}", TelegramNotifier::new("123456:hunter2-secret", "-100200300"));
+
+        assert!(debug.contains("-100200300"));
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use export::json::{self, Object, ToJson};
+use super::{Notifier, NotifyError, RenderedChangeset};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+use sha2::Sha256;
+
+const SIGNATURE_HEADER: &str = "X-Rvue-Signature";
+const TIMESTAMP_HEADER: &str = "X-Rvue-Timestamp";
+// how far a delivery's timestamp may be from the receiver's clock before `verify` turns it down
+pub const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    url: String,
+    secret: Option<String>,
+    attempts: u32,
+    retry_delay: Duration,
+    // built on the first delivery and reused for every one after it
+    http: Arc<Mutex<Option<reqwest::blocking::Client>>>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// hex-encoded HMAC-SHA256 of the request body, so receivers can check the payload came from
+// someone holding the shared secret
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(payload);
+    to_hex(&mac.finalize().into_bytes())
+}
+
+// what goes in the signature header: the HMAC of the timestamp header and the body joined by a
+// '.', so a captured delivery can't be replayed later under a fresh timestamp
+pub fn signature(secret: &str, timestamp: i64, payload: &str) -> String {
+    format!("sha256={}", sign(secret, format!("{}.{}", timestamp, payload).as_bytes()))
+}
+
+// for receivers: true if `signature` is `payload`'s at `timestamp`, and `timestamp` is within
+// `SIGNATURE_TOLERANCE_SECS` of `now`
+pub fn verify(secret: &str, timestamp: i64, payload: &str, signature_header: &str, now: i64) -> bool {
+    let expected = signature(secret, timestamp, payload);
+
+    // compared in full, rather than stopping at the first difference
+    let same = expected.len() == signature_header.len() &&
+        expected.bytes().zip(signature_header.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0;
+    same && (now - timestamp).abs() <= SIGNATURE_TOLERANCE_SECS
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> WebhookNotifier {
+        WebhookNotifier {
+            url: url.to_string(),
+            secret: None,
+            attempts: 3,
+            retry_delay: Duration::from_secs(2),
+            http: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn secret(mut self, secret: &str) -> WebhookNotifier {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    // attempts includes the first try, so 1 means no retries
+    pub fn retries(mut self, attempts: u32, delay: Duration) -> WebhookNotifier {
+        self.attempts = if attempts == 0 { 1 } else { attempts };
+        self.retry_delay = delay;
+        self
+    }
+
+    fn payload(rendered: &RenderedChangeset) -> String {
+        Object::new()
+            .field("student", json::optional(rendered.student.as_ref(), |s| json::string(s)))
+            .field("subject", json::string(&rendered.subject))
+            .field("text", json::string(&rendered.text))
+            .field("changeset", rendered.changeset.to_json())
+            .build()
+    }
+
+    fn http(&self) -> Result<reqwest::blocking::Client, NotifyError> {
+        let mut http = match self.http.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if http.is_none() {
+            *http = Some(reqwest::blocking::Client::builder().build().map_err(NotifyError::HttpError)?);
+        }
+
+        Ok(http.as_ref().unwrap().clone())
+    }
+
+    fn post(&self, client: &reqwest::blocking::Client, payload: &str) -> Result<(), NotifyError> {
+        let mut request = client.post(&self.url)
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(ref secret) = self.secret {
+            // each attempt is signed afresh, so a retry isn't turned down as stale
+            let timestamp = Utc::now().timestamp();
+            request = request.header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, signature(secret, timestamp, payload));
+        }
+
+        let resp = request
+            .body(payload.to_string())
+            .send()
+            .map_err(NotifyError::HttpError)?;
+
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(NotifyError::HttpStatus(resp.status()))
+        }
+    }
+}
+
+impl fmt::Debug for WebhookNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebhookNotifier")
+            .field("url", &self.url)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("attempts", &self.attempts)
+            .field("retry_delay", &self.retry_delay)
+            .finish()
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+        let client = self.http()?;
+        let payload = Self::payload(rendered);
+        let mut errors = Vec::new();
+
+        for attempt in 0..self.attempts {
+            if attempt > 0 {
+                thread::sleep(self.retry_delay * attempt);
+            }
+
+            match self.post(&client, &payload) {
+                Ok(()) => { return Ok(()); }
+                Err(e) => { errors.push(e); }
+            }
+        }
+
+        Err(NotifyError::Failed(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn signs_the_timestamp_with_the_body() {
+        let payload = r#"{"subject":"1 change"}"#;
+
+        assert_eq!(signature("key", 1700000000, payload),
+            format!("sha256={}", sign("key", br#"1700000000.{"subject":"1 change"}"#)));
+        assert_ne!(signature("key", 1700000000, payload), signature("key", 1700000001, payload));
+    }
+
+    #[test]
+    fn verifies_only_fresh_untampered_deliveries() {
+        let payload = r#"{"subject":"1 change"}"#;
+        let sig = signature("key", 1700000000, payload);
+
+        assert!(verify("key", 1700000000, payload, &sig, 1700000000 + SIGNATURE_TOLERANCE_SECS));
+        // replayed later
+        assert!(!verify("key", 1700000000, payload, &sig, 1700000000 + SIGNATURE_TOLERANCE_SECS + 1));
+        // replayed under a new timestamp
+        assert!(!verify("key", 1700000100, payload, &sig, 1700000100));
+        assert!(!verify("key", 1700000000, r#"{"subject":"2 changes"}"#, &sig, 1700000000));
+        assert!(!verify("other", 1700000000, payload, &sig, 1700000000));
+    }
+
+    #[test]
+    fn debug_redacts_the_secret() {
+        let debug = format!("{:?}", WebhookNotifier::new("https://example.com/hook").secret("hunter2-secret"));
+
+        assert!(debug.contains("https://example.com/hook"));
+        assert!(!debug.contains("hunter2-secret"));
+    }
+}
@@ -0,0 +1,226 @@
+// Moving between reporting periods. Every gradebook response lists all of the year's periods
+// along with the one it's for, so a single fetch is enough to know where the others are; each
+// step after that is one more fetch.
+use api::{SVUEClient, SVUERequestError};
+use gradebook::{Gradebook, ReportPeriod, ReportingPeriod};
+
+// the gradebook for `period` (or the district's current one, for None) along with the period list
+// and the period it's for, all from the same response
+pub fn retrieve_with_periods(client: &SVUEClient, period: Option<i8>)
+    -> Result<(Gradebook, Vec<ReportPeriod>, ReportingPeriod), SVUERequestError> {
+
+    let gradebook = Gradebook::retrieve_with(client, period)?;
+    let periods = gradebook.reporting_periods.clone();
+    let current = gradebook.reporting_period.clone();
+
+    Ok((gradebook, periods, current))
+}
+
+pub struct PeriodNavigator<'a> {
+    client: &'a SVUEClient,
+    gradebook: Gradebook,
+    // from the first fetch, in case a later response leaves the list out
+    periods: Vec<ReportPeriod>,
+}
+
+impl<'a> PeriodNavigator<'a> {
+    pub fn open(client: &'a SVUEClient, period: Option<i8>) -> Result<PeriodNavigator<'a>, SVUERequestError> {
+        let (gradebook, mut periods, _) = retrieve_with_periods(client, period)?;
+        periods.sort_by_key(|p| p.index);
+
+        Ok(PeriodNavigator {
+            client,
+            gradebook,
+            periods,
+        })
+    }
+
+    pub fn gradebook(&self) -> &Gradebook {
+        &self.gradebook
+    }
+
+    pub fn into_gradebook(self) -> Gradebook {
+        self.gradebook
+    }
+
+    // oldest first
+    pub fn periods(&self) -> &[ReportPeriod] {
+        &self.periods
+    }
+
+    pub fn current(&self) -> &ReportingPeriod {
+        &self.gradebook.reporting_period
+    }
+
+    pub fn index(&self) -> Option<i8> {
+        self.periods.iter()
+            .find(|p| p.is(&self.gradebook.reporting_period))
+            .map(|p| p.index)
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.neighbor(true).is_some()
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.neighbor(false).is_some()
+    }
+
+    // fetches the following period; Ok(None) (without fetching) when this is the last one
+    pub fn next_period(&mut self) -> Result<Option<&Gradebook>, SVUERequestError> {
+        match self.neighbor(true) {
+            Some(index) => self.go_to(index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn previous_period(&mut self) -> Result<Option<&Gradebook>, SVUERequestError> {
+        match self.neighbor(false) {
+            Some(index) => self.go_to(index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // on error the navigator stays on the period it was on
+    pub fn go_to(&mut self, index: i8) -> Result<&Gradebook, SVUERequestError> {
+        let gradebook = Gradebook::retrieve_with(self.client, Some(index))?;
+
+        if self.periods.is_empty() {
+            self.periods = gradebook.reporting_periods.clone();
+            self.periods.sort_by_key(|p| p.index);
+        }
+        self.gradebook = gradebook;
+
+        Ok(&self.gradebook)
+    }
+
+    fn neighbor(&self, forward: bool) -> Option<i8> {
+        // without knowing where we are there's nothing to be next to
+        let index = match self.index() {
+            Some(i) => i,
+            None => { return None; }
+        };
+
+        if forward {
+            self.periods.iter().map(|p| p.index).filter(|&i| i > index).min()
+        } else {
+            self.periods.iter().map(|p| p.index).filter(|&i| i < index).max()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use transport::{Transport, TransportResponse};
+
+    use xml::escape::escape_str_pcdata;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+    const FIXTURE_PERIODS: &str = "  <ReportingPeriods>
+    <ReportPeriod Index=\"0\" GradePeriod=\"Quarter 1\" StartDate=\"9/4/2018\" EndDate=\"11/9/2018\" />
+  </ReportingPeriods>
+  <ReportingPeriod GradePeriod=\"Quarter 1\" StartDate=\"9/4/2018\" EndDate=\"11/9/2018\" />";
+    const PERIODS: &[(&str, &str, &str)] = &[
+        ("Quarter 1", "9/4/2018", "11/9/2018"),
+        ("Quarter 2", "11/12/2018", "1/25/2019"),
+        ("Quarter 3", "1/28/2019", "4/5/2019"),
+    ];
+
+    // answers a gradebook request for the period it asks for, the second one when it asks for
+    // none, and `current` instead when that's set; keeps track of the periods asked for
+    struct Periods {
+        asked: Arc<Mutex<Vec<Option<i8>>>>,
+        current: Option<(&'static str, &'static str, &'static str)>,
+    }
+
+    impl Transport for Periods {
+        fn post_soap(&self, _: &str, body: Vec<u8>) -> Result<TransportResponse, SVUERequestError> {
+            let body = String::from_utf8(body).unwrap();
+            // `paramStr` is escaped, so the element shows up as `&lt;ReportPeriod&gt;1&lt;...`
+            let asked = body.find("ReportPeriod&gt;").map(|start| {
+                let digits = &body[start + "ReportPeriod&gt;".len()..];
+                digits[..digits.find('&').unwrap()].parse::<i8>().unwrap()
+            });
+            self.asked.lock().unwrap().push(asked);
+
+            let list: String = PERIODS.iter().enumerate()
+                .map(|(i, p)| format!("<ReportPeriod Index=\"{}\" GradePeriod=\"{}\" StartDate=\"{}\" EndDate=\"{}\" />",
+                                      i, p.0, p.1, p.2))
+                .collect();
+            let current = self.current.unwrap_or(PERIODS[asked.unwrap_or(1) as usize]);
+            let periods = format!("<ReportingPeriods>{}</ReportingPeriods>\
+                <ReportingPeriod GradePeriod=\"{}\" StartDate=\"{}\" EndDate=\"{}\" />", list, current.0, current.1, current.2);
+            let document = GRADEBOOK.replacen(FIXTURE_PERIODS, &periods, 1);
+            assert!(document != GRADEBOOK);
+
+            let envelope = format!("<soap:Envelope xmlns:soap=\"http://schemas.xmlsoap.org/soap/envelope/\"><soap:Body>\
+                <ProcessWebServiceRequestResponse xmlns=\"http://edupoint.com/webservices/\"><ProcessWebServiceRequestResult>\
+                {}</ProcessWebServiceRequestResult></ProcessWebServiceRequestResponse></soap:Body></soap:Envelope>",
+                escape_str_pcdata(&document));
+
+            Ok(TransportResponse {
+                body: Box::new(Cursor::new(envelope.into_bytes())),
+                url: "http://district.example/Service/PXPCommunication.asmx".to_string(),
+            })
+        }
+    }
+
+    fn client(current: Option<(&'static str, &'static str, &'static str)>) -> (SVUEClient, Arc<Mutex<Vec<Option<i8>>>>) {
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let client = SVUEClient::new("user", "pass").transport(Periods { asked: asked.clone(), current });
+        (client, asked)
+    }
+
+    fn grade_period(gradebook: Option<&Gradebook>) -> Option<String> {
+        gradebook.map(|g| g.reporting_period.grade_period.clone())
+    }
+
+    #[test]
+    fn steps_forward_and_back_from_the_current_period() {
+        let (client, asked) = client(None);
+        let mut navigator = PeriodNavigator::open(&client, None).unwrap();
+
+        assert_eq!(navigator.index(), Some(1));
+        assert_eq!(navigator.periods().len(), 3);
+        assert!(navigator.has_next() && navigator.has_previous());
+
+        assert_eq!(grade_period(navigator.next_period().unwrap()), Some("Quarter 3".to_string()));
+        assert_eq!(navigator.index(), Some(2));
+        assert!(!navigator.has_next());
+
+        assert_eq!(grade_period(navigator.previous_period().unwrap()), Some("Quarter 2".to_string()));
+        assert_eq!(grade_period(navigator.previous_period().unwrap()), Some("Quarter 1".to_string()));
+        assert_eq!(navigator.index(), Some(0));
+
+        assert_eq!(*asked.lock().unwrap(), vec![None, Some(2), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn stops_at_either_end_without_fetching() {
+        let (client, asked) = client(None);
+        let mut navigator = PeriodNavigator::open(&client, Some(2)).unwrap();
+        assert!(navigator.next_period().unwrap().is_none());
+
+        navigator.go_to(0).unwrap();
+        assert!(navigator.previous_period().unwrap().is_none());
+
+        assert_eq!(*asked.lock().unwrap(), vec![Some(2), Some(0)]);
+    }
+
+    #[test]
+    fn has_no_neighbors_when_the_current_period_is_not_listed() {
+        let (client, asked) = client(Some(("Summer", "6/17/2019", "8/16/2019")));
+        let mut navigator = PeriodNavigator::open(&client, None).unwrap();
+
+        assert_eq!(navigator.index(), None);
+        assert!(!navigator.has_next() && !navigator.has_previous());
+        assert!(navigator.next_period().unwrap().is_none());
+        assert!(navigator.previous_period().unwrap().is_none());
+
+        assert_eq!(*asked.lock().unwrap(), vec![None]);
+    }
+}
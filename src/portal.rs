@@ -0,0 +1,159 @@
+// Links into a district's StudentVUE web portal, e.g. for an "open in StudentVUE" link in a
+// notification. The portal lives next to the web service, so the base URL can usually be worked
+// out from the SOAP endpoint. Its pages are addressed by query parameters that have changed
+// between StudentVUE versions; paths are templates so a district on a different version can
+// override them. Templates can use `{period}`, `{course_id}`, `{gradebook_id}` and `{message_id}`.
+#[cfg(feature="client")]
+use api::SVUEClient;
+use gradebook::{Assignment, Course, CourseTitle};
+
+const GRADEBOOK_PATH: &str = "PXP2_Gradebook.aspx?AGU=0";
+const COURSE_PATH: &str = "PXP2_Gradebook.aspx?AGU=0&period={period}&course={course_id}";
+const ASSIGNMENT_PATH: &str = "PXP2_Gradebook.aspx?AGU=0&period={period}&course={course_id}&gradebookID={gradebook_id}";
+const MESSAGE_PATH: &str = "PXP2_Messages.aspx?AGU=0&messageID={message_id}";
+
+#[derive(Clone, Debug)]
+pub struct Portal {
+    assignment_path: String,
+    base_url: String,
+    course_path: String,
+    message_path: String,
+}
+
+fn query_encode(s: &str) -> String {
+    s.bytes().fold(String::with_capacity(s.len()), |mut acc, b| {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => acc.push(b as char),
+            b => acc.push_str(&format!("%{:02X}", b)),
+        }
+        acc
+    })
+}
+
+fn course_id(course: &Course) -> String {
+    match course.title {
+        CourseTitle::Parsed(_, ref id) => id.clone(),
+        CourseTitle::Unparseable(ref title) => title.clone(),
+    }
+}
+
+impl Portal {
+    // `base_url` is where the portal's pages live, e.g. `https://student-portland.cascadetech.org/portland/`
+    pub fn new(base_url: &str) -> Portal {
+        Portal {
+            assignment_path: ASSIGNMENT_PATH.to_string(),
+            base_url: format!("{}/", base_url.trim_end_matches('/')),
+            course_path: COURSE_PATH.to_string(),
+            message_path: MESSAGE_PATH.to_string(),
+        }
+    }
+
+    // the web service is at `<portal>/Service/PXPCommunication.asmx`
+    pub fn from_endpoint(endpoint: &str) -> Portal {
+        match endpoint.rfind("/Service/").or_else(|| endpoint.rfind("/service/")) {
+            Some(i) => Self::new(&endpoint[..i]),
+            None => Self::new(endpoint),
+        }
+    }
+
+    #[cfg(feature="client")]
+    pub fn for_client(client: &SVUEClient) -> Portal {
+        Self::from_endpoint(client.endpoint_url())
+    }
+
+    pub fn course_path(mut self, template: &str) -> Portal {
+        self.course_path = template.to_string();
+        self
+    }
+
+    pub fn assignment_path(mut self, template: &str) -> Portal {
+        self.assignment_path = template.to_string();
+        self
+    }
+
+    pub fn message_path(mut self, template: &str) -> Portal {
+        self.message_path = template.to_string();
+        self
+    }
+
+    pub fn gradebook(&self) -> String {
+        format!("{}{}", self.base_url, GRADEBOOK_PATH)
+    }
+
+    pub fn course(&self, course: &Course) -> String {
+        let path = self.course_path
+            .replace("{period}", &course.period.to_string())
+            .replace("{course_id}", &query_encode(&course_id(course)));
+
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn assignment(&self, course: &Course, assignment: &Assignment) -> String {
+        let path = self.assignment_path
+            .replace("{period}", &course.period.to_string())
+            .replace("{course_id}", &query_encode(&course_id(course)))
+            .replace("{gradebook_id}", &query_encode(&assignment.gradebook_id));
+
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn message(&self, message_id: &str) -> String {
+        let path = self.message_path.replace("{message_id}", &query_encode(message_id));
+
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::Gradebook;
+
+    fn english() -> Course {
+        let gradebook = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+        gradebook.courses[0].clone()
+    }
+
+    #[test]
+    fn finds_the_portal_next_to_the_web_service() {
+        let cases = [
+            ("https://sis.example.org/portland/Service/PXPCommunication.asmx", "https://sis.example.org/portland/"),
+            ("https://sis.example.org/service/PXPCommunication.asmx", "https://sis.example.org/"),
+            ("https://sis.example.org/portland/", "https://sis.example.org/portland/"),
+            ("https://sis.example.org/portland", "https://sis.example.org/portland/"),
+        ];
+
+        for &(endpoint, base) in cases.iter() {
+            assert_eq!(Portal::from_endpoint(endpoint).gradebook(), format!("{}PXP2_Gradebook.aspx?AGU=0", base));
+        }
+    }
+
+    #[test]
+    fn links_courses_and_assignments_by_period_and_id() {
+        let portal = Portal::new("https://sis.example.org/portland");
+        let course = english();
+
+        assert_eq!(portal.course(&course),
+            "https://sis.example.org/portland/PXP2_Gradebook.aspx?AGU=0&period=2&course=EN1002");
+        assert_eq!(portal.assignment(&course, &course.marks[0].assignments[0]),
+            "https://sis.example.org/portland/PXP2_Gradebook.aspx?AGU=0&period=2&course=EN1002&gradebookID=2001");
+        assert_eq!(portal.message("A&B 7"),
+            "https://sis.example.org/portland/PXP2_Messages.aspx?AGU=0&messageID=A%26B%207");
+    }
+
+    #[test]
+    fn encodes_unparseable_titles_and_fills_in_custom_templates() {
+        let portal = Portal::new("https://sis.example.org")
+            .course_path("Gradebook.aspx?p={period}&c={course_id}")
+            .assignment_path("Assignment.aspx?id={gradebook_id}")
+            .message_path("Inbox.aspx#{message_id}");
+        let mut course = english();
+        course.title = CourseTitle::Unparseable("Art & Design/2 {period}".to_string());
+
+        assert_eq!(portal.course(&course),
+            "https://sis.example.org/Gradebook.aspx?p=2&c=Art%20%26%20Design%2F2%20%7Bperiod%7D");
+        assert_eq!(portal.assignment(&course, &course.marks[0].assignments[1]),
+            "https://sis.example.org/Assignment.aspx?id=2002");
+        assert_eq!(portal.message("42"), "https://sis.example.org/Inbox.aspx#42");
+    }
+}
@@ -0,0 +1,141 @@
+// Feeds of a `ChangesetHistory`, newest entry first. Each entry is one captured changeset, titled
+// like the notification subject and carrying the HTML rendering as its content. Entry ids are
+// derived from `link` and the capture time, so they stay stable across regenerations as long as
+// the history does.
+use super::{html, text};
+
+use history::{ChangesetHistory, TimestampedChangeset};
+
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+fn entry_id(link: &str, entry: &TimestampedChangeset) -> String {
+    format!("{}#{}-{:09}", link, entry.captured_at.timestamp(), entry.captured_at.timestamp_subsec_nanos())
+}
+
+pub fn atom(history: &ChangesetHistory, title: &str, link: &str) -> String {
+    let updated = history.latest()
+        .map(|e| e.captured_at.to_rfc3339())
+        .unwrap_or("1970-01-01T00:00:00+00:00".to_string());
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_str_pcdata(title)));
+    out.push_str(&format!("<id>{}</id>\n", escape_str_pcdata(link)));
+    out.push_str(&format!("<link href=\"{}\"/>\n", escape_str_attribute(link)));
+    out.push_str(&format!("<updated>{}</updated>\n", updated));
+    out.push_str("<author><name>rvue</name></author>\n");
+
+    for entry in history.entries().iter().rev() {
+        out.push_str("<entry>\n");
+        out.push_str(&format!("<title>{}</title>\n", escape_str_pcdata(&text::subject(&entry.changeset))));
+        out.push_str(&format!("<id>{}</id>\n", escape_str_pcdata(&entry_id(link, entry))));
+        out.push_str(&format!("<updated>{}</updated>\n", entry.captured_at.to_rfc3339()));
+        out.push_str(&format!("<content type=\"html\">{}</content>\n",
+            escape_str_pcdata(&html::changeset(&entry.changeset))));
+        out.push_str("</entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+pub fn rss(history: &ChangesetHistory, title: &str, link: &str) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<rss version=\"2.0\">\n<channel>\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_str_pcdata(title)));
+    out.push_str(&format!("<link>{}</link>\n", escape_str_pcdata(link)));
+    out.push_str(&format!("<description>{}</description>\n", escape_str_pcdata(title)));
+    if let Some(latest) = history.latest() {
+        out.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", latest.captured_at.to_rfc2822()));
+    }
+
+    for entry in history.entries().iter().rev() {
+        out.push_str("<item>\n");
+        out.push_str(&format!("<title>{}</title>\n", escape_str_pcdata(&text::subject(&entry.changeset))));
+        out.push_str(&format!("<guid isPermaLink=\"false\">{}</guid>\n", escape_str_pcdata(&entry_id(link, entry))));
+        out.push_str(&format!("<pubDate>{}</pubDate>\n", entry.captured_at.to_rfc2822()));
+        out.push_str(&format!("<description>{}</description>\n", escape_str_pcdata(&html::changeset(&entry.changeset))));
+        out.push_str("</item>\n");
+    }
+
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diff::Changeset;
+    use gradebook::Gradebook;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    const LINK: &str = "https://example.org/feed?student=1&term=2";
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2018, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn history() -> ChangesetHistory {
+        let old = Gradebook::from_xml(include_str!("../../fixtures/gradebook_no_standards.xml")).unwrap();
+        let mut history = ChangesetHistory::new();
+
+        let mut moved = old.clone();
+        moved.courses[1].room = "Lab <3>".to_string();
+        history.push(at(1), Changeset::diff(&old, &moved).unwrap());
+
+        let mut renamed = moved.clone();
+        renamed.courses[0].staff = "Okafor-Reyes, Chris".to_string();
+        history.push(at(2), Changeset::diff(&moved, &renamed).unwrap());
+
+        history
+    }
+
+    fn position(feed: &str, needle: &str) -> usize {
+        feed.find(needle).unwrap_or_else(|| panic!("{:?} not in the feed", needle))
+    }
+
+    #[test]
+    fn lists_atom_entries_newest_first() {
+        let feed = atom(&history(), "Sam & Alex", LINK);
+
+        assert!(feed.contains("<title>Sam &amp; Alex</title>\n"));
+        assert!(feed.contains("<link href=\"https://example.org/feed?student=1&amp;term=2\"/>\n"));
+        assert!(feed.contains("<updated>2018-10-02T12:00:00+00:00</updated>\n<author>"));
+        assert!(position(&feed, "<title>Grade changes: English 10</title>")
+            < position(&feed, "<title>Grade changes: Chemistry</title>"));
+        assert!(feed.contains("<id>https://example.org/feed?student=1&amp;term=2#1538395200-000000000</id>"));
+        assert!(feed.ends_with("</entry>\n</feed>\n"));
+    }
+
+    #[test]
+    fn escapes_the_html_content_once_more() {
+        let feed = atom(&history(), "Sam & Alex", LINK);
+
+        assert!(feed.contains("&lt;li&gt;room: Lab 2 -&amp;gt; Lab &amp;lt;3&amp;gt;&lt;/li&gt;"));
+    }
+
+    #[test]
+    fn lists_rss_items_newest_first() {
+        let feed = rss(&history(), "Sam & Alex", LINK);
+
+        assert!(feed.contains("<lastBuildDate>Tue, 2 Oct 2018 12:00:00 +0000</lastBuildDate>\n"));
+        assert!(position(&feed, "<pubDate>Tue, 2 Oct 2018 12:00:00 +0000</pubDate>")
+            < position(&feed, "<pubDate>Mon, 1 Oct 2018 12:00:00 +0000</pubDate>"));
+        assert!(feed.contains(
+            "<guid isPermaLink=\"false\">https://example.org/feed?student=1&amp;term=2#1538481600-000000000</guid>"));
+    }
+
+    #[test]
+    fn keeps_an_empty_feed_valid() {
+        let history = ChangesetHistory::new();
+
+        let feed = atom(&history, "Sam", LINK);
+        assert!(feed.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+        assert!(!feed.contains("<entry>"));
+
+        let feed = rss(&history, "Sam", LINK);
+        assert!(!feed.contains("<lastBuildDate>"));
+        assert!(feed.ends_with("<description>Sam</description>\n</channel>\n</rss>\n"));
+    }
+}
@@ -0,0 +1,72 @@
+use diff::*;
+use render::i18n::{self, Strings};
+use render::text;
+use tags::CourseTags;
+
+pub fn escape(s: &str) -> String {
+    s.chars().fold(String::with_capacity(s.len()), |mut acc, c| {
+        match c {
+            '&' => acc.push_str("&amp;"),
+            '<' => acc.push_str("&lt;"),
+            '>' => acc.push_str("&gt;"),
+            '"' => acc.push_str("&quot;"),
+            '\'' => acc.push_str("&#39;"),
+            c => acc.push(c),
+        }
+        acc
+    })
+}
+
+pub fn course_changes(ccs: &CourseChanges) -> String {
+    course_changes_in(ccs, None, &i18n::english())
+}
+
+// the same, with each course's color as a bar beside its heading and its tags after it
+pub fn course_changes_with(ccs: &CourseChanges, tags: &CourseTags) -> String {
+    course_changes_in(ccs, Some(tags), &i18n::english())
+}
+
+pub fn course_changes_in(ccs: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+    let course = match ccs.new.as_ref().or(ccs.old.as_ref()) {
+        Some(c) => c,
+        None => { return String::new(); }
+    };
+    let style = match tags.and_then(|t| t.color_for(&course.title)) {
+        Some(color) => format!(" style=\"border-left: 4px solid {}; padding-left: 4px\"", escape(color)),
+        None => String::new(),
+    };
+    let labels: Vec<String> = tags.map(|t| t.tags_for(&course.title)).unwrap_or(&[]).iter()
+        .map(|t| format!(" <mark>{}</mark>", escape(t)))
+        .collect();
+    let mut out = format!("<h3{}>{} <small>{} {}</small>{}</h3>\n<ul>\n",
+                          style, escape(&course.title.to_string()), escape(&s.period), course.period, labels.join(""));
+
+    for c in ccs.changes.iter().flat_map(|cs| cs.iter()) {
+        out.push_str(&format!("<li>{}</li>\n", escape(&text::course_change_in(c, s))));
+    }
+
+    for acs in ccs.assignment_changes.iter().flat_map(|acs| acs.iter()) {
+        let title = acs.new.as_ref().or(acs.old.as_ref()).map(|a| a.measure.as_str()).unwrap_or("");
+        let changes: Vec<String> = acs.changes.iter().map(|c| escape(&text::assignment_change_in(c, s))).collect();
+
+        out.push_str(&format!("<li><strong>{}</strong>: {}</li>\n", escape(title), changes.join(", ")));
+    }
+
+    out.push_str("</ul>\n");
+    out
+}
+
+pub fn changeset(changeset: &Changeset) -> String {
+    changeset_in(changeset, None, &i18n::english())
+}
+
+pub fn changeset_with(changeset: &Changeset, tags: &CourseTags) -> String {
+    changeset_in(changeset, Some(tags), &i18n::english())
+}
+
+pub fn changeset_in(changeset: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+    changeset.changes.iter()
+        .map(|ccs| course_changes_in(ccs, tags, s))
+        .collect::<Vec<_>>()
+        .join("")
+}
@@ -0,0 +1,276 @@
+// The words the renderers put around gradebook data, per language, so notifications and reports
+// can go out in whatever language a family reads. Only rvue's own words are translated; course
+// names, assignment titles and the like are shown as the district wrote them. Labels are lowercase
+// and capitalized where a heading needs it. More languages can be added with `register`.
+use std::sync::RwLock;
+
+#[derive(Clone, Debug)]
+pub struct Strings {
+    // a language tag, e.g. "en" or "es"
+    pub name: String,
+
+    // changes
+    pub course_added: String,
+    pub course_dropped: String,
+    pub grade: String,
+    pub highlight_cutoff: String,
+    pub period: String,
+    pub room: String,
+    pub teacher: String,
+    pub teacher_email: String,
+    pub added: String,
+    pub removed: String,
+    pub date: String,
+    pub due_date: String,
+    pub notes: String,
+    pub points: String,
+    pub score: String,
+    pub score_type: String,
+    pub proficiency: String,
+    pub renamed: String,
+    pub none: String,
+    pub grade_changes: String,
+    pub daily_digest: String,
+    pub weekly_digest: String,
+
+    // reports
+    pub gradebook: String,
+    pub course: String,
+    pub assignment: String,
+    pub kind: String,
+    pub target: String,
+    pub gpa: String,
+    pub missing_work: String,
+    pub no_courses: String,
+    pub no_assignments: String,
+    pub due: String,
+    pub goal: String,
+    pub met: String,
+    pub above: String,
+    pub to_go: String,
+    pub out_of_reach: String,
+    pub needed_on_remaining: String,
+    pub no_remaining_work: String,
+}
+
+fn s(word: &str) -> String {
+    word.to_string()
+}
+
+pub fn english() -> Strings {
+    Strings {
+        name: s("en"),
+        course_added: s("course added"),
+        course_dropped: s("course dropped"),
+        grade: s("grade"),
+        highlight_cutoff: s("highlight cutoff"),
+        period: s("period"),
+        room: s("room"),
+        teacher: s("teacher"),
+        teacher_email: s("teacher email"),
+        added: s("added"),
+        removed: s("removed"),
+        date: s("date"),
+        due_date: s("due date"),
+        notes: s("notes"),
+        points: s("points"),
+        score: s("score"),
+        score_type: s("score type"),
+        proficiency: s("proficiency"),
+        renamed: s("renamed"),
+        none: s("none"),
+        grade_changes: s("Grade changes"),
+        daily_digest: s("Daily digest"),
+        weekly_digest: s("Weekly digest"),
+        gradebook: s("gradebook"),
+        course: s("course"),
+        assignment: s("assignment"),
+        kind: s("type"),
+        target: s("target"),
+        gpa: s("GPA (unweighted)"),
+        missing_work: s("missing work"),
+        no_courses: s("No courses."),
+        no_assignments: s("No assignments."),
+        due: s("due"),
+        goal: s("goal"),
+        met: s("met"),
+        above: s("above"),
+        to_go: s("to go"),
+        out_of_reach: s("out of reach"),
+        needed_on_remaining: s("needed on remaining work"),
+        no_remaining_work: s("no remaining work"),
+    }
+}
+
+pub fn spanish() -> Strings {
+    Strings {
+        name: s("es"),
+        course_added: s("curso añadido"),
+        course_dropped: s("curso retirado"),
+        grade: s("calificación"),
+        highlight_cutoff: s("límite de resaltado"),
+        period: s("período"),
+        room: s("salón"),
+        teacher: s("maestro"),
+        teacher_email: s("correo del maestro"),
+        added: s("añadida"),
+        removed: s("eliminada"),
+        date: s("fecha"),
+        due_date: s("fecha de entrega"),
+        notes: s("notas"),
+        points: s("puntos"),
+        score: s("puntuación"),
+        score_type: s("tipo de puntuación"),
+        proficiency: s("dominio"),
+        renamed: s("renombrada"),
+        none: s("ninguno"),
+        grade_changes: s("Cambios de calificaciones"),
+        daily_digest: s("Resumen diario"),
+        weekly_digest: s("Resumen semanal"),
+        gradebook: s("libro de calificaciones"),
+        course: s("curso"),
+        assignment: s("tarea"),
+        kind: s("tipo"),
+        target: s("meta"),
+        gpa: s("GPA (sin ponderar)"),
+        missing_work: s("trabajo pendiente"),
+        no_courses: s("No hay cursos."),
+        no_assignments: s("No hay tareas."),
+        due: s("entrega"),
+        goal: s("meta"),
+        met: s("alcanzada"),
+        above: s("por encima"),
+        to_go: s("por alcanzar"),
+        out_of_reach: s("fuera de alcance"),
+        needed_on_remaining: s("necesario en el trabajo restante"),
+        no_remaining_work: s("no queda trabajo"),
+    }
+}
+
+pub fn vietnamese() -> Strings {
+    Strings {
+        name: s("vi"),
+        course_added: s("đã thêm khóa học"),
+        course_dropped: s("đã bỏ khóa học"),
+        grade: s("điểm"),
+        highlight_cutoff: s("ngưỡng tô sáng"),
+        period: s("tiết"),
+        room: s("phòng"),
+        teacher: s("giáo viên"),
+        teacher_email: s("email giáo viên"),
+        added: s("đã thêm"),
+        removed: s("đã xóa"),
+        date: s("ngày"),
+        due_date: s("hạn nộp"),
+        notes: s("ghi chú"),
+        points: s("số điểm"),
+        score: s("điểm số"),
+        score_type: s("loại điểm"),
+        proficiency: s("mức thành thạo"),
+        renamed: s("đổi tên"),
+        none: s("không có"),
+        grade_changes: s("Thay đổi điểm"),
+        daily_digest: s("Tóm tắt hằng ngày"),
+        weekly_digest: s("Tóm tắt hằng tuần"),
+        gradebook: s("sổ điểm"),
+        course: s("khóa học"),
+        assignment: s("bài tập"),
+        kind: s("loại"),
+        target: s("mục tiêu"),
+        gpa: s("GPA (không trọng số)"),
+        missing_work: s("bài còn thiếu"),
+        no_courses: s("Không có khóa học."),
+        no_assignments: s("Không có bài tập."),
+        due: s("hạn"),
+        goal: s("mục tiêu"),
+        met: s("đã đạt"),
+        above: s("vượt"),
+        to_go: s("còn thiếu"),
+        out_of_reach: s("không thể đạt"),
+        needed_on_remaining: s("cần đạt ở các bài còn lại"),
+        no_remaining_work: s("không còn bài nào"),
+    }
+}
+
+lazy_static! {
+    static ref LANGUAGES: RwLock<Vec<Strings>> = RwLock::new(vec![english(), spanish(), vietnamese()]);
+}
+
+// strings with the same name replace the registered ones
+pub fn register(strings: Strings) {
+    let mut languages = match LANGUAGES.write() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    languages.retain(|l| l.name != strings.name);
+    languages.push(strings);
+}
+
+// an exact match for `tag` if there is one, then one for its language alone (so "es-MX" gets
+// "es"), and English otherwise
+pub fn for_language(tag: &str) -> Strings {
+    let languages = match LANGUAGES.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let primary = tag.split(['-', '_']).next().unwrap_or(tag);
+
+    languages.iter().find(|l| l.name.eq_ignore_ascii_case(tag))
+        .or_else(|| languages.iter().find(|l| l.name.eq_ignore_ascii_case(primary)))
+        .cloned()
+        .unwrap_or_else(english)
+}
+
+// for headings and the start of sentences
+pub fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_the_primary_language_then_english() {
+        let cases = [
+            ("es", "es"),
+            ("ES", "es"),
+            ("es-MX", "es"),
+            ("vi_VN", "vi"),
+            ("fr", "en"),
+            ("", "en"),
+        ];
+
+        for &(tag, expected) in cases.iter() {
+            assert_eq!(for_language(tag).name, expected, "{:?}", tag);
+        }
+    }
+
+    #[test]
+    fn prefers_a_registered_regional_variant() {
+        let mut canadian = english();
+        canadian.name = "fr-CA".to_string();
+        canadian.gradebook = "bulletin".to_string();
+        register(canadian.clone());
+        canadian.gradebook = "carnet de notes".to_string();
+        register(canadian);
+
+        assert_eq!(for_language("fr-ca").gradebook, "carnet de notes");
+        // "fr" alone isn't registered
+        assert_eq!(for_language("fr").name, "en");
+    }
+
+    #[test]
+    fn capitalizes_the_first_letter_only() {
+        assert_eq!(capitalize("missing work"), "Missing work");
+        assert_eq!(capitalize("élève"), "Élève");
+        assert_eq!(capitalize("sổ điểm"), "Sổ điểm");
+        assert_eq!(capitalize(""), "");
+    }
+}
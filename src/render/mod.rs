@@ -0,0 +1,104 @@
+// Turning rvue's types into something to read. `Render` is what every renderer implements, one impl
+// per type it can render, so other crates can add their own (chat blocks, terminal colors, ...)
+// and use them anywhere rvue's are; `Text`, `Markdown` and `Html` are the built-in ones, backed by
+// the functions in their modules.
+use diff::{Changeset, CourseChanges};
+use gradebook::Gradebook;
+use report::markdown::Report;
+use tags::CourseTags;
+
+use self::i18n::Strings;
+use self::template::ChangesetTemplate;
+
+pub mod feed;
+pub mod html;
+pub mod i18n;
+pub mod template;
+pub mod text;
+
+pub trait Render<T: ?Sized> {
+    // `tags` are shown however the format can show them, and `s` is the language of rvue's own words
+    fn render(&self, value: &T, tags: Option<&CourseTags>, s: &Strings) -> String;
+}
+
+impl<T: ?Sized, R: Render<T> + ?Sized> Render<T> for &R {
+    fn render(&self, value: &T, tags: Option<&CourseTags>, s: &Strings) -> String {
+        (**self).render(value, tags, s)
+    }
+}
+
+impl<T: ?Sized, R: Render<T> + ?Sized> Render<T> for Box<R> {
+    fn render(&self, value: &T, tags: Option<&CourseTags>, s: &Strings) -> String {
+        (**self).render(value, tags, s)
+    }
+}
+
+// the plain text notifications are sent as
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Text;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Markdown;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Html;
+
+impl Render<Changeset> for Text {
+    fn render(&self, value: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+        text::changeset_in(value, tags, s)
+    }
+}
+
+impl Render<CourseChanges> for Text {
+    fn render(&self, value: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+        text::course_changes_in(value, tags, s)
+    }
+}
+
+impl Render<Changeset> for Markdown {
+    fn render(&self, value: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+        template::markdown().changeset(value, tags, s)
+    }
+}
+
+impl Render<CourseChanges> for Markdown {
+    fn render(&self, value: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+        template::markdown().course_changes(value, tags, s)
+    }
+}
+
+// the full report; `report::markdown::Report` has the options this leaves at their defaults
+impl Render<Gradebook> for Markdown {
+    fn render(&self, value: &Gradebook, tags: Option<&CourseTags>, s: &Strings) -> String {
+        let report = Report::new(value).strings(s.clone());
+
+        match tags {
+            Some(tags) => report.tags(tags).render(),
+            None => report.render(),
+        }
+    }
+}
+
+impl Render<Changeset> for Html {
+    fn render(&self, value: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+        html::changeset_in(value, tags, s)
+    }
+}
+
+impl Render<CourseChanges> for Html {
+    fn render(&self, value: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+        html::course_changes_in(value, tags, s)
+    }
+}
+
+impl Render<Changeset> for ChangesetTemplate {
+    fn render(&self, value: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+        self.changeset(value, tags, s)
+    }
+}
+
+impl Render<CourseChanges> for ChangesetTemplate {
+    fn render(&self, value: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+        self.course_changes(value, tags, s)
+    }
+}
@@ -0,0 +1,343 @@
+// Placeholder templates for changeset notifications, so the wording can be changed without touching
+// the renderers. A template is plain text with `{name}` placeholders (`{{` and `}}` for literal
+// braces); each part of a changeset has its own template and its own set of placeholders:
+//
+//   course      {course} {name} {id} {period} {period_label} {tags} {changes} {assignments}
+//   change      {change}
+//   assignment  {assignment} {changes}
+//   subject     {label} {courses}
+//
+// `{changes}` and `{assignments}` in a course are the change and assignment templates filled in for
+// each of its changes, one after another; `{tags}` is " [a, b]" for tagged courses and empty
+// otherwise. Values are inserted as is, without escaping.
+use std::fmt;
+
+use diff::{Changeset, CourseChanges};
+use gradebook::CourseTitle;
+use render::i18n::Strings;
+use render::text::{assignment_change_in, course_change_in};
+use tags::CourseTags;
+
+#[derive(Debug)]
+pub enum TemplateError {
+    // a `{` at this byte offset without a matching `}`
+    Unclosed(usize),
+    // a `}` at this byte offset that doesn't close anything
+    Unopened(usize),
+    // a placeholder the template it's in doesn't fill, along with that template's name
+    UnknownPlaceholder(&'static str, String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TemplateError::Unclosed(at) => write!(f, "unclosed `{{` at {}", at),
+            TemplateError::Unopened(at) => write!(f, "unmatched `}}` at {}", at),
+            TemplateError::UnknownPlaceholder(part, ref name) => {
+                write!(f, "unknown placeholder `{{{}}}` in the {} template", name, part)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Field(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, n)| n == '{').unwrap_or(false) => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek().map(|&(_, n)| n == '}').unwrap_or(false) => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => { break; }
+                            Some((_, c)) => name.push(c),
+                            None => { return Err(TemplateError::Unclosed(i)); }
+                        }
+                    }
+
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(text));
+                        text = String::new();
+                    }
+                    pieces.push(Piece::Field(name.trim().to_string()));
+                }
+                '}' => { return Err(TemplateError::Unopened(i)); }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        Ok(Template { pieces })
+    }
+
+    // the placeholders used, in order, with repeats
+    pub fn fields(&self) -> Vec<&str> {
+        self.pieces.iter()
+            .filter_map(|p| match *p {
+                Piece::Field(ref name) => Some(name.as_str()),
+                Piece::Text(_) => None,
+            })
+            .collect()
+    }
+
+    // placeholders without a value are left empty
+    pub fn fill(&self, values: &[(&str, String)]) -> String {
+        let mut out = String::new();
+
+        for piece in &self.pieces {
+            match *piece {
+                Piece::Text(ref text) => out.push_str(text),
+                Piece::Field(ref name) => {
+                    if let Some((_, value)) = values.iter().find(|&&(n, _)| n == name) {
+                        out.push_str(value);
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+const COURSE_FIELDS: &[&str] =
+    &["course", "name", "id", "period", "period_label", "tags", "changes", "assignments"];
+const CHANGE_FIELDS: &[&str] = &["change"];
+const ASSIGNMENT_FIELDS: &[&str] = &["assignment", "changes"];
+const SUBJECT_FIELDS: &[&str] = &["label", "courses"];
+
+fn checked(part: &'static str, source: &str, allowed: &[&str]) -> Result<Template, TemplateError> {
+    let template = Template::parse(source)?;
+
+    match template.fields().into_iter().find(|f| !allowed.contains(f)) {
+        Some(unknown) => Err(TemplateError::UnknownPlaceholder(part, unknown.to_string())),
+        None => Ok(template),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangesetTemplate {
+    course: Template,
+    change: Template,
+    assignment: Template,
+    subject: Template,
+    // put between courses
+    separator: String,
+}
+
+// the built-in templates are known to parse
+fn builtin(part: &'static str, source: &str, allowed: &[&str]) -> Template {
+    checked(part, source, allowed).expect("built-in template")
+}
+
+// the plain text that notifications have always been sent as
+pub fn text() -> ChangesetTemplate {
+    ChangesetTemplate {
+        course: builtin("course", "{course} ({period_label} {period}){tags}\n{changes}{assignments}", COURSE_FIELDS),
+        change: builtin("change", "  {change}\n", CHANGE_FIELDS),
+        assignment: builtin("assignment", "  {assignment}: {changes}\n", ASSIGNMENT_FIELDS),
+        subject: builtin("subject", "{label}: {courses}", SUBJECT_FIELDS),
+        separator: "\n".to_string(),
+    }
+}
+
+// a heading per course and a list item per change, for chat sinks and anything else that renders
+// Markdown
+pub fn markdown() -> ChangesetTemplate {
+    ChangesetTemplate {
+        course: builtin("course", "### {course} ({period_label} {period}){tags}\n\n{changes}{assignments}",
+                        COURSE_FIELDS),
+        change: builtin("change", "- {change}\n", CHANGE_FIELDS),
+        assignment: builtin("assignment", "- **{assignment}**: {changes}\n", ASSIGNMENT_FIELDS),
+        subject: builtin("subject", "{label}: {courses}", SUBJECT_FIELDS),
+        separator: "\n".to_string(),
+    }
+}
+
+impl ChangesetTemplate {
+    pub fn course(mut self, source: &str) -> Result<ChangesetTemplate, TemplateError> {
+        self.course = checked("course", source, COURSE_FIELDS)?;
+        Ok(self)
+    }
+
+    pub fn change(mut self, source: &str) -> Result<ChangesetTemplate, TemplateError> {
+        self.change = checked("change", source, CHANGE_FIELDS)?;
+        Ok(self)
+    }
+
+    pub fn assignment(mut self, source: &str) -> Result<ChangesetTemplate, TemplateError> {
+        self.assignment = checked("assignment", source, ASSIGNMENT_FIELDS)?;
+        Ok(self)
+    }
+
+    pub fn subject(mut self, source: &str) -> Result<ChangesetTemplate, TemplateError> {
+        self.subject = checked("subject", source, SUBJECT_FIELDS)?;
+        Ok(self)
+    }
+
+    pub fn separator(mut self, separator: &str) -> ChangesetTemplate {
+        self.separator = separator.to_string();
+        self
+    }
+
+    pub fn course_changes(&self, ccs: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+        let course = match ccs.new.as_ref().or(ccs.old.as_ref()) {
+            Some(c) => c,
+            None => { return String::new(); }
+        };
+
+        let changes: String = ccs.changes.iter()
+            .flat_map(|cs| cs.iter())
+            .map(|c| self.change.fill(&[("change", course_change_in(c, s))]))
+            .collect();
+
+        let assignments: String = ccs.assignment_changes.iter()
+            .flat_map(|acs| acs.iter())
+            .map(|acs| {
+                let title = acs.new.as_ref().or(acs.old.as_ref()).map(|a| a.measure.as_str()).unwrap_or("");
+                let changes: Vec<String> = acs.changes.iter().map(|c| assignment_change_in(c, s)).collect();
+
+                self.assignment.fill(&[("assignment", title.to_string()), ("changes", changes.join(", "))])
+            })
+            .collect();
+
+        let tag_list = match tags.map(|t| t.tags_for(&course.title)) {
+            Some(ts) if !ts.is_empty() => format!(" [{}]", ts.join(", ")),
+            _ => String::new(),
+        };
+
+        self.course.fill(&[
+            ("course", course.title.to_string()),
+            ("name", name(&course.title)),
+            ("id", course.title.id().unwrap_or("").to_string()),
+            ("period", course.period.to_string()),
+            ("period_label", s.period.clone()),
+            ("tags", tag_list),
+            ("changes", changes),
+            ("assignments", assignments),
+        ])
+    }
+
+    pub fn changeset(&self, changeset: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+        changeset.changes.iter()
+            .map(|ccs| self.course_changes(ccs, tags, s))
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+
+    pub fn subject_for(&self, changeset: &Changeset, s: &Strings) -> String {
+        let courses: Vec<String> = changeset.changes.iter()
+            .filter_map(|ccs| ccs.new.as_ref().or(ccs.old.as_ref()))
+            .map(|c| name(&c.title))
+            .collect();
+
+        self.subject.fill(&[("label", s.grade_changes.clone()), ("courses", courses.join(", "))])
+    }
+}
+
+fn name(title: &CourseTitle) -> String {
+    match *title {
+        CourseTitle::Parsed(ref name, _) => name.clone(),
+        CourseTitle::Unparseable(ref title) => title.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::{AssignmentScore, Gradebook};
+    use render::i18n;
+
+    // Reading Log gets graded in English, and Chemistry moves rooms
+    fn changeset() -> Changeset {
+        let old = Gradebook::from_xml(include_str!("../../fixtures/gradebook_no_standards.xml")).unwrap();
+        let mut new = old.clone();
+        new.courses[0].marks[0].assignments[1].score = AssignmentScore::Percentage(95.0);
+        new.courses[1].room = "Lab 3".to_string();
+
+        Changeset::diff(&old, &new).unwrap()
+    }
+
+    #[test]
+    fn parses_placeholders_and_escaped_braces() {
+        let template = Template::parse("{{{ name }}} scored {score}").unwrap();
+
+        assert_eq!(template.fields(), vec!["name", "score"]);
+        assert_eq!(template.fill(&[("name", "Sam".to_string())]), "{Sam} scored ");
+    }
+
+    #[test]
+    fn rejects_unbalanced_braces_and_unknown_placeholders() {
+        match Template::parse("ab{course") {
+            Err(TemplateError::Unclosed(2)) => {}
+            other => panic!("{:?}", other),
+        }
+        match Template::parse("a}b") {
+            Err(TemplateError::Unopened(1)) => {}
+            other => panic!("{:?}", other),
+        }
+        match text().change("{change} in {course}") {
+            Err(TemplateError::UnknownPlaceholder("change", ref name)) if name == "course" => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn renders_the_built_in_templates() {
+        let changeset = changeset();
+        let s = i18n::english();
+
+        assert_eq!(text().changeset(&changeset, None, &s), concat!(
+            "English 10 (EN1002) (period 2)\n  Reading Log: score: Not Due -> 95\n",
+            "\n",
+            "Chemistry (SC2010) (period 3)\n  room: Lab 2 -> Lab 3\n"));
+        assert_eq!(markdown().changeset(&changeset, None, &s), concat!(
+            "### English 10 (EN1002) (period 2)\n\n- **Reading Log**: score: Not Due -> 95\n",
+            "\n",
+            "### Chemistry (SC2010) (period 3)\n\n- room: Lab 2 -> Lab 3\n"));
+        assert_eq!(text().subject_for(&changeset, &s), "Grade changes: English 10, Chemistry");
+    }
+
+    #[test]
+    fn fills_in_custom_templates_with_tags() {
+        let changeset = changeset();
+        let mut tags = CourseTags::new();
+        tags.tag(&CourseTitle::Parsed("Chemistry".to_string(), "SC2010".to_string()), "lab");
+        tags.tag(&CourseTitle::Parsed("Chemistry".to_string(), "SC2010".to_string()), "stem");
+
+        let template = text()
+            .course("{id} {name}{tags}: {changes}{assignments}").unwrap()
+            .change("<{change}>").unwrap()
+            .assignment("[{assignment}]").unwrap()
+            .subject("{courses}").unwrap()
+            .separator(" | ");
+
+        assert_eq!(template.changeset(&changeset, Some(&tags), &i18n::english()),
+            "EN1002 English 10: [Reading Log] | SC2010 Chemistry [lab, stem]: <room: Lab 2 -> Lab 3>");
+        assert_eq!(template.subject_for(&changeset, &i18n::english()), "English 10, Chemistry");
+    }
+}
@@ -0,0 +1,82 @@
+use diff::*;
+use render::i18n::{self, Strings};
+use render::template;
+use tags::CourseTags;
+
+pub fn course_change(change: &CourseChange) -> String {
+    course_change_in(change, &i18n::english())
+}
+
+pub fn course_change_in(change: &CourseChange, s: &Strings) -> String {
+    match *change {
+        CourseChange::Added => s.course_added.clone(),
+        CourseChange::Dropped => s.course_dropped.clone(),
+        CourseChange::CalculatedGradeChange { ref old, ref new } => format!("{}: {} -> {}", s.grade, old, new),
+        CourseChange::HighlightCutoffChange { old, new } => format!("{}: {}% -> {}%", s.highlight_cutoff, old, new),
+        CourseChange::PeriodChange { old, new } => format!("{}: {} -> {}", s.period, old, new),
+        CourseChange::RoomChange { ref old, ref new } => format!("{}: {} -> {}", s.room, old, new),
+        CourseChange::StaffChange { ref old, ref new } => format!("{}: {} -> {}", s.teacher, old, new),
+        CourseChange::StaffEmailChange { ref old, ref new } => format!("{}: {} -> {}", s.teacher_email, old, new),
+        CourseChange::TitleChange { ref old, ref new } => format!("{}: {} -> {}", s.renamed, old, new),
+    }
+}
+
+pub fn assignment_change(change: &AssignmentChange) -> String {
+    assignment_change_in(change, &i18n::english())
+}
+
+pub fn assignment_change_in(change: &AssignmentChange, s: &Strings) -> String {
+    match *change {
+        AssignmentChange::Added => s.added.clone(),
+        AssignmentChange::Removed => s.removed.clone(),
+        AssignmentChange::DateChange { old, new } => format!("{}: {} -> {}", s.date, old, new),
+        AssignmentChange::DueDateChange { old, new } => format!("{}: {} -> {}", s.due_date, old, new),
+        AssignmentChange::NotesChange { ref old, ref new } => format!("{}: \"{}\" -> \"{}\"", s.notes, old, new),
+        AssignmentChange::PointsChange { ref old, ref new } => format!("{}: {} -> {}", s.points, old, new),
+        AssignmentChange::ScoreChange { ref old, ref new } => format!("{}: {} -> {}", s.score, old, new),
+        AssignmentChange::ScoreTypeChange { ref old, ref new } => format!("{}: {} -> {}", s.score_type, old, new),
+        AssignmentChange::StandardAdded { ref standard, ref mark } => format!("{}: {} ({})", standard, s.added, mark),
+        AssignmentChange::StandardRemoved { ref standard, ref mark } => format!("{}: {} ({})", standard, s.removed, mark),
+        AssignmentChange::StandardMarkChange { ref standard, ref old, ref new } => {
+            format!("{}: {} -> {}", standard, old, new)
+        }
+        AssignmentChange::StandardProficiencyChange { ref standard, old, new } => {
+            let fmt = |p: Option<f64>| p.map(|p| p.to_string()).unwrap_or(s.none.clone());
+            format!("{} {}: {} -> {}", standard, s.proficiency, fmt(old), fmt(new))
+        }
+        AssignmentChange::TitleChange { ref old, ref new } => format!("{}: {} -> {}", s.renamed, old, new),
+    }
+}
+
+pub fn course_changes(ccs: &CourseChanges) -> String {
+    course_changes_in(ccs, None, &i18n::english())
+}
+
+// the same, with each course's tags after its heading
+pub fn course_changes_with(ccs: &CourseChanges, tags: &CourseTags) -> String {
+    course_changes_in(ccs, Some(tags), &i18n::english())
+}
+
+pub fn course_changes_in(ccs: &CourseChanges, tags: Option<&CourseTags>, s: &Strings) -> String {
+    template::text().course_changes(ccs, tags, s)
+}
+
+pub fn changeset(changeset: &Changeset) -> String {
+    changeset_in(changeset, None, &i18n::english())
+}
+
+pub fn changeset_with(changeset: &Changeset, tags: &CourseTags) -> String {
+    changeset_in(changeset, Some(tags), &i18n::english())
+}
+
+pub fn changeset_in(changeset: &Changeset, tags: Option<&CourseTags>, s: &Strings) -> String {
+    template::text().changeset(changeset, tags, s)
+}
+
+pub fn subject(changeset: &Changeset) -> String {
+    subject_in(changeset, &i18n::english())
+}
+
+pub fn subject_in(changeset: &Changeset, s: &Strings) -> String {
+    template::text().subject_for(changeset, s)
+}
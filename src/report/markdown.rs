@@ -0,0 +1,248 @@
+use super::{current_mark, gpa, missing_assignments};
+
+use completion::CompletionOverlay;
+use goals::{GoalProgress, Goals};
+use gradebook::*;
+use grading::RoundingPolicy;
+use render::i18n::{self, capitalize, Strings};
+use tags::CourseTags;
+
+// table cells can't contain pipes or line breaks
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|").replace("\r\n", " ").replace('\n', " ")
+}
+
+fn tag_list(course: &Course, tags: Option<&CourseTags>) -> String {
+    tags.map(|t| t.tags_for(&course.title)).unwrap_or(&[]).iter()
+        .map(|t| format!(" `{}`", t.replace('`', "'")))
+        .collect()
+}
+
+fn course_section(course: &Course, overlay: Option<&CompletionOverlay>, goal: Option<&GoalProgress>,
+                  rounding: &RoundingPolicy, tags: Option<&CourseTags>, s: &Strings) -> String {
+    let mut out = format!("## {}{}\n\n", course.title, tag_list(course, tags));
+    out.push_str(&format!("{} {} · {} {} · {}\n\n", capitalize(&s.period), course.period, capitalize(&s.room),
+        cell(&course.room), cell(&course.staff)));
+    if let Some(progress) = goal {
+        out.push_str(&format!("**{}:** {}% — {}\n\n", capitalize(&s.goal), progress.target, goal_summary(progress, s)));
+    }
+
+    for mark in course.marks() {
+        out.push_str(&format!("### {}: {}\n\n", mark.mark_name, mark.calculated_grade_with(rounding)));
+
+        if mark.assignments.is_empty() {
+            out.push_str(&format!("{}\n\n", s.no_assignments));
+            continue;
+        }
+
+        out.push_str(&format!("| {} | {} | {} | {} | {} | {} |\n", capitalize(&s.due), capitalize(&s.assignment),
+            capitalize(&s.kind), capitalize(&s.score), capitalize(&s.points), capitalize(&s.notes)));
+        out.push_str("| --- | --- | --- | --- | --- | --- |\n");
+        for a in mark.assignments() {
+            out.push_str(&format!("| {} | {} | {} | {} | {} | {} |\n",
+                a.due_date.format("%Y-%m-%d"),
+                cell(&measure(a, overlay)),
+                cell(&a._type),
+                cell(&a.score.to_string()),
+                cell(&a.points.to_string()),
+                cell(&a.notes)));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn measure(a: &Assignment, overlay: Option<&CompletionOverlay>) -> String {
+    match overlay {
+        Some(o) if o.is_done(a) => format!("{} ✓", a.measure),
+        _ => a.measure.clone(),
+    }
+}
+
+pub fn gradebook(gradebook: &Gradebook) -> String {
+    Report::new(gradebook).render()
+}
+
+// assignments marked done in `overlay` are checked off and left out of the missing work
+pub fn gradebook_with(gradebook: &Gradebook, overlay: &CompletionOverlay) -> String {
+    Report::new(gradebook).completion(overlay).render()
+}
+
+fn goal_summary(progress: &GoalProgress, s: &Strings) -> String {
+    if progress.is_met() {
+        return format!("{} ({:.1}% {})", s.met, -progress.distance(), s.above);
+    }
+
+    match progress.required {
+        Some(r) if r > 100.0 => format!("{:.1}% {}; {} ({:.1}% {})",
+            progress.distance(), s.to_go, s.out_of_reach, r, s.needed_on_remaining),
+        Some(r) => format!("{:.1}% {}; {:.1}% {}", progress.distance(), s.to_go, r, s.needed_on_remaining),
+        None => format!("{:.1}% {}; {}", progress.distance(), s.to_go, s.no_remaining_work),
+    }
+}
+
+// the gradebook report with optional extras
+pub struct Report<'a> {
+    goals: Option<&'a Goals>,
+    gradebook: &'a Gradebook,
+    overlay: Option<&'a CompletionOverlay>,
+    rounding: RoundingPolicy,
+    strings: Strings,
+    tags: Option<&'a CourseTags>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(gradebook: &'a Gradebook) -> Report<'a> {
+        Report {
+            goals: None,
+            gradebook,
+            overlay: None,
+            rounding: Default::default(),
+            strings: i18n::english(),
+            tags: None,
+        }
+    }
+
+    // how scores are shown; unrounded unless set
+    pub fn rounding(mut self, policy: RoundingPolicy) -> Report<'a> {
+        self.rounding = policy;
+        self
+    }
+
+    pub fn completion(mut self, overlay: &'a CompletionOverlay) -> Report<'a> {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    // adds a target column to the summary, and each course's distance to its goal
+    pub fn goals(mut self, goals: &'a Goals) -> Report<'a> {
+        self.goals = Some(goals);
+        self
+    }
+
+    // the language of the report's own words (see `render::i18n`)
+    pub fn strings(mut self, strings: Strings) -> Report<'a> {
+        self.strings = strings;
+        self
+    }
+
+    // shows each course's tags after its name; markdown has no colors, so those are left out
+    pub fn tags(mut self, tags: &'a CourseTags) -> Report<'a> {
+        self.tags = Some(tags);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let gradebook = self.gradebook;
+        let s = &self.strings;
+        let mut out = format!("# {}\n\n", capitalize(&s.gradebook));
+
+        if gradebook.courses.is_empty() {
+            out.push_str(&format!("{}\n", s.no_courses));
+            return out;
+        }
+
+        out.push_str(&format!("| {} | {} | {} | {} |", capitalize(&s.course), capitalize(&s.period),
+            capitalize(&s.teacher), capitalize(&s.grade)));
+        match self.goals {
+            Some(_) => {
+                out.push_str(&format!(" {} |\n", capitalize(&s.target)));
+                out.push_str("| --- | --- | --- | --- | --- |\n");
+            }
+            None => {
+                out.push_str("\n| --- | --- | --- | --- |\n");
+            }
+        }
+        for course in gradebook.courses() {
+            let grade = current_mark(course).map(|m| m.calculated_grade_with(&self.rounding)).unwrap_or_default();
+            let title = format!("{}{}", course.title, tag_list(course, self.tags));
+            out.push_str(&format!("| {} | {} | {} | {} |", cell(&title), course.period,
+                cell(&course.staff), cell(&grade)));
+            if let Some(goals) = self.goals {
+                let target = goals.target_for(course).map(|t| format!("{}%", t)).unwrap_or(String::new());
+                out.push_str(&format!(" {} |", target));
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+
+        if let Some(gpa) = gpa(gradebook) {
+            out.push_str(&format!("**{}:** {:.2}\n\n", s.gpa, gpa));
+        }
+
+        let missing = match self.overlay {
+            Some(o) => o.missing(gradebook),
+            None => missing_assignments(gradebook),
+        };
+        out.push_str(&format!("## {}\n\n", capitalize(&s.missing_work)));
+        if missing.is_empty() {
+            out.push_str(&format!("{}.\n\n", capitalize(&s.none)));
+        } else {
+            for (course, a) in missing {
+                out.push_str(&format!("- **{}**: {} ({} {})\n", course.title, a.measure, s.due,
+                    a.due_date.format("%Y-%m-%d")));
+            }
+            out.push('\n');
+        }
+
+        for course in gradebook.courses() {
+            let progress = self.goals.and_then(|g| g.progress(course));
+            out.push_str(&course_section(course, self.overlay, progress.as_ref(), &self.rounding, self.tags, s));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    const GRADEBOOK: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    fn fixture() -> Gradebook {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses[0].marks[0].assignments[1].notes = "Missing | see me\nsoon".to_string();
+        gradebook
+    }
+
+    #[test]
+    fn renders_the_summary_missing_work_and_each_course() {
+        let report = gradebook(&fixture());
+
+        assert!(report.starts_with("# Gradebook\n\n| Course | Period | Teacher | Grade |\n| --- | --- | --- | --- |\n"));
+        assert!(report.contains("| English 10 (EN1002) | 2 | Okafor, Chris | A- (91.2) |\n"));
+        assert!(report.contains("**GPA (unweighted):** 3.70\n"));
+        assert!(report.contains("## Missing work\n\n- **English 10 (EN1002)**: Reading Log (due 2018-10-05)\n"));
+        assert!(report.contains("## Chemistry (SC2010)\n\nPeriod 3 · Room Lab 2 · Nguyen, Sam\n"));
+        assert!(report.contains("### Quarter 1: N/A (0)\n\nNo assignments.\n"));
+    }
+
+    #[test]
+    fn escapes_pipes_and_line_breaks_in_table_cells() {
+        let report = gradebook(&fixture());
+
+        assert!(report.contains(
+            "| 2018-10-05 | Reading Log | Essays | Not Due | 20 Points Possible | Missing \\| see me soon |\n"));
+    }
+
+    #[test]
+    fn checks_off_completed_work_and_drops_it_from_missing() {
+        let mut overlay = CompletionOverlay::new();
+        overlay.mark_done("2002", Utc.with_ymd_and_hms(2018, 10, 4, 20, 0, 0).unwrap());
+        let report = gradebook_with(&fixture(), &overlay);
+
+        assert!(report.contains("## Missing work\n\nNone.\n"));
+        assert!(report.contains("| 2018-10-05 | Reading Log ✓ |"));
+    }
+
+    #[test]
+    fn says_so_when_there_are_no_courses() {
+        let mut empty = fixture();
+        empty.courses.clear();
+
+        assert_eq!(gradebook(&empty), "# Gradebook\n\nNo courses.\n");
+    }
+}
@@ -0,0 +1,130 @@
+use gradebook::{Assignment, Course, Gradebook, Mark};
+use turnin::{TurnInMarkers, TurnInStatus};
+
+pub mod markdown;
+pub mod pdf;
+
+// unweighted 4.0 scale; anything that isn't a letter grade (e.g. "P" or "N/A") doesn't count
+pub fn grade_points(grade: &str) -> Option<f64> {
+    let grade = grade.trim();
+    let mut chars = grade.chars();
+
+    let base = match chars.next() {
+        Some('A') | Some('a') => 4.0,
+        Some('B') | Some('b') => 3.0,
+        Some('C') | Some('c') => 2.0,
+        Some('D') | Some('d') => 1.0,
+        Some('F') | Some('f') => { return if grade.len() == 1 { Some(0.0) } else { None }; }
+        _ => { return None; }
+    };
+
+    match chars.as_str() {
+        "" => Some(base),
+        "+" if base < 4.0 => Some(base + 0.3),
+        "+" => Some(base),
+        "-" => Some(base - 0.3),
+        _ => None,
+    }
+}
+
+// the mark currently being graded (see `Course::official_mark`)
+pub fn current_mark(course: &Course) -> Option<&Mark> {
+    course.official_mark()
+}
+
+pub fn gpa(gradebook: &Gradebook) -> Option<f64> {
+    let points: Vec<f64> = gradebook.courses().iter()
+        .filter_map(|c| current_mark(c))
+        .filter_map(|m| grade_points(&m.calculated_score_string))
+        .collect();
+
+    if points.is_empty() {
+        None
+    } else {
+        Some(points.iter().sum::<f64>() / points.len() as f64)
+    }
+}
+
+pub fn missing_assignments(gradebook: &Gradebook) -> Vec<(&Course, &Assignment)> {
+    gradebook.courses().iter()
+        .flat_map(|c| {
+            current_mark(c).into_iter()
+                .flat_map(|m| m.assignments().iter())
+                .filter(|a| a.is_missing())
+                .map(move |a| (c, a))
+        })
+        .collect()
+}
+
+// e.g. everything turned in late this term, for a "late work" report
+pub fn assignments_with_status<'a>(gradebook: &'a Gradebook, status: TurnInStatus, markers: &TurnInMarkers)
+    -> Vec<(&'a Course, &'a Assignment)> {
+
+    gradebook.courses().iter()
+        .flat_map(|c| {
+            current_mark(c).into_iter()
+                .flat_map(|m| m.assignments().iter())
+                .filter(|a| a.turn_in_status_with(markers) == status)
+                .map(move |a| (c, a))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRADEBOOK: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    #[test]
+    fn scores_letter_grades_on_a_four_point_scale() {
+        let cases = [
+            ("A", Some(4.0)),
+            ("A+", Some(4.0)),
+            ("a-", Some(3.7)),
+            (" B+ ", Some(3.3)),
+            ("C", Some(2.0)),
+            ("D-", Some(0.7)),
+            ("F", Some(0.0)),
+            ("F-", None),
+            ("P", None),
+            ("N/A", None),
+            ("", None),
+            ("B++", None),
+        ];
+
+        for &(grade, expected) in cases.iter() {
+            assert_eq!(grade_points(grade).map(|p| (p * 10.0).round() / 10.0), expected, "{:?}", grade);
+        }
+    }
+
+    #[test]
+    fn averages_only_the_courses_with_a_letter_grade() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        // Chemistry is "N/A", so only English's A- counts
+        assert_eq!(gpa(&gradebook).map(|g| (g * 100.0).round()), Some(370.0));
+
+        gradebook.courses[1].marks[0].calculated_score_string = "B".to_string();
+        assert_eq!(gpa(&gradebook).map(|g| (g * 100.0).round()), Some(335.0));
+
+        gradebook.courses.clear();
+        assert_eq!(gpa(&gradebook), None);
+    }
+
+    #[test]
+    fn lists_missing_and_late_work_from_the_current_mark() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        assert!(missing_assignments(&gradebook).is_empty());
+
+        gradebook.courses[0].marks[0].assignments[1].notes = "Missing".to_string();
+        let missing = missing_assignments(&gradebook);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].0.period, 2);
+        assert_eq!(missing[0].1.measure, "Reading Log");
+
+        let flagged = assignments_with_status(&gradebook, TurnInStatus::Missing, &TurnInMarkers::default());
+        let measures: Vec<&str> = flagged.iter().map(|&(_, a)| a.measure.as_str()).collect();
+        assert_eq!(measures, vec!["Reading Log", "Vocabulary Check"]);
+        assert!(assignments_with_status(&gradebook, TurnInStatus::Late, &TurnInMarkers::default()).is_empty());
+    }
+}
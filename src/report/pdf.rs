@@ -0,0 +1,311 @@
+// A printable progress report written as a bare PDF 1.4 document. Everything is set in the
+// standard Helvetica fonts, which every viewer has built in, so nothing needs to be embedded;
+// the flip side is that only Latin-1 text can be shown and anything else becomes `?`.
+use std::io::{self, Write};
+
+use super::{current_mark, gpa, missing_assignments};
+
+use gradebook::*;
+
+use chrono::{DateTime, Utc};
+
+const PAGE_WIDTH: f64 = 612.0;
+const PAGE_HEIGHT: f64 = 792.0;
+const MARGIN: f64 = 50.0;
+const LINE_HEIGHT: f64 = 14.0;
+
+// (x offset, max characters) of each column in the course table
+const COLUMNS: [(f64, usize); 5] = [(0.0, 34), (200.0, 6), (240.0, 24), (380.0, 14), (450.0, 18)];
+
+fn escape(s: &str) -> String {
+    s.chars().fold(String::with_capacity(s.len()), |mut acc, c| {
+        match c {
+            '(' | ')' | '\\' => { acc.push('\\'); acc.push(c); }
+            c if (c as u32) < 0x20 => acc.push(' '),
+            c if (c as u32) < 0x80 => acc.push(c),
+            c if (c as u32) < 0x100 => acc.push_str(&format!("\\{:03o}", c as u32)),
+            _ => acc.push('?'),
+        }
+        acc
+    })
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let mut t: String = s.chars().take(max - 3).collect();
+        t.push_str("...");
+        t
+    }
+}
+
+struct Page {
+    content: String,
+    y: f64,
+}
+
+struct Document {
+    pages: Vec<Page>,
+}
+
+impl Document {
+    fn new() -> Document {
+        let mut d = Document {
+            pages: Vec::new(),
+        };
+        d.new_page();
+        d
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page {
+            content: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        });
+    }
+
+    // moves down `lines` lines, starting a new page if they wouldn't fit
+    fn advance(&mut self, lines: f64) -> f64 {
+        if self.pages.last().map(|p| p.y - lines * LINE_HEIGHT < MARGIN).unwrap_or(true) {
+            self.new_page();
+        }
+
+        let page = self.pages.last_mut().unwrap();
+        page.y -= lines * LINE_HEIGHT;
+        page.y
+    }
+
+    fn text_at(&mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+        let font = if bold { "F2" } else { "F1" };
+        let page = self.pages.last_mut().unwrap();
+        page.content.push_str(&format!("BT /{} {} Tf {:.2} {:.2} Td ({}) Tj ET\n", font, size, x, y, escape(text)));
+    }
+
+    fn line(&mut self, size: f64, bold: bool, text: &str) {
+        let y = self.advance(size / 10.0 + 0.2);
+        self.text_at(MARGIN, y, size, bold, text);
+    }
+
+    fn row(&mut self, bold: bool, cells: &[String]) {
+        let y = self.advance(1.0);
+        for (&(x, max), c) in COLUMNS.iter().zip(cells) {
+            self.text_at(MARGIN + x, y, 10.0, bold, &truncate(c, max));
+        }
+    }
+
+    fn rule(&mut self) {
+        let y = self.advance(0.5) + LINE_HEIGHT * 0.25;
+        let page = self.pages.last_mut().unwrap();
+        page.content.push_str(&format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", MARGIN, y, PAGE_WIDTH - MARGIN, y));
+    }
+
+    fn skip(&mut self) {
+        self.advance(0.6);
+    }
+
+    fn write<W: Write>(self, w: &mut W) -> io::Result<()> {
+        // 1: catalog, 2: page tree, 3/4: fonts, then a page and its content stream for each page
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            String::new(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_string(),
+        ];
+        let mut kids = Vec::new();
+
+        for page in self.pages {
+            let page_id = objects.len() + 1;
+            kids.push(format!("{} 0 R", page_id));
+            objects.push(format!(concat!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] ",
+                "/Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>"),
+                PAGE_WIDTH, PAGE_HEIGHT, page_id + 1));
+            objects.push(format!("<< /Length {} >>\nstream\n{}endstream", page.content.len(), page.content));
+        }
+        objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len());
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"%PDF-1.4\n");
+
+        let mut offsets = Vec::new();
+        for (i, o) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, o).as_bytes());
+        }
+
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1, xref).as_bytes());
+
+        w.write_all(&out)
+    }
+}
+
+fn score_in(gradebook: &Gradebook, title: &CourseTitle) -> Option<f64> {
+    gradebook.courses().iter()
+        .find(|c| &c.title == title)
+        .and_then(current_mark)
+        .map(|m| m.calculated_score_raw)
+}
+
+// change in the current mark's score since the oldest snapshot in `history` that has the course
+fn trend(course: &Course, history: &[(DateTime<Utc>, &Gradebook)]) -> String {
+    let current = match current_mark(course) {
+        Some(m) => m.calculated_score_raw,
+        None => { return String::new(); }
+    };
+
+    history.iter()
+        .filter_map(|&(at, gb)| score_in(gb, &course.title).map(|s| (at, s)))
+        .next()
+        .map(|(at, s)| format!("{:+.1} since {}", current - s, at.format("%m/%d")))
+        .unwrap_or(String::new())
+}
+
+// `history` is optional (pass an empty slice); when given, oldest first, the course table gets
+// a trend column and each course's score over time is listed at the end
+pub fn progress_report<W: Write>(w: &mut W, gradebook: &Gradebook, history: &[(DateTime<Utc>, &Gradebook)])
+    -> io::Result<()> {
+
+    let mut doc = Document::new();
+
+    doc.line(18.0, true, "Progress Report");
+    let period = &gradebook.reporting_period;
+    doc.line(10.0, false, &format!("{} ({} - {})", period.grade_period, period.start_date.format("%m/%d/%Y"),
+        period.end_date.format("%m/%d/%Y")));
+    if let Some(gpa) = gpa(gradebook) {
+        doc.line(10.0, false, &format!("Unweighted GPA: {:.2}", gpa));
+    }
+    doc.skip();
+
+    let trends = !history.is_empty();
+    let mut header = vec!["Course".to_string(), "Period".to_string(), "Teacher".to_string(), "Grade".to_string()];
+    if trends {
+        header.push("Trend".to_string());
+    }
+    doc.row(true, &header);
+    doc.rule();
+
+    for course in gradebook.courses() {
+        let mut row = vec![
+            course.title.to_string(),
+            course.period.to_string(),
+            course.staff.clone(),
+            current_mark(course).map(|m| m.calculated_grade()).unwrap_or_default(),
+        ];
+        if trends {
+            row.push(trend(course, history));
+        }
+        doc.row(false, &row);
+    }
+    doc.skip();
+
+    let missing = missing_assignments(gradebook);
+    if !missing.is_empty() {
+        doc.line(13.0, true, "Missing Work");
+        for (course, a) in missing {
+            doc.line(10.0, false, &format!("{}: {} (due {})", course.title, a.measure, a.due_date.format("%m/%d")));
+        }
+        doc.skip();
+    }
+
+    if trends {
+        doc.line(13.0, true, "Grade History");
+        for course in gradebook.courses() {
+            let points: Vec<String> = history.iter()
+                .filter_map(|&(at, gb)| score_in(gb, &course.title).map(|s| format!("{} {:.1}", at.format("%m/%d"), s)))
+                .collect();
+            if points.is_empty() {
+                continue;
+            }
+
+            // keep the most recent entries if they don't all fit on one line
+            let shown = points.len().saturating_sub(8);
+            doc.line(10.0, true, &course.title.to_string());
+            doc.line(10.0, false, &points[shown..].join("   "));
+        }
+    }
+
+    doc.write(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const GRADEBOOK: &str = include_str!("../../fixtures/gradebook_no_standards.xml");
+
+    fn render(gradebook: &Gradebook, history: &[(DateTime<Utc>, &Gradebook)]) -> String {
+        let mut out = Vec::new();
+        progress_report(&mut out, gradebook, history).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn escapes_strings_and_replaces_what_latin1_cant_show() {
+        assert_eq!(escape("Essay (draft) \\ final"), "Essay \\(draft\\) \\\\ final");
+        assert_eq!(escape("Café"), "Caf\\351");
+        assert_eq!(escape("Nguyễn\ttab"), "Nguy?n tab");
+    }
+
+    #[test]
+    fn truncates_long_cells_with_an_ellipsis() {
+        assert_eq!(truncate("Chemistry", 9), "Chemistry");
+        assert_eq!(truncate("Chemistry (SC2010)", 9), "Chemis...");
+    }
+
+    #[test]
+    fn writes_a_cross_reference_table_that_points_at_each_object() {
+        let pdf = render(&Gradebook::from_xml(GRADEBOOK).unwrap(), &[]);
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("<< /Type /Pages /Kids [5 0 R] /Count 1 >>"));
+
+        let xref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 7\n"));
+        let offsets: Vec<usize> = pdf[xref..].lines().skip(3).take(6)
+            .map(|l| l[..10].parse().unwrap())
+            .collect();
+        for (i, offset) in offsets.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(&format!("{} 0 obj\n", i + 1)), "object {}", i + 1);
+        }
+    }
+
+    #[test]
+    fn lists_courses_missing_work_and_trends() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses[0].marks[0].assignments[1].notes = "Missing".to_string();
+        let mut earlier = gradebook.clone();
+        earlier.courses[0].marks[0].calculated_score_raw = 88.7;
+        let at = Utc.with_ymd_and_hms(2018, 9, 28, 12, 0, 0).unwrap();
+
+        let pdf = render(&gradebook, &[(at, &earlier)]);
+
+        assert!(pdf.contains("(Quarter 1 \\(09/04/2018 - 11/09/2018\\)) Tj"));
+        assert!(pdf.contains("(Unweighted GPA: 3.70) Tj"));
+        assert!(pdf.contains("(Trend) Tj"));
+        assert!(pdf.contains("(+2.5 since 09/28) Tj"));
+        assert!(pdf.contains("(English 10 \\(EN1002\\): Reading Log \\(due 10/05\\)) Tj"));
+        assert!(pdf.contains("(09/28 88.7) Tj"));
+    }
+
+    #[test]
+    fn starts_a_new_page_when_the_table_runs_off_the_bottom() {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        let course = gradebook.courses[1].clone();
+        for _ in 0..60 {
+            gradebook.courses.push(course.clone());
+        }
+
+        let pdf = render(&gradebook, &[]);
+
+        assert!(pdf.contains("/Kids [5 0 R 7 0 R] /Count 2"));
+        assert!(!pdf.contains("(Trend) Tj"));
+    }
+}
@@ -0,0 +1,100 @@
+// How changes to each course are delivered, e.g. instant alerts for AP classes but only a digest
+// for PE. Rules are checked in order and the first one matching a course wins; courses no rule
+// matches get the default.
+use diff::{Changeset, CourseChanges};
+use gradebook::Course;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delivery {
+    // sent as soon as they're seen, and included in digests
+    Immediate,
+    DigestOnly,
+    Mute,
+}
+
+#[derive(Clone, Debug)]
+pub enum CourseMatcher {
+    // matched case-insensitively against anywhere in the course title
+    Title(String),
+    Period(i8),
+}
+
+impl CourseMatcher {
+    pub fn matches(&self, course: &Course) -> bool {
+        match *self {
+            CourseMatcher::Title(ref pattern) => {
+                course.title.to_string().to_lowercase().contains(&pattern.to_lowercase())
+            }
+            CourseMatcher::Period(period) => course.period == period,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NotificationRules {
+    default: Delivery,
+    rules: Vec<(CourseMatcher, Delivery)>,
+}
+
+impl Default for NotificationRules {
+    fn default() -> NotificationRules {
+        NotificationRules::new()
+    }
+}
+
+impl NotificationRules {
+    pub fn new() -> NotificationRules {
+        NotificationRules {
+            default: Delivery::Immediate,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn rule(mut self, matcher: CourseMatcher, delivery: Delivery) -> NotificationRules {
+        self.rules.push((matcher, delivery));
+        self
+    }
+
+    pub fn default_delivery(mut self, delivery: Delivery) -> NotificationRules {
+        self.default = delivery;
+        self
+    }
+
+    pub fn delivery_for(&self, course: &Course) -> Delivery {
+        self.rules.iter()
+            .find(|&(matcher, _)| matcher.matches(course))
+            .map(|&(_, delivery)| delivery)
+            .unwrap_or(self.default)
+    }
+
+    // the changes to courses whose delivery `keep` accepts; None if there aren't any
+    pub fn select<F: Fn(Delivery) -> bool>(&self, changeset: &Changeset, keep: F) -> Option<Changeset> {
+        let changes: Vec<CourseChanges> = changeset.changes.iter()
+            .filter(|ccs| {
+                match ccs.new.as_ref().or(ccs.old.as_ref()) {
+                    Some(course) => keep(self.delivery_for(course)),
+                    None => false,
+                }
+            })
+            .cloned()
+            .collect();
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(Changeset {
+                old: changeset.old.clone(),
+                new: changeset.new.clone(),
+                changes,
+            })
+        }
+    }
+
+    pub fn immediate(&self, changeset: &Changeset) -> Option<Changeset> {
+        self.select(changeset, |d| d == Delivery::Immediate)
+    }
+
+    pub fn digest(&self, changeset: &Changeset) -> Option<Changeset> {
+        self.select(changeset, |d| d != Delivery::Mute)
+    }
+}
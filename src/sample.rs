@@ -0,0 +1,353 @@
+// Plausible fake gradebooks for demos, UI work, and load testing the diff and export code. Output
+// is deterministic for a given seed, and `gradebook_as_of` shows the same term at different
+// points in time (assignments appear once assigned and get graded once due), so a run of dates
+// makes a realistic snapshot history:
+//
+//     let sample = SampleGenerator::new().courses(7).assignments_per_course(40).seed(42);
+//     let history: Vec<_> = dates.iter().map(|d| (*d, sample.gradebook_as_of(d.date().naive_utc()))).collect();
+use std::cmp::Reverse;
+
+use gradebook::*;
+
+use chrono::{Duration, NaiveDate};
+
+const COURSES: &[(&str, &str, &str)] = &[
+    ("Algebra 2", "MA2201", "Nguyen, Linh"),
+    ("AP Biology", "SC4410", "Okafor, Chidi"),
+    ("Chemistry", "SC3301", "Larsen, Ingrid"),
+    ("English 10", "EN1002", "Alvarez, Sofia"),
+    ("Spanish 3", "WL3303", "Moreau, Julien"),
+    ("US History", "SS2104", "Whitfield, Grace"),
+    ("Physical Education", "PE1101", "Kowalski, Adam"),
+    ("Computer Science", "CS2201", "Tanaka, Hiro"),
+    ("Ceramics", "AR1105", "Bianchi, Marco"),
+];
+
+const TITLES: &[&str] = &[
+    "Chapter Review", "Problem Set", "Reading Response", "Lab Report", "Unit Quiz", "Unit Test",
+    "Project", "Worksheet", "Essay Draft", "Presentation", "Vocabulary Check", "Warm-up",
+];
+
+const STANDARDS: &[&str] = &[
+    "Communicates reasoning clearly",
+    "Applies concepts to new problems",
+    "Uses evidence to support claims",
+    "Demonstrates procedural fluency",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradingStyle {
+    Points,
+    Standards,
+    // each course picks one
+    Mixed,
+}
+
+// an assignment type: how often it comes up relative to the other kinds, its weight (in percent)
+// in the grade calculation, and what each one is worth
+#[derive(Clone, Debug)]
+pub struct AssignmentKind {
+    pub name: String,
+    pub frequency: u32,
+    pub weight: f64,
+    pub points: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SampleGenerator {
+    assignments_per_course: usize,
+    courses: usize,
+    kinds: Vec<AssignmentKind>,
+    seed: u64,
+    style: GradingStyle,
+    term_start: NaiveDate,
+    term_weeks: u32,
+}
+
+// xorshift64*, so samples don't need a dependency and stay the same across platforms
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed })
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 { 0 } else { self.next() % n }
+    }
+
+    // uniform in [lo, hi)
+    fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (self.next() >> 11) as f64 / (1u64 << 53) as f64 * (hi - lo)
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+fn letter(pct: f64) -> &'static str {
+    match pct {
+        p if p >= 93.0 => "A",
+        p if p >= 90.0 => "A-",
+        p if p >= 87.0 => "B+",
+        p if p >= 83.0 => "B",
+        p if p >= 80.0 => "B-",
+        p if p >= 77.0 => "C+",
+        p if p >= 70.0 => "C",
+        p if p >= 60.0 => "D",
+        _ => "F",
+    }
+}
+
+fn round(n: f64) -> f64 {
+    (n * 10.0).round() / 10.0
+}
+
+impl Default for SampleGenerator {
+    fn default() -> SampleGenerator {
+        let kind = |name: &str, frequency, weight, points| AssignmentKind {
+            name: name.to_string(),
+            frequency,
+            weight,
+            points,
+        };
+
+        SampleGenerator {
+            assignments_per_course: 25,
+            courses: 6,
+            kinds: vec![
+                kind("Homework", 6, 20.0, 10.0),
+                kind("Classwork", 4, 15.0, 5.0),
+                kind("Quiz", 2, 25.0, 20.0),
+                kind("Test", 1, 40.0, 100.0),
+            ],
+            seed: 1,
+            style: GradingStyle::Points,
+            term_start: NaiveDate::from_ymd_opt(2016, 9, 6).unwrap(),
+            term_weeks: 9,
+        }
+    }
+}
+
+impl SampleGenerator {
+    pub fn new() -> SampleGenerator {
+        Default::default()
+    }
+
+    pub fn courses(mut self, courses: usize) -> SampleGenerator {
+        self.courses = courses;
+        self
+    }
+
+    pub fn assignments_per_course(mut self, assignments: usize) -> SampleGenerator {
+        self.assignments_per_course = assignments;
+        self
+    }
+
+    // replaces the default homework/classwork/quiz/test mix
+    pub fn assignment_mix(mut self, kinds: Vec<AssignmentKind>) -> SampleGenerator {
+        if !kinds.is_empty() {
+            self.kinds = kinds;
+        }
+        self
+    }
+
+    pub fn style(mut self, style: GradingStyle) -> SampleGenerator {
+        self.style = style;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> SampleGenerator {
+        self.seed = seed;
+        self
+    }
+
+    // a term is at least a week long, so 0 is taken as 1
+    pub fn term(mut self, start: NaiveDate, weeks: u32) -> SampleGenerator {
+        self.term_start = start;
+        self.term_weeks = weeks.max(1);
+        self
+    }
+
+    pub fn term_end(&self) -> NaiveDate {
+        self.term_start + Duration::weeks(self.term_weeks as i64)
+    }
+
+    // the term as it stands on its last day
+    pub fn gradebook(&self) -> Gradebook {
+        self.gradebook_as_of(self.term_end())
+    }
+
+    pub fn gradebook_as_of(&self, as_of: NaiveDate) -> Gradebook {
+        let mut rng = Rng::new(self.seed);
+        let term_end = self.term_end();
+
+        let courses = (0..self.courses)
+            .map(|i| {
+                let standards_based = match self.style {
+                    GradingStyle::Points => false,
+                    GradingStyle::Standards => true,
+                    GradingStyle::Mixed => rng.below(3) == 0,
+                };
+                self.course(&mut rng, i, standards_based, as_of)
+            })
+            .collect();
+
+        let reporting_periods = (0..4)
+            .map(|i| ReportPeriod {
+                end_date: self.term_start + Duration::weeks(self.term_weeks as i64 * (i + 1)) - Duration::days(1),
+                grade_period: format!("Quarter {}", i + 1),
+                index: i as i8,
+                start_date: self.term_start + Duration::weeks(self.term_weeks as i64 * i),
+            })
+            .collect();
+
+        Gradebook {
+            courses,
+            last_updated: None,
+            reporting_period: ReportingPeriod {
+                end_date: term_end - Duration::days(1),
+                grade_period: "Quarter 1".to_string(),
+                start_date: self.term_start,
+            },
+            reporting_periods,
+        }
+    }
+
+    fn course(&self, rng: &mut Rng, index: usize, standards_based: bool, as_of: NaiveDate) -> Course {
+        let &(name, id, staff) = &COURSES[index % COURSES.len()];
+        // how well this student does in this course, so grades aren't uniformly random
+        let ability = rng.range(0.65, 1.0);
+        let total_frequency: u32 = self.kinds.iter().map(|k| k.frequency).sum();
+
+        let mut assignments = Vec::new();
+        for n in 0..self.assignments_per_course {
+            let mut roll = rng.below(total_frequency as u64) as u32;
+            let kind = self.kinds.iter()
+                .find(|k| if roll < k.frequency { true } else { roll -= k.frequency; false })
+                .unwrap_or(&self.kinds[0]);
+
+            let date = self.term_start + Duration::days(rng.below(self.term_weeks as u64 * 7) as i64);
+            let due_date = date + Duration::days(1 + rng.below(7) as i64);
+            let pct = (ability + rng.range(-0.15, 0.1)).clamp(0.0, 1.05);
+            let forgotten = rng.below(25) == 0;
+
+            if date > as_of {
+                continue;
+            }
+
+            let graded = due_date < as_of;
+            let (score, points) = if !graded {
+                (AssignmentScore::NotDue, AssignmentPoints::Ungraded(Points(kind.points)))
+            } else if standards_based {
+                (AssignmentScore::SeeStandards, AssignmentPoints::Ungraded(Points(kind.points)))
+            } else {
+                let (earned, possible) = (Points(if forgotten { 0.0 } else { round(kind.points * pct) }), Points(kind.points));
+                (AssignmentScore::Score(earned, possible), AssignmentPoints::Graded(earned, possible))
+            };
+
+            let standards = if standards_based {
+                vec![Standard {
+                    subject: name.to_string(),
+                    mark: if graded { format!("{}", (pct * 4.0).round()) } else { String::new() },
+                    description: rng.pick(STANDARDS).to_string(),
+                    proficiency: if graded { Some(round(pct * 4.0)) } else { None },
+                    proficiency_max_value: 4.0,
+                    standard_screen_assignments: Vec::new(),
+                }]
+            } else {
+                Vec::new()
+            };
+
+            assignments.push(Assignment {
+                _type: kind.name.clone(),
+                gradebook_id: format!("{}{:04}", id, n),
+                measure: format!("{} {}", rng.pick(TITLES), n + 1),
+                date,
+                due_date,
+                score,
+                score_type: if standards_based { "Standards".to_string() } else { "Raw Score".to_string() },
+                points,
+                notes: if graded && forgotten && !standards_based { "Missing".to_string() } else { String::new() },
+                teacher_id: format!("T{}", id),
+                student_id: "123456".to_string(),
+                has_drop_box: rng.below(4) == 0,
+                drop_start_date: date,
+                drop_end_date: due_date,
+                standards,
+                class_stats: None,
+            });
+        }
+        assignments.sort_by_key(|a| Reverse(a.due_date));
+
+        let summary: Vec<AssignmentGradeCalc> = self.kinds.iter()
+            .map(|k| {
+                let (earned, possible) = assignments.iter()
+                    .filter(|a| a._type == k.name)
+                    .fold((0.0, 0.0), |acc, a| match a.points {
+                        AssignmentPoints::Graded(e, p) => (acc.0 + e.value(), acc.1 + p.value()),
+                        _ => acc,
+                    });
+                let pct = if possible > 0.0 { earned / possible * 100.0 } else { 0.0 };
+
+                AssignmentGradeCalc {
+                    _type: k.name.clone(),
+                    calculated_mark: letter(pct).to_string(),
+                    points: earned,
+                    points_possible: possible,
+                    weight: AssignmentGradeCalcWeight::Percentage(k.weight),
+                    weighted_pct: AssignmentGradeCalcWeight::Percentage(round(pct * k.weight / 100.0)),
+                }
+            })
+            .collect();
+
+        let total_weight: f64 = summary.iter()
+            .filter(|s| s.points_possible > 0.0)
+            .map(|s| match s.weight { AssignmentGradeCalcWeight::Percentage(w) => w, _ => 0.0 })
+            .sum();
+        let weighted: f64 = summary.iter()
+            .map(|s| match s.weighted_pct { AssignmentGradeCalcWeight::Percentage(w) => w, _ => 0.0 })
+            .sum();
+        let raw = if total_weight > 0.0 { round(weighted / total_weight * 100.0) } else { 0.0 };
+
+        let standard_views = if standards_based {
+            STANDARDS.iter().enumerate().map(|(i, s)| StandardView {
+                cal_value: round(ability * 4.0),
+                description: s.to_string(),
+                mark: format!("{}", (ability * 4.0).round()),
+                proficiency: Some(round(ability * 4.0)),
+                proficiency_max_value: 4.0,
+                standard_assignment_views: Vec::new(),
+                subject: name.to_string(),
+                subject_id: i as i8,
+            }).collect()
+        } else {
+            Vec::new()
+        };
+
+        Course {
+            highlight_percentage_cut_off_for_progress_bar: 50,
+            marks: vec![Mark {
+                assignments,
+                calculated_score_raw: raw,
+                calculated_score_string: if total_weight > 0.0 { letter(raw).to_string() } else { "N/A".to_string() },
+                grade_calculation_summary: if standards_based { Vec::new() } else { summary },
+                mark_name: "Q1".to_string(),
+                standard_views,
+            }],
+            period: (index % 8) as i8 + 1,
+            room: format!("{}", 100 + index * 7),
+            staff: staff.to_string(),
+            staff_email: format!("{}@example.org", staff.split(',').next().unwrap_or("staff").to_lowercase()),
+            title: CourseTitle::Parsed(name.to_string(), id.to_string()),
+        }
+    }
+}
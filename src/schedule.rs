@@ -0,0 +1,435 @@
+// The `StudentClassList` document: the classes the student is enrolled in for a term, the year's
+// terms, and today's bell schedule for each school they attend (with start and end times per
+// period). Periods are kept as given since some districts use names like "HR" or "0A".
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use api::SVUEResponse;
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUEClient, SVUERequestError};
+use decoder::*;
+use diff::{FallbackPairable, Pairable};
+
+use chrono::{NaiveDate, NaiveTime};
+use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassListing {
+    pub course_title: String,
+    pub period: String,
+    pub room: String,
+    // the district's id for the section
+    pub section_gu: String,
+    pub teacher: String,
+    pub teacher_email: String,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+    pub begin_date: NaiveDate,
+    pub code: String,
+    pub end_date: NaiveDate,
+    pub index: i8,
+    pub name: String,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BellPeriod {
+    pub class_name: String,
+    // None when the district leaves them blank, as some do for periods without a fixed time
+    pub end_time: Option<NaiveTime>,
+    pub period: String,
+    pub room: String,
+    pub start_time: Option<NaiveTime>,
+    pub teacher: String,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BellSchedule {
+    // e.g. "Regular" or "Late Start"
+    pub name: String,
+    pub periods: Vec<BellPeriod>,
+    pub school_name: String,
+}
+
+field_slice_helpers!(BellSchedule, {
+    periods => BellPeriod
+});
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schedule {
+    pub classes: Vec<ClassListing>,
+    // the day `today` is for; districts leave it out on days without school
+    pub date: Option<NaiveDate>,
+    pub term_index: Option<i8>,
+    pub term_name: String,
+    pub terms: Vec<Term>,
+    pub today: Vec<BellSchedule>,
+}
+
+field_slice_helpers!(Schedule, {
+    classes => ClassListing,
+    terms => Term,
+    today => BellSchedule
+});
+
+// classes are paired by title, like a gradebook's courses
+impl<'a> Pairable<'a, String> for ClassListing {
+    fn unique_key(&'a self) -> &'a String {
+        &self.course_title
+    }
+}
+
+// a retitled class is still the same section
+impl FallbackPairable for ClassListing {
+    fn fallback_eq(&self, other: &ClassListing) -> bool {
+        !self.section_gu.is_empty() && self.section_gu == other.section_gu
+    }
+}
+
+impl ClassListing {
+    pub fn period_number(&self) -> Option<i8> {
+        i8::from_str(self.period.trim()).ok()
+    }
+}
+
+impl BellPeriod {
+    pub fn period_number(&self) -> Option<i8> {
+        i8::from_str(self.period.trim()).ok()
+    }
+}
+
+impl Term {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date >= self.begin_date && date <= self.end_date
+    }
+}
+
+impl Schedule {
+    #[cfg(feature="client")]
+    pub fn retrieve(user: &str, password: &str) -> Result<Schedule, SVUERequestError> {
+        Self::retrieve_with(&SVUEClient::new(user, password), None)
+    }
+
+    // `term` is the index of a term, as in `Term::index`
+    #[cfg(feature="client")]
+    pub fn retrieve_for_term(user: &str, password: &str, term: i8) -> Result<Schedule, SVUERequestError> {
+        Self::retrieve_with(&SVUEClient::new(user, password), Some(term))
+    }
+
+    #[cfg(feature="client")]
+    pub fn retrieve_with(client: &SVUEClient, term: Option<i8>) -> Result<Schedule, SVUERequestError> {
+        let resp = client.perform(SVUEAPIAction::RetrieveClassSchedule(term))?;
+
+        Self::from_response(&resp).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<Schedule> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<Schedule> {
+        Self::from_xml_with(xml, &DecodeLimits::new())
+    }
+
+    pub fn from_xml_with(xml: &str, limits: &DecodeLimits) -> DecoderResult<Schedule> {
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+        limits.reset();
+
+        match events_iter.next() {
+            Some(Ok(event)) => Self::from_event(event, &mut events_iter, limits),
+            Some(Err(e)) => Err(DecodingError::EventError(e)),
+            None => Err(DecodingError::UnexpectedEnd),
+        }
+    }
+
+    pub fn class_in_period(&self, period: &str) -> Option<&ClassListing> {
+        self.classes.iter().find(|c| c.period.trim() == period.trim())
+    }
+
+    pub fn term_on(&self, date: NaiveDate) -> Option<&Term> {
+        self.terms.iter().find(|t| t.contains(date))
+    }
+}
+
+// "8:05 AM", or "13:05" from districts that use a 24-hour clock; anything else is left out rather
+// than failing the whole schedule
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    let time = time.trim();
+
+    NaiveTime::parse_from_str(time, "%I:%M %p")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()
+}
+
+fn attr_or_empty(attrs: &HashMap<&str, &str>, attr: &str) -> String {
+    attrs.get(attr).map(|v| v.to_string()).unwrap_or_default()
+}
+
+impl SVUEDecodeable for Schedule {
+    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Schedule> {
+        let mut schedule: Schedule = Default::default();
+
+        loop {
+            match events_iter.next() {
+                Some(Ok(event)) => {
+                    match event {
+                        ReaderEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "StudentClassSchedule" => {
+                            let attrs = attributes_vec_to_map(attributes);
+
+                            schedule.term_index = attrs.get("TermIndex").and_then(|i| i8::from_str(i).ok());
+                            schedule.term_name = attr_or_empty(&attrs, "TermIndexName");
+                        }
+                        ReaderEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "TodayScheduleInfoData" => {
+                            let attrs = attributes_vec_to_map(attributes);
+
+                            schedule.date = attrs.get("SchoolDate").and_then(|d| parse_date(d).ok());
+                        }
+                        _ if is_start_of(&event, "ClassListing") => {
+                            schedule.classes.push(ClassListing::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_start_of(&event, "SchoolInfo") => {
+                            schedule.today.push(BellSchedule::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_start_of(&event, "TermListing") => {
+                            schedule.terms.push(Term::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_end_of(&event, "StudentClassSchedule") => {
+                            return Ok(schedule);
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                None => { return Err(DecodingError::UnexpectedEnd); }
+            }
+        }
+    }
+}
+
+impl SVUEDecodeable for ClassListing {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<ClassListing> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "ClassListing" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        let listing = ClassListing {
+                            course_title: limits.text(get_attr!(attrs, "CourseTitle")),
+                            period: get_attr_owned!(attrs, "Period"),
+                            room: attr_or_empty(&attrs, "RoomName"),
+                            section_gu: attr_or_empty(&attrs, "SectionGU"),
+                            teacher: attr_or_empty(&attrs, "Teacher"),
+                            teacher_email: attr_or_empty(&attrs, "TeacherEmail"),
+                        };
+                        skip_element(events_iter)?;
+
+                        Ok(listing)
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+impl SVUEDecodeable for Term {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Term> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "TermListing" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        let term = Term {
+                            begin_date: parse_date!(attrs, "BeginDate"),
+                            code: attr_or_empty(&attrs, "TermCode"),
+                            end_date: parse_date!(attrs, "EndDate"),
+                            index: parse_int!(i8, attrs, "TermIndex"),
+                            name: get_attr_owned!(attrs, "TermName"),
+                        };
+                        // `TermDefCodes` says which marking codes the term covers; nothing uses it
+                        skip_element(events_iter)?;
+
+                        Ok(term)
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+impl SVUEDecodeable for BellSchedule {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<BellSchedule> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "SchoolInfo" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        let name = attr_or_empty(&attrs, "BellSchedName");
+                        let school_name = attr_or_empty(&attrs, "SchoolName");
+                        let mut periods = Vec::new();
+
+                        loop {
+                            match events_iter.next() {
+                                Some(Ok(event)) => {
+                                    match event {
+                                        _ if is_start_of(&event, "ClassInfo") => {
+                                            periods.push(BellPeriod::from_event(event, events_iter, limits)?);
+                                        }
+                                        _ if is_start_of(&event, "Classes") => {}
+                                        _ if is_end_of(&event, "SchoolInfo") => {
+                                            break;
+                                        }
+                                        ReaderEvent::StartElement { .. } => { skip_element(events_iter)?; }
+                                        // end tags, and any stray text or comments between the
+                                        // periods, which isn't worth failing the schedule over
+                                        _ => {}
+                                    }
+                                }
+                                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                                None => { return Err(DecodingError::UnexpectedEnd); }
+                            }
+                        }
+
+                        Ok(BellSchedule {
+                            name,
+                            periods,
+                            school_name,
+                        })
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+impl SVUEDecodeable for BellPeriod {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<BellPeriod> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "ClassInfo" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+
+                        let period = BellPeriod {
+                            class_name: limits.text(&attr_or_empty(&attrs, "ClassName")),
+                            end_time: attrs.get("EndTime").and_then(|t| parse_time(t)),
+                            period: get_attr_owned!(attrs, "Period"),
+                            room: attr_or_empty(&attrs, "RoomName"),
+                            start_time: attrs.get("StartTime").and_then(|t| parse_time(t)),
+                            teacher: attr_or_empty(&attrs, "TeacherName"),
+                        };
+                        skip_element(events_iter)?;
+
+                        Ok(period)
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEDULE: &str = include_str!("../fixtures/class_schedule.xml");
+
+    fn time(h: u32, m: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(h, m, 0)
+    }
+
+    #[test]
+    fn parses_both_clock_formats() {
+        assert_eq!(parse_time("8:05 AM"), time(8, 5));
+        assert_eq!(parse_time("12:30 PM"), time(12, 30));
+        assert_eq!(parse_time(" 13:05 "), time(13, 5));
+        assert_eq!(parse_time("08:05"), time(8, 5));
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("TBA"), None);
+        assert_eq!(parse_time("25:00"), None);
+    }
+
+    #[test]
+    fn decodes_a_class_schedule() {
+        let schedule = Schedule::from_xml(SCHEDULE).unwrap();
+
+        assert_eq!(schedule.term_index, Some(1));
+        assert_eq!(schedule.term_name, "Semester 2");
+        assert_eq!(schedule.date, NaiveDate::from_ymd_opt(2019, 2, 4));
+
+        let periods: Vec<&str> = schedule.classes.iter().map(|c| c.period.as_str()).collect();
+        assert_eq!(periods, vec!["1", "2", "HR"]);
+        assert_eq!(schedule.classes[1], ClassListing {
+            course_title: "Chemistry".to_string(),
+            period: "2".to_string(),
+            room: "Lab 3".to_string(),
+            section_gu: "C-1002".to_string(),
+            teacher: "Larsen, Ingrid".to_string(),
+            teacher_email: "ilarsen@example.org".to_string(),
+        });
+        assert_eq!(schedule.class_in_period("HR").map(|c| c.room.as_str()), Some(""));
+        assert_eq!(schedule.classes[2].period_number(), None);
+
+        assert_eq!(schedule.terms.len(), 2);
+        assert_eq!(schedule.terms[0].code, "S1");
+        assert_eq!(schedule.term_on(NaiveDate::from_ymd_opt(2019, 3, 1).unwrap()).map(|t| t.index), Some(1));
+        assert!(schedule.term_on(NaiveDate::from_ymd_opt(2019, 7, 1).unwrap()).is_none());
+    }
+
+    #[test]
+    fn decodes_the_bell_schedule_with_either_clock_and_blank_times() {
+        let schedule = Schedule::from_xml(SCHEDULE).unwrap();
+        assert_eq!(schedule.today.len(), 1);
+
+        let bells = &schedule.today[0];
+        assert_eq!(bells.name, "Late Start");
+        assert_eq!(bells.school_name, "Lincoln High School");
+
+        let times: Vec<(&str, Option<NaiveTime>, Option<NaiveTime>)> = bells.periods.iter()
+            .map(|p| (p.period.as_str(), p.start_time, p.end_time))
+            .collect();
+        assert_eq!(times, vec![
+            // blank
+            ("0A", None, None),
+            ("1", time(9, 35), time(10, 20)),
+            // a 24-hour clock
+            ("2", time(10, 25), time(13, 5)),
+            // neither format, and no end time at all
+            ("3", None, None),
+        ]);
+        assert_eq!(bells.periods[2].teacher, "Larsen, Ingrid");
+        assert_eq!(bells.periods[1].period_number(), Some(1));
+    }
+
+    #[test]
+    fn tolerates_stray_text_in_a_bell_schedule() {
+        let xml = "<StudentClassSchedule><TodayScheduleInfoData SchoolDate=\"2/4/2019\"><SchoolInfos>\
+            <SchoolInfo SchoolName=\"Lincoln High School\" BellSchedName=\"Regular\">&#160;<!-- periods -->\
+            <Classes>\n<ClassInfo Period=\"1\" ClassName=\"English 10\" StartTime=\"8:05 AM\" EndTime=\"8:55 AM\" />stray\
+            </Classes></SchoolInfo></SchoolInfos></TodayScheduleInfoData></StudentClassSchedule>";
+
+        let schedule = Schedule::from_xml(xml).unwrap();
+        assert_eq!(schedule.today[0].periods.len(), 1);
+        assert_eq!(schedule.today[0].periods[0].start_time, time(8, 5));
+    }
+}
@@ -0,0 +1,269 @@
+// The `StudentSchoolInfo` document: the student's school, its principal and how to reach it, and
+// the staff directory. Districts fill in as much of it as they like, so everything but the school's
+// name may be empty.
+use api::SVUEResponse;
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUEClient, SVUERequestError};
+use decoder::*;
+use teachers::Teacher;
+
+use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Address {
+    pub city: String,
+    pub line1: String,
+    pub line2: String,
+    pub state: String,
+    pub zip: String,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaffMember {
+    pub email: String,
+    pub extension: String,
+    pub name: String,
+    pub phone: String,
+    // e.g. "Teacher" or "Counselor"
+    pub title: String,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SchoolInfo {
+    pub address: Address,
+    pub name: String,
+    pub phone: String,
+    pub principal: String,
+    pub principal_email: String,
+    pub staff: Vec<StaffMember>,
+    pub url: String,
+}
+
+field_slice_helpers!(SchoolInfo, {
+    staff => StaffMember
+});
+
+impl Address {
+    // on one line, skipping whatever's missing
+    pub fn one_line(&self) -> String {
+        let state_zip = vec![self.state.trim(), self.zip.trim()].into_iter()
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        vec![self.line1.trim(), self.line2.trim(), self.city.trim(), state_zip.as_str()].into_iter()
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl StaffMember {
+    // the phone number with the extension, if there is one
+    pub fn full_phone(&self) -> String {
+        match (self.phone.trim(), self.extension.trim()) {
+            (phone, "") => phone.to_string(),
+            ("", ext) => format!("ext. {}", ext),
+            (phone, ext) => format!("{} ext. {}", phone, ext),
+        }
+    }
+}
+
+impl SchoolInfo {
+    #[cfg(feature="client")]
+    pub fn retrieve(user: &str, password: &str) -> Result<SchoolInfo, SVUERequestError> {
+        Self::retrieve_with(&SVUEClient::new(user, password))
+    }
+
+    #[cfg(feature="client")]
+    pub fn retrieve_with(client: &SVUEClient) -> Result<SchoolInfo, SVUERequestError> {
+        let resp = client.perform(SVUEAPIAction::RetrieveSchoolInfo)?;
+
+        Self::from_response(&resp).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<SchoolInfo> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<SchoolInfo> {
+        Self::from_xml_with(xml, &DecodeLimits::new())
+    }
+
+    pub fn from_xml_with(xml: &str, limits: &DecodeLimits) -> DecoderResult<SchoolInfo> {
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+        limits.reset();
+
+        match events_iter.next() {
+            Some(Ok(event)) => Self::from_event(event, &mut events_iter, limits),
+            Some(Err(e)) => Err(DecodingError::EventError(e)),
+            None => Err(DecodingError::UnexpectedEnd),
+        }
+    }
+
+    // the directory entry for a course's teacher (see `teachers::Teacher::of`), going by email
+    // and then by name
+    pub fn contact_for(&self, teacher: &Teacher) -> Option<&StaffMember> {
+        let email = teacher.email.trim();
+        let name = teacher.name.trim();
+
+        self.staff.iter()
+            .find(|s| !email.is_empty() && s.email.trim().eq_ignore_ascii_case(email))
+            .or_else(|| self.staff.iter().find(|s| !name.is_empty() && s.name.trim().eq_ignore_ascii_case(name)))
+    }
+
+    // everyone whose title contains `title`, ignoring case, e.g. "counselor"
+    pub fn staff_titled(&self, title: &str) -> Vec<&StaffMember> {
+        let title = title.trim().to_lowercase();
+
+        self.staff.iter().filter(|s| s.title.to_lowercase().contains(&title)).collect()
+    }
+}
+
+impl SVUEDecodeable for SchoolInfo {
+    fn from_event(_: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<SchoolInfo> {
+        let mut info: SchoolInfo = Default::default();
+
+        loop {
+            match events_iter.next() {
+                Some(Ok(event)) => {
+                    match event {
+                        ReaderEvent::StartElement { ref name, ref attributes, .. } if name.local_name == "StudentSchoolInfoListing" => {
+                            let attrs = attributes_vec_to_map(attributes);
+                            let attr = |name: &str| attrs.get(name).map(|v| v.trim().to_string()).unwrap_or_default();
+
+                            info.address = Address {
+                                city: attr("SchoolCity"),
+                                line1: attr("SchoolAddress"),
+                                line2: attr("SchoolAddress2"),
+                                state: attr("SchoolState"),
+                                zip: attr("SchoolZip"),
+                            };
+                            info.name = get_attr_owned!(attrs, "School");
+                            info.phone = attr("Phone");
+                            info.principal = attr("Principal");
+                            info.principal_email = attr("PrincipalEmail");
+                            info.url = attr("URL");
+                        }
+                        _ if is_start_of(&event, "StaffList") => {
+                            info.staff.push(StaffMember::from_event(event, events_iter, limits)?);
+                        }
+                        _ if is_end_of(&event, "StudentSchoolInfoListing") => {
+                            return Ok(info);
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                None => { return Err(DecodingError::UnexpectedEnd); }
+            }
+        }
+    }
+}
+
+impl SVUEDecodeable for StaffMember {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<StaffMember> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "StaffList" => {
+                        limits.count_element()?;
+                        let attrs = attributes_vec_to_map(attributes);
+                        let attr = |name: &str| attrs.get(name).map(|v| v.trim().to_string()).unwrap_or_default();
+
+                        let member = StaffMember {
+                            email: attr("EMail"),
+                            extension: attr("Extn"),
+                            name: limits.text(get_attr!(attrs, "Name")),
+                            phone: attr("Phone"),
+                            title: attr("Title"),
+                        };
+                        skip_element(events_iter)?;
+
+                        Ok(member)
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHOOL_INFO: &str = include_str!("../fixtures/school_info.xml");
+
+    fn teacher(name: &str, email: &str) -> Teacher {
+        Teacher {
+            name: name.to_string(),
+            email: email.to_string(),
+        }
+    }
+
+    #[test]
+    fn decodes_the_school_and_its_staff() {
+        let info = SchoolInfo::from_xml(SCHOOL_INFO).unwrap();
+
+        assert_eq!(info.name, "Lincoln High School");
+        assert_eq!(info.principal, "Rivera, Ana");
+        assert_eq!(info.principal_email, "arivera@example.org");
+        assert_eq!(info.url, "https://lincoln.example.org");
+        assert_eq!(info.address.one_line(), "1600 SW Salmon St, Portland, OR 97205");
+
+        let names: Vec<&str> = info.staff.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Okafor, Chris", "Nguyen, Sam", "Bauer, Kim"]);
+        assert_eq!(info.staff[0].full_phone(), "503-555-0100 ext. 2201");
+        assert_eq!(info.staff[1].full_phone(), "");
+    }
+
+    #[test]
+    fn joins_whatever_parts_of_the_address_there_are() {
+        let address = Address {
+            city: "Portland".to_string(),
+            zip: "97205".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(address.one_line(), "Portland, 97205");
+        assert_eq!(Address::default().one_line(), "");
+    }
+
+    #[test]
+    fn finds_a_teacher_by_email_then_by_name() {
+        let info = SchoolInfo::from_xml(SCHOOL_INFO).unwrap();
+
+        let okafor = info.contact_for(&teacher("Chris Okafor", "COKAFOR@example.org")).unwrap();
+        assert_eq!(okafor.extension, "2201");
+        let nguyen = info.contact_for(&teacher(" nguyen, sam ", "snguyen@example.org")).unwrap();
+        assert_eq!(nguyen.title, "Teacher");
+        assert!(info.contact_for(&teacher("", "")).is_none());
+        assert!(info.contact_for(&teacher("Lee, Dana", "dlee@example.org")).is_none());
+    }
+
+    #[test]
+    fn finds_staff_by_title() {
+        let info = SchoolInfo::from_xml(SCHOOL_INFO).unwrap();
+
+        let counselors = info.staff_titled("counselor");
+        assert_eq!(counselors.len(), 1);
+        assert_eq!(counselors[0].full_phone(), "503-555-0101");
+        assert_eq!(info.staff_titled(" TEACHER").len(), 2);
+    }
+
+    #[test]
+    fn needs_a_school_name_and_a_whole_document() {
+        assert!(SchoolInfo::from_xml("<StudentSchoolInfoListing Principal=\"Rivera, Ana\"></StudentSchoolInfoListing>")
+            .is_err());
+        assert!(SchoolInfo::from_xml("<StudentSchoolInfoListing School=\"Lincoln\"><StaffLists>").is_err());
+        match SchoolInfo::from_xml_with(SCHOOL_INFO, &DecodeLimits::new().max_elements(2)) {
+            Err(DecodingError::ElementLimitExceeded(2)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// A small read-only JSON API for dashboards:
+//
+// * `GET /gradebook`: the current gradebook, fetched at most once per `max_age`
+// * `GET /changes[?since=<RFC 3339 time>]`: changesets seen while the server has been running
+// * `GET /attendance`: absences and tardies so far this year, fetched on every request
+//
+// Every request needs `Authorization: Bearer <token>`, with the token given to `ApiServer::new`;
+// without it the answer is a 401. Bodies use the `export::json` format; errors are
+// `{"error": "..."}`.
+use std::io;
+use std::sync::Mutex;
+
+use api::SVUEClient;
+use attendance::Attendance;
+use clock::{Clock, SystemClock};
+use diff::Changeset;
+use export::json::{self, Object, ToJson};
+use gradebook::Gradebook;
+use history::ChangesetHistory;
+use http_server::{self, Handler, Listening, Method, Request, Response, StatusCode};
+
+use chrono::{DateTime, Duration, Utc};
+
+struct Cache {
+    fetched: Option<(DateTime<Utc>, Gradebook)>,
+    history: ChangesetHistory,
+}
+
+pub struct ApiServer {
+    cache: Mutex<Cache>,
+    client: SVUEClient,
+    clock: Box<dyn Clock + Send + Sync>,
+    max_age: Duration,
+    token: String,
+}
+
+fn error(message: &str) -> String {
+    Object::new().field("error", json::string(message)).build()
+}
+
+// `%XX` escapes are decoded (a `+` in a time zone offset has to be sent as `%2B`); anything that
+// isn't one is left as it is
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 3) {
+            Some(hex) if bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit) => {
+                u8::from_str_radix(&s[i + 1..i + 3], 16).ok()
+            }
+            _ => None,
+        };
+
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&')
+        .filter_map(|pair| {
+            let mut kv = pair.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some(k), Some(v)) if percent_decode(k) == name => Some(percent_decode(v)),
+                _ => None,
+            }
+        })
+        .next()
+}
+
+impl ApiServer {
+    // `token` should be long and random; an empty one turns every request down
+    pub fn new(client: SVUEClient, token: &str) -> ApiServer {
+        ApiServer {
+            cache: Mutex::new(Cache {
+                fetched: None,
+                history: ChangesetHistory::new(),
+            }),
+            client,
+            clock: Box::new(SystemClock),
+            max_age: Duration::minutes(15),
+            token: token.to_string(),
+        }
+    }
+
+    // how long a fetched gradebook is served before asking StudentVUE again
+    pub fn max_age(mut self, max_age: Duration) -> ApiServer {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn clock<C: Clock + Send + Sync + 'static>(mut self, clock: C) -> ApiServer {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn listen(self, addr: &str) -> io::Result<Listening> {
+        http_server::listen(self, addr)
+    }
+
+    // refreshes the cache if it's stale; a changed gradebook is recorded in the history
+    fn gradebook(&self) -> Result<String, String> {
+        let now = self.clock.now();
+        {
+            let cache = self.cache.lock().map_err(|_| "cache poisoned".to_string())?;
+            if let Some((at, ref gradebook)) = cache.fetched {
+                if at + self.max_age > now {
+                    return Ok(gradebook.to_json());
+                }
+            }
+        }
+
+        // fetched without holding the lock, so a slow district doesn't hold up `/changes`. The
+        // error is kept to a fixed message, since the request's own can include the district's
+        // response
+        let gradebook = Gradebook::retrieve_with(&self.client, None)
+            .map_err(|_| "couldn't retrieve the gradebook".to_string())?;
+
+        let mut cache = self.cache.lock().map_err(|_| "cache poisoned".to_string())?;
+        // another request that found the cache stale got there first with a newer one
+        if let Some((at, ref newer)) = cache.fetched {
+            if at > now {
+                return Ok(newer.to_json());
+            }
+        }
+
+        let changeset = cache.fetched.as_ref().and_then(|(_, old)| Changeset::diff(old, &gradebook));
+        if let Some(changeset) = changeset {
+            cache.history.push(now, changeset);
+        }
+        let body = gradebook.to_json();
+        cache.fetched = Some((now, gradebook));
+
+        Ok(body)
+    }
+
+    fn attendance(&self) -> Result<String, String> {
+        Attendance::retrieve_with(&self.client)
+            .map(|a| a.to_json())
+            .map_err(|_| "couldn't retrieve attendance".to_string())
+    }
+
+    fn changes(&self, since: Option<&str>) -> Result<String, (StatusCode, String)> {
+        let since = match since {
+            Some(s) => {
+                let parsed = DateTime::parse_from_rfc3339(s)
+                    .map_err(|_| (StatusCode::BAD_REQUEST, "`since` must be an RFC 3339 timestamp".to_string()))?;
+                Some(parsed.with_timezone(&Utc))
+            }
+            None => None,
+        };
+
+        let cache = self.cache.lock().map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "cache poisoned".to_string()))?;
+        let entries = match since {
+            Some(since) => cache.history.since(since),
+            None => cache.history.entries(),
+        };
+
+        Ok(json::array(entries))
+    }
+
+    fn route(&self, req: &Request) -> (StatusCode, String) {
+        // before anything else, so nothing about the server is given away without the token
+        if !req.has_bearer_token(&self.token) {
+            return (StatusCode::UNAUTHORIZED, error("missing or wrong token"));
+        }
+        if req.method != Method::GET {
+            return (StatusCode::METHOD_NOT_ALLOWED, error("only GET is supported"));
+        }
+
+        match req.path.as_str() {
+            "/gradebook" => {
+                match self.gradebook() {
+                    Ok(body) => (StatusCode::OK, body),
+                    Err(e) => (StatusCode::BAD_GATEWAY, error(&e)),
+                }
+            }
+            "/changes" => {
+                match self.changes(query_param(&req.query, "since").as_deref()) {
+                    Ok(body) => (StatusCode::OK, body),
+                    Err((status, e)) => (status, error(&e)),
+                }
+            }
+            "/attendance" => {
+                match self.attendance() {
+                    Ok(body) => (StatusCode::OK, body),
+                    Err(e) => (StatusCode::BAD_GATEWAY, error(&e)),
+                }
+            }
+            _ => (StatusCode::NOT_FOUND, error("not found")),
+        }
+    }
+}
+
+impl Handler for ApiServer {
+    fn handle(&self, req: &Request) -> Response {
+        let (status, body) = self.route(req);
+        Response::new(status, "application/json", body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_server::HeaderMap;
+    #[cfg(feature="mock-server")]
+    use mock::{self, MockServer};
+
+    fn get(server: &ApiServer, path: &str, authorization: Option<&str>) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(a) = authorization {
+            headers.insert("Authorization", a.parse().unwrap());
+        }
+
+        let mut parts = path.splitn(2, '?');
+        server.handle(&Request {
+            method: Method::GET,
+            path: parts.next().unwrap().to_string(),
+            query: parts.next().unwrap_or("").to_string(),
+            headers,
+            body: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn turns_down_every_route_without_the_token() {
+        let server = ApiServer::new(SVUEClient::new("user", "pass"), "s3cret");
+
+        for path in &["/gradebook", "/changes", "/attendance", "/missing"] {
+            for auth in &[None, Some("Bearer wrong!"), Some("Bearer s3cret "), Some("s3cret")] {
+                assert_eq!(get(&server, path, *auth).status, StatusCode::UNAUTHORIZED, "{} {:?}", path, auth);
+            }
+        }
+        assert_eq!(get(&server, "/changes", Some("Bearer s3cret")).status, StatusCode::OK);
+    }
+
+    #[test]
+    fn turns_down_everything_with_an_empty_token() {
+        let server = ApiServer::new(SVUEClient::new("user", "pass"), "");
+
+        assert_eq!(get(&server, "/changes", None).status, StatusCode::UNAUTHORIZED);
+        assert_eq!(get(&server, "/changes", Some("Bearer ")).status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn decodes_query_parameters() {
+        let query = "since=2026-03-01T08%3A00%3A00%2B00%3A00&note=%E2%9C%93&bad=%2&sign=%+1";
+
+        assert_eq!(query_param(query, "since").as_deref(), Some("2026-03-01T08:00:00+00:00"));
+        assert_eq!(query_param(query, "note").as_deref(), Some("\u{2713}"));
+        // not escapes, so left alone
+        assert_eq!(query_param(query, "bad").as_deref(), Some("%2"));
+        assert_eq!(query_param(query, "sign").as_deref(), Some("%+1"));
+        assert_eq!(query_param(query, "missing"), None);
+    }
+
+    #[test]
+    fn reads_an_encoded_since() {
+        let server = ApiServer::new(SVUEClient::new("user", "pass"), "s3cret");
+
+        let resp = get(&server, "/changes?since=2026-03-01T08:00:00%2B00:00", Some("Bearer s3cret"));
+        assert_eq!(resp.status, StatusCode::OK);
+        let resp = get(&server, "/changes?since=yesterday", Some("Bearer s3cret"));
+        assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn answers_district_errors_with_a_fixed_message() {
+        let district = MockServer::new()
+            .rt_error("Gradebook", "Invalid user id or password")
+            .rt_error("Attendance", "Invalid user id or password")
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let client = SVUEClient::new("user", "pass").endpoint(&mock::endpoint_url(&district));
+        let server = ApiServer::new(client, "s3cret");
+
+        let resp = get(&server, "/gradebook", Some("Bearer s3cret"));
+        assert_eq!((resp.status, resp.body.as_str()), (StatusCode::BAD_GATEWAY, r#"{"error":"couldn't retrieve the gradebook"}"#));
+        let resp = get(&server, "/attendance", Some("Bearer s3cret"));
+        assert_eq!((resp.status, resp.body.as_str()), (StatusCode::BAD_GATEWAY, r#"{"error":"couldn't retrieve attendance"}"#));
+
+        district.close();
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn serves_the_cached_gradebook_until_it_is_stale() {
+        let district = MockServer::new()
+            .document("Gradebook", include_str!("../fixtures/gradebook_no_standards.xml"))
+            .listen("127.0.0.1:0")
+            .unwrap();
+        let client = SVUEClient::new("user", "pass").endpoint(&mock::endpoint_url(&district));
+        let server = ApiServer::new(client, "s3cret");
+
+        let first = get(&server, "/gradebook", Some("Bearer s3cret"));
+        assert_eq!(first.status, StatusCode::OK);
+        district.close();
+
+        // the district is gone, but the cached copy is still fresh
+        let second = get(&server, "/gradebook", Some("Bearer s3cret"));
+        assert_eq!((second.status, second.body), (StatusCode::OK, first.body));
+    }
+}
@@ -0,0 +1,192 @@
+// A compact alternative to JSON for long-lived snapshot and changeset archives. Every record
+// starts with a six byte header: the magic `RVUE`, a format version and the kind of record that
+// follows, then the bincode-encoded body. The version is bumped whenever the body encoding
+// changes incompatibly (including changes to the model structs themselves), so old archives are
+// rejected with `UnsupportedVersion` rather than decoded into garbage.
+use std::io::{Read, Write};
+
+use super::{Snapshot, StoreError};
+
+use diff::Changeset;
+use history::TimestampedChangeset;
+
+use bincode::{self, Options};
+
+pub const MAGIC: &[u8; 4] = b"RVUE";
+// 1: the first format
+// 2: assignments gained `class_stats`
+// 3: gradebooks gained `last_changed`
+// 4: bincode 1.x, which writes integers little-endian
+pub const FORMAT_VERSION: u8 = 4;
+
+// no real record comes close; this only stops a corrupt length prefix from allocating gigabytes
+const MAX_RECORD_BYTES: u64 = 64 * 1024 * 1024;
+
+// the same fixed-width encoding as `bincode::serialize`, so existing version 4 archives still read
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_RECORD_BYTES)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordKind {
+    Changeset,
+    Snapshot,
+    TimestampedChangeset,
+}
+
+impl RecordKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            RecordKind::Snapshot => 1,
+            RecordKind::Changeset => 2,
+            RecordKind::TimestampedChangeset => 3,
+        }
+    }
+
+    fn from_byte(b: u8) -> Option<RecordKind> {
+        match b {
+            1 => Some(RecordKind::Snapshot),
+            2 => Some(RecordKind::Changeset),
+            3 => Some(RecordKind::TimestampedChangeset),
+            _ => None,
+        }
+    }
+}
+
+fn write_header<W: Write>(w: &mut W, kind: RecordKind) -> Result<(), StoreError> {
+    w.write_all(MAGIC).map_err(StoreError::Io)?;
+    w.write_all(&[FORMAT_VERSION, kind.to_byte()]).map_err(StoreError::Io)
+}
+
+// checks the header and returns what kind of record follows
+pub fn read_header<R: Read>(r: &mut R) -> Result<RecordKind, StoreError> {
+    let mut header = [0; 6];
+    r.read_exact(&mut header).map_err(StoreError::Io)?;
+
+    if &header[..4] != MAGIC {
+        return Err(StoreError::InvalidHeader);
+    }
+    if header[4] != FORMAT_VERSION {
+        return Err(StoreError::UnsupportedVersion(header[4]));
+    }
+
+    RecordKind::from_byte(header[5]).ok_or(StoreError::InvalidHeader)
+}
+
+fn expect_kind<R: Read>(r: &mut R, expected: RecordKind) -> Result<(), StoreError> {
+    if read_header(r)? == expected {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidHeader)
+    }
+}
+
+macro_rules! binary_record {
+    ( $t:ty, $kind:expr, $write:ident, $read:ident ) => {
+        pub fn $write<W: Write>(w: &mut W, value: &$t) -> Result<(), StoreError> {
+            write_header(w, $kind)?;
+            options().serialize_into(w, value).map_err(StoreError::Binary)
+        }
+
+        pub fn $read<R: Read>(r: &mut R) -> Result<$t, StoreError> {
+            expect_kind(r, $kind)?;
+            options().deserialize_from(r).map_err(StoreError::Binary)
+        }
+    };
+}
+
+binary_record!(Snapshot, RecordKind::Snapshot, write_snapshot, read_snapshot);
+binary_record!(Changeset, RecordKind::Changeset, write_changeset, read_changeset);
+binary_record!(TimestampedChangeset, RecordKind::TimestampedChangeset, write_timestamped_changeset,
+    read_timestamped_changeset);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sample::SampleGenerator;
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            captured_at: Utc.with_ymd_and_hms(2017, 10, 2, 15, 0, 0).unwrap(),
+            gradebook: SampleGenerator::new().seed(7).gradebook_as_of(NaiveDate::from_ymd_opt(2017, 10, 2).unwrap()),
+        }
+    }
+
+    fn encoded(snapshot: &Snapshot) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_snapshot(&mut buf, snapshot).unwrap();
+        buf
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let original = snapshot();
+        let buf = encoded(&original);
+        assert_eq!(read_header(&mut &buf[..]).unwrap(), RecordKind::Snapshot);
+
+        let decoded = read_snapshot(&mut &buf[..]).unwrap();
+
+        assert_eq!(decoded.captured_at, original.captured_at);
+        assert!(Changeset::diff(&original.gradebook, &decoded.gradebook).is_none());
+        assert_eq!(encoded(&decoded), buf);
+    }
+
+    #[test]
+    fn matches_plain_bincode() {
+        let original = snapshot();
+        let buf = encoded(&original);
+
+        assert_eq!(&buf[6..], &bincode::serialize(&original).unwrap()[..]);
+    }
+
+    #[test]
+    fn invalid_magic() {
+        let mut buf = encoded(&snapshot());
+        buf[..4].copy_from_slice(b"JSON");
+
+        match read_snapshot(&mut &buf[..]) {
+            Err(StoreError::InvalidHeader) => (),
+            other => panic!("expected InvalidHeader, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn wrong_record_kind() {
+        let buf = encoded(&snapshot());
+
+        match read_changeset(&mut &buf[..]) {
+            Err(StoreError::InvalidHeader) => (),
+            other => panic!("expected InvalidHeader, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut buf = encoded(&snapshot());
+        buf[4] = 3;
+
+        match read_snapshot(&mut &buf[..]) {
+            Err(StoreError::UnsupportedVersion(3)) => (),
+            other => panic!("expected UnsupportedVersion(3), got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn oversized_length_prefix() {
+        let mut buf = Vec::new();
+        write_header(&mut buf, RecordKind::Snapshot).unwrap();
+        // `captured_at` is encoded as a string, so this claims it is u64::MAX bytes long
+        buf.extend_from_slice(&[0xff; 8]);
+
+        match read_snapshot(&mut &buf[..]) {
+            Err(StoreError::Binary(ref e)) if matches!(**e, bincode::ErrorKind::SizeLimit) => (),
+            other => panic!("expected SizeLimit, got {:?}", other.map(|_| ())),
+        }
+    }
+}
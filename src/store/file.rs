@@ -0,0 +1,336 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{Snapshot, SnapshotStore, StoreError};
+use tags::CourseTags;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde_json;
+
+pub type FileResult<T> = Result<T, StoreError>;
+
+#[derive(Clone, Debug, Default)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_snapshots: Option<usize>,
+}
+
+pub struct FileStore {
+    dir: PathBuf,
+    retention: RetentionPolicy,
+}
+
+const TAGS_FILE: &str = "course-tags.json";
+
+// snapshots are stored one per file, named by capture time so listing the directory is enough to
+// order them without opening anything
+fn file_name(captured_at: &DateTime<Utc>) -> String {
+    format!("snapshot-{}-{:09}.json", captured_at.timestamp(), captured_at.timestamp_subsec_nanos())
+}
+
+fn parse_file_name(name: &str) -> Option<DateTime<Utc>> {
+    if !name.starts_with("snapshot-") || !name.ends_with(".json") {
+        return None;
+    }
+
+    // split from the right, since the seconds are negative before 1970 ("snapshot--5-...")
+    let stamp = &name["snapshot-".len()..name.len() - ".json".len()];
+    let mut parts = stamp.rsplitn(2, '-');
+
+    match (parts.next().and_then(|n| n.parse().ok()), parts.next().and_then(|s| s.parse().ok())) {
+        (Some(nanos), Some(secs)) => Utc.timestamp_opt(secs, nanos).single(),
+        _ => None,
+    }
+}
+
+impl FileStore {
+    pub fn open<P: AsRef<Path>>(dir: P) -> FileResult<FileStore> {
+        fs::create_dir_all(dir.as_ref()).map_err(StoreError::Io)?;
+
+        Ok(FileStore {
+            dir: dir.as_ref().to_path_buf(),
+            retention: Default::default(),
+        })
+    }
+
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> FileStore {
+        self.retention = retention;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn put_snapshot(&self, snapshot: &Snapshot) -> FileResult<PathBuf> {
+        let path = self.dir.join(file_name(&snapshot.captured_at));
+        // write to a temporary file first so a crash mid-write never leaves a truncated snapshot
+        // that looks like the latest one
+        let tmp = path.with_extension("json.tmp");
+
+        {
+            let f = File::create(&tmp).map_err(StoreError::Io)?;
+            let mut w = BufWriter::new(f);
+            serde_json::to_writer(&mut w, snapshot).map_err(StoreError::Serialization)?;
+            // on disk before the rename, or a crash just after it could leave an empty snapshot
+            // in place of the last good one
+            w.flush().map_err(StoreError::Io)?;
+            w.get_ref().sync_all().map_err(StoreError::Io)?;
+        }
+        fs::rename(&tmp, &path).map_err(StoreError::Io)?;
+
+        self.prune()?;
+        Ok(path)
+    }
+
+    pub fn latest_snapshot(&self) -> FileResult<Option<Snapshot>> {
+        match self.entries()?.pop() {
+            Some((_, path)) => self.read(&path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // snapshots captured in [from, to), oldest first
+    pub fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> FileResult<Vec<Snapshot>> {
+        self.entries()?
+            .into_iter()
+            .filter(|&(captured_at, _)| captured_at >= from && captured_at < to)
+            .map(|(_, path)| self.read(&path))
+            .collect()
+    }
+
+    pub fn prune_snapshots_before(&self, before: DateTime<Utc>) -> FileResult<usize> {
+        let mut removed = 0;
+
+        for (captured_at, path) in self.entries()? {
+            if captured_at < before {
+                fs::remove_file(&path).map_err(StoreError::Io)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    pub fn course_tags(&self) -> FileResult<CourseTags> {
+        let path = self.dir.join(TAGS_FILE);
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+
+        let f = File::open(path).map_err(StoreError::Io)?;
+        serde_json::from_reader(BufReader::new(f)).map_err(StoreError::Serialization)
+    }
+
+    pub fn put_course_tags(&self, tags: &CourseTags) -> FileResult<()> {
+        let path = self.dir.join(TAGS_FILE);
+        let tmp = path.with_extension("json.tmp");
+
+        {
+            let f = File::create(&tmp).map_err(StoreError::Io)?;
+            let mut w = BufWriter::new(f);
+            serde_json::to_writer(&mut w, tags).map_err(StoreError::Serialization)?;
+            w.flush().map_err(StoreError::Io)?;
+            w.get_ref().sync_all().map_err(StoreError::Io)?;
+        }
+
+        fs::rename(&tmp, &path).map_err(StoreError::Io)
+    }
+
+    // applies the retention policy, returning how many snapshots were removed; the newest snapshot
+    // is always kept
+    pub fn prune(&self) -> FileResult<usize> {
+        let mut entries = self.entries()?;
+        let latest = match entries.pop() {
+            Some(e) => e,
+            None => { return Ok(0); }
+        };
+
+        let mut removed = 0;
+        let keep = self.retention.max_snapshots.map(|n| if n > 0 { n - 1 } else { 0 });
+        let cutoff = self.retention.max_age.map(|age| latest.0 - age);
+        let total = entries.len();
+
+        for (i, (captured_at, path)) in entries.into_iter().enumerate() {
+            let too_many = keep.map(|k| total - i > k).unwrap_or(false);
+            let too_old = cutoff.map(|c| captured_at < c).unwrap_or(false);
+
+            if too_many || too_old {
+                fs::remove_file(&path).map_err(StoreError::Io)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn read(&self, path: &Path) -> FileResult<Snapshot> {
+        let f = File::open(path).map_err(StoreError::Io)?;
+        serde_json::from_reader(BufReader::new(f)).map_err(StoreError::Serialization)
+    }
+
+    // every snapshot file in the directory, oldest first
+    fn entries(&self) -> FileResult<Vec<(DateTime<Utc>, PathBuf)>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&self.dir).map_err(StoreError::Io)? {
+            let path = entry.map_err(StoreError::Io)?.path();
+            let captured_at = path.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(parse_file_name);
+
+            if let Some(captured_at) = captured_at {
+                entries.push((captured_at, path));
+            }
+        }
+
+        entries.sort_by_key(|a| a.0);
+        Ok(entries)
+    }
+}
+
+impl SnapshotStore for FileStore {
+    fn put_snapshot(&self, snapshot: &Snapshot) -> FileResult<()> {
+        FileStore::put_snapshot(self, snapshot).map(|_| ())
+    }
+
+    fn latest_snapshot(&self) -> FileResult<Option<Snapshot>> {
+        FileStore::latest_snapshot(self)
+    }
+
+    fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> FileResult<Vec<Snapshot>> {
+        FileStore::snapshots_between(self, from, to)
+    }
+
+    fn prune_snapshots_before(&self, before: DateTime<Utc>) -> FileResult<usize> {
+        FileStore::prune_snapshots_before(self, before)
+    }
+
+    fn course_tags(&self) -> FileResult<CourseTags> {
+        FileStore::course_tags(self)
+    }
+
+    fn put_course_tags(&self, tags: &CourseTags) -> FileResult<()> {
+        FileStore::put_course_tags(self, tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::Gradebook;
+
+    // an empty store of the test's own
+    fn store(name: &str) -> FileStore {
+        let dir = ::std::env::temp_dir().join(format!("rvue-file-store-test-{}-{}", ::std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        FileStore::open(dir).unwrap()
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    fn put(store: &FileStore, captured_at: DateTime<Utc>) {
+        store.put_snapshot(&Snapshot { captured_at, gradebook: Gradebook::default() }).unwrap();
+    }
+
+    fn captured(store: &FileStore) -> Vec<DateTime<Utc>> {
+        store.entries().unwrap().into_iter().map(|(t, _)| t).collect()
+    }
+
+    #[test]
+    fn round_trips_file_names() {
+        let times = [
+            Utc.timestamp_opt(1_772_352_000, 123_456_789).unwrap(),
+            Utc.timestamp_opt(0, 0).unwrap(),
+            // before 1970, so the seconds are negative
+            Utc.timestamp_opt(-5, 250_000_000).unwrap(),
+            Utc.with_ymd_and_hms(1969, 7, 20, 20, 17, 40).unwrap(),
+        ];
+
+        for t in times.iter() {
+            assert_eq!(parse_file_name(&file_name(t)), Some(*t), "{}", file_name(t));
+        }
+        for name in &["snapshot-5.json", "snapshot-a-1.json", "snapshot-5-1.json.tmp", "course-tags.json"] {
+            assert_eq!(parse_file_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_newest_snapshots() {
+        let store = store("max-snapshots").with_retention(RetentionPolicy { max_snapshots: Some(3), ..Default::default() });
+        for hour in 1..6 {
+            put(&store, at(hour));
+        }
+
+        assert_eq!(captured(&store), vec![at(3), at(4), at(5)]);
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn drops_snapshots_older_than_the_max_age() {
+        let store = store("max-age").with_retention(RetentionPolicy { max_age: Some(Duration::hours(2)), ..Default::default() });
+        for hour in &[1, 2, 4, 5, 6] {
+            put(&store, at(*hour));
+        }
+
+        // measured back from the newest snapshot, not from now
+        assert_eq!(captured(&store), vec![at(4), at(5), at(6)]);
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn always_keeps_the_newest_snapshot() {
+        let store = store("keep-newest").with_retention(RetentionPolicy {
+            max_age: Some(Duration::zero()),
+            max_snapshots: Some(0),
+        });
+        put(&store, at(1));
+        put(&store, at(2));
+
+        assert_eq!(captured(&store), vec![at(2)]);
+        assert_eq!(store.prune().unwrap(), 0);
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn lists_snapshots_from_the_start_up_to_the_end() {
+        let store = store("between");
+        for hour in 1..5 {
+            put(&store, at(hour));
+        }
+
+        let between = |from, to| -> Vec<DateTime<Utc>> {
+            store.snapshots_between(from, to).unwrap().into_iter().map(|s| s.captured_at).collect()
+        };
+        assert_eq!(between(at(2), at(4)), vec![at(2), at(3)]);
+        assert_eq!(between(at(0), at(9)), vec![at(1), at(2), at(3), at(4)]);
+        assert!(between(at(3), at(3)).is_empty());
+        assert!(between(at(4), at(2)).is_empty());
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn writes_through_a_temporary_file() {
+        let store = store("tmp");
+        put(&store, at(1));
+
+        let names: Vec<String> = fs::read_dir(store.dir()).unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(names, vec![file_name(&at(1))]);
+
+        // what a crash partway through writing a newer one leaves behind
+        let tmp = store.dir().join(file_name(&at(2))).with_extension("json.tmp");
+        fs::write(&tmp, "{\"captured_at\":").unwrap();
+        assert_eq!(store.latest_snapshot().unwrap().unwrap().captured_at, at(1));
+
+        // and the next write of that snapshot replaces it
+        put(&store, at(2));
+        assert!(!tmp.exists());
+        assert_eq!(store.latest_snapshot().unwrap().unwrap().captured_at, at(2));
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use std::io;
+
+use diff::Changeset;
+use gradebook::Gradebook;
+use history::ChangesetHistory;
+use tags::CourseTags;
+
+#[cfg(feature="binary")]
+use bincode;
+use chrono::{DateTime, Utc};
+#[cfg(feature="sqlite")]
+use rusqlite;
+use serde_json;
+
+#[cfg(feature="binary")]
+pub mod binary;
+pub mod file;
+#[cfg(feature="redis-store")]
+pub mod redis;
+#[cfg(feature="sqlite")]
+pub mod sqlite;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub captured_at: DateTime<Utc>,
+    pub gradebook: Gradebook,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PollRecord {
+    pub polled_at: DateTime<Utc>,
+    pub succeeded: bool,
+    pub message: Option<String>,
+}
+
+// what the watcher and the history helpers below need from a backend, so they work with any of
+// them (or one supplied by the user)
+pub trait SnapshotStore {
+    fn put_snapshot(&self, snapshot: &Snapshot) -> Result<(), StoreError>;
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot>, StoreError>;
+
+    // snapshots captured in [from, to), oldest first
+    fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Snapshot>, StoreError>;
+
+    // returns how many were removed
+    fn prune_snapshots_before(&self, before: DateTime<Utc>) -> Result<usize, StoreError>;
+
+    // kept next to the snapshots so every tool pointed at the same store sees the same labels;
+    // empty if none were ever saved
+    fn course_tags(&self) -> Result<CourseTags, StoreError>;
+
+    fn put_course_tags(&self, tags: &CourseTags) -> Result<(), StoreError>;
+}
+
+impl<S: SnapshotStore + ?Sized> SnapshotStore for &S {
+    fn put_snapshot(&self, snapshot: &Snapshot) -> Result<(), StoreError> {
+        (**self).put_snapshot(snapshot)
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot>, StoreError> {
+        (**self).latest_snapshot()
+    }
+
+    fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Snapshot>, StoreError> {
+        (**self).snapshots_between(from, to)
+    }
+
+    fn prune_snapshots_before(&self, before: DateTime<Utc>) -> Result<usize, StoreError> {
+        (**self).prune_snapshots_before(before)
+    }
+
+    fn course_tags(&self) -> Result<CourseTags, StoreError> {
+        (**self).course_tags()
+    }
+
+    fn put_course_tags(&self, tags: &CourseTags) -> Result<(), StoreError> {
+        (**self).put_course_tags(tags)
+    }
+}
+
+impl<S: SnapshotStore + ?Sized> SnapshotStore for Box<S> {
+    fn put_snapshot(&self, snapshot: &Snapshot) -> Result<(), StoreError> {
+        (**self).put_snapshot(snapshot)
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot>, StoreError> {
+        (**self).latest_snapshot()
+    }
+
+    fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Snapshot>, StoreError> {
+        (**self).snapshots_between(from, to)
+    }
+
+    fn prune_snapshots_before(&self, before: DateTime<Utc>) -> Result<usize, StoreError> {
+        (**self).prune_snapshots_before(before)
+    }
+
+    fn course_tags(&self) -> Result<CourseTags, StoreError> {
+        (**self).course_tags()
+    }
+
+    fn put_course_tags(&self, tags: &CourseTags) -> Result<(), StoreError> {
+        (**self).put_course_tags(tags)
+    }
+}
+
+// the changes between each pair of consecutive snapshots captured in [from, to), stamped with the
+// later snapshot's capture time
+pub fn changeset_history<S>(store: &S, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<ChangesetHistory, StoreError>
+    where S: SnapshotStore + ?Sized {
+
+    let snapshots = store.snapshots_between(from, to)?;
+    let mut history = ChangesetHistory::new();
+
+    for pair in snapshots.windows(2) {
+        if let Some(changeset) = Changeset::diff(&pair[0].gradebook, &pair[1].gradebook) {
+            history.push(pair[1].captured_at, changeset);
+        }
+    }
+
+    Ok(history)
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    #[cfg(feature="binary")]
+    Binary(bincode::Error),
+    #[cfg(feature="binary")]
+    InvalidHeader,
+    Io(io::Error),
+    // `::redis` because the `redis` module here would shadow the crate
+    #[cfg(feature="redis-store")]
+    Redis(::redis::RedisError),
+    Serialization(serde_json::Error),
+    #[cfg(feature="binary")]
+    UnsupportedVersion(u8),
+    #[cfg(feature="sqlite")]
+    Sqlite(rusqlite::Error),
+    // sqlite can only hold times between 1677 and 2262
+    #[cfg(feature="sqlite")]
+    TimestampOutOfRange(DateTime<Utc>),
+}
@@ -0,0 +1,307 @@
+// Snapshots live in Redis so several watcher instances can share them without a common disk.
+// Each snapshot is a JSON string under `<prefix>:snapshot:<secs>-<nanos>`, optionally with a TTL,
+// and a sorted set `<prefix>:snapshots` indexes those keys by capture time. Expired snapshots
+// leave dangling index entries behind; reads skip them and `prune_expired` cleans them up. Course
+// tags are a single JSON string under `<prefix>:course_tags`, which never expires.
+use super::{Snapshot, SnapshotStore, StoreError};
+use tags::CourseTags;
+
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{DateTime, Duration, Utc};
+use redis::{self, Client, Connection};
+use serde_json;
+
+pub type RedisResult<T> = Result<T, StoreError>;
+
+pub struct RedisStore {
+    // commands need the connection mutably, but the store is shared
+    conn: Mutex<Connection>,
+    prefix: String,
+    ttl: Option<Duration>,
+}
+
+// sorted set scores are doubles, which is plenty for second resolution
+fn score(at: &DateTime<Utc>) -> f64 {
+    at.timestamp() as f64 + at.timestamp_subsec_nanos() as f64 / 1e9
+}
+
+// the key and retention logic is kept out of `RedisStore` so it can be tested without a server
+
+fn index_key(prefix: &str) -> String {
+    format!("{}:snapshots", prefix)
+}
+
+fn snapshot_key(prefix: &str, at: &DateTime<Utc>) -> String {
+    format!("{}:snapshot:{}-{:09}", prefix, at.timestamp(), at.timestamp_subsec_nanos())
+}
+
+fn course_tags_key(prefix: &str) -> String {
+    format!("{}:course_tags", prefix)
+}
+
+// SETEX only takes whole seconds, and none at all is an error, so anything shorter is a second
+fn ttl_secs(ttl: Duration) -> i64 {
+    if ttl.num_seconds() > 0 { ttl.num_seconds() } else { 1 }
+}
+
+// ZRANGEBYSCORE bounds for [from, to); a `(` makes a bound exclusive
+fn score_range(from: Option<&DateTime<Utc>>, to: &DateTime<Utc>) -> (String, String) {
+    let min = match from {
+        Some(from) => score(from).to_string(),
+        None => "-inf".to_string(),
+    };
+    (min, format!("({}", score(to)))
+}
+
+// the index entries whose snapshot keys are gone, in the order given
+fn expired<F>(keys: Vec<String>, mut exists: F) -> RedisResult<Vec<String>> where F: FnMut(&str) -> RedisResult<bool> {
+    let mut expired = Vec::new();
+    for key in keys {
+        if !exists(&key)? {
+            expired.push(key);
+        }
+    }
+
+    Ok(expired)
+}
+
+impl RedisStore {
+    pub fn open(url: &str) -> RedisResult<RedisStore> {
+        let conn = Client::open(url)
+            .and_then(|c| c.get_connection())
+            .map_err(StoreError::Redis)?;
+
+        Ok(Self::from_connection(conn))
+    }
+
+    pub fn from_connection(conn: Connection) -> RedisStore {
+        RedisStore {
+            conn: Mutex::new(conn),
+            prefix: "rvue".to_string(),
+            ttl: None,
+        }
+    }
+
+    // lets several students (or deployments) share one database
+    pub fn prefix(mut self, prefix: &str) -> RedisStore {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    // snapshots expire this long after they're written
+    pub fn ttl(mut self, ttl: Duration) -> RedisStore {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // a panic mid-command leaves nothing half-written on our side
+        match self.conn.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn index_key(&self) -> String {
+        index_key(&self.prefix)
+    }
+
+    pub fn put_snapshot(&self, snapshot: &Snapshot) -> RedisResult<()> {
+        let key = snapshot_key(&self.prefix, &snapshot.captured_at);
+        let json = serde_json::to_string(snapshot).map_err(StoreError::Serialization)?;
+
+        match self.ttl {
+            Some(ttl) => redis::cmd("SETEX").arg(&key).arg(ttl_secs(ttl)).arg(json).query::<()>(&mut *self.conn()),
+            None => redis::cmd("SET").arg(&key).arg(json).query::<()>(&mut *self.conn()),
+        }.map_err(StoreError::Redis)?;
+
+        redis::cmd("ZADD").arg(self.index_key()).arg(score(&snapshot.captured_at)).arg(&key)
+            .query::<()>(&mut *self.conn())
+            .map_err(StoreError::Redis)
+    }
+
+    pub fn latest_snapshot(&self) -> RedisResult<Option<Snapshot>> {
+        let keys: Vec<String> = redis::cmd("ZREVRANGE").arg(self.index_key()).arg(0).arg(-1)
+            .query(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        // the newest entries may have expired already
+        for key in keys {
+            if let Some(snapshot) = self.get(&key)? {
+                return Ok(Some(snapshot));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // snapshots captured in [from, to), oldest first
+    pub fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> RedisResult<Vec<Snapshot>> {
+        let (min, max) = score_range(Some(&from), &to);
+        let keys: Vec<String> = redis::cmd("ZRANGEBYSCORE").arg(self.index_key()).arg(min).arg(max)
+            .query(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        let mut snapshots = Vec::new();
+        for key in keys {
+            if let Some(snapshot) = self.get(&key)? {
+                snapshots.push(snapshot);
+            }
+        }
+
+        Ok(snapshots)
+    }
+
+    pub fn prune_snapshots_before(&self, before: DateTime<Utc>) -> RedisResult<usize> {
+        let (min, max) = score_range(None, &before);
+        let keys: Vec<String> = redis::cmd("ZRANGEBYSCORE").arg(self.index_key()).arg(min).arg(max)
+            .query(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        self.remove(&keys)
+    }
+
+    pub fn course_tags(&self) -> RedisResult<CourseTags> {
+        let json: Option<String> = redis::cmd("GET").arg(course_tags_key(&self.prefix))
+            .query(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        match json {
+            Some(json) => serde_json::from_str(&json).map_err(StoreError::Serialization),
+            None => Ok(Default::default()),
+        }
+    }
+
+    pub fn put_course_tags(&self, tags: &CourseTags) -> RedisResult<()> {
+        let json = serde_json::to_string(tags).map_err(StoreError::Serialization)?;
+
+        redis::cmd("SET").arg(course_tags_key(&self.prefix)).arg(json)
+            .query::<()>(&mut *self.conn())
+            .map_err(StoreError::Redis)
+    }
+
+    // drops index entries whose snapshots have expired, returning how many were removed
+    pub fn prune_expired(&self) -> RedisResult<usize> {
+        let keys: Vec<String> = redis::cmd("ZRANGE").arg(self.index_key()).arg(0).arg(-1)
+            .query(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        let expired = expired(keys, |key| {
+            redis::cmd("EXISTS").arg(key).query(&mut *self.conn()).map_err(StoreError::Redis)
+        })?;
+
+        self.remove(&expired)
+    }
+
+    fn get(&self, key: &str) -> RedisResult<Option<Snapshot>> {
+        let json: Option<String> = redis::cmd("GET").arg(key)
+            .query(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        match json {
+            Some(json) => serde_json::from_str(&json).map(Some).map_err(StoreError::Serialization),
+            None => Ok(None),
+        }
+    }
+
+    fn remove(&self, keys: &[String]) -> RedisResult<usize> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        redis::cmd("DEL").arg(keys).query::<()>(&mut *self.conn()).map_err(StoreError::Redis)?;
+        redis::cmd("ZREM").arg(self.index_key()).arg(keys)
+            .query::<()>(&mut *self.conn())
+            .map_err(StoreError::Redis)?;
+
+        Ok(keys.len())
+    }
+}
+
+impl SnapshotStore for RedisStore {
+    fn put_snapshot(&self, snapshot: &Snapshot) -> RedisResult<()> {
+        RedisStore::put_snapshot(self, snapshot)
+    }
+
+    fn latest_snapshot(&self) -> RedisResult<Option<Snapshot>> {
+        RedisStore::latest_snapshot(self)
+    }
+
+    fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> RedisResult<Vec<Snapshot>> {
+        RedisStore::snapshots_between(self, from, to)
+    }
+
+    fn prune_snapshots_before(&self, before: DateTime<Utc>) -> RedisResult<usize> {
+        RedisStore::prune_snapshots_before(self, before)
+    }
+
+    fn course_tags(&self) -> RedisResult<CourseTags> {
+        RedisStore::course_tags(self)
+    }
+
+    fn put_course_tags(&self, tags: &CourseTags) -> RedisResult<()> {
+        RedisStore::put_course_tags(self, tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn names_keys_under_the_prefix() {
+        let at = Utc.timestamp_opt(1_772_352_000, 5).unwrap();
+
+        assert_eq!(index_key("rvue"), "rvue:snapshots");
+        assert_eq!(snapshot_key("rvue", &at), "rvue:snapshot:1772352000-000000005");
+        assert_eq!(snapshot_key("ann", &Utc.timestamp_opt(-5, 0).unwrap()), "ann:snapshot:-5-000000000");
+        assert_eq!(course_tags_key("ann"), "ann:course_tags");
+    }
+
+    #[test]
+    fn orders_scores_by_capture_time() {
+        let times = [
+            Utc.timestamp_opt(-5, 0).unwrap(),
+            Utc.timestamp_opt(-4, 500_000_000).unwrap(),
+            Utc.timestamp_opt(0, 0).unwrap(),
+            Utc.timestamp_opt(1_772_352_000, 0).unwrap(),
+            Utc.timestamp_opt(1_772_352_000, 250_000_000).unwrap(),
+            Utc.timestamp_opt(1_772_352_001, 0).unwrap(),
+        ];
+
+        for pair in times.windows(2) {
+            assert!(score(&pair[0]) < score(&pair[1]), "{} and {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn rounds_a_ttl_to_at_least_a_second() {
+        assert_eq!(ttl_secs(Duration::hours(2)), 7200);
+        assert_eq!(ttl_secs(Duration::milliseconds(1500)), 1);
+        assert_eq!(ttl_secs(Duration::milliseconds(500)), 1);
+        assert_eq!(ttl_secs(Duration::zero()), 1);
+        assert_eq!(ttl_secs(Duration::seconds(-30)), 1);
+    }
+
+    #[test]
+    fn ranges_include_the_start_and_leave_out_the_end() {
+        let from = Utc.timestamp_opt(100, 0).unwrap();
+        let to = Utc.timestamp_opt(200, 500_000_000).unwrap();
+
+        assert_eq!(score_range(Some(&from), &to), ("100".to_string(), "(200.5".to_string()));
+        assert_eq!(score_range(None, &to), ("-inf".to_string(), "(200.5".to_string()));
+    }
+
+    #[test]
+    fn finds_index_entries_whose_snapshots_expired() {
+        let keys = vec!["rvue:snapshot:1-0".to_string(), "rvue:snapshot:2-0".to_string(), "rvue:snapshot:3-0".to_string()];
+
+        let gone = expired(keys.clone(), |key| Ok(key != "rvue:snapshot:2-0")).unwrap();
+        assert_eq!(gone, vec!["rvue:snapshot:2-0".to_string()]);
+        assert!(expired(keys.clone(), |_| Ok(true)).unwrap().is_empty());
+        assert_eq!(expired(keys.clone(), |_| Ok(false)).unwrap(), keys);
+    }
+}
@@ -0,0 +1,346 @@
+use std::path::Path;
+
+use diff::Changeset;
+use gradebook::{Course, CourseTitle};
+use history::ChangesetHistory;
+use super::{PollRecord, Snapshot, SnapshotStore, StoreError};
+use tags::CourseTags;
+
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{Connection, Error as SqliteError};
+use serde_json;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id          INTEGER PRIMARY KEY,
+        captured_at INTEGER NOT NULL,
+        gradebook   TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_captured_at ON snapshots (captured_at);
+
+    CREATE TABLE IF NOT EXISTS changesets (
+        id          INTEGER PRIMARY KEY,
+        captured_at INTEGER NOT NULL,
+        changeset   TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS changesets_captured_at ON changesets (captured_at);
+
+    CREATE TABLE IF NOT EXISTS polls (
+        id        INTEGER PRIMARY KEY,
+        polled_at INTEGER NOT NULL,
+        succeeded INTEGER NOT NULL,
+        message   TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS course_tags (
+        id   INTEGER PRIMARY KEY CHECK (id = 1),
+        tags TEXT NOT NULL
+    );
+";
+
+pub type SqliteResult<T> = Result<T, StoreError>;
+
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+// bumped whenever `migrate` has something new to do to an existing database
+const SCHEMA_VERSION: i64 = 1;
+
+// timestamps are stored as unix nanoseconds, so they sort and compare correctly in sql and two
+// polls in the same second stay apart; that covers 1677 to 2262
+fn to_timestamp(at: &DateTime<Utc>) -> SqliteResult<i64> {
+    at.timestamp_nanos_opt().ok_or(StoreError::TimestampOutOfRange(*at))
+}
+
+fn from_timestamp(nanos: i64) -> DateTime<Utc> {
+    Utc.timestamp_nanos(nanos)
+}
+
+// for the ends of a range, where anything before or after what can be stored is as good as
+// the first or last timestamp that can
+fn bound(at: &DateTime<Utc>) -> i64 {
+    at.timestamp_nanos_opt().unwrap_or(if at.timestamp() < 0 { i64::MIN } else { i64::MAX })
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> SqliteResult<SqliteStore> {
+        let conn = Connection::open(path).map_err(StoreError::Sqlite)?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> SqliteResult<SqliteStore> {
+        let conn = Connection::open_in_memory().map_err(StoreError::Sqlite)?;
+        Self::from_connection(conn)
+    }
+
+    pub fn from_connection(conn: Connection) -> SqliteResult<SqliteStore> {
+        conn.execute_batch(SCHEMA).map_err(StoreError::Sqlite)?;
+        Self::migrate(&conn).map_err(StoreError::Sqlite)?;
+
+        Ok(SqliteStore {
+            conn,
+        })
+    }
+
+    fn migrate(conn: &Connection) -> Result<(), SqliteError> {
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        if version < 1 {
+            // version 0 stored unix seconds
+            conn.execute_batch("
+                BEGIN;
+                UPDATE snapshots SET captured_at = captured_at * 1000000000;
+                UPDATE changesets SET captured_at = captured_at * 1000000000;
+                UPDATE polls SET polled_at = polled_at * 1000000000;
+                COMMIT;
+            ")?;
+        }
+
+        if version < SCHEMA_VERSION {
+            conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn put_snapshot(&self, snapshot: &Snapshot) -> SqliteResult<()> {
+        let gradebook = serde_json::to_string(&snapshot.gradebook).map_err(StoreError::Serialization)?;
+
+        self.conn.execute("INSERT INTO snapshots (captured_at, gradebook) VALUES (?1, ?2)",
+                          (to_timestamp(&snapshot.captured_at)?, &gradebook))
+            .map(|_| ())
+            .map_err(StoreError::Sqlite)
+    }
+
+    pub fn latest_snapshot(&self) -> SqliteResult<Option<Snapshot>> {
+        let rows = self.select_snapshots(
+            "SELECT captured_at, gradebook FROM snapshots ORDER BY captured_at DESC, id DESC LIMIT 1", None)?;
+
+        Ok(rows.into_iter().next())
+    }
+
+    // snapshots captured in [from, to), oldest first
+    pub fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> SqliteResult<Vec<Snapshot>> {
+        self.select_snapshots(
+            "SELECT captured_at, gradebook FROM snapshots \
+             WHERE captured_at >= ?1 AND captured_at < ?2 ORDER BY captured_at ASC, id ASC",
+            Some((bound(&from), bound(&to))))
+    }
+
+    // every stored version of a course, oldest first
+    pub fn course_history(&self, title: &CourseTitle) -> SqliteResult<Vec<(DateTime<Utc>, Course)>> {
+        let snapshots = self.select_snapshots(
+            "SELECT captured_at, gradebook FROM snapshots ORDER BY captured_at ASC, id ASC", None)?;
+
+        Ok(snapshots.into_iter().fold(Vec::new(), |mut acc, s| {
+            let captured_at = s.captured_at;
+
+            if let Some(c) = s.gradebook.courses.into_iter().find(|c| c.title == *title) {
+                acc.push((captured_at, c));
+            }
+            acc
+        }))
+    }
+
+    pub fn prune_snapshots_before(&self, before: DateTime<Utc>) -> SqliteResult<usize> {
+        self.conn.execute("DELETE FROM snapshots WHERE captured_at < ?1", [bound(&before)])
+            .map_err(StoreError::Sqlite)
+    }
+
+    pub fn put_changeset(&self, captured_at: DateTime<Utc>, changeset: &Changeset) -> SqliteResult<()> {
+        let changeset = serde_json::to_string(changeset).map_err(StoreError::Serialization)?;
+
+        self.conn.execute("INSERT INTO changesets (captured_at, changeset) VALUES (?1, ?2)",
+                          (to_timestamp(&captured_at)?, &changeset))
+            .map(|_| ())
+            .map_err(StoreError::Sqlite)
+    }
+
+    pub fn changesets_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> SqliteResult<ChangesetHistory> {
+        let mut stmt = self.conn.prepare(
+            "SELECT captured_at, changeset FROM changesets \
+             WHERE captured_at >= ?1 AND captured_at < ?2 ORDER BY captured_at ASC, id ASC")
+            .map_err(StoreError::Sqlite)?;
+        let rows = stmt.query_map((bound(&from), bound(&to)), |row| {
+                let captured_at: i64 = row.get(0)?;
+                let changeset: String = row.get(1)?;
+                Ok((captured_at, changeset))
+            })
+            .map_err(StoreError::Sqlite)?
+            .collect::<Result<Vec<_>, SqliteError>>()
+            .map_err(StoreError::Sqlite)?;
+
+        let mut history = ChangesetHistory::new();
+        for (captured_at, changeset) in rows {
+            let changeset = serde_json::from_str(&changeset).map_err(StoreError::Serialization)?;
+            history.push(from_timestamp(captured_at), changeset);
+        }
+
+        Ok(history)
+    }
+
+    pub fn record_poll(&self, poll: &PollRecord) -> SqliteResult<()> {
+        self.conn.execute("INSERT INTO polls (polled_at, succeeded, message) VALUES (?1, ?2, ?3)",
+                          (to_timestamp(&poll.polled_at)?, poll.succeeded, &poll.message))
+            .map(|_| ())
+            .map_err(StoreError::Sqlite)
+    }
+
+    pub fn last_poll(&self) -> SqliteResult<Option<PollRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT polled_at, succeeded, message FROM polls ORDER BY polled_at DESC, id DESC LIMIT 1")
+            .map_err(StoreError::Sqlite)?;
+        let mut rows = stmt.query_map([], |row| {
+                let polled_at: i64 = row.get(0)?;
+
+                Ok(PollRecord {
+                    polled_at: from_timestamp(polled_at),
+                    succeeded: row.get(1)?,
+                    message: row.get(2)?,
+                })
+            })
+            .map_err(StoreError::Sqlite)?;
+
+        match rows.next() {
+            Some(Ok(poll)) => Ok(Some(poll)),
+            Some(Err(e)) => Err(StoreError::Sqlite(e)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn course_tags(&self) -> SqliteResult<CourseTags> {
+        let mut stmt = self.conn.prepare("SELECT tags FROM course_tags WHERE id = 1")
+            .map_err(StoreError::Sqlite)?;
+        let mut rows = stmt.query_map([], |row| {
+                let tags: String = row.get(0)?;
+                Ok(tags)
+            })
+            .map_err(StoreError::Sqlite)?;
+
+        match rows.next() {
+            Some(Ok(tags)) => serde_json::from_str(&tags).map_err(StoreError::Serialization),
+            Some(Err(e)) => Err(StoreError::Sqlite(e)),
+            None => Ok(Default::default()),
+        }
+    }
+
+    pub fn put_course_tags(&self, tags: &CourseTags) -> SqliteResult<()> {
+        let tags = serde_json::to_string(tags).map_err(StoreError::Serialization)?;
+
+        self.conn.execute("INSERT OR REPLACE INTO course_tags (id, tags) VALUES (1, ?1)", [&tags])
+            .map(|_| ())
+            .map_err(StoreError::Sqlite)
+    }
+
+    fn select_snapshots(&self, sql: &str, range: Option<(i64, i64)>) -> SqliteResult<Vec<Snapshot>> {
+        let mut stmt = self.conn.prepare(sql).map_err(StoreError::Sqlite)?;
+        let map_row = |row: &::rusqlite::Row| {
+            let captured_at: i64 = row.get(0)?;
+            let gradebook: String = row.get(1)?;
+            Ok((captured_at, gradebook))
+        };
+        let rows = match range {
+            Some((from, to)) => stmt.query_map((from, to), map_row),
+            None => stmt.query_map([], map_row),
+        }
+            .map_err(StoreError::Sqlite)?
+            .collect::<Result<Vec<_>, SqliteError>>()
+            .map_err(StoreError::Sqlite)?;
+
+        rows.into_iter()
+            .map(|(captured_at, gradebook)| {
+                serde_json::from_str(&gradebook)
+                    .map(|gradebook| Snapshot { captured_at: from_timestamp(captured_at), gradebook })
+                    .map_err(StoreError::Serialization)
+            })
+            .collect()
+    }
+}
+
+impl SnapshotStore for SqliteStore {
+    fn put_snapshot(&self, snapshot: &Snapshot) -> SqliteResult<()> {
+        SqliteStore::put_snapshot(self, snapshot)
+    }
+
+    fn latest_snapshot(&self) -> SqliteResult<Option<Snapshot>> {
+        SqliteStore::latest_snapshot(self)
+    }
+
+    fn snapshots_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> SqliteResult<Vec<Snapshot>> {
+        SqliteStore::snapshots_between(self, from, to)
+    }
+
+    fn prune_snapshots_before(&self, before: DateTime<Utc>) -> SqliteResult<usize> {
+        SqliteStore::prune_snapshots_before(self, before)
+    }
+
+    fn course_tags(&self) -> SqliteResult<CourseTags> {
+        SqliteStore::course_tags(self)
+    }
+
+    fn put_course_tags(&self, tags: &CourseTags) -> SqliteResult<()> {
+        SqliteStore::put_course_tags(self, tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gradebook::Gradebook;
+
+    use chrono::Duration;
+
+    fn snapshot(captured_at: DateTime<Utc>) -> Snapshot {
+        Snapshot {
+            captured_at,
+            gradebook: Gradebook::default(),
+        }
+    }
+
+    #[test]
+    fn keeps_snapshots_in_the_same_second_apart() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let at = Utc.with_ymd_and_hms(2018, 9, 28, 15, 12, 45).unwrap();
+        let later = at + Duration::milliseconds(250);
+
+        store.put_snapshot(&snapshot(later)).unwrap();
+        store.put_snapshot(&snapshot(at)).unwrap();
+
+        assert_eq!(store.latest_snapshot().unwrap().map(|s| s.captured_at), Some(later));
+        let between: Vec<_> = store.snapshots_between(at, later).unwrap().into_iter().map(|s| s.captured_at).collect();
+        assert_eq!(between, vec![at]);
+        let all: Vec<_> = store.snapshots_between(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap()
+            .into_iter()
+            .map(|s| s.captured_at)
+            .collect();
+        assert_eq!(all, vec![at, later]);
+    }
+
+    #[test]
+    fn rejects_times_it_cant_store() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let at = Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap();
+
+        match store.put_snapshot(&snapshot(at)) {
+            Err(StoreError::TimestampOutOfRange(t)) => assert_eq!(t, at),
+            other => panic!("expected the timestamp to be rejected, got {:?}", other),
+        }
+        assert!(store.latest_snapshot().unwrap().is_none());
+    }
+
+    #[test]
+    fn migrates_timestamps_stored_in_seconds() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.execute("INSERT INTO polls (polled_at, succeeded, message) VALUES (?1, 1, NULL)", [1538147565i64]).unwrap();
+
+        let store = SqliteStore::from_connection(conn).unwrap();
+        let poll = store.last_poll().unwrap().unwrap();
+        assert_eq!(poll.polled_at, Utc.with_ymd_and_hms(2018, 9, 28, 15, 12, 45).unwrap());
+
+        // and only once
+        let store = SqliteStore::from_connection(store.conn).unwrap();
+        assert_eq!(store.last_poll().unwrap().unwrap().polled_at, poll.polled_at);
+    }
+}
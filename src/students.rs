@@ -0,0 +1,211 @@
+// The `ChildList` document: the students an account can see, which is every child on a parent's
+// account and the student themselves otherwise. Districts disagree on whether a child's details
+// are attributes of `<Child>` or elements inside it (and on some of the names), so both are read.
+use std::collections::HashMap;
+
+use api::SVUEResponse;
+#[cfg(feature="client")]
+use api::{SVUEAPIAction, SVUEClient, SVUERequestError};
+use decoder::*;
+
+use xml::reader::{Events, EventReader, XmlEvent as ReaderEvent};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Student {
+    // what requests about this student are made with
+    pub child_int_id: String,
+    // as the district gives it, e.g. "10" or "K"
+    pub grade: String,
+    pub name: String,
+    // base64-encoded, usually a JPEG; only some districts send it
+    pub photo: Option<String>,
+    pub school: String,
+}
+
+impl Student {
+    #[cfg(feature="client")]
+    pub fn retrieve_all(user: &str, password: &str) -> Result<Vec<Student>, SVUERequestError> {
+        Self::retrieve_with(&SVUEClient::new(user, password))
+    }
+
+    #[cfg(feature="client")]
+    pub fn retrieve_with(client: &SVUEClient) -> Result<Vec<Student>, SVUERequestError> {
+        let resp = client.perform(SVUEAPIAction::RetrieveStudentInfo)?;
+
+        Self::from_response(&resp).map_err(|e| SVUERequestError::DecodingError(Box::new(e)))
+    }
+
+    pub fn from_response(resp: &SVUEResponse) -> DecoderResult<Vec<Student>> {
+        Self::from_xml(&resp.xml)
+    }
+
+    pub fn from_xml(xml: &str) -> DecoderResult<Vec<Student>> {
+        let limits = DecodeLimits::new();
+        let mut events_iter = EventReader::new(xml.as_bytes()).into_iter();
+        let mut students = Vec::new();
+
+        loop {
+            match events_iter.next() {
+                Some(Ok(event)) => {
+                    match event {
+                        _ if is_start_of(&event, "Child") => {
+                            students.push(Student::from_event(event, &mut events_iter, &limits)?);
+                        }
+                        _ if is_end_of(&event, "ChildList") => { return Ok(students); }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                None => { return Err(DecodingError::UnexpectedEnd); }
+            }
+        }
+    }
+
+    // first names are shown by themselves in places (a parent's list of children, say)
+    pub fn first_name(&self) -> &str {
+        self.name.split_whitespace().next().unwrap_or("")
+    }
+}
+
+// the first of `names` with a non-empty value
+fn first_of(fields: &HashMap<String, String>, names: &[&str]) -> Option<String> {
+    names.iter()
+        .filter_map(|n| fields.get(*n))
+        .map(|v| v.trim())
+        .find(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+impl SVUEDecodeable for Student {
+    fn from_event(event: ReaderEvent, events_iter: &mut Events<&[u8]>, limits: &DecodeLimits) -> DecoderResult<Student> {
+        match event {
+            ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                match name.local_name.as_str() {
+                    "Child" => {
+                        limits.count_element()?;
+
+                        // attributes and the text of child elements, by name
+                        let mut fields: HashMap<String, String> = attributes_vec_to_map(attributes).iter()
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect();
+                        let mut current: Option<String> = None;
+
+                        loop {
+                            match events_iter.next() {
+                                Some(Ok(event)) => {
+                                    match event {
+                                        ReaderEvent::StartElement { ref name, ref attributes, .. } => {
+                                            for (k, v) in attributes_vec_to_map(attributes) {
+                                                fields.entry(k.to_string()).or_insert(v.to_string());
+                                            }
+                                            current = Some(name.local_name.clone());
+                                        }
+                                        ReaderEvent::Characters(ref text) | ReaderEvent::CData(ref text) => {
+                                            if let Some(ref field) = current {
+                                                fields.entry(field.clone()).or_default().push_str(text);
+                                            }
+                                        }
+                                        _ if is_end_of(&event, "Child") => { break; }
+                                        ReaderEvent::EndElement { .. } => { current = None; }
+                                        _ => {}
+                                    }
+                                }
+                                Some(Err(e)) => { return Err(DecodingError::EventError(e)); }
+                                None => { return Err(DecodingError::UnexpectedEnd); }
+                            }
+                        }
+
+                        let child_int_id = match first_of(&fields, &["ChildIntID", "ChildIntId"]) {
+                            Some(id) => id,
+                            None => { return Err(DecodingError::MissingAttribute("ChildIntID".into())); }
+                        };
+                        let name = first_of(&fields, &["ChildName", "FormattedName", "Name"])
+                            .or_else(|| {
+                                let first = first_of(&fields, &["ChildFirstName", "FirstName"]);
+                                let last = first_of(&fields, &["ChildLastName", "LastName"]);
+                                match (first, last) {
+                                    (Some(f), Some(l)) => Some(format!("{} {}", f, l)),
+                                    (f, l) => f.or(l),
+                                }
+                            })
+                            .unwrap_or_default();
+
+                        Ok(Student {
+                            child_int_id,
+                            grade: first_of(&fields, &["Grade", "GradeLevel"]).unwrap_or_default(),
+                            name: limits.text(&name),
+                            photo: first_of(&fields, &["photo", "Photo"]),
+                            school: first_of(&fields, &["OrganizationName", "SchoolName", "CurrentSchool"])
+                                .unwrap_or_default(),
+                        })
+                    }
+                    _ => Err(DecodingError::UnexpectedEvent(event.clone()))
+                }
+            }
+            _ => Err(DecodingError::UnexpectedEvent(event))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTES: &str = include_str!("../fixtures/child_list_attributes.xml");
+    const ELEMENTS: &str = include_str!("../fixtures/child_list_elements.xml");
+
+    fn student(id: &str, name: &str, grade: &str, school: &str, photo: Option<&str>) -> Student {
+        Student {
+            child_int_id: id.to_string(),
+            grade: grade.to_string(),
+            name: name.to_string(),
+            photo: photo.map(|p| p.to_string()),
+            school: school.to_string(),
+        }
+    }
+
+    #[test]
+    fn decodes_children_given_as_attributes() {
+        assert_eq!(Student::from_xml(ATTRIBUTES).unwrap(), vec![
+            student("9043", "Maya Okafor", "10", "Lincoln High School", Some("/9j/4AAQSkZJRgABAQ")),
+            // `ChildIntId`, and first and last names without a full one
+            student("9044", "Theo Okafor", "7", "Hayhurst Middle School", None),
+            // a first name alone
+            student("9045", "Ivy", "K", "Hayhurst Elementary", None),
+        ]);
+    }
+
+    #[test]
+    fn decodes_children_given_as_elements() {
+        assert_eq!(Student::from_xml(ELEMENTS).unwrap(), vec![
+            student("9043", "Maya Okafor", "10", "Lincoln High School", Some("/9j/4AAQSkZJRgABAQ")),
+            // an empty `ChildName` falls through to the first and last names, and a nested
+            // element's attributes count too
+            student("9044", "Theo Okafor", "7", "Hayhurst Middle School", None),
+            // a last name alone, and no school at all
+            student("9045", "Okafor", "K", "", None),
+        ]);
+    }
+
+    #[test]
+    fn an_empty_list_has_no_students() {
+        assert!(Student::from_xml("<ChildList />").unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_child_without_an_id_is_an_error() {
+        let xml = "<ChildList><Child ChildName=\"Maya Okafor\"><ChildIntID>  </ChildIntID></Child></ChildList>";
+
+        match Student::from_xml(xml) {
+            Err(DecodingError::MissingAttribute(ref name)) => assert_eq!(name, "ChildIntID"),
+            other => panic!("expected MissingAttribute, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn first_name_is_the_first_word() {
+        assert_eq!(student("1", "Maya Okafor", "10", "", None).first_name(), "Maya");
+        assert_eq!(student("1", "", "10", "", None).first_name(), "");
+    }
+}
@@ -0,0 +1,157 @@
+// User-defined labels for courses, for telling a lot of them (or several students' worth) apart at
+// a glance. Nothing here comes from StudentVUE; labels are keyed by the course's full title, so
+// they follow a course between fetches and across period changes.
+use std::collections::HashMap;
+
+use gradebook::{Course, CourseTitle, Gradebook};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CourseLabel {
+    // anything a renderer understands, e.g. "#4a90d9" or "teal"; passed through as is
+    pub color: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default)]
+pub struct CourseTags {
+    labels: HashMap<String, CourseLabel>,
+}
+
+fn key(title: &CourseTitle) -> String {
+    title.to_string()
+}
+
+impl CourseTags {
+    pub fn new() -> CourseTags {
+        Default::default()
+    }
+
+    // tags are kept in the order they were added; adding one twice does nothing
+    pub fn tag(&mut self, title: &CourseTitle, tag: &str) {
+        let label = self.labels.entry(key(title)).or_default();
+
+        if !label.tags.iter().any(|t| t == tag) {
+            label.tags.push(tag.to_string());
+        }
+    }
+
+    // returns whether the course had the tag
+    pub fn untag(&mut self, title: &CourseTitle, tag: &str) -> bool {
+        let removed = match self.labels.get_mut(&key(title)) {
+            Some(label) => {
+                let before = label.tags.len();
+                label.tags.retain(|t| t != tag);
+                label.tags.len() != before
+            }
+            None => false,
+        };
+
+        self.drop_if_empty(title);
+        removed
+    }
+
+    pub fn set_color(&mut self, title: &CourseTitle, color: &str) {
+        self.labels.entry(key(title)).or_default().color = Some(color.to_string());
+    }
+
+    pub fn clear_color(&mut self, title: &CourseTitle) {
+        if let Some(label) = self.labels.get_mut(&key(title)) {
+            label.color = None;
+        }
+
+        self.drop_if_empty(title);
+    }
+
+    pub fn label(&self, title: &CourseTitle) -> Option<&CourseLabel> {
+        self.labels.get(&key(title))
+    }
+
+    pub fn tags_for(&self, title: &CourseTitle) -> &[String] {
+        self.label(title).map(|l| l.tags.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn color_for(&self, title: &CourseTitle) -> Option<&str> {
+        self.label(title).and_then(|l| l.color.as_ref()).map(|c| c.as_str())
+    }
+
+    // the courses in `gradebook` with `tag`, in gradebook order
+    pub fn courses_tagged<'a>(&self, gradebook: &'a Gradebook, tag: &str) -> Vec<&'a Course> {
+        gradebook.courses().iter()
+            .filter(|c| self.tags_for(&c.title).iter().any(|t| t == tag))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    fn drop_if_empty(&mut self, title: &CourseTitle) {
+        let empty = self.label(title).map(|l| l.color.is_none() && l.tags.is_empty()).unwrap_or(false);
+
+        if empty {
+            self.labels.remove(&key(title));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradebook() -> Gradebook {
+        Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap()
+    }
+
+    fn title(name: &str, id: &str) -> CourseTitle {
+        CourseTitle::Parsed(name.to_string(), id.to_string())
+    }
+
+    #[test]
+    fn keeps_tags_in_order_without_duplicates() {
+        let english = title("English 10", "EN1002");
+        let mut tags = CourseTags::new();
+        tags.tag(&english, "core");
+        tags.tag(&english, "honors");
+        tags.tag(&english, "core");
+
+        assert_eq!(tags.tags_for(&english), ["core", "honors"]);
+        assert!(tags.tags_for(&title("English 10", "EN1003")).is_empty());
+        assert_eq!(tags.len(), 1);
+    }
+
+    #[test]
+    fn drops_a_label_once_it_has_no_tags_or_color() {
+        let english = title("English 10", "EN1002");
+        let mut tags = CourseTags::new();
+        tags.tag(&english, "core");
+        tags.set_color(&english, "#4a90d9");
+
+        assert!(tags.untag(&english, "core"));
+        assert!(!tags.untag(&english, "core"));
+        assert_eq!(tags.color_for(&english), Some("#4a90d9"));
+
+        tags.clear_color(&english);
+        assert!(tags.label(&english).is_none());
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn follows_courses_across_fetches_by_title() {
+        let mut gradebook = gradebook();
+        let mut tags = CourseTags::new();
+        tags.tag(&title("Chemistry", "SC2010"), "lab");
+
+        // a period change doesn't lose the tag
+        gradebook.courses[1].period = 5;
+        let tagged = tags.courses_tagged(&gradebook, "lab");
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].period, 5);
+        assert!(tags.courses_tagged(&gradebook, "core").is_empty());
+    }
+}
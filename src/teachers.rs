@@ -0,0 +1,161 @@
+// Everything grouped by who teaches it. Only courses carry a teacher for now; messages and class
+// notes can join in here once rvue can retrieve them.
+use gradebook::{Assignment, Course, Gradebook};
+use report::current_mark;
+
+// the portal has no teacher IDs on courses, so a teacher is whoever has the same email (or the
+// same name, for courses without one)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Teacher {
+    pub name: String,
+    pub email: String,
+}
+
+impl Teacher {
+    pub fn of(course: &Course) -> Teacher {
+        Teacher {
+            name: course.staff.trim().to_string(),
+            email: course.staff_email.trim().to_string(),
+        }
+    }
+
+    pub fn teaches(&self, course: &Course) -> bool {
+        self.is(&Teacher::of(course))
+    }
+
+    fn is(&self, other: &Teacher) -> bool {
+        if !self.email.is_empty() && !other.email.is_empty() {
+            self.email.eq_ignore_ascii_case(&other.email)
+        } else {
+            self.name.eq_ignore_ascii_case(&other.name)
+        }
+    }
+
+    // `query` against the name or email, ignoring case, so "smith" finds "Ms. Smith"
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+
+        !query.is_empty() && (self.name.to_lowercase().contains(&query) || self.email.to_lowercase().contains(&query))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TeacherView<'a> {
+    pub teacher: Teacher,
+    pub courses: Vec<&'a Course>,
+}
+
+impl<'a> TeacherView<'a> {
+    // assignments in the current mark of each course that have notes from the teacher
+    pub fn notes(&self) -> Vec<(&'a Course, &'a Assignment)> {
+        self.courses.iter()
+            .flat_map(|&c| {
+                current_mark(c).into_iter()
+                    .flat_map(|m| m.assignments().iter())
+                    .filter(|a| !a.notes.trim().is_empty())
+                    .map(move |a| (c, a))
+            })
+            .collect()
+    }
+}
+
+// one view per teacher, in the order their first course appears
+pub fn by_teacher<'a>(gradebook: &'a Gradebook) -> Vec<TeacherView<'a>> {
+    let mut views: Vec<TeacherView<'a>> = Vec::new();
+
+    for course in gradebook.courses() {
+        let teacher = Teacher::of(course);
+
+        match views.iter().position(|v| v.teacher.is(&teacher)) {
+            Some(i) => views[i].courses.push(course),
+            None => views.push(TeacherView {
+                teacher,
+                courses: vec![course],
+            }),
+        }
+    }
+
+    views
+}
+
+// every teacher whose name or email contains `query`
+pub fn find<'a>(gradebook: &'a Gradebook, query: &str) -> Vec<TeacherView<'a>> {
+    by_teacher(gradebook).into_iter()
+        .filter(|v| v.teacher.matches(query))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // English and Chemistry, plus a second English section taught by the same teacher under a
+    // differently cased email, and an art class with no email at all
+    fn gradebook() -> Gradebook {
+        let mut gradebook = Gradebook::from_xml(include_str!("../fixtures/gradebook_no_standards.xml")).unwrap();
+
+        let mut section = gradebook.courses[0].clone();
+        section.period = 6;
+        section.staff = "Chris Okafor".to_string();
+        section.staff_email = " COkafor@Example.org".to_string();
+        section.marks[0].assignments[0].notes = "See me about the hook".to_string();
+        gradebook.courses.push(section);
+
+        let mut art = gradebook.courses[1].clone();
+        art.period = 7;
+        art.staff = "Smith, Pat".to_string();
+        art.staff_email = String::new();
+        gradebook.courses.push(art);
+
+        gradebook
+    }
+
+    #[test]
+    fn groups_courses_by_email_in_gradebook_order() {
+        let gradebook = gradebook();
+        let views = by_teacher(&gradebook);
+
+        let names: Vec<&str> = views.iter().map(|v| v.teacher.name.as_str()).collect();
+        assert_eq!(names, vec!["Okafor, Chris", "Nguyen, Sam", "Smith, Pat"]);
+        let periods: Vec<i8> = views[0].courses.iter().map(|c| c.period).collect();
+        assert_eq!(periods, vec![2, 6]);
+        assert!(views[0].teacher.teaches(&gradebook.courses[2]));
+        assert!(!views[0].teacher.teaches(&gradebook.courses[1]));
+    }
+
+    #[test]
+    fn falls_back_to_the_name_without_an_email() {
+        let gradebook = gradebook();
+        let pat = Teacher {
+            name: "smith, pat".to_string(),
+            email: "psmith@example.org".to_string(),
+        };
+
+        assert!(pat.teaches(&gradebook.courses[3]));
+        assert!(!pat.teaches(&gradebook.courses[1]));
+    }
+
+    #[test]
+    fn finds_teachers_by_part_of_a_name_or_email() {
+        let gradebook = gradebook();
+
+        let found = find(&gradebook, " OKAFOR ");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].courses.len(), 2);
+        assert_eq!(find(&gradebook, "snguyen@")[0].teacher.name, "Nguyen, Sam");
+        assert!(find(&gradebook, "").is_empty());
+        assert!(find(&gradebook, "lee").is_empty());
+    }
+
+    #[test]
+    fn collects_notes_from_every_course_a_teacher_has() {
+        let gradebook = gradebook();
+        let views = by_teacher(&gradebook);
+
+        let notes = views[0].notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].0.period, 6);
+        assert_eq!(notes[0].1.notes, "See me about the hook");
+        assert!(views[1].notes().is_empty());
+    }
+}
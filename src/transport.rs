@@ -0,0 +1,61 @@
+// How a request's SOAP envelope gets to the district. `SVUEClient` sends through a `Transport`,
+// which is rvue's own reqwest client unless replaced, so an application built on a newer HTTP
+// stack (or one pinned to an older one) can send rvue's requests with it instead and nothing else
+// has to change: bodies, errors from the district and decoding all stay rvue's. Errors from a
+// transport of your own go in `SVUERequestError::TransportError`.
+use std::error::Error;
+use std::io::Read;
+
+use api::{SOAP_ACTION, SVUERequestError};
+
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+
+pub struct TransportResponse {
+    // read as it's decoded, so it never has to be buffered whole
+    pub body: Box<dyn Read + Send>,
+    // where the request ended up after any redirects; some districts redirect to the portal's
+    // change-password page instead of answering
+    pub url: String,
+}
+
+pub trait Transport {
+    // POSTs `body` to `endpoint` with the `SOAPAction` header set to `SOAP_ACTION` and a
+    // `text/xml; charset=utf-8` content type
+    fn post_soap(&self, endpoint: &str, body: Vec<u8>) -> Result<TransportResponse, SVUERequestError>;
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn post_soap(&self, endpoint: &str, body: Vec<u8>) -> Result<TransportResponse, SVUERequestError> {
+        (**self).post_soap(endpoint, body)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn post_soap(&self, endpoint: &str, body: Vec<u8>) -> Result<TransportResponse, SVUERequestError> {
+        (**self).post_soap(endpoint, body)
+    }
+}
+
+// for wrapping a transport's own error type
+pub fn error<E: Error + Send + Sync + 'static>(e: E) -> SVUERequestError {
+    SVUERequestError::TransportError(Box::new(e))
+}
+
+impl Transport for reqwest::blocking::Client {
+    fn post_soap(&self, endpoint: &str, body: Vec<u8>) -> Result<TransportResponse, SVUERequestError> {
+        // a 4xx or 5xx has no envelope to decode, so it's the status that's reported
+        let resp = self.post(endpoint)
+            .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+            .header("SOAPAction", &SOAP_ACTION[..])
+            .body(body)
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .map_err(SVUERequestError::ReqwestError)?;
+
+        Ok(TransportResponse {
+            url: resp.url().as_str().to_string(),
+            body: Box::new(resp),
+        })
+    }
+}
@@ -0,0 +1,127 @@
+// Lets something outside the schedule (a phone shortcut, a home-automation rule) make a running
+// `Watcher` or `HouseholdWatcher` poll right away:
+//
+//     POST /refresh
+//     Authorization: Bearer <token>
+//
+// answers 202 once the refresh is queued. The watcher then polls, diffs and notifies as it does on
+// its own schedule, with the same snapshots and delivery state, so a triggered poll never sends
+// anything twice; see `Watcher::refresh_cooldown` for how often it'll go along with it. Bodies
+// are `{"status": "queued"}`, or `{"error": "..."}`.
+use std::io;
+
+use export::json::{self, Object};
+use http_server::{self, Handler, Listening, Method, Request, Response, StatusCode};
+use watcher::RefreshHandle;
+
+pub struct TriggerServer {
+    handle: RefreshHandle,
+    token: String,
+}
+
+fn error(message: &str) -> String {
+    Object::new().field("error", json::string(message)).build()
+}
+
+impl TriggerServer {
+    // `token` should be long and random; an empty one turns every request down
+    pub fn new(handle: RefreshHandle, token: &str) -> TriggerServer {
+        TriggerServer {
+            handle,
+            token: token.to_string(),
+        }
+    }
+
+    pub fn listen(self, addr: &str) -> io::Result<Listening> {
+        http_server::listen(self, addr)
+    }
+
+    fn route(&self, req: &Request) -> (StatusCode, String) {
+        // before anything else, as `serve::ApiServer` does
+        if !req.has_bearer_token(&self.token) {
+            return (StatusCode::UNAUTHORIZED, error("missing or wrong token"));
+        }
+        if req.path != "/refresh" {
+            return (StatusCode::NOT_FOUND, error("not found"));
+        }
+        if req.method != Method::POST {
+            return (StatusCode::METHOD_NOT_ALLOWED, error("only POST is supported"));
+        }
+
+        self.handle.request();
+        (StatusCode::ACCEPTED, Object::new().field("status", json::string("queued")).build())
+    }
+}
+
+impl Handler for TriggerServer {
+    fn handle(&self, req: &Request) -> Response {
+        let (status, body) = self.route(req);
+        Response::new(status, "application/json", body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_server::HeaderMap;
+
+    fn send(server: &TriggerServer, method: Method, path: &str, authorization: Option<&str>) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(a) = authorization {
+            headers.insert("Authorization", a.parse().unwrap());
+        }
+
+        server.handle(&Request {
+            method,
+            path: path.to_string(),
+            query: String::new(),
+            headers,
+            body: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn queues_a_refresh() {
+        let handle = RefreshHandle::new();
+        let server = TriggerServer::new(handle.clone(), "s3cret");
+
+        let resp = send(&server, Method::POST, "/refresh", Some("Bearer s3cret"));
+        assert_eq!(resp.status, StatusCode::ACCEPTED);
+        assert_eq!(resp.body, r#"{"status":"queued"}"#);
+        assert!(handle.is_requested());
+    }
+
+    #[test]
+    fn turns_down_every_route_without_the_token() {
+        let handle = RefreshHandle::new();
+        let server = TriggerServer::new(handle.clone(), "s3cret");
+
+        for &(ref method, path) in &[(Method::POST, "/refresh"), (Method::GET, "/refresh"), (Method::POST, "/missing")] {
+            for auth in &[None, Some("Bearer wrong!"), Some("Bearer s3cret "), Some("s3cret")] {
+                let resp = send(&server, method.clone(), path, *auth);
+                assert_eq!(resp.status, StatusCode::UNAUTHORIZED, "{} {} {:?}", method, path, auth);
+            }
+        }
+        assert!(!handle.is_requested());
+    }
+
+    #[test]
+    fn only_refreshes_on_a_post_to_refresh() {
+        let handle = RefreshHandle::new();
+        let server = TriggerServer::new(handle.clone(), "s3cret");
+
+        assert_eq!(send(&server, Method::GET, "/refresh", Some("Bearer s3cret")).status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(send(&server, Method::POST, "/missing", Some("Bearer s3cret")).status, StatusCode::NOT_FOUND);
+        assert!(!handle.is_requested());
+    }
+
+    #[test]
+    fn turns_down_everything_with_an_empty_token() {
+        let handle = RefreshHandle::new();
+        let server = TriggerServer::new(handle.clone(), "");
+
+        assert_eq!(send(&server, Method::POST, "/refresh", None).status, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(&server, Method::POST, "/refresh", Some("Bearer ")).status, StatusCode::UNAUTHORIZED);
+        assert!(!handle.is_requested());
+    }
+}
@@ -0,0 +1,133 @@
+// Whether an assignment was turned in late, not at all, and so on. StudentVUE has no field for it;
+// teachers put a code where the score goes ("LATE", "MSG") or a word in the notes ("turned in
+// late"), and which ones differs by district, so the markers are a table that can be added to.
+use gradebook::{Assignment, AssignmentScore};
+
+#[cfg_attr(feature="serde-serialize", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TurnInStatus {
+    Absent,
+    Excused,
+    Incomplete,
+    Late,
+    Missing,
+    // nothing says otherwise, which usually means it was turned in on time or isn't due yet
+    Unmarked,
+}
+
+impl TurnInStatus {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TurnInStatus::Absent => "absent",
+            TurnInStatus::Excused => "excused",
+            TurnInStatus::Incomplete => "incomplete",
+            TurnInStatus::Late => "late",
+            TurnInStatus::Missing => "missing",
+            TurnInStatus::Unmarked => "unmarked",
+        }
+    }
+
+    // the inverse of `as_str`
+    pub fn parse(name: &str) -> Option<TurnInStatus> {
+        match name.trim().to_lowercase().as_str() {
+            "absent" => Some(TurnInStatus::Absent),
+            "excused" => Some(TurnInStatus::Excused),
+            "incomplete" => Some(TurnInStatus::Incomplete),
+            "late" => Some(TurnInStatus::Late),
+            "missing" => Some(TurnInStatus::Missing),
+            "unmarked" => Some(TurnInStatus::Unmarked),
+            _ => None,
+        }
+    }
+}
+
+// lowercase words, separated and surrounded by single spaces, so " late " can't match "translate"
+fn words(s: &str) -> String {
+    let joined = s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(" {} ", joined)
+}
+
+#[derive(Clone, Debug)]
+pub struct TurnInMarkers {
+    // the whole of the score, e.g. "M"; short enough that they'd be wrong anywhere else
+    codes: Vec<(String, TurnInStatus)>,
+    // words or phrases anywhere in the score or the notes
+    phrases: Vec<(String, TurnInStatus)>,
+}
+
+impl Default for TurnInMarkers {
+    fn default() -> TurnInMarkers {
+        TurnInMarkers::new().defaults()
+    }
+}
+
+impl TurnInMarkers {
+    // no markers at all; see `default` for the common ones
+    pub fn new() -> TurnInMarkers {
+        TurnInMarkers {
+            codes: Vec::new(),
+            phrases: Vec::new(),
+        }
+    }
+
+    // adds the markers districts commonly use, after any already there (earlier ones win); in
+    // order, so "excused" beats "missing" in "missing (excused)"
+    pub fn defaults(self) -> TurnInMarkers {
+        self.code("EX", TurnInStatus::Excused)
+            .code("EXC", TurnInStatus::Excused)
+            .code("ABS", TurnInStatus::Absent)
+            .code("AB", TurnInStatus::Absent)
+            .code("M", TurnInStatus::Missing)
+            .code("MSG", TurnInStatus::Missing)
+            .code("MI", TurnInStatus::Missing)
+            .code("L", TurnInStatus::Late)
+            .code("INC", TurnInStatus::Incomplete)
+            .code("I", TurnInStatus::Incomplete)
+            .phrase("excused", TurnInStatus::Excused)
+            .phrase("exempt", TurnInStatus::Excused)
+            .phrase("absent", TurnInStatus::Absent)
+            .phrase("missing", TurnInStatus::Missing)
+            .phrase("not turned in", TurnInStatus::Missing)
+            .phrase("not submitted", TurnInStatus::Missing)
+            .phrase("late", TurnInStatus::Late)
+            .phrase("incomplete", TurnInStatus::Incomplete)
+    }
+
+    // matched case-insensitively against the whole score
+    pub fn code(mut self, code: &str, status: TurnInStatus) -> TurnInMarkers {
+        self.codes.push((code.trim().to_lowercase(), status));
+        self
+    }
+
+    // matched case-insensitively as whole words, so "late" doesn't match "translate"
+    pub fn phrase(mut self, phrase: &str, status: TurnInStatus) -> TurnInMarkers {
+        self.phrases.push((words(phrase), status));
+        self
+    }
+
+    pub fn classify(&self, assignment: &Assignment) -> TurnInStatus {
+        // only a score that isn't a number can be a marker
+        let score = match assignment.score {
+            AssignmentScore::Unparseable(ref s) => s.as_str(),
+            _ => "",
+        };
+
+        let code = score.trim().to_lowercase();
+        if !code.is_empty() {
+            if let Some(&(_, status)) = self.codes.iter().find(|&(c, _)| *c == code) {
+                return status;
+            }
+        }
+
+        let text = format!("{}{}", words(score), words(&assignment.notes));
+        self.phrases.iter()
+            .find(|&(p, _)| p.trim() != "" && text.contains(p.as_str()))
+            .map(|&(_, status)| status)
+            .unwrap_or(TurnInStatus::Unmarked)
+    }
+}
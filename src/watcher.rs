@@ -0,0 +1,1122 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use api::{SVUEClient, SVUERequestError};
+use clock::{Clock, SystemClock, Zone};
+use dedup::{Deduplicator, Fingerprint};
+use diff::{Changeset, HouseholdChangeset, StudentChangeset};
+use gradebook::Gradebook;
+use household::Household;
+use notify::{Notifier, NotifyError, RenderedChangeset};
+use render::i18n::{self, Strings};
+use render::template::{self, ChangesetTemplate};
+use rules::NotificationRules;
+use store::{Snapshot, SnapshotStore, StoreError};
+use store::file::FileStore;
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, FixedOffset, NaiveTime, Utc, Weekday};
+#[cfg(feature="tz")]
+use chrono_tz::Tz;
+#[cfg(feature="daemon")]
+use ctrlc;
+use serde_json;
+
+pub type WatchResult<T> = Result<T, WatchError>;
+
+#[derive(Debug)]
+pub enum WatchError {
+    // two household labels that would share a store directory (see `HouseholdWatcher::new`)
+    LabelsShareDirectory(String, String),
+    NotifyError(NotifyError),
+    RequestError(SVUERequestError),
+    StoreError(StoreError),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Health {
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_poll: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+impl Health {
+    // healthy until `max_failures` polls in a row have failed
+    pub fn is_healthy(&self, max_failures: u32) -> bool {
+        self.consecutive_failures < max_failures
+    }
+}
+
+// when to send a summary of everything that changed since the last one
+#[derive(Clone, Copy, Debug)]
+pub struct DigestSchedule {
+    // `None` for every day
+    day: Option<Weekday>,
+    time: NaiveTime,
+    zone: Zone,
+}
+
+impl DigestSchedule {
+    pub fn daily(time: NaiveTime) -> DigestSchedule {
+        DigestSchedule {
+            day: None,
+            time,
+            zone: Zone::utc(),
+        }
+    }
+
+    pub fn weekly(day: Weekday, time: NaiveTime) -> DigestSchedule {
+        DigestSchedule {
+            day: Some(day),
+            ..Self::daily(time)
+        }
+    }
+
+    // `time` is in UTC unless given an offset, e.g. the school's (see `SchoolClock`)
+    pub fn utc_offset(mut self, offset: FixedOffset) -> DigestSchedule {
+        self.zone = Zone::Fixed(offset);
+        self
+    }
+
+    // or a time zone, which unlike an offset follows daylight saving time
+    #[cfg(feature="tz")]
+    pub fn time_zone(mut self, tz: Tz) -> DigestSchedule {
+        self.zone = Zone::Named(tz);
+        self
+    }
+
+    // the first scheduled time strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let local = self.zone.local(after);
+        let mut next = local.date().and_time(self.time);
+
+        let period = match self.day {
+            Some(day) => {
+                let ahead = (7 + day.num_days_from_monday() - next.weekday().num_days_from_monday()) % 7;
+                next += ChronoDuration::days(ahead as i64);
+                ChronoDuration::weeks(1)
+            }
+            None => ChronoDuration::days(1),
+        };
+
+        if next <= local {
+            next += period;
+        }
+
+        self.zone.instant(next)
+    }
+
+    fn title<'a>(&self, strings: &'a Strings) -> &'a str {
+        match self.day {
+            Some(_) => &strings.weekly_digest,
+            None => &strings.daily_digest,
+        }
+    }
+}
+
+// what has to survive a restart besides the snapshots themselves
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct WatcherState {
+    // what the next digest is diffed against; kept here rather than looked up in the store so
+    // the retention policy can't prune it
+    #[serde(default)]
+    digest_baseline: Option<Snapshot>,
+    // least recently seen first (see `Deduplicator`)
+    seen: Vec<Fingerprint>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShutdownHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl ShutdownHandle {
+    fn new() -> ShutdownHandle {
+        ShutdownHandle {
+            requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn shutdown(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    // SIGINT/SIGTERM stop the watcher once the current poll finishes; can only be installed once
+    // per process
+    #[cfg(feature="daemon")]
+    pub fn install_signal_handler(&self) -> Result<(), ctrlc::Error> {
+        let handle = self.clone();
+        ctrlc::set_handler(move || handle.shutdown())
+    }
+}
+
+// asks a running watcher to poll now rather than at the end of its interval, e.g. from
+// `trigger::TriggerServer`; requests made while one is pending are merged into it
+#[derive(Clone, Debug)]
+pub struct RefreshHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl RefreshHandle {
+    pub(crate) fn new() -> RefreshHandle {
+        RefreshHandle {
+            requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+}
+
+// see `Watcher::on_health`
+type HealthHook = Box<dyn Fn(&Health) + Send>;
+
+pub struct Watcher<N> {
+    client: SVUEClient,
+    clock: Box<dyn Clock + Send>,
+    dedup: Deduplicator,
+    digest: Option<DigestSchedule>,
+    digest_baseline: Option<Snapshot>,
+    // where notifications go instead of `notifier` in a dry run
+    dry_run: Option<Box<dyn Notifier + Send>>,
+    health: Health,
+    health_hook: Option<HealthHook>,
+    immediate: bool,
+    interval: Duration,
+    notifier: N,
+    refresh: RefreshHandle,
+    refresh_cooldown: Duration,
+    rules: NotificationRules,
+    shutdown: ShutdownHandle,
+    state_path: PathBuf,
+    store: Box<dyn SnapshotStore + Send>,
+    strings: Strings,
+    student: Option<String>,
+    template: ChangesetTemplate,
+}
+
+impl<N: Notifier> Watcher<N> {
+    // picks up where a previous run left off: the newest snapshot in `store` is diffed against
+    // the next poll, and changes already delivered aren't sent again
+    pub fn new(client: SVUEClient, store: FileStore, notifier: N) -> WatchResult<Watcher<N>> {
+        let state_path = store.dir().join("watcher-state.json");
+        Self::with_store(client, store, state_path, notifier)
+    }
+
+    // for any other backend; the watcher's own state (what's been delivered, the digest baseline)
+    // is kept in a file at `state_path`
+    pub fn with_store<S, P>(client: SVUEClient, store: S, state_path: P, notifier: N) -> WatchResult<Watcher<N>>
+        where S: SnapshotStore + Send + 'static,
+              P: AsRef<Path> {
+
+        let state_path = state_path.as_ref().to_path_buf();
+        let state = Self::load_state(&state_path)?;
+
+        Ok(Watcher {
+            client,
+            clock: Box::new(SystemClock),
+            dedup: Deduplicator::from_seen(state.seen),
+            digest: None,
+            digest_baseline: state.digest_baseline,
+            dry_run: None,
+            health: Default::default(),
+            health_hook: None,
+            immediate: true,
+            interval: Duration::from_secs(60 * 60),
+            notifier,
+            refresh: RefreshHandle::new(),
+            refresh_cooldown: Duration::from_secs(60),
+            rules: NotificationRules::new(),
+            shutdown: ShutdownHandle::new(),
+            state_path,
+            store: Box::new(store),
+            strings: i18n::english(),
+            student: None,
+            template: template::text(),
+        })
+    }
+
+    // stamps polls and snapshots; the system clock unless replaced
+    pub fn clock<C: Clock + Send + 'static>(mut self, clock: C) -> Watcher<N> {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    // also sends a merged summary of the changes since the previous digest on `schedule`; it's
+    // sent by the first poll after the scheduled time, so it can be up to an interval late
+    pub fn digest(mut self, schedule: DigestSchedule) -> Watcher<N> {
+        self.digest = Some(schedule);
+        self
+    }
+
+    // whether each poll's changes are sent as soon as they're seen; turn off to only get digests
+    pub fn immediate(mut self, immediate: bool) -> Watcher<N> {
+        self.immediate = immediate;
+        self
+    }
+
+    // which courses' changes are sent immediately, only in digests, or not at all; everything is
+    // sent immediately unless replaced
+    pub fn rules(mut self, rules: NotificationRules) -> Watcher<N> {
+        self.rules = rules;
+        self
+    }
+
+    // the language notifications are written in; English unless set
+    pub fn strings(mut self, strings: Strings) -> Watcher<N> {
+        self.strings = strings;
+        self
+    }
+
+    // the wording of notification text and subjects; the built-in plain text unless set
+    pub fn template(mut self, template: ChangesetTemplate) -> Watcher<N> {
+        self.template = template;
+        self
+    }
+
+    // puts the child's name on everything sent, for households with more than one
+    pub fn student(mut self, label: &str) -> Watcher<N> {
+        self.student = Some(label.to_string());
+        self
+    }
+
+    // polls and diffs as usual, but sends everything to `preview` (e.g. `Printer::stdout()`)
+    // instead of the real notifier, and saves nothing: no snapshots, and no record of what was
+    // sent, so going live afterwards starts from where the last real run left off
+    pub fn dry_run<P: Notifier + Send + 'static>(mut self, preview: P) -> Watcher<N> {
+        self.dry_run = Some(Box::new(preview));
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Watcher<N> {
+        self.interval = interval;
+        self
+    }
+
+    // called after every poll, successful or not, e.g. to touch a liveness file or report to a
+    // monitoring endpoint
+    pub fn on_health<F: Fn(&Health) + Send + 'static>(mut self, hook: F) -> Watcher<N> {
+        self.health_hook = Some(Box::new(hook));
+        self
+    }
+
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    pub fn refresh_handle(&self) -> RefreshHandle {
+        self.refresh.clone()
+    }
+
+    // refreshes asked for sooner than this after a poll wait until it's passed, so a trigger
+    // pressed over and over doesn't hammer the district's server; a minute unless set
+    pub fn refresh_cooldown(mut self, cooldown: Duration) -> Watcher<N> {
+        self.refresh_cooldown = cooldown;
+        self
+    }
+
+    // polls until shutdown is requested; a failed poll is recorded in `Health` and retried at the
+    // next interval rather than ending the loop
+    pub fn run(&mut self) {
+        while !self.shutdown.is_shutdown() {
+            let _ = self.poll();
+            self.sleep();
+        }
+    }
+
+    pub fn poll(&mut self) -> WatchResult<Option<Changeset>> {
+        let now = self.clock.now();
+        let result = self.poll_at(now);
+
+        self.health.last_poll = Some(now);
+        match result {
+            Ok(_) => {
+                self.health.consecutive_failures = 0;
+                self.health.last_error = None;
+                self.health.last_success = Some(now);
+            }
+            Err(ref e) => {
+                self.health.consecutive_failures += 1;
+                self.health.last_error = Some(format!("{:?}", e));
+            }
+        }
+
+        if let Some(ref hook) = self.health_hook {
+            hook(&self.health);
+        }
+
+        result
+    }
+
+    // what would have been sent immediately over the snapshots captured in [from, to), each diffed
+    // against the one before it, sent to `sink` with the watcher's rules, template and language.
+    // Nothing is deduplicated or saved, so it can be run as often as needed.
+    pub fn replay<S: Notifier>(&self, from: DateTime<Utc>, to: DateTime<Utc>, sink: &S) -> WatchResult<Vec<Changeset>> {
+        let snapshots = self.store.snapshots_between(from, to)
+            .map_err(WatchError::StoreError)?;
+        let mut sent = Vec::new();
+
+        for pair in snapshots.windows(2) {
+            let changeset = Changeset::diff(&pair[0].gradebook, &pair[1].gradebook)
+                .and_then(|c| self.rules.immediate(&c));
+
+            if let Some(changeset) = changeset {
+                let rendered = self.render(changeset);
+                sink.notify(&rendered).map_err(WatchError::NotifyError)?;
+                sent.push(rendered.changeset);
+            }
+        }
+
+        Ok(sent)
+    }
+
+    fn poll_at(&mut self, now: DateTime<Utc>) -> WatchResult<Option<Changeset>> {
+        let previous = self.store.latest_snapshot()
+            .map_err(WatchError::StoreError)?;
+        let gradebook = Gradebook::retrieve_if_any(&self.client, None)
+            .map_err(WatchError::RequestError)?;
+
+        // between terms some districts send nothing for the period, or a gradebook without any
+        // courses; diffing against that would report every course as dropped and make the empty
+        // gradebook the baseline, so the last real snapshot is kept until courses show up again
+        let had_courses = previous.as_ref().map(|p| !p.gradebook.courses.is_empty()).unwrap_or(false);
+        let gradebook = match gradebook {
+            Some(ref g) if g.courses.is_empty() && had_courses => { return Ok(None); }
+            None if previous.is_some() => { return Ok(None); }
+            Some(g) => g,
+            None => Gradebook::default(),
+        };
+
+        if self.digest.is_some() && self.digest_baseline.is_none() {
+            // the first digest covers everything since the last snapshot we know of
+            self.digest_baseline = previous.clone();
+        }
+
+        let changeset = previous
+            // no need to diff if the district says nothing's changed, or if nothing has (the
+            // usual case when polling often)
+            .and_then(|p| if gradebook.unchanged_since(&p.gradebook) || gradebook.same_content(&p.gradebook) {
+                None
+            } else {
+                Some(p)
+            })
+            .and_then(|p| Changeset::diff(&p.gradebook, &gradebook))
+            .and_then(|c| if self.immediate { self.rules.immediate(&c) } else { None });
+        let delivered = match changeset.map(|c| self.deliver(c)) {
+            Some(Ok(delivered)) => delivered,
+            // held back by quiet hours or a rate cap: a digest will pick the changes up, and
+            // without one the old baseline is kept so a later poll sends them with anything newer
+            Some(Err(WatchError::NotifyError(NotifyError::Deferred))) => {
+                if self.digest.is_none() {
+                    return Ok(None);
+                }
+                None
+            }
+            Some(Err(e)) => { return Err(e); }
+            // anything else is reported as part of the next digest, if at all
+            None => None,
+        };
+
+        // only saved once delivery succeeded, so a failed notification is retried against the
+        // same baseline next time
+        let snapshot = Snapshot {
+            captured_at: now,
+            gradebook,
+        };
+        if self.dry_run.is_none() {
+            self.store.put_snapshot(&snapshot).map_err(WatchError::StoreError)?;
+        }
+
+        self.deliver_digest(snapshot)?;
+
+        Ok(delivered)
+    }
+
+    // digests aren't deduplicated: with immediate notifications on they repeat them on purpose
+    fn deliver_digest(&mut self, current: Snapshot) -> WatchResult<()> {
+        let schedule = match self.digest {
+            Some(s) => s,
+            None => { return Ok(()); }
+        };
+
+        let changeset = match self.digest_baseline {
+            Some(ref baseline) if current.captured_at >= schedule.next_after(baseline.captured_at) => {
+                Changeset::diff(&baseline.gradebook, &current.gradebook).and_then(|c| self.rules.digest(&c))
+            }
+            Some(_) => { return Ok(()); }
+            None => None,
+        };
+
+        if let Some(changeset) = changeset {
+            let mut rendered = self.render(changeset);
+            rendered.subject = format!("{}: {}", schedule.title(&self.strings), rendered.subject);
+            match self.sink().notify(&rendered) {
+                Ok(_) => {}
+                // sent by a later poll, once it's let through
+                Err(NotifyError::Deferred) => { return Ok(()); }
+                Err(e) => { return Err(WatchError::NotifyError(e)); }
+            }
+        }
+
+        // the baseline only moves once the digest is out, so a failed one is retried next poll
+        self.digest_baseline = Some(current);
+        self.save_state()
+    }
+
+    // each sink is sent what it hasn't had yet, and only what it took is marked as seen; when some
+    // of a `FanOut`'s sinks fail, the snapshot isn't saved, so the next poll diffs the same way and
+    // retries just the ones that failed
+    fn deliver(&mut self, changeset: Changeset) -> WatchResult<Option<Changeset>> {
+        let sinks = self.sink().sink_count();
+        let unseen: Vec<Option<Changeset>> = (0..sinks)
+            .map(|i| self.dedup.unseen_for(i, changeset.clone()))
+            .collect();
+
+        if unseen.iter().all(|c| c.is_none()) {
+            return Ok(None);
+        }
+
+        let rendered: Vec<Option<RenderedChangeset>> = unseen.into_iter()
+            .map(|c| c.map(|c| self.render(c)))
+            .collect();
+        let results = self.sink().notify_sinks(&rendered);
+
+        let mut delivered = None;
+        let mut errors = Vec::new();
+        for (i, (result, rendered)) in results.into_iter().zip(rendered).enumerate() {
+            match (result, rendered) {
+                (Ok(_), Some(rendered)) => {
+                    self.dedup.mark_for(i, &rendered.changeset);
+                    delivered = delivered.or(Some(rendered.changeset));
+                }
+                (Ok(_), None) => {}
+                (Err(e), _) => { errors.push(e); }
+            }
+        }
+
+        if delivered.is_some() {
+            self.save_state()?;
+        }
+
+        if errors.is_empty() {
+            Ok(delivered)
+        } else if errors.iter().all(|e| matches!(*e, NotifyError::Deferred)) {
+            Err(WatchError::NotifyError(NotifyError::Deferred))
+        } else if errors.len() == 1 {
+            Err(WatchError::NotifyError(errors.remove(0)))
+        } else {
+            Err(WatchError::NotifyError(NotifyError::Failed(errors)))
+        }
+    }
+
+    fn render(&self, changeset: Changeset) -> RenderedChangeset {
+        match self.student {
+            Some(ref student) => {
+                RenderedChangeset::render_for_student_with(student, changeset, self.template.clone(), &self.strings)
+            }
+            None => RenderedChangeset::render_with(changeset, self.template.clone(), &self.strings),
+        }
+    }
+
+    fn sink(&self) -> &dyn Notifier {
+        match self.dry_run {
+            Some(ref preview) => preview,
+            None => &self.notifier,
+        }
+    }
+
+    fn sleep(&self) {
+        // wake up every second so a shutdown or refresh request doesn't have to wait out the whole
+        // interval
+        let step = Duration::from_secs(1);
+        let mut slept = Duration::from_secs(0);
+
+        while slept < self.interval && !self.shutdown.is_shutdown() {
+            if slept >= self.refresh_cooldown && self.refresh.take() {
+                return;
+            }
+
+            thread::sleep(step);
+            slept += step;
+        }
+
+        // the poll about to happen covers anything asked for in the meantime
+        self.refresh.take();
+    }
+
+    fn load_state(path: &Path) -> WatchResult<WatcherState> {
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+
+        let f = File::open(path).map_err(|e| WatchError::StoreError(StoreError::Io(e)))?;
+        serde_json::from_reader(BufReader::new(f))
+            .map_err(|e| WatchError::StoreError(StoreError::Serialization(e)))
+    }
+
+    fn save_state(&self) -> WatchResult<()> {
+        if self.dry_run.is_some() {
+            return Ok(());
+        }
+
+        let path = &self.state_path;
+        let tmp = path.with_extension("json.tmp");
+        let state = WatcherState {
+            digest_baseline: self.digest_baseline.clone(),
+            seen: self.dedup.seen().cloned().collect(),
+        };
+
+        {
+            let f = File::create(&tmp).map_err(|e| WatchError::StoreError(StoreError::Io(e)))?;
+            let mut w = BufWriter::new(f);
+            serde_json::to_writer(&mut w, &state)
+                .map_err(|e| WatchError::StoreError(StoreError::Serialization(e)))?;
+            // on disk before the rename, or a crash could lose what's been delivered and send it again
+            w.flush().map_err(|e| WatchError::StoreError(StoreError::Io(e)))?;
+            w.get_ref().sync_all().map_err(|e| WatchError::StoreError(StoreError::Io(e)))?;
+        }
+
+        fs::rename(&tmp, path).map_err(|e| WatchError::StoreError(StoreError::Io(e)))
+    }
+}
+
+// a watcher per child of a household, polled one after another and all sending to the same
+// notifier; each child's snapshots and state are kept apart, so they're deduplicated and digested
+// separately
+pub struct HouseholdWatcher<N> {
+    interval: Duration,
+    members: Vec<(String, Watcher<Arc<N>>)>,
+    refresh: RefreshHandle,
+    refresh_cooldown: Duration,
+    shutdown: ShutdownHandle,
+}
+
+// labels can be anything, but they name directories here
+fn member_dir(label: &str) -> String {
+    label.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+// "Ann B" and "Ann_B", or "Ann" and "ann" on a case-insensitive filesystem, would end up sharing
+// one directory and so one child's snapshots
+fn check_member_dirs(household: &Household) -> WatchResult<()> {
+    let mut dirs: Vec<(String, &str)> = Vec::new();
+
+    for m in household.members() {
+        let dir = member_dir(&m.label).to_lowercase();
+
+        if let Some(&(_, other)) = dirs.iter().find(|&(d, _)| *d == dir) {
+            return Err(WatchError::LabelsShareDirectory(other.to_string(), m.label.clone()));
+        }
+        dirs.push((dir, &m.label));
+    }
+
+    Ok(())
+}
+
+impl<N: Notifier> HouseholdWatcher<N> {
+    // each child gets a `FileStore` in a directory under `dir` named after their label; labels
+    // that only differ in case or punctuation are turned down, since they'd name the same directory
+    pub fn new<P: AsRef<Path>>(household: Household, dir: P, notifier: N) -> WatchResult<HouseholdWatcher<N>> {
+        check_member_dirs(&household)?;
+
+        let notifier = Arc::new(notifier);
+        let mut members = Vec::new();
+
+        for m in household.members() {
+            let store = FileStore::open(dir.as_ref().join(member_dir(&m.label)))
+                .map_err(WatchError::StoreError)?;
+            let watcher = Watcher::new(m.client.clone(), store, notifier.clone())?.student(&m.label);
+            members.push((m.label.clone(), watcher));
+        }
+
+        Ok(HouseholdWatcher {
+            interval: Duration::from_secs(60 * 60),
+            members,
+            refresh: RefreshHandle::new(),
+            refresh_cooldown: Duration::from_secs(60),
+            shutdown: ShutdownHandle::new(),
+        })
+    }
+
+    // configures every child's watcher the same way, e.g. `.each(|w| w.digest(schedule))`
+    pub fn each<F: Fn(Watcher<Arc<N>>) -> Watcher<Arc<N>>>(mut self, f: F) -> HouseholdWatcher<N> {
+        self.members = self.members.into_iter().map(|(label, w)| (label, f(w))).collect();
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> HouseholdWatcher<N> {
+        self.interval = interval;
+        self
+    }
+
+    pub fn health(&self, label: &str) -> Option<&Health> {
+        self.members.iter().find(|&(l, _)| l == label).map(|(_, w)| w.health())
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    // a refresh polls every child; the children's own watchers' handles do nothing here, since
+    // this loop is what polls them
+    pub fn refresh_handle(&self) -> RefreshHandle {
+        self.refresh.clone()
+    }
+
+    // see `Watcher::refresh_cooldown`
+    pub fn refresh_cooldown(mut self, cooldown: Duration) -> HouseholdWatcher<N> {
+        self.refresh_cooldown = cooldown;
+        self
+    }
+
+    pub fn run(&mut self) {
+        while !self.shutdown.is_shutdown() {
+            self.poll();
+            self.sleep();
+        }
+    }
+
+    // what was sent for each child this time, if anything; a child whose poll failed is left out
+    // and has the failure in their `health`, the same as a lone watcher
+    pub fn poll(&mut self) -> Option<HouseholdChangeset> {
+        let changesets: Vec<StudentChangeset> = self.members.iter_mut()
+            .filter_map(|&mut (ref label, ref mut watcher)| match watcher.poll() {
+                Ok(Some(changeset)) => Some(StudentChangeset {
+                    student: label.clone(),
+                    changeset,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        if changesets.is_empty() {
+            None
+        } else {
+            Some(HouseholdChangeset {
+                changesets,
+            })
+        }
+    }
+
+    // see `Watcher::sleep`
+    fn sleep(&self) {
+        let step = Duration::from_secs(1);
+        let mut slept = Duration::from_secs(0);
+
+        while slept < self.interval && !self.shutdown.is_shutdown() {
+            if slept >= self.refresh_cooldown && self.refresh.take() {
+                return;
+            }
+            thread::sleep(step);
+            slept += step;
+        }
+
+        self.refresh.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature="mock-server")]
+    use std::sync::Mutex;
+
+    #[cfg(feature="mock-server")]
+    use clock::FixedClock;
+    use gradebook::AssignmentScore;
+    #[cfg(feature="mock-server")]
+    use http_server::Listening;
+    #[cfg(feature="mock-server")]
+    use mock::{self, MockServer};
+    #[cfg(feature="mock-server")]
+    use notify::FanOut;
+    use notify::Printer;
+
+    use chrono::TimeZone;
+
+    const GRADEBOOK: &str = include_str!("../fixtures/gradebook_no_standards.xml");
+
+    // a directory of the test's own, empty to start with
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("rvue-watcher-test-{}-{}", ::std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // what a sink does with the next changeset it's given
+    #[cfg(feature="mock-server")]
+    #[derive(Clone, Copy)]
+    enum Answer {
+        Take,
+        Fail,
+        Defer,
+    }
+
+    // remembers the subject of everything it's taken
+    #[cfg(feature="mock-server")]
+    #[derive(Clone)]
+    struct Recorder {
+        answer: Arc<Mutex<Answer>>,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[cfg(feature="mock-server")]
+    impl Recorder {
+        fn new() -> Recorder {
+            Recorder {
+                answer: Arc::new(Mutex::new(Answer::Take)),
+                sent: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        fn answer(&self, answer: Answer) {
+            *self.answer.lock().unwrap() = answer;
+        }
+
+        fn sent(&self) -> Vec<String> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    #[cfg(feature="mock-server")]
+    impl Notifier for Recorder {
+        fn notify(&self, rendered: &RenderedChangeset) -> Result<(), NotifyError> {
+            match *self.answer.lock().unwrap() {
+                Answer::Take => {}
+                Answer::Fail => { return Err(NotifyError::Io(::std::io::Error::other("unreachable"))); }
+                Answer::Defer => { return Err(NotifyError::Deferred); }
+            }
+            self.sent.lock().unwrap().push(rendered.subject.clone());
+            Ok(())
+        }
+    }
+
+    // on the first of March 2026
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, minute, 0).unwrap()
+    }
+
+    // the fixture as it was before the first English assignment was regraded and chemistry moved
+    // out of lab 3; the mock server answers with the fixture itself
+    fn before_changes() -> Gradebook {
+        let mut gradebook = Gradebook::from_xml(GRADEBOOK).unwrap();
+        gradebook.courses[0].marks[0].assignments[0].score = AssignmentScore::Percentage(12.5);
+        gradebook.courses[1].room = "Lab 3".to_string();
+        gradebook
+    }
+
+    // a store in `dir` whose newest snapshot is `gradebook`, captured at `captured_at`
+    fn seeded(dir: &Path, gradebook: Gradebook, captured_at: DateTime<Utc>) -> FileStore {
+        let store = FileStore::open(dir).unwrap();
+        store.put_snapshot(&Snapshot { captured_at, gradebook }).unwrap();
+        store
+    }
+
+    #[cfg(feature="mock-server")]
+    fn serving(document: &str) -> (Listening, SVUEClient) {
+        let server = MockServer::new().document("Gradebook", document).listen("127.0.0.1:0").unwrap();
+        let client = SVUEClient::new("user", "pass").endpoint(&mock::endpoint_url(&server));
+        (server, client)
+    }
+
+    fn latest_capture(dir: &Path) -> DateTime<Utc> {
+        FileStore::open(dir).unwrap().latest_snapshot().unwrap().unwrap().captured_at
+    }
+
+    #[test]
+    fn schedules_digests_at_a_fixed_offset() {
+        let schedule = DigestSchedule::daily(NaiveTime::from_hms_opt(7, 0, 0).unwrap())
+            .utc_offset(FixedOffset::west_opt(8 * 3600).unwrap());
+        let after = Utc.with_ymd_and_hms(2026, 3, 7, 20, 0, 0).unwrap();
+
+        assert_eq!(schedule.next_after(after), Utc.with_ymd_and_hms(2026, 3, 8, 15, 0, 0).unwrap());
+    }
+
+    #[cfg(feature="tz")]
+    #[test]
+    fn follows_daylight_saving_time_in_a_time_zone() {
+        let schedule = DigestSchedule::daily(NaiveTime::from_hms_opt(7, 0, 0).unwrap())
+            .time_zone(chrono_tz::America::Los_Angeles);
+        // noon on the Saturday before the clocks go forward; 7:00 Sunday is already PDT
+        let after = Utc.with_ymd_and_hms(2026, 3, 7, 20, 0, 0).unwrap();
+
+        assert_eq!(schedule.next_after(after), Utc.with_ymd_and_hms(2026, 3, 8, 14, 0, 0).unwrap());
+    }
+
+    #[cfg(feature="tz")]
+    #[test]
+    fn moves_a_skipped_time_past_the_gap() {
+        let schedule = DigestSchedule::daily(NaiveTime::from_hms_opt(2, 30, 0).unwrap())
+            .time_zone(chrono_tz::America::Los_Angeles);
+        let after = Utc.with_ymd_and_hms(2026, 3, 8, 8, 0, 0).unwrap();
+
+        // 2:30 PST, which is 3:30 PDT
+        assert_eq!(schedule.next_after(after), Utc.with_ymd_and_hms(2026, 3, 8, 10, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn turns_down_labels_that_share_a_directory() {
+        let dir = scratch_dir("household-labels");
+        let cases = [
+            ("Ann B", "Ann_B"),
+            ("Ann", "ann"),
+            ("Ann/B", "Ann:B"),
+        ];
+
+        for &(first, second) in cases.iter() {
+            let household = Household::new()
+                .member(first, SVUEClient::new("a", "a"))
+                .member("Sam", SVUEClient::new("s", "s"))
+                .member(second, SVUEClient::new("b", "b"));
+
+            match HouseholdWatcher::new(household, &dir, Printer::new(Vec::new())) {
+                Err(WatchError::LabelsShareDirectory(ref a, ref b)) => assert_eq!((a.as_str(), b.as_str()), (first, second)),
+                Err(e) => panic!("expected {:?} and {:?} to be turned down, got {:?}", first, second, e),
+                Ok(_) => panic!("expected {:?} and {:?} to be turned down", first, second),
+            }
+        }
+        // before any store was opened
+        assert!(!dir.exists());
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn does_not_resend_after_a_restart() {
+        let dir = scratch_dir("restart");
+        let (server, client) = serving(GRADEBOOK);
+        let recorder = Recorder::new();
+
+        let store = seeded(&dir, before_changes(), at(6, 0));
+        let mut watcher = Watcher::new(client.clone(), store, recorder.clone()).unwrap().clock(FixedClock(at(6, 30)));
+        let sent = watcher.poll().unwrap().unwrap();
+        assert_eq!(sent.changes.len(), 2);
+        assert_eq!(recorder.sent().len(), 1);
+        assert_eq!(latest_capture(&dir), at(6, 30));
+        drop(watcher);
+
+        // as if the process died after notifying but before the snapshot was written, so the next
+        // run diffs against the same baseline; what was sent is read back from the state file
+        let store = seeded(&dir, before_changes(), at(6, 45));
+        let mut watcher = Watcher::new(client, store, recorder.clone()).unwrap().clock(FixedClock(at(7, 0)));
+        assert!(watcher.poll().unwrap().is_none());
+        assert_eq!(recorder.sent().len(), 1);
+        assert_eq!(latest_capture(&dir), at(7, 0));
+
+        server.close();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn retries_only_the_sink_that_failed() {
+        let dir = scratch_dir("fan-out");
+        let (server, client) = serving(GRADEBOOK);
+        let (working, failing) = (Recorder::new(), Recorder::new());
+        failing.answer(Answer::Fail);
+
+        let notifier = FanOut::new().sink(working.clone()).sink(failing.clone());
+        let store = seeded(&dir, before_changes(), at(6, 0));
+        let mut watcher = Watcher::new(client, store, notifier).unwrap().clock(FixedClock(at(6, 30)));
+
+        match watcher.poll() {
+            Err(WatchError::NotifyError(NotifyError::Io(_))) => {}
+            other => panic!("expected the failing sink's error, got {:?}", other),
+        }
+        assert_eq!((working.sent().len(), failing.sent().len()), (1, 0));
+        // not saved, so the next poll diffs the same way
+        assert_eq!(latest_capture(&dir), at(6, 0));
+        assert_eq!(watcher.health().consecutive_failures, 1);
+
+        failing.answer(Answer::Take);
+        watcher = watcher.clock(FixedClock(at(7, 30)));
+        assert!(watcher.poll().unwrap().is_some());
+        assert_eq!((working.sent().len(), failing.sent().len()), (1, 1));
+        assert_eq!(latest_capture(&dir), at(7, 30));
+
+        server.close();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn keeps_the_last_snapshot_when_the_district_sends_nothing() {
+        let no_data = MockServer::new().rt_error("Gradebook", "There are no grades to display").listen("127.0.0.1:0").unwrap();
+        let no_courses = MockServer::new()
+            .document("Gradebook", "<Gradebook><ReportingPeriods /><Courses /></Gradebook>")
+            .listen("127.0.0.1:0")
+            .unwrap();
+
+        for (i, server) in vec![no_data, no_courses].into_iter().enumerate() {
+            let dir = scratch_dir(&format!("nothing-{}", i));
+            let client = SVUEClient::new("user", "pass").endpoint(&mock::endpoint_url(&server));
+            let recorder = Recorder::new();
+
+            let store = seeded(&dir, Gradebook::from_xml(GRADEBOOK).unwrap(), at(6, 0));
+            let mut watcher = Watcher::new(client, store, recorder.clone()).unwrap().clock(FixedClock(at(6, 30)));
+            assert!(watcher.poll().unwrap().is_none());
+            assert!(recorder.sent().is_empty());
+            assert_eq!(latest_capture(&dir), at(6, 0));
+            assert_eq!(watcher.health().last_success, Some(at(6, 30)));
+
+            server.close();
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn sends_a_dry_run_to_the_preview_and_saves_nothing() {
+        let dir = scratch_dir("dry-run");
+        let (server, client) = serving(GRADEBOOK);
+        let (live, preview) = (Recorder::new(), Recorder::new());
+
+        let store = seeded(&dir, before_changes(), at(6, 0));
+        let mut watcher = Watcher::new(client, store, live.clone()).unwrap()
+            .clock(FixedClock(at(6, 30)))
+            .dry_run(preview.clone());
+        assert!(watcher.poll().unwrap().is_some());
+
+        assert!(live.sent().is_empty());
+        assert_eq!(preview.sent().len(), 1);
+        assert_eq!(latest_capture(&dir), at(6, 0));
+        assert!(!dir.join("watcher-state.json").exists());
+
+        server.close();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replays_the_stored_history() {
+        let dir = scratch_dir("replay");
+        let mut moved = Gradebook::from_xml(GRADEBOOK).unwrap();
+        moved.courses[1].room = "Lab 5".to_string();
+
+        let store = seeded(&dir, before_changes(), at(6, 0));
+        store.put_snapshot(&Snapshot { captured_at: at(7, 0), gradebook: Gradebook::from_xml(GRADEBOOK).unwrap() }).unwrap();
+        store.put_snapshot(&Snapshot { captured_at: at(8, 0), gradebook: moved }).unwrap();
+        // after the end of the range, so left out
+        store.put_snapshot(&Snapshot { captured_at: at(9, 0), gradebook: before_changes() }).unwrap();
+
+        let watcher = Watcher::new(SVUEClient::new("user", "pass"), store, Printer::new(Vec::new())).unwrap();
+        let sink = Printer::new(Vec::new());
+        let changed = |sent: &[Changeset]| -> Vec<usize> { sent.iter().map(|c| c.changes.len()).collect() };
+
+        let sent = watcher.replay(at(6, 0), at(9, 0), &sink).unwrap();
+        assert_eq!(changed(&sent), vec![2, 1]);
+        // nothing's marked as sent, so it can be replayed again
+        let again = watcher.replay(at(6, 0), at(9, 0), &sink).unwrap();
+        assert_eq!(changed(&again), vec![2, 1]);
+        assert!(!dir.join("watcher-state.json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn holds_changes_for_the_next_digest() {
+        let dir = scratch_dir("digest");
+        let (server, client) = serving(GRADEBOOK);
+        let recorder = Recorder::new();
+
+        let store = seeded(&dir, before_changes(), at(6, 0));
+        let mut watcher = Watcher::new(client, store, recorder.clone()).unwrap()
+            .digest(DigestSchedule::daily(NaiveTime::from_hms_opt(7, 0, 0).unwrap()))
+            .immediate(false)
+            .clock(FixedClock(at(6, 30)));
+        assert!(watcher.poll().unwrap().is_none());
+        assert!(recorder.sent().is_empty());
+
+        // due, but held back; the baseline stays put so the next poll sends it
+        recorder.answer(Answer::Defer);
+        watcher = watcher.clock(FixedClock(at(7, 30)));
+        assert!(watcher.poll().unwrap().is_none());
+        assert!(recorder.sent().is_empty());
+
+        recorder.answer(Answer::Take);
+        watcher = watcher.clock(FixedClock(at(8, 0)));
+        watcher.poll().unwrap();
+        let sent = recorder.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("Daily digest: "), "{:?}", sent[0]);
+
+        // nothing new since the digest, which isn't due again until tomorrow anyway
+        watcher = watcher.clock(FixedClock(at(9, 0)));
+        watcher.poll().unwrap();
+        assert_eq!(recorder.sent().len(), 1);
+
+        server.close();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature="mock-server")]
+    #[test]
+    fn keeps_the_baseline_when_delivery_is_deferred() {
+        let dir = scratch_dir("deferred");
+        let (server, client) = serving(GRADEBOOK);
+        let recorder = Recorder::new();
+        recorder.answer(Answer::Defer);
+
+        let store = seeded(&dir, before_changes(), at(6, 0));
+        let mut watcher = Watcher::new(client, store, recorder.clone()).unwrap().clock(FixedClock(at(6, 30)));
+        assert!(watcher.poll().unwrap().is_none());
+        assert!(recorder.sent().is_empty());
+        assert_eq!(latest_capture(&dir), at(6, 0));
+
+        recorder.answer(Answer::Take);
+        watcher = watcher.clock(FixedClock(at(7, 30)));
+        assert_eq!(watcher.poll().unwrap().unwrap().changes.len(), 2);
+        assert_eq!(recorder.sent().len(), 1);
+        assert_eq!(latest_capture(&dir), at(7, 30));
+
+        server.close();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wakes_a_household_for_a_refresh() {
+        // no children, so there's nothing to poll, only the sleep in between
+        let mut watcher = HouseholdWatcher::new(Household::new(), scratch_dir("household-refresh"), Printer::new(Vec::new()))
+            .unwrap()
+            .interval(Duration::from_secs(60 * 60))
+            .refresh_cooldown(Duration::from_secs(0));
+        let (refresh, shutdown) = (watcher.refresh_handle(), watcher.shutdown_handle());
+
+        let running = thread::spawn(move || watcher.run());
+        refresh.request();
+        // taken by the sleep, which would otherwise leave it until the hour is up
+        for _ in 0..50 {
+            if !refresh.is_requested() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(!refresh.is_requested());
+
+        shutdown.shutdown();
+        running.join().unwrap();
+    }
+}